@@ -0,0 +1,167 @@
+use std::path::Path;
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, DynamicImage, Frame};
+
+use crate::config::body_radius;
+use crate::image_gen::{compute_outcome_grid, draw_bodies, render_outcome_grid, MapParams};
+use crate::image_format::ImageOutput;
+use crate::physics::Vec2;
+use crate::scene::Scene;
+
+// Parameter swept across the frames of an animation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AnimatedParameter {
+    // Direction of the initial velocity in degrees, at a fixed speed
+    VelocityAngle,
+    Mass,
+    PositionX,
+    PositionY,
+    // Zooms around the center of the view, interpolated geometrically so the zoom speed looks constant
+    Zoom,
+}
+
+impl AnimatedParameter {
+    pub const ALL: [AnimatedParameter; 5] = [
+        AnimatedParameter::VelocityAngle,
+        AnimatedParameter::Mass,
+        AnimatedParameter::PositionX,
+        AnimatedParameter::PositionY,
+        AnimatedParameter::Zoom,
+    ];
+
+    // Identifier used on the command line
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            AnimatedParameter::VelocityAngle => "velocity-angle",
+            AnimatedParameter::Mass => "mass",
+            AnimatedParameter::PositionX => "position-x",
+            AnimatedParameter::PositionY => "position-y",
+            AnimatedParameter::Zoom => "zoom",
+        }
+    }
+
+    fn affects_body(&self) -> bool {
+        matches!(self, AnimatedParameter::Mass | AnimatedParameter::PositionX | AnimatedParameter::PositionY)
+    }
+}
+
+impl std::str::FromStr for AnimatedParameter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AnimatedParameter::ALL.iter()
+            .find(|parameter| parameter.short_name() == s)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = AnimatedParameter::ALL.iter().map(|parameter| parameter.short_name()).collect();
+                format!("unknown parameter '{}', expected one of: {}", s, names.join(", "))
+            })
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct AnimationSettings {
+    pub parameter: AnimatedParameter,
+    pub from: f32,
+    pub to: f32,
+    pub frames: usize,
+    // Body whose mass or position is animated
+    pub body: usize,
+    // Magnitude of the initial velocity when sweeping its angle
+    pub speed: f32,
+    // Milliseconds each frame is shown for in the GIF
+    pub frame_delay_ms: u32,
+    // Draw the bodies into every frame, where they are as the frame's scene has them
+    pub draw_bodies: bool,
+    pub image_output: ImageOutput,
+}
+
+impl AnimationSettings {
+    // Value of the animated parameter in the given frame; the first and last frames hit `from` and `to` exactly
+    pub fn value_at(&self, frame: usize) -> f32 {
+        let t = if self.frames > 1 { frame as f32 / (self.frames - 1) as f32 } else { 0.0 };
+        if self.parameter == AnimatedParameter::Zoom {
+            self.from * (self.to / self.from).powf(t)
+        } else {
+            self.from + (self.to - self.from) * t
+        }
+    }
+
+    // Map parameters and scene for one frame, starting from the base view
+    pub fn frame(&self, base: &MapParams, scene: &Scene, frame: usize) -> (MapParams, Scene) {
+        let value = self.value_at(frame);
+        let mut params = *base;
+        let mut scene = scene.clone();
+        match self.parameter {
+            AnimatedParameter::VelocityAngle => {
+                let angle = value.to_radians();
+                params.initial_velocity = Vec2::new(angle.cos(), angle.sin()) * self.speed;
+            }
+            AnimatedParameter::Mass => {
+                let body = &mut scene.bodies[self.body];
+                body.mass = value;
+                body.radius = body_radius(value);
+            }
+            AnimatedParameter::PositionX => scene.bodies[self.body].pos.x = value,
+            AnimatedParameter::PositionY => scene.bodies[self.body].pos.y = value,
+            AnimatedParameter::Zoom => {
+                // Keep the world point at the center of the view fixed
+                let center = Vec2::new(base.width as f32, base.height as f32) / 2.0;
+                let world_center = center / base.zoom_factor - base.camera_offset;
+                params.zoom_factor = value;
+                params.camera_offset = center / value - world_center;
+            }
+        }
+        params.scene_key = scene.cache_key();
+        (params, scene)
+    }
+}
+
+// Renders every frame of the animation into `output_dir` as numbered PNGs (with their parameters
+// in the metadata), optionally also combining them into an animated GIF
+pub fn render_animation(
+    base: &MapParams,
+    scene: &Scene,
+    settings: &AnimationSettings,
+    output_dir: &str,
+    write_gif: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if settings.parameter.affects_body() && settings.body >= scene.bodies.len() {
+        return Err(format!("body {} does not exist, the scene has {} bodies", settings.body, scene.bodies.len()).into());
+    }
+    if settings.parameter == AnimatedParameter::Zoom && (settings.from <= 0.0 || settings.to <= 0.0) {
+        return Err("zoom animations need positive start and end values".into());
+    }
+    std::fs::create_dir_all(output_dir)?;
+
+    let mut gif_frames = Vec::new();
+    for frame in 0..settings.frames {
+        let (params, frame_scene) = settings.frame(base, scene, frame);
+        println!("Rendering frame {}/{} ({} = {:.3})",
+                 frame + 1, settings.frames, settings.parameter.short_name(), settings.value_at(frame));
+        let grid = compute_outcome_grid(&frame_scene, &params);
+        let mut img = render_outcome_grid(&grid, &frame_scene.bodies);
+        if settings.draw_bodies {
+            draw_bodies(&mut img, &frame_scene.bodies, &params, 1.0);
+        }
+
+        let filename = Path::new(output_dir).join(format!("frame_{:04}.{}", frame, settings.image_output.extension()));
+        settings.image_output.save(&img, &filename.to_string_lossy(), &params, &frame_scene)?;
+
+        if write_gif {
+            let rgba = DynamicImage::ImageRgb8(img).to_rgba8();
+            gif_frames.push(Frame::from_parts(rgba, 0, 0, Delay::from_numer_denom_ms(settings.frame_delay_ms, 1)));
+        }
+    }
+    println!("Frames saved to {}", output_dir);
+
+    if write_gif {
+        let filename = Path::new(output_dir).join("animation.gif");
+        let mut encoder = GifEncoder::new(std::fs::File::create(&filename)?);
+        encoder.set_repeat(Repeat::Infinite)?;
+        encoder.encode_frames(gif_frames)?;
+        println!("Animated GIF saved to {}", filename.display());
+    }
+    Ok(())
+}
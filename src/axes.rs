@@ -0,0 +1,63 @@
+use crate::camera::Camera;
+
+// Screen distance the grid aims for between lines, before scaling; actual spacing ends up
+// between 40% and 100% of it
+pub const GRID_SPACING: f32 = 120.0;
+// Share of the view width the scale bar covers at most
+const SCALE_BAR_FRACTION: f32 = 0.2;
+
+// Largest 1, 2 or 5 times a power of ten that isn't above `value`
+pub fn nice_floor(value: f32) -> f32 {
+    let magnitude = 10f32.powf(value.log10().floor());
+    let mantissa = value / magnitude;
+    let nice = if mantissa >= 5.0 { 5.0 } else if mantissa >= 2.0 { 2.0 } else { 1.0 };
+    nice * magnitude
+}
+
+// World coordinate lines crossing a view, as (pixel position, world value) pairs along each axis
+pub struct WorldGrid {
+    pub step: f32,
+    pub columns: Vec<(f32, f32)>,
+    pub rows: Vec<(f32, f32)>,
+}
+
+impl WorldGrid {
+    // Grid across the camera's viewport with lines about `spacing` pixels apart
+    pub fn new(camera: &Camera, spacing: f32) -> Self {
+        let Camera { offset: camera_offset, zoom: zoom_factor, viewport } = *camera;
+        let step = nice_floor(spacing / zoom_factor);
+        let lines = |offset: f32, extent: f32| -> Vec<(f32, f32)> {
+            let first = (-offset / step).ceil() as i64;
+            let last = ((extent / zoom_factor - offset) / step).floor() as i64;
+            (first..=last)
+                .map(|i| {
+                    let value = i as f32 * step;
+                    ((value + offset) * zoom_factor, value)
+                })
+                .collect()
+        };
+        Self { step, columns: lines(camera_offset.x, viewport.x), rows: lines(camera_offset.y, viewport.y) }
+    }
+
+    // Whether a line is one of the world axes
+    pub fn is_axis(&self, value: f32) -> bool {
+        value.abs() < self.step * 1e-3
+    }
+
+    // Coordinate label with as many decimals as the step needs
+    pub fn label(&self, value: f32) -> String {
+        format_length(if self.is_axis(value) { 0.0 } else { value }, self.step)
+    }
+}
+
+// Length in world units of a scale bar for a view, and its length in pixels
+pub fn scale_bar(width: f32, zoom_factor: f32) -> (f32, f32) {
+    let length = nice_floor(width * SCALE_BAR_FRACTION / zoom_factor);
+    (length, length * zoom_factor)
+}
+
+// A length or coordinate printed with as many decimals as `resolution` needs
+pub fn format_length(value: f32, resolution: f32) -> String {
+    let decimals = (-resolution.log10().floor()).max(0.0) as usize;
+    format!("{:.*}", decimals, value)
+}
@@ -0,0 +1,241 @@
+use crate::nbody::step_system;
+use crate::physics::{apply_surroundings, calculate_acceleration, TestParticle, Vec2};
+use crate::scene::Scene;
+use crate::simulation::{collide, settles, IntegrationMethod, SimulationParams};
+
+// Defaults for SimulationParams: the opening angle trades accuracy for speed, and scenes with
+// fewer bodies than the threshold are summed exactly since a tree wouldn't pay off
+pub const OPENING_ANGLE: f32 = 0.5;
+pub const BARNES_HUT_BODIES: usize = 64;
+
+// Deep enough for any sensible scene; bodies sharing a position would otherwise split forever
+const MAX_DEPTH: usize = 24;
+
+enum NodeKind {
+    // Bodies summed exactly when the node is opened
+    Leaf(Vec<usize>),
+    // Indices of the non-empty child quadrants
+    Branch(Vec<usize>),
+}
+
+struct Node {
+    mass: f32,
+    center_of_mass: Vec2,
+    // Side length of the node's square
+    size: f32,
+    // Bounding box of the bodies below the node, for skipping it in collision checks
+    min: Vec2,
+    max: Vec2,
+    kind: NodeKind,
+}
+
+// Quadtree over the bodies of a scene. Distant groups of bodies pull like a single body at
+// their center of mass, which makes the acceleration roughly O(log bodies) instead of O(bodies).
+pub struct QuadTree {
+    nodes: Vec<Node>,
+    bodies: Vec<(Vec2, f32)>,
+    gravitational_constant: f32,
+    opening_angle: f32,
+}
+
+impl QuadTree {
+    pub fn new(scene: &Scene, opening_angle: f32) -> Self {
+        let bodies: Vec<(Vec2, f32)> = scene.bodies.iter().map(|body| (body.pos, body.mass)).collect();
+        let mut tree = Self { nodes: Vec::new(), bodies, gravitational_constant: scene.gravitational_constant, opening_angle };
+        if !tree.bodies.is_empty() {
+            let (min, max) = tree.bounds(&(0..tree.bodies.len()).collect::<Vec<_>>());
+            let size = (max.x - min.x).max(max.y - min.y).max(f32::MIN_POSITIVE);
+            let center = (min + max) / 2.0;
+            tree.build((0..tree.bodies.len()).collect(), center, size, 0);
+        }
+        tree
+    }
+
+    fn bounds(&self, indices: &[usize]) -> (Vec2, Vec2) {
+        indices.iter().fold((Vec2::new(f32::INFINITY, f32::INFINITY), Vec2::new(f32::NEG_INFINITY, f32::NEG_INFINITY)),
+            |(min, max), &i| {
+                let pos = self.bodies[i].0;
+                (Vec2::new(min.x.min(pos.x), min.y.min(pos.y)), Vec2::new(max.x.max(pos.x), max.y.max(pos.y)))
+            })
+    }
+
+    // Builds the node for `indices` inside the square at `center` and returns its index
+    fn build(&mut self, indices: Vec<usize>, center: Vec2, size: f32, depth: usize) -> usize {
+        let mass: f32 = indices.iter().map(|&i| self.bodies[i].1).sum();
+        let weighted = indices.iter().fold(Vec2::new(0.0, 0.0), |sum, &i| sum + self.bodies[i].0 * self.bodies[i].1);
+        let (min, max) = self.bounds(&indices);
+        let index = self.nodes.len();
+        self.nodes.push(Node { mass, center_of_mass: weighted / mass, size, min, max, kind: NodeKind::Leaf(Vec::new()) });
+
+        if indices.len() == 1 || depth >= MAX_DEPTH {
+            self.nodes[index].kind = NodeKind::Leaf(indices);
+            return index;
+        }
+        let mut quadrants: [Vec<usize>; 4] = Default::default();
+        for i in indices {
+            let pos = self.bodies[i].0;
+            let quadrant = (pos.x >= center.x) as usize + 2 * (pos.y >= center.y) as usize;
+            quadrants[quadrant].push(i);
+        }
+        let quarter = size / 4.0;
+        let mut children = Vec::new();
+        for (quadrant, members) in quadrants.into_iter().enumerate() {
+            if members.is_empty() {
+                continue;
+            }
+            let offset = Vec2::new(if quadrant & 1 == 1 { quarter } else { -quarter }, if quadrant & 2 == 2 { quarter } else { -quarter });
+            children.push(self.build(members, center + offset, size / 2.0, depth + 1));
+        }
+        self.nodes[index].kind = NodeKind::Branch(children);
+        index
+    }
+
+    // Pull of a point mass, as in calculate_gravitational_force (the test particle's mass cancels)
+    fn pull(&self, pos: Vec2, source: Vec2, mass: f32) -> Vec2 {
+        let dir = source - pos;
+        let dist = dir.length();
+        if dist == 0.0 {
+            return Vec2::new(0.0, 0.0);
+        }
+        dir.normalize() * (self.gravitational_constant * mass / (dist * dist))
+    }
+
+    pub fn acceleration(&self, pos: Vec2) -> Vec2 {
+        let mut total = Vec2::new(0.0, 0.0);
+        let mut stack = if self.nodes.is_empty() { Vec::new() } else { vec![0] };
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            let dist = node.center_of_mass.distance(&pos);
+            match &node.kind {
+                NodeKind::Branch(children) if node.size >= self.opening_angle * dist => stack.extend(children),
+                NodeKind::Branch(_) => total += self.pull(pos, node.center_of_mass, node.mass),
+                NodeKind::Leaf(bodies) => {
+                    for &i in bodies {
+                        total += self.pull(pos, self.bodies[i].0, self.bodies[i].1);
+                    }
+                }
+            }
+        }
+        total
+    }
+
+    // Lowest-numbered body within `threshold` of `pos`, matching check_collision
+    pub fn collision(&self, pos: Vec2, threshold: f32) -> Option<usize> {
+        let mut hit: Option<usize> = None;
+        let mut stack = if self.nodes.is_empty() { Vec::new() } else { vec![0] };
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            let dx = (node.min.x - pos.x).max(pos.x - node.max.x).max(0.0);
+            let dy = (node.min.y - pos.y).max(pos.y - node.max.y).max(0.0);
+            if dx * dx + dy * dy >= threshold * threshold {
+                continue;
+            }
+            match &node.kind {
+                NodeKind::Branch(children) => stack.extend(children),
+                NodeKind::Leaf(bodies) => {
+                    for &i in bodies {
+                        if pos.distance(&self.bodies[i].0) < threshold && hit.is_none_or(|h| i < h) {
+                            hit = Some(i);
+                        }
+                    }
+                }
+            }
+        }
+        hit
+    }
+}
+
+// Where the simulation gets gravity from: the exact sum over bodies, a quadtree when the scene
+// has enough bodies for the approximation to pay off, or a scene of its own whose bodies move
+// with the particle when they aren't stationary
+pub enum Gravity<'a> {
+    Exact(&'a Scene),
+    // The tree only approximates gravity, so the scene is kept next to it for the rest of the forces
+    BarnesHut(QuadTree, &'a Scene),
+    // Every particle pulls the bodies its own way, so each simulation steps its own copy
+    Dynamic(Box<Scene>),
+}
+
+impl<'a> Gravity<'a> {
+    pub fn new(scene: &'a Scene, params: &SimulationParams) -> Self {
+        if scene.is_dynamic() {
+            Gravity::Dynamic(Box::new(scene.clone()))
+        } else if uses_barnes_hut(scene, params) {
+            Gravity::BarnesHut(QuadTree::new(scene, params.opening_angle), scene)
+        } else {
+            Gravity::Exact(scene)
+        }
+    }
+
+    // The scene as the particle currently sees it, with dynamic bodies where they have got to
+    pub fn scene(&self) -> &Scene {
+        match self {
+            Gravity::Exact(scene) | Gravity::BarnesHut(_, scene) => scene,
+            Gravity::Dynamic(scene) => scene,
+        }
+    }
+
+    // Advances a particle by one substep of length dt
+    pub fn step(&mut self, integration_method: IntegrationMethod, particle: &mut TestParticle, dt: f32) {
+        match self {
+            Gravity::Exact(scene) => integration_method.update(particle, scene, dt),
+            Gravity::BarnesHut(..) => integration_method.step_with(particle, dt, |p| self.acceleration(p)),
+            Gravity::Dynamic(scene) => step_system(scene, particle, integration_method, dt, |_| Vec2::new(0.0, 0.0)),
+        }
+    }
+
+    // step with `extra` acceleration on top of the scene's, e.g. thrust
+    pub fn step_with(
+        &mut self,
+        integration_method: IntegrationMethod,
+        particle: &mut TestParticle,
+        dt: f32,
+        extra: impl Fn(&TestParticle) -> Vec2,
+    ) {
+        match self {
+            Gravity::Dynamic(scene) => step_system(scene, particle, integration_method, dt, extra),
+            _ => integration_method.step_with(particle, dt, |p| self.acceleration(p) + extra(p)),
+        }
+    }
+
+    // Acceleration of a particle from the scene, gravity and everything else acting on it
+    pub fn acceleration(&self, particle: &TestParticle) -> Vec2 {
+        match self {
+            Gravity::Exact(scene) => calculate_acceleration(particle, scene),
+            Gravity::BarnesHut(tree, scene) => apply_surroundings(tree.acceleration(particle.pos), particle, scene),
+            Gravity::Dynamic(scene) => calculate_acceleration(particle, scene),
+        }
+    }
+
+    // Body the particle stopped at, after bouncing it off any it hit in scenes with a restitution
+    pub fn collision(&self, particle: &mut TestParticle, collision_threshold: f32) -> Option<usize> {
+        match self {
+            Gravity::Exact(scene) => collide(particle, scene, collision_threshold),
+            Gravity::Dynamic(scene) => collide(particle, scene, collision_threshold).map(|index| scene.original_index(index)),
+            Gravity::BarnesHut(tree, scene) => {
+                if let Some(walls) = &scene.walls {
+                    walls.confine(particle);
+                }
+                tree.collision(particle.pos, collision_threshold)
+                    .filter(|&index| settles(particle, scene, index, collision_threshold))
+            }
+        }
+    }
+}
+
+// Whether simulations of this scene approximate gravity with a quadtree. Centers of mass only
+// make sense for positive spherical point masses, and the tree doesn't track charge or the
+// potential or periodic images, so scenes with any other bodies, with moving bodies, with
+// electrostatics, with a magnetic field acting on a charged particle, with the post-Newtonian
+// correction, with periodic walls or whose bodies aren't stationary are always summed exactly.
+pub fn uses_barnes_hut(scene: &Scene, params: &SimulationParams) -> bool {
+    params.opening_angle > 0.0
+        && scene.bodies.len() >= params.barnes_hut_bodies
+        && scene.bodies.iter().all(|body| body.mass > 0.0 && body.shape.is_point() && body.j2 == 0.0)
+        && !scene.is_charged()
+        && !scene.is_magnetized()
+        && !scene.is_relativistic()
+        && !scene.has_moving_bodies()
+        && !scene.walls.is_some_and(|walls| walls.is_periodic())
+        && !scene.is_dynamic()
+}
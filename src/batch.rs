@@ -0,0 +1,378 @@
+use wide::{f32x8, CmpEq, CmpLt};
+
+use crate::physics::{Body, Vec2};
+use crate::scene::Scene;
+use crate::barnes_hut::uses_barnes_hut;
+use crate::simulation::{early_outcome, run_simulation_with_time, settles, IntegrationMethod, SimulationParams, TEST_PARTICLE_MASS};
+
+// Particles advanced together in one batch
+pub const LANES: usize = 8;
+
+// Positions and velocities of a batch of particles, one particle per SIMD lane
+#[derive(Clone, Copy)]
+struct Particles {
+    x: f32x8,
+    y: f32x8,
+    vx: f32x8,
+    vy: f32x8,
+}
+
+// Same operations in the same order as calculate_acceleration, so every lane matches the scalar
+// simulation bit for bit. `bodies` are the scene's bodies where they are at the lanes' time.
+fn acceleration(x: f32x8, y: f32x8, vx: f32x8, vy: f32x8, scene: &Scene, bodies: &[Body]) -> (f32x8, f32x8) {
+    let zero = f32x8::splat(0.0);
+    let particle_mass = f32x8::splat(TEST_PARTICLE_MASS);
+    let mut fx = zero;
+    let mut fy = zero;
+    for body in bodies {
+        let dx = f32x8::splat(body.pos.x) - x;
+        let dy = f32x8::splat(body.pos.y) - y;
+        let dist = (dx * dx + dy * dy).sqrt();
+        let at_body = dist.cmp_eq(zero);
+        let force_mag = f32x8::splat(scene.gravitational_constant) * particle_mass * f32x8::splat(body.mass) / (dist * dist);
+        let (mut gx, mut gy) = (dx / dist * force_mag, dy / dist * force_mag);
+        if body.j2 != 0.0 {
+            let (ox, oy) = oblateness_force(body, scene.gravitational_constant * TEST_PARTICLE_MASS * body.mass, x, y);
+            gx += ox;
+            gy += oy;
+        }
+        fx += at_body.blend(zero, gx);
+        fy += at_body.blend(zero, gy);
+    }
+    // As in calculate_post_newtonian_acceleration, with the potential summed as in jacobi::potential
+    let post_newtonian = scene.speed_of_light.map(|c| {
+        let mut potential = zero;
+        for body in bodies {
+            let dx = f32x8::splat(body.pos.x) - x;
+            let dy = f32x8::splat(body.pos.y) - y;
+            let dist = (dx * dx + dy * dy).sqrt();
+            let mut body_potential = f32x8::splat(-scene.gravitational_constant) * f32x8::splat(body.mass) / dist;
+            if body.j2 != 0.0 {
+                body_potential += oblateness_potential(body, scene.gravitational_constant * body.mass, x, y);
+            }
+            potential += dist.cmp_eq(zero).blend(zero, body_potential);
+        }
+        let u = -potential;
+        let (gx, gy) = (fx / particle_mass, fy / particle_mass);
+        let v2 = vx * vx + vy * vy;
+        let v_gravity = vx * gx + vy * gy;
+        let four = f32x8::splat(4.0);
+        let c2 = f32x8::splat(c * c);
+        ((gx * (v2 - four * u) - vx * (four * v_gravity)) / c2, (gy * (v2 - four * u) - vy * (four * v_gravity)) / c2)
+    });
+    if scene.is_charged() {
+        let particle_charge = f32x8::splat(scene.particle_charge);
+        for body in bodies {
+            let dx = f32x8::splat(body.pos.x) - x;
+            let dy = f32x8::splat(body.pos.y) - y;
+            let dist = (dx * dx + dy * dy).sqrt();
+            let at_body = dist.cmp_eq(zero);
+            let force_mag = f32x8::splat(-scene.coulomb_constant) * particle_charge * f32x8::splat(body.charge) / (dist * dist);
+            fx += at_body.blend(zero, dx / dist * force_mag);
+            fy += at_body.blend(zero, dy / dist * force_mag);
+        }
+    }
+    if scene.is_magnetized() {
+        let qb = f32x8::splat(scene.particle_charge * scene.magnetic_field);
+        fx += vy * qb;
+        fy += -vx * qb;
+    }
+    let (mut ax, mut ay) = (fx / particle_mass, fy / particle_mass);
+    if let Some((px, py)) = post_newtonian {
+        ax += px;
+        ay += py;
+    }
+    if scene.drag.is_active() {
+        let factor = -(f32x8::splat(scene.drag.linear) + f32x8::splat(scene.drag.quadratic) * (vx * vx + vy * vy).sqrt());
+        ax += vx * factor;
+        ay += vy * factor;
+    }
+    let field = &scene.external_field;
+    if field.is_active() {
+        let [xx, xy, yy] = field.tidal.map(f32x8::splat);
+        let dx = x - f32x8::splat(field.origin.x);
+        let dy = y - f32x8::splat(field.origin.y);
+        ax += f32x8::splat(field.acceleration.x) + xx * dx + xy * dy;
+        ay += f32x8::splat(field.acceleration.y) + xy * dx + yy * dy;
+    }
+    (ax, ay)
+}
+
+// Same operations as Body::oblateness_force, for lanes away from the body
+fn oblateness_force(body: &Body, strength: f32, x: f32x8, y: f32x8) -> (f32x8, f32x8) {
+    let ox = x - f32x8::splat(body.pos.x);
+    let oy = y - f32x8::splat(body.pos.y);
+    let r2 = ox * ox + oy * oy;
+    let pole = body.pole();
+    let z = ox * f32x8::splat(pole.x) + oy * f32x8::splat(pole.y);
+    let scale = f32x8::splat(strength * body.j2 * body.radius * body.radius) / (r2 * r2 * r2.sqrt());
+    let radial = f32x8::splat(1.5) - f32x8::splat(7.5) * z * z / r2;
+    let three_z = f32x8::splat(3.0) * z;
+    ((f32x8::splat(pole.x) * three_z + ox * radial) * -scale, (f32x8::splat(pole.y) * three_z + oy * radial) * -scale)
+}
+
+// Same operations as Body::oblateness_potential
+fn oblateness_potential(body: &Body, strength: f32, x: f32x8, y: f32x8) -> f32x8 {
+    let ox = x - f32x8::splat(body.pos.x);
+    let oy = y - f32x8::splat(body.pos.y);
+    let r2 = ox * ox + oy * oy;
+    let pole = body.pole();
+    let z = ox * f32x8::splat(pole.x) + oy * f32x8::splat(pole.y);
+    f32x8::splat(strength * body.j2 * body.radius * body.radius) * (f32x8::splat(3.0) * z * z - r2)
+        / (f32x8::splat(2.0) * r2 * r2 * r2.sqrt())
+}
+
+// Steps from simulated time `time` to `time + dt`, which all lanes share
+fn step_euler(p: &mut Particles, scene: &Scene, time: f32, dt: f32) {
+    let (ax, ay) = acceleration(p.x, p.y, p.vx, p.vy, scene, &scene.bodies_at(time));
+    let dt = f32x8::splat(dt);
+    p.vx += ax * dt;
+    p.vy += ay * dt;
+    p.x += p.vx * dt;
+    p.y += p.vy * dt;
+}
+
+// Mirrors update_particle_rk4, including the time each stage is evaluated at
+fn step_rk4(p: &mut Particles, scene: &Scene, time: f32, dt: f32) {
+    let (halfway, end) = (scene.bodies_at(time + dt * 0.5), scene.bodies_at(time + dt));
+    let dt = f32x8::splat(dt);
+    let half = f32x8::splat(0.5);
+    let two = f32x8::splat(2.0);
+    let six = f32x8::splat(6.0);
+    let o = *p;
+
+    let (ax, ay) = acceleration(o.x, o.y, o.vx, o.vy, scene, &scene.bodies_at(time));
+    let (k1_vx, k1_vy) = (ax * dt, ay * dt);
+    let (k1_x, k1_y) = (o.vx * dt, o.vy * dt);
+
+    let (tx, ty) = (o.x + k1_x * half, o.y + k1_y * half);
+    let (tvx, tvy) = (o.vx + k1_vx * half, o.vy + k1_vy * half);
+    let (ax, ay) = acceleration(tx, ty, tvx, tvy, scene, &halfway);
+    let (k2_vx, k2_vy) = (ax * dt, ay * dt);
+    let (k2_x, k2_y) = (tvx * dt, tvy * dt);
+
+    let (tx, ty) = (o.x + k2_x * half, o.y + k2_y * half);
+    let (tvx, tvy) = (o.vx + k2_vx * half, o.vy + k2_vy * half);
+    let (ax, ay) = acceleration(tx, ty, tvx, tvy, scene, &halfway);
+    let (k3_vx, k3_vy) = (ax * dt, ay * dt);
+    let (k3_x, k3_y) = (tvx * dt, tvy * dt);
+
+    let (tx, ty) = (o.x + k3_x, o.y + k3_y);
+    let (tvx, tvy) = (o.vx + k3_vx, o.vy + k3_vy);
+    let (ax, ay) = acceleration(tx, ty, tvx, tvy, scene, &end);
+    let (k4_vx, k4_vy) = (ax * dt, ay * dt);
+    let (k4_x, k4_y) = (tvx * dt, tvy * dt);
+
+    p.vx = o.vx + (k1_vx + k2_vx * two + k3_vx * two + k4_vx) / six;
+    p.vy = o.vy + (k1_vy + k2_vy * two + k3_vy * two + k4_vy) / six;
+    p.x = o.x + (k1_x + k2_x * two + k3_x * two + k4_x) / six;
+    p.y = o.y + (k1_y + k2_y * two + k3_y * two + k4_y) / six;
+}
+
+// simulation::settles for one lane that hit body `index` at simulated time `time`, having
+// bounced `bounces` times before, bouncing it off the body in place if it doesn't stop there
+fn settles_lane(p: &mut Particles, lane: usize, bounces: &mut u32, scene: &Scene, index: usize, time: f32, threshold: f32) -> bool {
+    let (mut x, mut y, mut vx, mut vy) = (p.x.to_array(), p.y.to_array(), p.vx.to_array(), p.vy.to_array());
+    let mut particle = scene.launch_particle(Vec2::new(x[lane], y[lane]), Vec2::new(vx[lane], vy[lane]));
+    particle.time = time;
+    particle.bounces = *bounces;
+    let settled = settles(&mut particle, scene, index, threshold);
+    (x[lane], y[lane], vx[lane], vy[lane]) = (particle.pos.x, particle.pos.y, particle.vel.x, particle.vel.y);
+    *p = Particles { x: f32x8::from(x), y: f32x8::from(y), vx: f32x8::from(vx), vy: f32x8::from(vy) };
+    *bounces = particle.bounces;
+    settled
+}
+
+// Batched run_simulation_with_time: simulates up to LANES particles with a shared initial
+// velocity, sharing the loop over bodies across lanes, and returns the body each one hit and the
+// timestep it hit at. Results are identical to calling run_simulation_with_time per particle.
+pub fn run_simulation_batch(
+    start_positions: &[Vec2],
+    initial_velocity: Vec2,
+    scene: &Scene,
+    integration_method: IntegrationMethod,
+    params: &SimulationParams,
+) -> Vec<Option<(usize, usize)>> {
+    let count = start_positions.len().min(LANES);
+    // The quadtree walk differs from particle to particle, force fields are evaluated and walls
+    // fold particles back one particle at a time, and every particle of a dynamic scene moves the
+    // bodies its own way, so none of them vectorize
+    if uses_barnes_hut(scene, params) || scene.has_force_fields() || scene.walls.is_some() || scene.is_dynamic() {
+        return start_positions.iter().take(count)
+            .map(|pos| run_simulation_with_time(*pos, initial_velocity, scene, integration_method, params))
+            .collect();
+    }
+    let mut xs = [0.0f32; LANES];
+    let mut ys = [0.0f32; LANES];
+    for (i, pos) in start_positions.iter().take(count).enumerate() {
+        xs[i] = pos.x;
+        ys[i] = pos.y;
+    }
+    let mut particles = Particles {
+        x: f32x8::from(xs),
+        y: f32x8::from(ys),
+        vx: f32x8::splat(initial_velocity.x),
+        vy: f32x8::splat(initial_velocity.y),
+    };
+
+    let mut outcomes = vec![None; count];
+    // Unused lanes and launches decided without integrating count as finished from the start
+    let mut finished = [false; LANES];
+    finished[count..].fill(true);
+    for (lane, pos) in start_positions.iter().take(count).enumerate() {
+        if let Some(outcome) = early_outcome(*pos, initial_velocity, scene, params) {
+            outcomes[lane] = outcome;
+            finished[lane] = true;
+        }
+    }
+    if finished.iter().all(|done| *done) {
+        return outcomes;
+    }
+    let dt = params.substep_dt();
+    let threshold = f32x8::splat(params.collision_threshold);
+    // Simulated time since launch, accumulated like TestParticle::time
+    let mut time = 0.0f32;
+    let mut bounces = [0u32; LANES];
+
+    for timestep in 0..params.timesteps {
+        for _ in 0..params.substeps {
+            match integration_method {
+                IntegrationMethod::Euler => step_euler(&mut particles, scene, time, dt),
+                IntegrationMethod::RungeKutta4 => step_rk4(&mut particles, scene, time, dt),
+            }
+            time += dt;
+
+            // The first body within the threshold counts, as in check_collision
+            let mut hit = [None; LANES];
+            for (index, body) in scene.bodies_at(time).iter().enumerate().rev() {
+                let dx = particles.x - f32x8::splat(body.pos.x);
+                let dy = particles.y - f32x8::splat(body.pos.y);
+                let close = (dx * dx + dy * dy).sqrt().cmp_lt(threshold).move_mask();
+                for (lane, lane_hit) in hit.iter_mut().enumerate() {
+                    if close & (1 << lane) != 0 {
+                        *lane_hit = Some(index);
+                    }
+                }
+            }
+            for lane in 0..count {
+                if let (false, Some(index)) = (finished[lane], hit[lane]) {
+                    if scene.bounces() && !settles_lane(&mut particles, lane, &mut bounces[lane], scene, index, time, params.collision_threshold) {
+                        continue;
+                    }
+                    outcomes[lane] = Some((index, timestep));
+                    finished[lane] = true;
+                }
+            }
+            if finished.iter().all(|done| *done) {
+                return outcomes;
+            }
+        }
+    }
+    outcomes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::motion::{BodyMotion, BodyPath};
+    use crate::physics::{Drag, ExternalField};
+    use crate::scene::ScenePreset;
+
+    // Rows of LANES launches across the presets' 600x600 area, at rest and with a sideways push,
+    // so lanes in the same batch hit different bodies at different timesteps or never hit
+    #[test]
+    fn batch_matches_scalar_simulation() {
+        let params = SimulationParams { timesteps: 500, ..Default::default() };
+        let mut escaped = 0;
+        let mut mixed_batches = 0;
+        for preset in ScenePreset::ALL {
+            let scene = preset.scene();
+            for method in [IntegrationMethod::Euler, IntegrationMethod::RungeKutta4] {
+                for velocity in [Vec2::new(0.0, 0.0), Vec2::new(15.0, -10.0)] {
+                    for row in 0..LANES {
+                        let positions: Vec<Vec2> = (0..LANES)
+                            .map(|lane| Vec2::new(37.0 + 75.0 * lane as f32, 41.0 + 75.0 * row as f32))
+                            .collect();
+                        let batch = run_simulation_batch(&positions, velocity, &scene, method, &params);
+                        let scalar: Vec<_> = positions.iter()
+                            .map(|pos| run_simulation_with_time(*pos, velocity, &scene, method, &params))
+                            .collect();
+                        assert_eq!(batch, scalar, "{:?} {:?} velocity {:?} row {}", preset, method, velocity, row);
+
+                        escaped += scalar.iter().filter(|outcome| outcome.is_none()).count();
+                        let mut times: Vec<usize> = scalar.iter().flatten().map(|(_, time)| *time).collect();
+                        times.dedup();
+                        if times.len() > 1 {
+                            mixed_batches += 1;
+                        }
+                    }
+                }
+            }
+        }
+        assert!(escaped > 0, "no launch escaped, so lanes finishing at different times went untested");
+        assert!(mixed_batches > 0, "no batch had lanes collide at different timesteps");
+    }
+
+    // The same launches under each physics feature the vectorized path handles, one scene each so
+    // a mismatch names the feature
+    #[test]
+    fn batch_matches_scalar_simulation_with_features() {
+        let params = SimulationParams { timesteps: 500, ..Default::default() };
+        let base = Scene::default();
+        let mut charged = base.clone();
+        charged.particle_charge = 1.0;
+        charged.magnetic_field = 0.5;
+        charged.bodies[0] = charged.bodies[0].with_charge(200.0);
+        charged.bodies[1] = charged.bodies[1].with_charge(-150.0);
+        let mut oblate = base.clone();
+        oblate.bodies[0] = oblate.bodies[0].with_oblateness(0.3, 30.0);
+        let scenes = [
+            ("charge and magnetic field", charged),
+            ("speed of light", Scene { speed_of_light: Some(500.0), ..base.clone() }),
+            ("drag", Scene { drag: Drag::new(0.05, 0.001), ..base.clone() }),
+            ("external field", Scene {
+                external_field: ExternalField {
+                    acceleration: Vec2::new(0.0, 5.0),
+                    tidal: [0.01, 0.002, -0.005],
+                    origin: Vec2::new(300.0, 300.0),
+                },
+                ..base.clone()
+            }),
+            ("oblate body", oblate),
+            ("moving body", Scene {
+                motions: vec![BodyMotion { body: 0, path: BodyPath::Circle { radius: 40.0, period: 3.0, phase: 0.0 } }],
+                ..base.clone()
+            }),
+            ("restitution", Scene { restitution: Some(0.8), ..base.clone() }),
+        ];
+        for (feature, scene) in &scenes {
+            for method in [IntegrationMethod::Euler, IntegrationMethod::RungeKutta4] {
+                for velocity in [Vec2::new(0.0, 0.0), Vec2::new(15.0, -10.0)] {
+                    for row in 0..LANES {
+                        let positions: Vec<Vec2> = (0..LANES)
+                            .map(|lane| Vec2::new(37.0 + 75.0 * lane as f32, 41.0 + 75.0 * row as f32))
+                            .collect();
+                        let batch = run_simulation_batch(&positions, velocity, scene, method, &params);
+                        let scalar: Vec<_> = positions.iter()
+                            .map(|pos| run_simulation_with_time(*pos, velocity, scene, method, &params))
+                            .collect();
+                        assert_eq!(batch, scalar, "{} {:?} velocity {:?} row {}", feature, method, velocity, row);
+                    }
+                }
+            }
+        }
+    }
+
+    // Fewer launches than lanes leave the rest of the batch unused
+    #[test]
+    fn partial_batch_matches_scalar_simulation() {
+        let scene = Scene::default();
+        let params = SimulationParams { timesteps: 500, ..Default::default() };
+        let positions = [Vec2::new(100.0, 120.0), Vec2::new(480.0, 90.0), Vec2::new(300.0, 500.0)];
+        let batch = run_simulation_batch(&positions, Vec2::new(0.0, 0.0), &scene, IntegrationMethod::RungeKutta4, &params);
+        let scalar: Vec<_> = positions.iter()
+            .map(|pos| run_simulation_with_time(*pos, Vec2::new(0.0, 0.0), &scene, IntegrationMethod::RungeKutta4, &params))
+            .collect();
+        assert_eq!(batch, scalar);
+    }
+}
@@ -0,0 +1,72 @@
+use std::sync::atomic::AtomicUsize;
+
+use image::{Rgb, RgbImage};
+use rayon::prelude::*;
+
+use crate::image_gen::{count_pixel, outcome_color, MapParams};
+use crate::parameter_map::{frame_with_axes, ParameterAxis};
+use crate::physics::{Body, Vec2};
+use crate::scene::Scene;
+use crate::simulation::run_simulation_with_time;
+use crate::sweep::{SweepAxis, SweepSettings};
+
+const PLOT_BACKGROUND: [u8; 3] = [0, 0, 0];
+
+// One launch repeated over a single swept parameter, one column per value. Each column is a bar
+// from the bottom up to the collision time, in the color of the body hit, and stays empty if the
+// launch never collides, so a whole sweep costs one simulation per column instead of a map's worth.
+pub struct Bifurcation {
+    pub axis: SweepAxis,
+    // Outcome of every column, left to right
+    pub outcomes: Vec<Option<(usize, usize)>>,
+    pub timesteps: usize,
+}
+
+// Launches from `start` with the base view's velocity, integrator and simulation settings, with
+// the axis' parameter set to each of its values in turn. `body` is the body whose mass or position
+// is swept.
+pub fn compute_bifurcation(
+    base: &MapParams,
+    scene: &Scene,
+    start: Vec2,
+    axis: SweepAxis,
+    body: usize,
+    progress: &(impl Fn(usize, usize) + Sync),
+) -> Result<Bifurcation, Box<dyn std::error::Error>> {
+    if axis.parameter.affects_body() && body >= scene.bodies.len() {
+        return Err(format!("body {} does not exist, the scene has {} bodies", body, scene.bodies.len()).into());
+    }
+    let settings = SweepSettings { columns: axis, rows: None, body };
+    let steps = axis.steps.max(1);
+    let counter = AtomicUsize::new(0);
+    let outcomes = (0..steps).into_par_iter().map(|column| {
+        let (params, scene) = settings.cell(base, scene, &settings.values_at(column, 0));
+        let outcome = run_simulation_with_time(start, params.initial_velocity, &scene, params.integration_method, &params.simulation);
+        count_pixel(&counter, steps, progress);
+        outcome
+    }).collect();
+    Ok(Bifurcation { axis, outcomes, timesteps: base.simulation.timesteps })
+}
+
+// The plot `height` pixels tall, framed with the swept values along the bottom and collision
+// times up the side. `bodies` gives the bar colors.
+pub fn render_bifurcation(bifurcation: &Bifurcation, bodies: &[Body], height: u32) -> RgbImage {
+    let width = bifurcation.outcomes.len() as u32;
+    let height = height.max(2);
+    let mut plot = RgbImage::from_pixel(width.max(1), height, Rgb(PLOT_BACKGROUND));
+    for (x, outcome) in bifurcation.outcomes.iter().enumerate() {
+        let Some((_, time)) = outcome else { continue };
+        let color = Rgb(outcome_color(*outcome, bifurcation.timesteps, bodies));
+        let fraction = (*time as f32 / bifurcation.timesteps.max(1) as f32).min(1.0);
+        // Even the quickest capture gets a pixel, so it doesn't read as an escape
+        let bar = ((fraction * (height - 1) as f32).round() as u32 + 1).min(height);
+        for y in height - bar..height {
+            plot.put_pixel(x as u32, y, color);
+        }
+    }
+
+    let axis = &bifurcation.axis;
+    let columns = ParameterAxis { name: axis.parameter.short_name().to_string(), from: axis.from, to: axis.to };
+    let rows = ParameterAxis { name: "collision timestep".to_string(), from: bifurcation.timesteps as f32, to: 0.0 };
+    frame_with_axes(&plot, &columns, &rows)
+}
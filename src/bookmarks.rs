@@ -0,0 +1,38 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::deep_zoom::DeepPoint;
+use crate::physics::Vec2;
+use crate::scene::Scene;
+use crate::simulation::IntegrationMethod;
+
+pub const BOOKMARKS_FILE: &str = "bookmarks.json";
+
+// A saved view: where the camera was and everything needed to regenerate the map seen there
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    pub camera_offset: Vec2,
+    // camera_offset at full precision, for deep zooms
+    #[serde(default)]
+    pub deep_camera: Option<DeepPoint>,
+    pub zoom_factor: f32,
+    pub initial_velocity: Vec2,
+    pub integration_method: IntegrationMethod,
+    pub scene: Scene,
+}
+
+// Reads the bookmark list; a missing file just means no bookmarks have been saved yet
+pub fn load_bookmarks(path: &str) -> Result<Vec<Bookmark>, Box<dyn std::error::Error>> {
+    if !Path::new(path).exists() {
+        return Ok(Vec::new());
+    }
+    let contents = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+pub fn save_bookmarks(path: &str, bookmarks: &[Bookmark]) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::write(path, serde_json::to_string_pretty(bookmarks)?)?;
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use crate::precise::DVec2;
+use crate::physics::Vec2;
+
+// How a view maps the world onto its pixels: world point p lands on pixel (p + offset) * zoom, so
+// the offset is the world position of the top-left corner negated. The viewer and the map
+// generator share it so clicks, drawn bodies and map pixels always line up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    pub offset: Vec2,
+    pub zoom: f32,
+    // Size of the view in pixels
+    pub viewport: Vec2,
+}
+
+impl Camera {
+    pub fn new(offset: Vec2, zoom: f32, viewport: Vec2) -> Self {
+        Self { offset, zoom, viewport }
+    }
+
+    pub fn world_to_screen(&self, p: Vec2) -> Vec2 {
+        (p + self.offset) * self.zoom
+    }
+
+    pub fn screen_to_world(&self, p: Vec2) -> Vec2 {
+        p / self.zoom - self.offset
+    }
+
+    // screen_to_world in f64, so adjacent (sub)pixels stay distinct at any zoom
+    pub fn screen_to_world_f64(&self, px: f64, py: f64) -> DVec2 {
+        DVec2::new(
+            px / self.zoom as f64 - self.offset.x as f64,
+            py / self.zoom as f64 - self.offset.y as f64,
+        )
+    }
+
+    // Whether a screen point lies within `margin` pixels of the view
+    pub fn is_visible(&self, p: Vec2, margin: f32) -> bool {
+        p.x >= -margin && p.x < self.viewport.x + margin && p.y >= -margin && p.y < self.viewport.y + margin
+    }
+}
@@ -0,0 +1,769 @@
+use clap::{Args, Parser, Subcommand};
+
+use gravity_wells::barnes_hut::{BARNES_HUT_BODIES, OPENING_ANGLE};
+use gravity_wells::animation::AnimatedParameter;
+use gravity_wells::escape::EscapeColoring;
+use gravity_wells::image_format::{ImageOutput, RasterFormat, DEFAULT_QUALITY};
+use gravity_wells::image_gen::{BasinPalette, ColorEncoding, MapParams};
+use gravity_wells::probability::ProbabilityColoring;
+use gravity_wells::physics::Vec2;
+use gravity_wells::stability::STABILITY_MAP_SIZE;
+use gravity_wells::sweep::SweepParameter;
+use gravity_wells::scene::Scene;
+
+use gravity_wells::scene::ScenePreset;
+use gravity_wells::simulation::{CollisionPolicy, SimulationParams, COLLISION_THRESHOLD, SIMULATION_TIMESTEPS, SUBSTEPS, TIMESTEP_DT, TRAJECTORY_POINT_LIMIT};
+use gravity_wells::thrust::{Burn, Thrust, ThrustDirection, THRUST_ACCELERATION};
+
+use crate::keybindings::Keybindings;
+
+#[derive(Parser, Debug)]
+#[command(name = "gravity-wells", about = "Interactive visualization of gravitational basins of attraction")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Number of timesteps each particle is simulated for before it counts as escaped
+    #[arg(long, global = true, default_value_t = SIMULATION_TIMESTEPS)]
+    pub timesteps: usize,
+
+    /// Integration substeps per timestep
+    #[arg(long, global = true, default_value_t = SUBSTEPS)]
+    pub substeps: usize,
+
+    /// Distance from a body's center at which the particle counts as collided
+    #[arg(long, global = true, default_value_t = COLLISION_THRESHOLD)]
+    pub collision_threshold: f32,
+
+    /// What the collision threshold is measured in: world (units), screen (pixels at the view's zoom) or body-radius (ignores the threshold and uses the largest body's drawn radius)
+    #[arg(long, global = true, default_value = "world")]
+    pub collision_policy: CollisionPolicy,
+
+    /// Simulated time covered by one timestep
+    #[arg(long, global = true, default_value_t = TIMESTEP_DT)]
+    pub dt: f32,
+
+    /// Simulate map pixels in double precision, avoiding blocky basin boundaries at deep zoom
+    #[arg(long, global = true)]
+    pub f64: bool,
+
+    /// Barnes-Hut opening angle for scenes with many bodies; smaller is more accurate, 0 sums every body exactly
+    #[arg(long, global = true, default_value_t = OPENING_ANGLE)]
+    pub opening_angle: f32,
+
+    /// Use the Barnes-Hut approximation for scenes with at least this many bodies
+    #[arg(long, global = true, default_value_t = BARNES_HUT_BODIES)]
+    pub barnes_hut_bodies: usize,
+
+    /// How pixels that never collide are colored: uniform, min-distance (closest approach to any body), close-approaches (number of close passes), winding (turns around the center of mass), final-distance, final-speed, energy (bound or unbound at the end) or bounces (off bodies, with a restitution)
+    #[arg(long, global = true, default_value = "uniform")]
+    pub escape_coloring: EscapeColoring,
+
+    /// Scene file (TOML or JSON) with the bodies to simulate; reloaded automatically when it changes
+    #[arg(long, global = true)]
+    pub scene: Option<String>,
+
+    /// Check the integrators against known solutions (circular orbit, two-well oscillation, convergence order) and exit
+    #[arg(long)]
+    pub self_test: bool,
+
+    /// Restore the view, scene and simulation settings stored in a PNG saved by this program
+    #[arg(long, value_name = "PNG")]
+    pub from_png: Option<String>,
+
+    /// Start with a built-in scene: default, triangle, binary, line or central
+    #[arg(long, global = true)]
+    pub preset: Option<ScenePreset>,
+
+    /// Start with a randomly generated scene of this many bodies
+    #[arg(long, global = true, value_name = "BODIES")]
+    pub random_scene: Option<usize>,
+
+    /// Seed for --random-scene (random if not given) and for the samples of `probability` and `uncertainty` (0 if not given)
+    #[arg(long, global = true)]
+    pub seed: Option<u64>,
+
+    /// Linear drag coefficient k1, damping the particle by -k1·v; overrides the scene's own
+    #[arg(long, global = true, value_name = "K1")]
+    pub drag_linear: Option<f32>,
+
+    /// Quadratic drag coefficient k2, damping the particle by -k2·|v|·v; overrides the scene's own
+    #[arg(long, global = true, value_name = "K2")]
+    pub drag_quadratic: Option<f32>,
+
+    /// Charge of the test particle, repelled by bodies of the same sign; overrides the scene's own
+    #[arg(long, global = true, value_name = "Q")]
+    pub particle_charge: Option<f32>,
+
+    /// Coulomb constant k scaling the electrostatic force k·q·Q/r²; overrides the scene's own
+    #[arg(long, global = true, value_name = "K")]
+    pub coulomb_constant: Option<f32>,
+
+    /// Speed of light c of the first post-Newtonian correction to gravity, making orbits precess; overrides the scene's own
+    #[arg(long, global = true, value_name = "C", value_parser = parse_speed_of_light)]
+    pub speed_of_light: Option<f32>,
+
+    /// Bounce particles off bodies with this coefficient of restitution, from 0 to 1, instead of capturing them; overrides the scene's own
+    #[arg(long, global = true, value_name = "E", value_parser = parse_restitution)]
+    pub restitution: Option<f32>,
+
+    /// Uniform magnetic field B perpendicular to the plane, turning a charged particle by q·v×B; overrides the scene's own
+    #[arg(long, global = true, value_name = "B")]
+    pub magnetic_field: Option<f32>,
+
+    /// Constant background acceleration on the particle, as AX,AY; overrides the scene's own
+    #[arg(long, global = true, value_name = "AX,AY", value_parser = parse_numbers::<2>)]
+    pub external_field: Option<[f32; 2]>,
+
+    /// Tidal gradient of the background field, the symmetric tensor XX,XY,YY applied to the offset from the scene's field origin; overrides the scene's own
+    #[arg(long, global = true, value_name = "XX,XY,YY", value_parser = parse_numbers::<3>)]
+    pub tidal: Option<[f32; 3]>,
+
+    /// Acceleration of the particle's engine, for the thrust keys and --burn-duration
+    #[arg(long, global = true, default_value_t = THRUST_ACCELERATION)]
+    pub thrust: f32,
+
+    /// Fire the engine for this long (in simulated time) on every launch, starting --burn-start after it
+    #[arg(long, global = true, value_name = "TIME")]
+    pub burn_duration: Option<f32>,
+
+    /// Simulated time after launch at which the scheduled burn starts
+    #[arg(long, global = true, value_name = "TIME", default_value_t = 0.0)]
+    pub burn_start: f32,
+
+    /// Direction of the scheduled burn: prograde, retrograde or an angle in degrees (counterclockwise from +x)
+    #[arg(long, global = true, allow_negative_numbers = true, default_value = "prograde")]
+    pub burn_direction: ThrustDirection,
+
+    /// Render the default view at this resolution (e.g. 3840x2160) without opening a window, then exit
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_resolution)]
+    pub export: Option<(u32, u32)>,
+
+    /// Simulations per pixel along each axis for exports
+    #[arg(long, default_value_t = 2)]
+    pub supersample: u32,
+
+    /// Draw the bodies, with outlines, on top of exported images and animate/zoom-video frames
+    #[arg(long, global = true)]
+    pub export_bodies: bool,
+
+    /// Draw a legend strip explaining the colors along the bottom of exported images
+    #[arg(long)]
+    pub export_legend: bool,
+
+    /// Draw world coordinate gridlines, axis labels and a scale bar on exported images
+    #[arg(long)]
+    pub export_grid: bool,
+
+    /// Overlay each body's basin with its own hatch or stipple pattern in exported images, so basins can be told apart without color (e.g. printed in grayscale)
+    #[arg(long)]
+    pub export_patterns: bool,
+
+    /// Format of exported images and animate/zoom-video frames: png, webp (lossless), jpg or bmp. Without it, outputs named by you follow their extension and the rest are PNG
+    #[arg(long, global = true)]
+    pub image_format: Option<RasterFormat>,
+
+    /// JPEG quality from 1 to 100
+    #[arg(long, global = true, default_value_t = DEFAULT_QUALITY, value_parser = clap::value_parser!(u8).range(1..=100))]
+    pub quality: u8,
+
+    /// How exported maps are colored: body-time (body color, brighter for faster collisions) or combined (saturation also shows how many supersamples agree)
+    #[arg(long, default_value = "body-time")]
+    pub color_encoding: ColorEncoding,
+
+    /// Colors of the bodies' basins in exported maps: scene (each body's own color) or okabe-ito (a palette that stays distinguishable with color blindness)
+    #[arg(long, default_value = "scene")]
+    pub palette: BasinPalette,
+
+    /// Number of threads used to generate maps (default: one per core)
+    #[arg(long, global = true)]
+    pub threads: Option<usize>,
+
+    /// Generate maps at low priority and leave two cores free, so the window stays smooth
+    #[arg(long, global = true)]
+    pub background: bool,
+
+    /// Start with default settings instead of restoring the previous session
+    #[arg(long)]
+    pub fresh: bool,
+
+    /// Most points the viewer keeps of each live trajectory; longer ones are thinned out evenly
+    #[arg(long, default_value_t = TRAJECTORY_POINT_LIMIT, value_name = "POINTS")]
+    pub trail_points: usize,
+
+    /// Record the session's mouse, keyboard, gamepad and control panel input to this file, to play back with --replay-input
+    #[arg(long, value_name = "FILE", conflicts_with = "replay_input")]
+    pub record_input: Option<String>,
+
+    /// Play back a session recorded with --record-input, then hand control back
+    #[arg(long, value_name = "FILE")]
+    pub replay_input: Option<String>,
+
+    /// TOML or JSON file whose [keybindings] table remaps the viewer's main controls; defaults to the --scene file
+    #[arg(long)]
+    pub keybindings: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Render a sequence of maps while sweeping one parameter, without opening a window
+    Animate(AnimateArgs),
+    /// Render a smooth zoom into a point as numbered frames for ffmpeg, without opening a window
+    ZoomVideo(ZoomVideoArgs),
+    /// Simulate a single launch and write every timestep (t, x, y, vx, vy, energy) to CSV or JSON
+    Trajectory(TrajectoryArgs),
+    /// Render a small map for every combination of one or two swept parameters into a labeled montage
+    Sweep(SweepArgs),
+    /// Read launches from stdin as JSON lines ({"x", "y", "vx", "vy"}) and write their outcomes to stdout
+    Stream(StreamArgs),
+    /// Serve rendered basin maps over HTTP, e.g. GET /render?vx=0&vy=-20&zoom=2
+    Serve(ServeArgs),
+    /// Trace the basin boundaries of a map into polylines, written as GeoJSON or SVG
+    Boundaries(BoundariesArgs),
+    /// Write the raw collision time and body of every pixel of a map, as OpenEXR or 16-bit PNG
+    GridData(GridDataArgs),
+    /// Simulate every pixel of a map several times under random velocity kicks or jittered starts and color it by capture probability
+    Probability(ProbabilityArgs),
+    /// Estimate the uncertainty exponent of the initial view's basin boundaries from launch pairs at shrinking separations
+    Uncertainty(UncertaintyArgs),
+    /// Launch from one point at every angle and a range of speeds, mapping the outcome over angle and speed
+    Polar(PolarArgs),
+    /// Map the outcome of one launch over the masses of two bodies
+    MassMap(MassMapArgs),
+    /// Plot the collision time of one launch against a single swept parameter, one column per value
+    Bifurcation(BifurcationArgs),
+    /// Open the three-body stability explorer: a map of how long three mutually orbiting bodies last over the initial speeds of two of them
+    Stability(StabilityArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct AnimateArgs {
+    /// Parameter to sweep: velocity-angle (degrees), mass, position-x, position-y or zoom
+    #[arg(long)]
+    pub parameter: AnimatedParameter,
+
+    /// Value in the first frame
+    #[arg(long, allow_negative_numbers = true)]
+    pub from: f32,
+
+    /// Value in the last frame
+    #[arg(long, allow_negative_numbers = true)]
+    pub to: f32,
+
+    /// Number of frames
+    #[arg(long, default_value_t = 30)]
+    pub frames: usize,
+
+    /// Body whose mass or position is animated
+    #[arg(long, default_value_t = 0)]
+    pub body: usize,
+
+    /// Initial speed used when sweeping the velocity angle
+    #[arg(long, default_value_t = 20.0)]
+    pub speed: f32,
+
+    /// Size of each frame
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_resolution, default_value = "600x600")]
+    pub size: (u32, u32),
+
+    /// Directory the numbered frames are written to
+    #[arg(long, default_value = "animation")]
+    pub output: String,
+
+    /// Also combine the frames into an animated GIF
+    #[arg(long)]
+    pub gif: bool,
+
+    /// Milliseconds each GIF frame is shown for
+    #[arg(long, default_value_t = 100)]
+    pub frame_delay: u32,
+}
+
+#[derive(Args, Debug)]
+pub struct ZoomVideoArgs {
+    /// World x coordinate of the point to zoom into, e.g. a spot on a basin boundary
+    #[arg(long, allow_negative_numbers = true)]
+    pub x: f64,
+
+    /// World y coordinate of the point to zoom into
+    #[arg(long, allow_negative_numbers = true)]
+    pub y: f64,
+
+    /// Zoom in the first frame
+    #[arg(long, default_value_t = 1.0)]
+    pub start_zoom: f32,
+
+    /// Zoom in the last frame
+    #[arg(long, default_value_t = 1000.0)]
+    pub end_zoom: f32,
+
+    /// Number of frames
+    #[arg(long, default_value_t = 300)]
+    pub frames: usize,
+
+    /// Size of each frame
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_resolution, default_value = "1280x720")]
+    pub size: (u32, u32),
+
+    /// Directory the numbered frames are written to
+    #[arg(long, default_value = "zoom")]
+    pub output: String,
+}
+
+#[derive(Args, Debug)]
+pub struct TrajectoryArgs {
+    /// Starting x coordinate
+    #[arg(long, allow_negative_numbers = true)]
+    pub x: f32,
+
+    /// Starting y coordinate
+    #[arg(long, allow_negative_numbers = true)]
+    pub y: f32,
+
+    /// Initial x velocity
+    #[arg(long, allow_negative_numbers = true, default_value_t = 0.0)]
+    pub vx: f32,
+
+    /// Initial y velocity
+    #[arg(long, allow_negative_numbers = true, default_value_t = 0.0)]
+    pub vy: f32,
+
+    /// Use Euler integration instead of Runge-Kutta 4
+    #[arg(long)]
+    pub euler: bool,
+
+    /// Run the launch under every integrator and write their energy error over time (CSV) instead
+    #[arg(long, conflicts_with = "euler")]
+    pub energy_drift: bool,
+
+    /// Output file; written as JSON if it ends in .json, as an SVG drawing over the initial view if it ends in .svg, otherwise as CSV
+    #[arg(long, default_value = "trajectory.csv")]
+    pub output: String,
+}
+
+#[derive(Args, Debug)]
+pub struct BoundariesArgs {
+    /// Initial x velocity
+    #[arg(long, allow_negative_numbers = true, default_value_t = 0.0)]
+    pub vx: f32,
+
+    /// Initial y velocity
+    #[arg(long, allow_negative_numbers = true, default_value_t = 0.0)]
+    pub vy: f32,
+
+    /// Size of the map the boundaries are traced on
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_resolution, default_value = "1200x1200")]
+    pub size: (u32, u32),
+
+    /// Output file; written as an SVG if it ends in .svg, otherwise as GeoJSON in world coordinates
+    #[arg(long, default_value = "boundaries.geojson")]
+    pub output: String,
+}
+
+#[derive(Args, Debug)]
+pub struct GridDataArgs {
+    /// Initial x velocity
+    #[arg(long, allow_negative_numbers = true, default_value_t = 0.0)]
+    pub vx: f32,
+
+    /// Initial y velocity
+    #[arg(long, allow_negative_numbers = true, default_value_t = 0.0)]
+    pub vy: f32,
+
+    /// Size of the map
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_resolution, default_value = "1200x1200")]
+    pub size: (u32, u32),
+
+    /// Output file: .exr for float time and body channels, .png for 16-bit collision timesteps, .npy for <name>_body.npy and <name>_time.npy arrays
+    #[arg(long, default_value = "grid.exr")]
+    pub output: String,
+}
+
+#[derive(Args, Debug)]
+pub struct ProbabilityArgs {
+    /// Initial x velocity
+    #[arg(long, allow_negative_numbers = true, default_value_t = 0.0)]
+    pub vx: f32,
+
+    /// Initial y velocity
+    #[arg(long, allow_negative_numbers = true, default_value_t = 0.0)]
+    pub vy: f32,
+
+    /// Size of the map
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_resolution, default_value = "300x300")]
+    pub size: (u32, u32),
+
+    /// Samples simulated per pixel
+    #[arg(long, default_value_t = 16)]
+    pub samples: usize,
+
+    /// Strength of the Gaussian velocity kicks, the spread of velocity they build up per square root of simulated time (default: 2, or 0 with --jitter)
+    #[arg(long)]
+    pub noise: Option<f32>,
+
+    /// Start every sample at a random point within its pixel
+    #[arg(long)]
+    pub jitter: bool,
+
+    /// How pixels are colored: mix (body colors mixed by capture probability) or dominant (most common outcome, faded by the share of samples that disagree)
+    #[arg(long, default_value = "mix")]
+    pub coloring: ProbabilityColoring,
+
+    /// Output image
+    #[arg(long, default_value = "probability.png")]
+    pub output: String,
+}
+
+#[derive(Args, Debug)]
+pub struct UncertaintyArgs {
+    /// Initial x velocity
+    #[arg(long, allow_negative_numbers = true, default_value_t = 0.0)]
+    pub vx: f32,
+
+    /// Initial y velocity
+    #[arg(long, allow_negative_numbers = true, default_value_t = 0.0)]
+    pub vy: f32,
+
+    /// Pairs of launches compared at every separation
+    #[arg(long, default_value_t = 2000)]
+    pub pairs: usize,
+
+    /// Largest separation between the launches of a pair, in world units
+    #[arg(long, default_value_t = 10.0)]
+    pub largest: f32,
+
+    /// Smallest separation between the launches of a pair, in world units
+    #[arg(long, default_value_t = 0.001)]
+    pub smallest: f32,
+
+    /// Number of separations, spaced evenly in log from the largest to the smallest
+    #[arg(long, default_value_t = 9)]
+    pub steps: usize,
+
+    /// Also write the fit and its points to this file, as JSON if it ends in .json, otherwise as CSV
+    #[arg(long)]
+    pub output: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct PolarArgs {
+    /// Starting x coordinate shared by every launch
+    #[arg(long, allow_negative_numbers = true)]
+    pub x: f32,
+
+    /// Starting y coordinate shared by every launch
+    #[arg(long, allow_negative_numbers = true)]
+    pub y: f32,
+
+    /// Speed along the bottom edge of the map
+    #[arg(long, default_value_t = 0.0)]
+    pub min_speed: f32,
+
+    /// Speed along the top edge of the map
+    #[arg(long, default_value_t = 60.0)]
+    pub max_speed: f32,
+
+    /// Size of the map, without the axis labels around it
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_resolution, default_value = "720x360")]
+    pub size: (u32, u32),
+
+    /// Use Euler integration instead of Runge-Kutta 4
+    #[arg(long)]
+    pub euler: bool,
+
+    /// Output image
+    #[arg(long, default_value = "polar.png")]
+    pub output: String,
+}
+
+#[derive(Args, Debug)]
+pub struct MassMapArgs {
+    /// Starting x coordinate of the launch
+    #[arg(long, allow_negative_numbers = true)]
+    pub x: f32,
+
+    /// Starting y coordinate of the launch
+    #[arg(long, allow_negative_numbers = true)]
+    pub y: f32,
+
+    /// Initial x velocity
+    #[arg(long, allow_negative_numbers = true, default_value_t = 0.0)]
+    pub vx: f32,
+
+    /// Initial y velocity
+    #[arg(long, allow_negative_numbers = true, default_value_t = 0.0)]
+    pub vy: f32,
+
+    /// Body whose mass varies across the map
+    #[arg(long, default_value_t = 0)]
+    pub body_x: usize,
+
+    /// Body whose mass varies up the map
+    #[arg(long, default_value_t = 1)]
+    pub body_y: usize,
+
+    /// Masses of --body-x at the left and right edges
+    #[arg(long, value_name = "FROM,TO", value_parser = parse_numbers::<2>, default_value = "10000,80000")]
+    pub mass_x: [f32; 2],
+
+    /// Masses of --body-y at the bottom and top edges
+    #[arg(long, value_name = "FROM,TO", value_parser = parse_numbers::<2>, default_value = "10000,80000")]
+    pub mass_y: [f32; 2],
+
+    /// Size of the map, without the axis labels around it
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_resolution, default_value = "400x400")]
+    pub size: (u32, u32),
+
+    /// Use Euler integration instead of Runge-Kutta 4
+    #[arg(long)]
+    pub euler: bool,
+
+    /// Output image
+    #[arg(long, default_value = "masses.png")]
+    pub output: String,
+}
+
+#[derive(Args, Debug)]
+pub struct BifurcationArgs {
+    /// Parameter varied across the plot: speed, angle (degrees), velocity-x, velocity-y, mass, position-x, position-y or g
+    #[arg(long)]
+    pub parameter: SweepParameter,
+
+    /// Value at the left edge
+    #[arg(long, allow_negative_numbers = true)]
+    pub from: f32,
+
+    /// Value at the right edge
+    #[arg(long, allow_negative_numbers = true)]
+    pub to: f32,
+
+    /// Number of values, one column each
+    #[arg(long, default_value_t = 600)]
+    pub steps: usize,
+
+    /// Body whose mass or position is swept
+    #[arg(long, default_value_t = 0)]
+    pub body: usize,
+
+    /// Starting x coordinate of the launch
+    #[arg(long, allow_negative_numbers = true)]
+    pub x: f32,
+
+    /// Starting y coordinate of the launch
+    #[arg(long, allow_negative_numbers = true)]
+    pub y: f32,
+
+    /// Initial x velocity, unless swept
+    #[arg(long, allow_negative_numbers = true, default_value_t = 0.0)]
+    pub vx: f32,
+
+    /// Initial y velocity, unless swept
+    #[arg(long, allow_negative_numbers = true, default_value_t = 0.0)]
+    pub vy: f32,
+
+    /// Height of the plot, without the axis labels around it
+    #[arg(long, default_value_t = 300)]
+    pub height: u32,
+
+    /// Use Euler integration instead of Runge-Kutta 4
+    #[arg(long)]
+    pub euler: bool,
+
+    /// Output image
+    #[arg(long, default_value = "bifurcation.png")]
+    pub output: String,
+}
+
+#[derive(Args, Debug)]
+pub struct StabilityArgs {
+    /// Size of the square stability map, shown next to an equally large view of the clicked launch
+    #[arg(long, default_value_t = STABILITY_MAP_SIZE)]
+    pub size: u32,
+
+    /// File the stability map is cached in; generated when missing or of a different size
+    #[arg(long, default_value = "stability.png")]
+    pub output: String,
+}
+
+#[derive(Args, Debug)]
+pub struct SweepArgs {
+    /// Parameter varied across the columns: speed, angle (degrees), velocity-x, velocity-y, mass, position-x, position-y or g
+    #[arg(long)]
+    pub parameter: SweepParameter,
+
+    /// Value in the first column
+    #[arg(long, allow_negative_numbers = true)]
+    pub from: f32,
+
+    /// Value in the last column
+    #[arg(long, allow_negative_numbers = true)]
+    pub to: f32,
+
+    /// Number of columns
+    #[arg(long, default_value_t = 5)]
+    pub steps: usize,
+
+    /// Optional second parameter varied down the rows
+    #[arg(long)]
+    pub rows_parameter: Option<SweepParameter>,
+
+    /// Value in the first row
+    #[arg(long, allow_negative_numbers = true, default_value_t = 0.0)]
+    pub rows_from: f32,
+
+    /// Value in the last row
+    #[arg(long, allow_negative_numbers = true, default_value_t = 0.0)]
+    pub rows_to: f32,
+
+    /// Number of rows
+    #[arg(long, default_value_t = 5)]
+    pub rows_steps: usize,
+
+    /// Body whose mass or position is swept
+    #[arg(long, default_value_t = 0)]
+    pub body: usize,
+
+    /// Size of each map in the montage
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_resolution, default_value = "200x200")]
+    pub cell_size: (u32, u32),
+
+    /// Montage image to write
+    #[arg(long, default_value = "sweep.png")]
+    pub output: String,
+}
+
+#[derive(Args, Debug)]
+pub struct StreamArgs {
+    /// Use Euler integration instead of Runge-Kutta 4
+    #[arg(long)]
+    pub euler: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ServeArgs {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    pub address: String,
+
+    /// Also stream live trajectories over WebSocket on this address, e.g. 127.0.0.1:8081
+    #[arg(long)]
+    pub websocket: Option<String>,
+}
+
+impl Cli {
+    pub fn simulation_params(&self) -> SimulationParams {
+        SimulationParams {
+            timesteps: self.timesteps.max(1),
+            substeps: self.substeps.max(1),
+            collision_threshold: self.collision_threshold.max(0.0),
+            dt: self.dt.max(1e-5),
+            double_precision: self.f64,
+            opening_angle: self.opening_angle.max(0.0),
+            barnes_hut_bodies: self.barnes_hut_bodies,
+            collision_policy: self.collision_policy,
+        }
+    }
+
+    pub fn image_output(&self) -> ImageOutput {
+        ImageOutput { format: self.image_format, quality: self.quality }
+    }
+
+    // The initial view for a scene with the map options given on the command line
+    pub fn initial_view(&self, scene: &Scene, width: u32, height: u32) -> MapParams {
+        MapParams { escape_coloring: self.escape_coloring, ..MapParams::initial_view(scene, self.simulation_params(), width, height) }
+    }
+
+    // Burns every launch fires, from --burn-duration
+    pub fn scheduled_burns(&self, params: &SimulationParams) -> Vec<Burn> {
+        self.burn_duration
+            .map(|duration| Burn::scheduled(self.burn_start, duration, Thrust::new(self.thrust, self.burn_direction), params))
+            .into_iter()
+            .collect()
+    }
+
+    // Viewer controls from --keybindings, or else the scene file, with the defaults for any the
+    // file leaves out or if it can't be read
+    pub fn keybindings(&self) -> Keybindings {
+        let Some(path) = self.keybindings.as_ref().or(self.scene.as_ref()) else {
+            return Keybindings::default();
+        };
+        Keybindings::load(path).unwrap_or_else(|e| {
+            eprintln!("Error loading keybindings from {}: {}", path, e);
+            Keybindings::default()
+        })
+    }
+
+    // Scene requested on the command line, if any: a scene file, a preset or a random scene, with
+    // any drag, charge, speed of light, magnetic or external field given on the command line,
+    // which alone apply to the default scene.
+    pub fn requested_scene(&self) -> Option<Scene> {
+        let mut scene = None;
+        if let Some(path) = &self.scene {
+            match Scene::load(path) {
+                Ok(loaded) => scene = Some(loaded),
+                Err(e) => eprintln!("Error loading scene {}: {}", path, e),
+            }
+        }
+        if let Some(preset) = self.preset {
+            scene = Some(preset.scene());
+        }
+        if let Some(count) = self.random_scene {
+            let seed = self.seed.unwrap_or_else(rand::random);
+            // On stderr so it never mixes with the output of `stream`
+            eprintln!("Generated random scene with {} bodies (seed {})", count, seed);
+            scene = Some(Scene::random(seed, count));
+        }
+        let overrides = [self.drag_linear, self.drag_quadratic, self.particle_charge, self.coulomb_constant, self.speed_of_light, self.restitution, self.magnetic_field];
+        if overrides.iter().all(Option::is_none) && self.external_field.is_none() && self.tidal.is_none() {
+            return scene;
+        }
+        let mut scene = scene.unwrap_or_default();
+        scene.drag.linear = self.drag_linear.unwrap_or(scene.drag.linear);
+        scene.drag.quadratic = self.drag_quadratic.unwrap_or(scene.drag.quadratic);
+        scene.particle_charge = self.particle_charge.unwrap_or(scene.particle_charge);
+        scene.coulomb_constant = self.coulomb_constant.unwrap_or(scene.coulomb_constant);
+        scene.speed_of_light = self.speed_of_light.or(scene.speed_of_light);
+        scene.restitution = self.restitution.or(scene.restitution);
+        scene.magnetic_field = self.magnetic_field.unwrap_or(scene.magnetic_field);
+        if let Some([x, y]) = self.external_field {
+            scene.external_field.acceleration = Vec2::new(x, y);
+        }
+        scene.external_field.tidal = self.tidal.unwrap_or(scene.external_field.tidal);
+        Some(scene)
+    }
+}
+
+// Parses N comma-separated numbers
+fn parse_numbers<const N: usize>(s: &str) -> Result<[f32; N], String> {
+    let numbers: Vec<f32> = s.split(',')
+        .map(|part| part.trim().parse().map_err(|_| format!("invalid number '{}'", part)))
+        .collect::<Result<_, _>>()?;
+    numbers.try_into().map_err(|numbers: Vec<f32>| format!("expected {} comma-separated numbers, got {}", N, numbers.len()))
+}
+
+fn parse_speed_of_light(s: &str) -> Result<f32, String> {
+    let c: f32 = s.parse().map_err(|_| format!("invalid number '{}'", s))?;
+    if c <= 0.0 {
+        return Err("the speed of light must be positive".to_string());
+    }
+    Ok(c)
+}
+
+fn parse_restitution(s: &str) -> Result<f32, String> {
+    let restitution: f32 = s.parse().map_err(|_| format!("invalid number '{}'", s))?;
+    if !(0.0..=1.0).contains(&restitution) {
+        return Err("the restitution must be between 0 and 1".to_string());
+    }
+    Ok(restitution)
+}
+
+// Parses resolutions written as WIDTHxHEIGHT
+fn parse_resolution(s: &str) -> Result<(u32, u32), String> {
+    let (width, height) = s.split_once('x').ok_or_else(|| format!("expected WIDTHxHEIGHT, got '{}'", s))?;
+    let width: u32 = width.parse().map_err(|_| format!("invalid width '{}'", width))?;
+    let height: u32 = height.parse().map_err(|_| format!("invalid height '{}'", height))?;
+    if width == 0 || height == 0 {
+        return Err("resolution must be at least 1x1".to_string());
+    }
+    Ok((width, height))
+}
@@ -1,8 +1,16 @@
-use crate::physics::{Vec2, StationaryBody};
+use crate::physics::{Vec2, Body};
 
+// Initial window size; maps are generated at whatever size the window currently has
 pub const IMAGE_SIZE: u32 = 600;
 pub const DEFAULT_NON_COLLISION_COLOR: [u8; 3] = [20, 20, 20]; // Dark gray
 
+// Constraints for randomly generated scenes
+pub const RANDOM_SCENE_BODIES: usize = 4;
+pub const RANDOM_MASS_RANGE: (f32, f32) = (10000.0, 60000.0);
+pub const RANDOM_MIN_SEPARATION: f32 = 120.0;
+// Distance kept from the edges of the default view
+pub const RANDOM_EDGE_MARGIN: f32 = 60.0;
+
 // Configuration for the gravity wells
 pub const STATIONARY_BODIES_CONFIG: &[(f32, f32, f32, [u8; 3])] = &[
     // (x, y, mass, color_rgb)
@@ -11,16 +19,28 @@ pub const STATIONARY_BODIES_CONFIG: &[(f32, f32, f32, [u8; 3])] = &[
     (300.0, 400.0, 40000.0, [100, 100, 255]), // Blue well
 ];
 
-pub fn create_stationary_bodies() -> Vec<StationaryBody> {
+pub fn create_stationary_bodies() -> Vec<Body> {
     STATIONARY_BODIES_CONFIG
         .iter()
         .map(|(x, y, mass, color)| {
-            StationaryBody::new(
+            Body::new(
                 Vec2::new(*x, *y),
                 *mass,
-                (*mass / 1000.0).sqrt().max(10.0), // Radius based on mass
+                body_radius(*mass),
                 *color,
             )
         })
         .collect()
 }
+
+// Color captures by a black hole's horizon are mixed with, half and half, to tell them apart
+// from collisions with the surface of an ordinary body
+pub const HORIZON_TINT: [u8; 3] = [120, 0, 200];
+
+// Width rings and segments are drawn with, in pixels
+pub const BODY_STROKE_WIDTH: f32 = 4.0;
+
+// Drawn radius of a body, based on its mass
+pub fn body_radius(mass: f32) -> f32 {
+    (mass / 1000.0).sqrt().max(10.0)
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use serde_json::{json, Value};
+
+use crate::image_gen::MapParams;
+use crate::outcome::OutcomeGrid;
+use crate::physics::Vec2;
+
+// Outline of one basin: a polyline in grid pixel coordinates, closed (first point repeated at
+// the end) unless it runs off the edge of the grid. Each boundary between two basins is traced
+// once for each side, so every basin's outlines are complete on their own.
+#[derive(Clone, Debug)]
+pub struct BasinBoundary {
+    // Body whose basin this outlines, None for the region that never collides
+    pub body: Option<usize>,
+    pub points: Vec<Vec2>,
+}
+
+impl BasinBoundary {
+    pub fn is_closed(&self) -> bool {
+        self.points.len() > 2 && self.points.first() == self.points.last()
+    }
+}
+
+// Points are kept at twice their pixel coordinates while tracing, where every sample (a pixel
+// center) and every edge midpoint lands on an integer, so segment ends can be matched exactly
+type Point2 = (i32, i32);
+
+// Segments through one square of samples, for the corners inside the basin given as bits
+// (1 = top left, 2 = top right, 4 = bottom right, 8 = bottom left) and the edges as
+// 0 = top, 1 = right, 2 = bottom, 3 = left. Segments keep the basin on their left as seen on
+// screen, so each edge midpoint starts one segment and ends another. The saddles
+// (5 and 10) are split so diagonal corners stay separate.
+const CASES: [&[(u8, u8)]; 16] = [
+    &[],
+    &[(3, 0)],
+    &[(0, 1)],
+    &[(3, 1)],
+    &[(1, 2)],
+    &[(3, 0), (1, 2)],
+    &[(0, 2)],
+    &[(3, 2)],
+    &[(2, 3)],
+    &[(2, 0)],
+    &[(0, 1), (2, 3)],
+    &[(2, 1)],
+    &[(1, 3)],
+    &[(1, 0)],
+    &[(0, 3)],
+    &[],
+];
+
+// Marching squares over the pixel centers of `grid`, once for every basin that meets a square
+// whose corners disagree, with the segments chained into polylines
+pub fn extract_boundaries(grid: &OutcomeGrid) -> Vec<BasinBoundary> {
+    let label = |px: u32, py: u32| grid.get(px, py).map(|(index, _)| index);
+    let mut segments: HashMap<Option<usize>, Vec<(Point2, Point2)>> = HashMap::new();
+
+    for py in 0..grid.height.saturating_sub(1) {
+        for px in 0..grid.width.saturating_sub(1) {
+            let corners = [label(px, py), label(px + 1, py), label(px + 1, py + 1), label(px, py + 1)];
+            if corners.iter().all(|&corner| corner == corners[0]) {
+                continue;
+            }
+            let (x, y) = (2 * px as i32 + 1, 2 * py as i32 + 1);
+            let midpoint = |edge: u8| match edge {
+                0 => (x + 1, y),
+                1 => (x + 2, y + 1),
+                2 => (x + 1, y + 2),
+                _ => (x, y + 1),
+            };
+            let mut seen = Vec::with_capacity(4);
+            for &basin in &corners {
+                if seen.contains(&basin) {
+                    continue;
+                }
+                seen.push(basin);
+                let case = corners.iter().enumerate().fold(0, |case, (i, &corner)| case | (((corner == basin) as usize) << i));
+                for &(from, to) in CASES[case] {
+                    segments.entry(basin).or_default().push((midpoint(from), midpoint(to)));
+                }
+            }
+        }
+    }
+
+    let mut boundaries = Vec::new();
+    let mut basins: Vec<Option<usize>> = segments.keys().copied().collect();
+    basins.sort();
+    for basin in basins {
+        for points in chain_segments(&segments[&basin]) {
+            boundaries.push(BasinBoundary {
+                body: basin,
+                points: points.into_iter().map(|(x, y)| Vec2::new(x as f32 / 2.0, y as f32 / 2.0)).collect(),
+            });
+        }
+    }
+    boundaries
+}
+
+// Joins directed segments end to start into polylines. Every point has at most one segment
+// leaving and one arriving, so each chain is followed forward to its end or back to its start,
+// then backward from its start when it turned out to be open.
+fn chain_segments(segments: &[(Point2, Point2)]) -> Vec<Vec<Point2>> {
+    let mut leaving: HashMap<Point2, usize> = HashMap::new();
+    let mut arriving: HashMap<Point2, usize> = HashMap::new();
+    for (i, &(from, to)) in segments.iter().enumerate() {
+        leaving.insert(from, i);
+        arriving.insert(to, i);
+    }
+
+    let mut used = vec![false; segments.len()];
+    let mut chains = Vec::new();
+    for first in 0..segments.len() {
+        if used[first] {
+            continue;
+        }
+        used[first] = true;
+        let mut chain = vec![segments[first].0, segments[first].1];
+        while let Some(&next) = leaving.get(chain.last().unwrap()) {
+            if used[next] {
+                break;
+            }
+            used[next] = true;
+            chain.push(segments[next].1);
+        }
+        if chain.first() != chain.last() {
+            let mut prefix = Vec::new();
+            let mut start = chain[0];
+            while let Some(&previous) = arriving.get(&start) {
+                if used[previous] {
+                    break;
+                }
+                used[previous] = true;
+                start = segments[previous].0;
+                prefix.push(start);
+            }
+            prefix.reverse();
+            prefix.extend(chain);
+            chain = prefix;
+        }
+        chains.push(chain);
+    }
+    chains
+}
+
+// Writes the boundaries as a GeoJSON FeatureCollection of LineStrings in world coordinates, each
+// with the body whose basin it outlines (null for the region that never collides)
+pub fn save_geojson(path: &str, boundaries: &[BasinBoundary], grid: &OutcomeGrid, params: &MapParams) -> Result<(), Box<dyn std::error::Error>> {
+    // Grid pixels to world coordinates, for grids at a different resolution than the view
+    let scale = params.width as f32 / grid.width.max(1) as f32;
+    let features: Vec<Value> = boundaries.iter()
+        .map(|boundary| {
+            let coordinates: Vec<[f32; 2]> = boundary.points.iter()
+                .map(|&p| {
+                    let world = p * scale / params.zoom_factor - params.camera_offset;
+                    [world.x, world.y]
+                })
+                .collect();
+            json!({
+                "type": "Feature",
+                "properties": { "body": boundary.body, "closed": boundary.is_closed() },
+                "geometry": { "type": "LineString", "coordinates": coordinates },
+            })
+        })
+        .collect();
+    let collection = json!({ "type": "FeatureCollection", "features": features });
+    std::fs::write(path, serde_json::to_string(&collection)?)?;
+    println!("{} basin boundaries saved to {}", boundaries.len(), path);
+    Ok(())
+}
@@ -0,0 +1,501 @@
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use serde::{Deserialize, Serialize};
+
+use crate::image_gen::MapParams;
+use crate::physics::Vec2;
+use crate::precise::{integrate_from, magnetic_turn, DVec2, PreciseScene};
+use crate::scene::Scene;
+use crate::simulation::{IntegrationMethod, SimulationParams, TEST_PARTICLE_MASS};
+
+// Zoom from which views carry a double-double camera and pixels are simulated by perturbation.
+// Below it the f32 camera resolves neighbouring pixels fine and the SIMD path is much faster.
+pub const DEEP_ZOOM_THRESHOLD: f32 = 1.0e3;
+
+// Deepest zoom the viewer allows; double-double coordinates run out around 1e28
+pub const MAX_ZOOM: f32 = 1.0e24;
+
+// Once a pixel's offset from the reference orbit grows past this, plain f64 resolves it and the
+// pixel is simulated on its own from there
+const REBASE_DISTANCE: f64 = 1e-6;
+
+// A number stored as the unevaluated sum of two f64s, giving roughly 32 significant digits
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DoubleDouble {
+    pub hi: f64,
+    pub lo: f64,
+}
+
+// Exact sum of two f64s as the rounded sum plus its rounding error
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    let bb = s - a;
+    (s, (a - (s - bb)) + (b - bb))
+}
+
+// two_sum for |a| >= |b|
+fn quick_two_sum(a: f64, b: f64) -> DoubleDouble {
+    let s = a + b;
+    DoubleDouble { hi: s, lo: b - (s - a) }
+}
+
+// Exact product of two f64s as the rounded product plus its rounding error
+fn two_prod(a: f64, b: f64) -> (f64, f64) {
+    let p = a * b;
+    (p, a.mul_add(b, -p))
+}
+
+impl DoubleDouble {
+    pub fn from_f64(value: f64) -> Self {
+        Self { hi: value, lo: 0.0 }
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.hi + self.lo
+    }
+
+    pub fn add_f64(self, value: f64) -> Self {
+        self + Self::from_f64(value)
+    }
+
+    pub fn sqrt(self) -> Self {
+        if self.hi <= 0.0 {
+            return Self::from_f64(0.0);
+        }
+        // One Newton step from the f64 square root doubles the precision
+        let x = self.hi.sqrt();
+        let (xx, xx_err) = two_prod(x, x);
+        let residual = (self - DoubleDouble { hi: xx, lo: xx_err }).to_f64();
+        quick_two_sum(x, residual / (2.0 * x))
+    }
+}
+
+impl Add for DoubleDouble {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output {
+        let (s, e) = two_sum(self.hi, rhs.hi);
+        quick_two_sum(s, e + self.lo + rhs.lo)
+    }
+}
+
+impl Sub for DoubleDouble {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self::Output {
+        self + -rhs
+    }
+}
+
+impl Neg for DoubleDouble {
+    type Output = Self;
+    fn neg(self) -> Self::Output {
+        Self { hi: -self.hi, lo: -self.lo }
+    }
+}
+
+impl Mul for DoubleDouble {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self::Output {
+        let (p, e) = two_prod(self.hi, rhs.hi);
+        quick_two_sum(p, e + self.hi * rhs.lo + self.lo * rhs.hi)
+    }
+}
+
+impl Mul<f64> for DoubleDouble {
+    type Output = Self;
+    fn mul(self, rhs: f64) -> Self::Output {
+        let (p, e) = two_prod(self.hi, rhs);
+        quick_two_sum(p, e + self.lo * rhs)
+    }
+}
+
+impl Div for DoubleDouble {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self::Output {
+        // Long division, one f64 quotient digit at a time
+        let q1 = self.hi / rhs.hi;
+        let r = self - rhs * q1;
+        let q2 = r.hi / rhs.hi;
+        let r = r - rhs * q2;
+        let q3 = r.hi / rhs.hi;
+        quick_two_sum(q1, q2).add_f64(q3)
+    }
+}
+
+// A point or vector in double-double precision: the camera of a deep zoom, and the reference
+// orbit its pixels are simulated around
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeepPoint {
+    pub x: DoubleDouble,
+    pub y: DoubleDouble,
+}
+
+impl DeepPoint {
+    pub fn from_vec2(v: Vec2) -> Self {
+        Self::from_dvec2(v.into())
+    }
+
+    pub fn from_dvec2(v: DVec2) -> Self {
+        Self { x: DoubleDouble::from_f64(v.x), y: DoubleDouble::from_f64(v.y) }
+    }
+
+    pub fn to_vec2(self) -> Vec2 {
+        Vec2::new(self.x.to_f64() as f32, self.y.to_f64() as f32)
+    }
+
+    pub fn to_dvec2(self) -> DVec2 {
+        DVec2::new(self.x.to_f64(), self.y.to_f64())
+    }
+
+    pub fn offset(self, dx: f64, dy: f64) -> Self {
+        Self { x: self.x.add_f64(dx), y: self.y.add_f64(dy) }
+    }
+
+    fn length_squared(self) -> DoubleDouble {
+        self.x * self.x + self.y * self.y
+    }
+
+    // Hash of the exact coordinates, so cached maps of nearby deep views don't collide
+    pub fn cache_key(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for value in [self.x.hi, self.x.lo, self.y.hi, self.y.lo] {
+            for byte in value.to_bits().to_le_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+        hash
+    }
+}
+
+impl Add for DeepPoint {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output {
+        Self { x: self.x + rhs.x, y: self.y + rhs.y }
+    }
+}
+
+impl Sub for DeepPoint {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self { x: self.x - rhs.x, y: self.y - rhs.y }
+    }
+}
+
+impl Mul<DoubleDouble> for DeepPoint {
+    type Output = Self;
+    fn mul(self, rhs: DoubleDouble) -> Self::Output {
+        Self { x: self.x * rhs, y: self.y * rhs }
+    }
+}
+
+impl Mul<f64> for DeepPoint {
+    type Output = Self;
+    fn mul(self, rhs: f64) -> Self::Output {
+        Self { x: self.x * rhs, y: self.y * rhs }
+    }
+}
+
+// Derivative of the acceleration with respect to position, as [xx, xy, yy] (it is symmetric)
+#[derive(Clone, Copy)]
+struct Jacobian([f64; 3]);
+
+impl Jacobian {
+    fn at(scene: &PreciseScene, pos: DVec2) -> Self {
+        let mut j = [0.0; 3];
+        for body in &scene.bodies {
+            let s = body.pos - pos;
+            let r2 = s.x * s.x + s.y * s.y;
+            if r2 == 0.0 {
+                continue;
+            }
+            let r = r2.sqrt();
+            let gm = scene.attraction(body);
+            let inv_r3 = gm / (r2 * r);
+            let inv_r5 = 3.0 * gm / (r2 * r2 * r);
+            j[0] += inv_r5 * s.x * s.x - inv_r3;
+            j[1] += inv_r5 * s.x * s.y;
+            j[2] += inv_r5 * s.y * s.y - inv_r3;
+        }
+        // The tidal term is linear in the position, so its derivative is the tensor itself
+        if let Some((_, tidal, _)) = scene.external_field {
+            for (total, value) in j.iter_mut().zip(tidal) {
+                *total += value;
+            }
+        }
+        Jacobian(j)
+    }
+
+    // Derivative of the drag with respect to velocity, the only part of the acceleration that
+    // depends on it
+    fn drag(scene: &PreciseScene, vel: DVec2) -> Self {
+        let (linear, quadratic) = scene.drag;
+        let speed = vel.length();
+        if speed == 0.0 {
+            return Jacobian([-linear, 0.0, -linear]);
+        }
+        let c = linear + quadratic * speed;
+        let q = quadratic / speed;
+        Jacobian([-c - q * vel.x * vel.x, -q * vel.x * vel.y, -c - q * vel.y * vel.y])
+    }
+
+    fn apply(&self, d: DVec2) -> DVec2 {
+        let [xx, xy, yy] = self.0;
+        DVec2::new(xx * d.x + xy * d.y, xy * d.x + yy * d.y)
+    }
+}
+
+// How far a point of the reference orbit is outside a body's collision radius. Pixels graze
+// the collision radius at offsets far below f64 resolution, so the squared distance is kept in
+// double-double rather than testing `pos + offset` directly.
+#[derive(Clone, Copy)]
+struct BodyGap {
+    // Squared distance to the body minus the squared collision threshold
+    gap: DoubleDouble,
+    // Reference position relative to the body
+    relative: DVec2,
+}
+
+impl BodyGap {
+    fn new(pos: DeepPoint, body: DeepPoint, threshold: DoubleDouble) -> Self {
+        let relative = pos - body;
+        Self { gap: relative.length_squared() - threshold * threshold, relative: relative.to_dvec2() }
+    }
+
+    // Whether the reference position moved by `offset` is inside the collision radius
+    fn collides(&self, offset: DVec2) -> bool {
+        let change = 2.0 * (self.relative.x * offset.x + self.relative.y * offset.y) + offset.x * offset.x + offset.y * offset.y;
+        self.gap.hi + (self.gap.lo + change) < 0.0
+    }
+}
+
+// Bodies of a scene with the strength of their inverse-square pull (G * mass, less any Coulomb push), for
+// integrating the reference orbit in double-double
+struct DeepScene {
+    bodies: Vec<(DeepPoint, DoubleDouble)>,
+    // Particle charge times the magnetic field over its mass, if the particle feels one
+    magnetic: Option<DoubleDouble>,
+    // Linear and quadratic drag coefficients, if the scene has drag
+    drag: Option<(DoubleDouble, DoubleDouble)>,
+    // Constant acceleration, tidal tensor [xx, xy, yy] and its origin, if the scene has an external field
+    external_field: Option<(DeepPoint, [DoubleDouble; 3], DeepPoint)>,
+}
+
+impl DeepScene {
+    fn new(scene: &Scene) -> Self {
+        let g = DoubleDouble::from_f64(scene.gravitational_constant as f64);
+        // The Coulomb term is inverse-square too, so it folds into each body's G * mass
+        let coulomb = if scene.is_charged() {
+            DoubleDouble::from_f64(scene.coulomb_constant as f64) * DoubleDouble::from_f64(scene.particle_charge as f64)
+                / DoubleDouble::from_f64(TEST_PARTICLE_MASS as f64)
+        } else {
+            DoubleDouble::from_f64(0.0)
+        };
+        Self {
+            bodies: scene.bodies.iter()
+                .map(|body| {
+                    let strength = g * DoubleDouble::from_f64(body.mass as f64) - coulomb * DoubleDouble::from_f64(body.charge as f64);
+                    (DeepPoint::from_vec2(body.pos), strength)
+                })
+                .collect(),
+            magnetic: scene.is_magnetized().then(|| {
+                DoubleDouble::from_f64(scene.particle_charge as f64) * DoubleDouble::from_f64(scene.magnetic_field as f64)
+                    / DoubleDouble::from_f64(TEST_PARTICLE_MASS as f64)
+            }),
+            drag: scene.drag.is_active().then(|| {
+                (DoubleDouble::from_f64(scene.drag.linear as f64), DoubleDouble::from_f64(scene.drag.quadratic as f64))
+            }),
+            external_field: scene.external_field.is_active().then(|| {
+                let field = &scene.external_field;
+                (DeepPoint::from_vec2(field.acceleration), field.tidal.map(|value| DoubleDouble::from_f64(value as f64)), DeepPoint::from_vec2(field.origin))
+            }),
+        }
+    }
+
+    // Same physics as calculate_acceleration; the test particle's mass cancels out
+    fn acceleration(&self, pos: DeepPoint, vel: DeepPoint) -> DeepPoint {
+        let mut total = DeepPoint::from_dvec2(DVec2::new(0.0, 0.0));
+        for &(body, gm) in &self.bodies {
+            let s = body - pos;
+            let r2 = s.length_squared();
+            if r2.hi == 0.0 {
+                continue;
+            }
+            total = total + s * (gm / (r2 * r2.sqrt()));
+        }
+        if let Some(magnetic) = self.magnetic {
+            total = total + DeepPoint { x: vel.y * magnetic, y: -vel.x * magnetic };
+        }
+        if let Some((constant, [xx, xy, yy], origin)) = self.external_field {
+            let d = pos - origin;
+            total = total + constant + DeepPoint { x: xx * d.x + xy * d.y, y: xy * d.x + yy * d.y };
+        }
+        match self.drag {
+            Some((linear, quadratic)) => total - vel * (linear + quadratic * vel.length_squared().sqrt()),
+            None => total,
+        }
+    }
+}
+
+// Trajectory of the particle launched from the view center, integrated in double-double so its
+// own rounding stays far below the pixel spacing, with the Jacobian at every point the
+// integrator evaluated the acceleration at. Pixels are integrated as small offsets from it.
+struct ReferenceOrbit {
+    start: (DeepPoint, DeepPoint),
+    // Position and velocity after each substep, until the reference collides or the simulation ends
+    states: Vec<(DeepPoint, DeepPoint)>,
+    // One Jacobian per substep for Euler, four (one per stage) for RK4
+    jacobians: Vec<Jacobian>,
+    // Velocity Jacobians of the drag at the same points, empty for scenes without drag
+    drag_jacobians: Vec<Jacobian>,
+    // Turn of the magnetic field per unit velocity, which is the same everywhere, 0 without one
+    magnetic: f64,
+    // One gap per body per substep
+    gaps: Vec<BodyGap>,
+}
+
+impl ReferenceOrbit {
+    fn new(scene: &Scene, precise_scene: &PreciseScene, start: DeepPoint, velocity: DeepPoint,
+           method: IntegrationMethod, params: &SimulationParams) -> Self {
+        let deep_scene = DeepScene::new(scene);
+        let dt = params.dt as f64 / params.substeps as f64;
+        let threshold = DoubleDouble::from_f64(params.collision_threshold as f64);
+        let (mut pos, mut vel) = (start, velocity);
+        let mut states = Vec::new();
+        let mut jacobians = Vec::new();
+        let mut drag_jacobians = Vec::new();
+        let mut gaps = Vec::new();
+
+        for _ in 0..params.timesteps * params.substeps {
+            let mut jacobian_at = |p: DeepPoint, v: DeepPoint| {
+                jacobians.push(Jacobian::at(precise_scene, p.to_dvec2()));
+                if deep_scene.drag.is_some() {
+                    drag_jacobians.push(Jacobian::drag(precise_scene, v.to_dvec2()));
+                }
+            };
+            match method {
+                IntegrationMethod::Euler => {
+                    jacobian_at(pos, vel);
+                    vel = vel + deep_scene.acceleration(pos, vel) * dt;
+                    pos = pos + vel * dt;
+                }
+                IntegrationMethod::RungeKutta4 => {
+                    let (p, v) = (pos, vel);
+                    jacobian_at(p, v);
+                    let k1_vel = deep_scene.acceleration(p, v) * dt;
+                    let k1_pos = v * dt;
+                    let (p2, v2) = (p + k1_pos * 0.5, v + k1_vel * 0.5);
+                    jacobian_at(p2, v2);
+                    let k2_vel = deep_scene.acceleration(p2, v2) * dt;
+                    let k2_pos = v2 * dt;
+                    let (p3, v3) = (p + k2_pos * 0.5, v + k2_vel * 0.5);
+                    jacobian_at(p3, v3);
+                    let k3_vel = deep_scene.acceleration(p3, v3) * dt;
+                    let k3_pos = v3 * dt;
+                    let (p4, v4) = (p + k3_pos, v + k3_vel);
+                    jacobian_at(p4, v4);
+                    let k4_vel = deep_scene.acceleration(p4, v4) * dt;
+                    let k4_pos = v4 * dt;
+                    vel = v + (k1_vel + k2_vel * 2.0 + k3_vel * 2.0 + k4_vel) * (1.0 / 6.0);
+                    pos = p + (k1_pos + k2_pos * 2.0 + k3_pos * 2.0 + k4_pos) * (1.0 / 6.0);
+                }
+            }
+            states.push((pos, vel));
+            let first_gap = gaps.len();
+            gaps.extend(deep_scene.bodies.iter().map(|&(body, _)| BodyGap::new(pos, body, threshold)));
+            if gaps[first_gap..].iter().any(|gap| gap.gap.to_f64() < 0.0) {
+                break;
+            }
+        }
+        Self { start: (start, velocity), states, jacobians, drag_jacobians, magnetic: precise_scene.magnetic, gaps }
+    }
+
+    // Linearized acceleration of a particle at offset `d` and velocity offset `u` from the
+    // reference, at the point the integrator evaluated it at in `stage`
+    fn perturbation(&self, stage: usize, d: DVec2, u: DVec2) -> DVec2 {
+        let mut acceleration = self.jacobians[stage].apply(d);
+        if let Some(drag) = self.drag_jacobians.get(stage) {
+            acceleration = acceleration + drag.apply(u);
+        }
+        if self.magnetic != 0.0 {
+            acceleration = acceleration + magnetic_turn(u, self.magnetic);
+        }
+        acceleration
+    }
+
+    // First body a particle at `offset` from the reference after `substep` has hit, if any
+    fn collision(&self, substep: usize, bodies: usize, offset: DVec2) -> Option<usize> {
+        self.gaps[substep * bodies..(substep + 1) * bodies].iter().position(|gap| gap.collides(offset))
+    }
+}
+
+// Position of a particle `offset` away from a reference position, rounded to f64
+fn rebased(reference: DeepPoint, offset: DVec2) -> DVec2 {
+    reference.offset(offset.x, offset.y).to_dvec2()
+}
+
+// A deep zoom view prepared for simulating its pixels. Pixel start positions are offsets from
+// the view center, which f64 holds exactly however deep the zoom, and each pixel follows the
+// linearized dynamics around the reference orbit until its offset is large enough for plain
+// f64 to take over.
+pub struct DeepView {
+    scene: PreciseScene,
+    orbit: ReferenceOrbit,
+    center_px: (f64, f64),
+    zoom: f64,
+    method: IntegrationMethod,
+    simulation: SimulationParams,
+}
+
+impl DeepView {
+    pub fn new(scene: &Scene, params: &MapParams, camera: DeepPoint) -> Self {
+        let precise_scene = PreciseScene::new(scene);
+        let zoom = params.zoom_factor as f64;
+        let center_px = (params.width as f64 / 2.0, params.height as f64 / 2.0);
+        // world = pixel / zoom - camera
+        let center = DeepPoint::from_dvec2(DVec2::new(center_px.0 / zoom, center_px.1 / zoom)) - camera;
+        let orbit = ReferenceOrbit::new(scene, &precise_scene, center, DeepPoint::from_vec2(params.initial_velocity),
+                                        params.integration_method, &params.simulation);
+        Self { scene: precise_scene, orbit, center_px, zoom, method: params.integration_method, simulation: params.simulation }
+    }
+
+    // Body hit and timestep of the particle starting at the given (sub)pixel
+    pub fn outcome_at(&self, px: f64, py: f64) -> Option<(usize, usize)> {
+        let params = &self.simulation;
+        let dt = params.dt as f64 / params.substeps as f64;
+        let mut d = DVec2::new((px - self.center_px.0) / self.zoom, (py - self.center_px.1) / self.zoom);
+        let mut u = DVec2::new(0.0, 0.0);
+
+        for (substep, &(pos, vel)) in self.orbit.states.iter().enumerate() {
+            match self.method {
+                IntegrationMethod::Euler => {
+                    u = u + self.orbit.perturbation(substep, d, u) * dt;
+                    d = d + u * dt;
+                }
+                IntegrationMethod::RungeKutta4 => {
+                    let stage = substep * 4;
+                    let k1_u = self.orbit.perturbation(stage, d, u) * dt;
+                    let k1_d = u * dt;
+                    let k2_u = self.orbit.perturbation(stage + 1, d + k1_d * 0.5, u + k1_u * 0.5) * dt;
+                    let k2_d = (u + k1_u * 0.5) * dt;
+                    let k3_u = self.orbit.perturbation(stage + 2, d + k2_d * 0.5, u + k2_u * 0.5) * dt;
+                    let k3_d = (u + k2_u * 0.5) * dt;
+                    let k4_u = self.orbit.perturbation(stage + 3, d + k3_d, u + k3_u) * dt;
+                    let k4_d = (u + k3_u) * dt;
+                    u = u + (k1_u + k2_u * 2.0 + k3_u * 2.0 + k4_u) / 6.0;
+                    d = d + (k1_d + k2_d * 2.0 + k3_d * 2.0 + k4_d) / 6.0;
+                }
+            }
+
+            if let Some(collision_index) = self.orbit.collision(substep, self.scene.bodies.len(), d) {
+                return Some((collision_index, substep / params.substeps));
+            }
+            if d.length() > REBASE_DISTANCE || u.length() > REBASE_DISTANCE {
+                return integrate_from(&self.scene, rebased(pos, d), rebased(vel, u), substep + 1, self.method, params);
+            }
+        }
+
+        // The reference collided without this pixel, or the simulation ran its full course
+        let (pos, vel) = self.orbit.states.last().copied().unwrap_or(self.orbit.start);
+        integrate_from(&self.scene, rebased(pos, d), rebased(vel, u), self.orbit.states.len(), self.method, params)
+    }
+}
@@ -0,0 +1,52 @@
+use std::io::Write;
+
+use crate::physics::Vec2;
+use crate::scene::Scene;
+use crate::simulation::{IntegrationMethod, SimulationParams};
+use crate::trajectory::record_trajectory;
+
+// Energy error of one integrator over a launch. Stationary bodies conserve the test particle's
+// energy exactly, so any change is error introduced by the integrator.
+pub struct EnergyDrift {
+    pub integration_method: IntegrationMethod,
+    // (time, energy, relative error (E - E0) / |E0|) at the end of every timestep
+    pub samples: Vec<(f32, f32, f32)>,
+}
+
+impl EnergyDrift {
+    // Largest relative error reached during the launch
+    pub fn max_error(&self) -> f32 {
+        self.samples.iter().map(|&(_, _, error)| error.abs()).fold(0.0, f32::max)
+    }
+}
+
+// Runs the same launch under every integrator
+pub fn compare_integrators(start_pos: Vec2, initial_velocity: Vec2, scene: &Scene, params: &SimulationParams) -> Vec<EnergyDrift> {
+    IntegrationMethod::ALL.iter().map(|&integration_method| {
+        let trajectory = record_trajectory(start_pos, initial_velocity, scene, integration_method, params, &[]);
+        let initial_energy = trajectory[0].energy;
+        // A launch with zero energy has no scale to compare against, so report the absolute error
+        let scale = if initial_energy == 0.0 { 1.0 } else { initial_energy.abs() };
+        let samples = trajectory.iter()
+            .map(|sample| (sample.t, sample.energy, (sample.energy - initial_energy) / scale))
+            .collect();
+        EnergyDrift { integration_method, samples }
+    }).collect()
+}
+
+// Writes one row per integrator and timestep: method, t, energy, relative_error
+pub fn save_energy_drift(path: &str, drifts: &[EnergyDrift]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    writeln!(file, "method,t,energy,relative_error")?;
+    for drift in drifts {
+        for (t, energy, error) in &drift.samples {
+            writeln!(file, "{},{},{},{}", drift.integration_method.short_name(), t, energy, error)?;
+        }
+    }
+    file.flush()?;
+    let summary: Vec<String> = drifts.iter()
+        .map(|drift| format!("{} max error {:.2e}", drift.integration_method.name(), drift.max_error()))
+        .collect();
+    println!("Energy drift saved to {} ({})", path, summary.join(", "));
+    Ok(())
+}
@@ -0,0 +1,239 @@
+use std::f32::consts::TAU;
+
+use serde::{Deserialize, Serialize};
+
+use crate::physics::{total_energy, TestParticle, Vec2};
+use crate::scene::Scene;
+use crate::simulation::SimulationParams;
+
+// A close approach is counted each time the particle comes within this many collision radii of a body
+pub const CLOSE_APPROACH_FACTOR: f32 = 3.0;
+// Minimum distances are shaded on a log scale from the collision radius up to this many times it
+const MIN_DISTANCE_RANGE: f32 = 64.0;
+// Close approach counts at which the shading saturates
+const MAX_CLOSE_APPROACHES: f32 = 10.0;
+// Bounce counts at which the shading saturates
+const MAX_SHADED_BOUNCES: f32 = 10.0;
+// Full turns around the center of mass at which the winding shading saturates
+const MAX_WINDINGS: f32 = 10.0;
+// Final distances are shaded on a log scale up to this many times the scene's extent
+const FINAL_DISTANCE_RANGE: f32 = 64.0;
+// Bound and unbound energies are kept to either side of a gap in the shading, so the boundary
+// between them stays visible
+const BOUND_SHADE: f32 = 0.4;
+const UNBOUND_SHADE: f32 = 0.6;
+// Escaped pixels are shaded in gray, from the default dark gray up to this level, so they can't
+// be mistaken for a basin
+const ESCAPE_MIN_LEVEL: f32 = 20.0;
+const ESCAPE_MAX_LEVEL: f32 = 210.0;
+
+// How pixels whose particle never collides are colored
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum EscapeColoring {
+    // All the same dark gray
+    #[default]
+    Uniform,
+    // Closest distance to any body over the trajectory
+    MinDistance,
+    // Number of times the particle passed close to a body
+    CloseApproaches,
+    // Number of turns the particle made around the bodies' center of mass
+    Winding,
+    // Distance from the origin at the end of the simulation
+    FinalDistance,
+    // Speed at the end of the simulation
+    FinalSpeed,
+    // Total energy, negative while the particle is bound to the bodies
+    Energy,
+    // Number of times the particle bounced off a body, in scenes with a restitution
+    Bounces,
+}
+
+impl EscapeColoring {
+    pub const ALL: [EscapeColoring; 8] = [
+        EscapeColoring::Uniform,
+        EscapeColoring::MinDistance,
+        EscapeColoring::CloseApproaches,
+        EscapeColoring::Winding,
+        EscapeColoring::FinalDistance,
+        EscapeColoring::FinalSpeed,
+        EscapeColoring::Energy,
+        EscapeColoring::Bounces,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EscapeColoring::Uniform => "uniform",
+            EscapeColoring::MinDistance => "closest approach",
+            EscapeColoring::CloseApproaches => "close approach count",
+            EscapeColoring::Winding => "winding number",
+            EscapeColoring::FinalDistance => "final distance",
+            EscapeColoring::FinalSpeed => "final speed",
+            EscapeColoring::Energy => "bound/unbound energy",
+            EscapeColoring::Bounces => "bounce count",
+        }
+    }
+
+    // Identifier used on the command line and in cache filenames
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            EscapeColoring::Uniform => "uniform",
+            EscapeColoring::MinDistance => "min-distance",
+            EscapeColoring::CloseApproaches => "close-approaches",
+            EscapeColoring::Winding => "winding",
+            EscapeColoring::FinalDistance => "final-distance",
+            EscapeColoring::FinalSpeed => "final-speed",
+            EscapeColoring::Energy => "energy",
+            EscapeColoring::Bounces => "bounces",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let index = EscapeColoring::ALL.iter().position(|&coloring| coloring == self).unwrap_or(0);
+        EscapeColoring::ALL[(index + 1) % EscapeColoring::ALL.len()]
+    }
+}
+
+impl std::str::FromStr for EscapeColoring {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        EscapeColoring::ALL.iter()
+            .find(|coloring| coloring.short_name() == s)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = EscapeColoring::ALL.iter().map(|coloring| coloring.short_name()).collect();
+                format!("unknown escape coloring '{}', expected one of: {}", s, names.join(", "))
+            })
+    }
+}
+
+// Statistics accumulated along a trajectory, recorded after every substep
+#[derive(Clone, Copy, Debug)]
+pub struct TrajectoryStats {
+    pub min_distance: f32,
+    pub close_approaches: u32,
+    pub bounces: u32,
+    // Signed angle swept around the center of mass, in radians, counterclockwise positive
+    pub winding_angle: f32,
+    // The particle as of the latest record
+    pub final_pos: Vec2,
+    pub final_vel: Vec2,
+    // Energy per unit mass as of the latest record
+    pub final_energy: f32,
+    // Whether the particle is currently within a close approach, so each pass counts once
+    approaching: bool,
+    // Offset from the center of mass at the previous record, to unwrap the angle swept since
+    previous_offset: Option<Vec2>,
+}
+
+impl TrajectoryStats {
+    pub fn new() -> Self {
+        Self {
+            min_distance: f32::INFINITY,
+            close_approaches: 0,
+            bounces: 0,
+            winding_angle: 0.0,
+            final_pos: Vec2::new(0.0, 0.0),
+            final_vel: Vec2::new(0.0, 0.0),
+            final_energy: 0.0,
+            approaching: false,
+            previous_offset: None,
+        }
+    }
+
+    // Full turns made around the center of mass, counterclockwise positive
+    pub fn windings(&self) -> f32 {
+        self.winding_angle / TAU
+    }
+
+    pub fn record(&mut self, particle: &TestParticle, scene: &Scene, params: &SimulationParams) {
+        let pos = particle.pos;
+        self.final_pos = pos;
+        self.final_vel = particle.vel;
+        self.final_energy = total_energy(particle, scene) / particle.mass;
+        let nearest = scene.bodies_around(particle).iter().map(|body| body.distance(pos)).fold(f32::INFINITY, f32::min);
+        self.min_distance = self.min_distance.min(nearest);
+        let approaching = nearest < CLOSE_APPROACH_FACTOR * params.collision_threshold;
+        if approaching && !self.approaching {
+            self.close_approaches += 1;
+        }
+        self.approaching = approaching;
+        self.bounces = particle.bounces;
+
+        // The angle between consecutive offsets stays well below half a turn at any usable
+        // timestep, so summing them unwraps the angle
+        let offset = pos - center_of_mass(scene);
+        if let Some(previous) = self.previous_offset {
+            let cross = previous.x * offset.y - previous.y * offset.x;
+            let dot = previous.dot(&offset);
+            self.winding_angle += cross.atan2(dot);
+        }
+        self.previous_offset = Some(offset);
+    }
+
+    // Position of the trajectory on the coloring's scale, from 0 to 1, or None for uniform coloring
+    pub fn shade(&self, coloring: EscapeColoring, scene: &Scene, params: &SimulationParams) -> Option<f32> {
+        let (length_scale, speed_scale) = reference_scales(scene);
+        match coloring {
+            EscapeColoring::Uniform => None,
+            EscapeColoring::MinDistance => {
+                let ratio = self.min_distance / params.collision_threshold.max(f32::EPSILON);
+                Some((ratio.max(1.0).ln() / MIN_DISTANCE_RANGE.ln()).min(1.0))
+            }
+            EscapeColoring::CloseApproaches => Some((self.close_approaches as f32 / MAX_CLOSE_APPROACHES).min(1.0)),
+            EscapeColoring::Bounces => Some((self.bounces as f32 / MAX_SHADED_BOUNCES).min(1.0)),
+            // Either direction shades the same, so mirror-image trajectories match
+            EscapeColoring::Winding => Some((self.windings().abs() / MAX_WINDINGS).min(1.0)),
+            EscapeColoring::FinalDistance => {
+                let ratio = self.final_pos.length() / length_scale;
+                Some((ratio.ln_1p() / FINAL_DISTANCE_RANGE.ln_1p()).min(1.0))
+            }
+            EscapeColoring::FinalSpeed => {
+                let speed = self.final_vel.length();
+                Some(speed / (speed + speed_scale))
+            }
+            EscapeColoring::Energy => {
+                // Deeply bound is darkest and barely bound just below the gap, mirrored for unbound
+                let energy_scale = 0.5 * speed_scale * speed_scale;
+                let depth = self.final_energy.abs() / (self.final_energy.abs() + energy_scale);
+                Some(if self.final_energy < 0.0 {
+                    BOUND_SHADE * (1.0 - depth)
+                } else {
+                    UNBOUND_SHADE + (1.0 - UNBOUND_SHADE) * depth
+                })
+            }
+        }
+    }
+}
+
+impl Default for TrajectoryStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Mass-weighted mean position of the bodies, or the origin for a scene without mass
+pub fn center_of_mass(scene: &Scene) -> Vec2 {
+    let total: f32 = scene.bodies.iter().map(|body| body.mass).sum();
+    if total == 0.0 {
+        return Vec2::new(0.0, 0.0);
+    }
+    let weighted = scene.bodies.iter().fold(Vec2::new(0.0, 0.0), |sum, body| sum + body.pos * body.mass);
+    weighted / total
+}
+
+// Lengths and speeds the final state is measured against: the distance of the farthest body from
+// the origin, and the speed of a circular orbit at that distance around the scene's whole mass
+fn reference_scales(scene: &Scene) -> (f32, f32) {
+    let length = scene.bodies.iter().map(|body| body.pos.length()).fold(1.0, f32::max);
+    let total_mass: f32 = scene.bodies.iter().map(|body| body.mass).sum();
+    let speed = (scene.gravitational_constant * total_mass / length).abs().sqrt().max(f32::EPSILON);
+    (length, speed)
+}
+
+// Color of an escaped pixel at a shade from TrajectoryStats::shade
+pub fn escaped_color(shade: f32) -> [u8; 3] {
+    let level = ESCAPE_MIN_LEVEL + (ESCAPE_MAX_LEVEL - ESCAPE_MIN_LEVEL) * shade.clamp(0.0, 1.0);
+    [level as u8; 3]
+}
@@ -0,0 +1,45 @@
+use crate::escape::CLOSE_APPROACH_FACTOR;
+use crate::physics::TestParticle;
+use crate::scene::Scene;
+
+// Hooks called while a particle is simulated, so the viewer, exporters and analyses can follow a
+// launch without detecting collisions or close approaches themselves. Every hook does nothing by
+// default, so an observer implements only those it needs.
+pub trait SimulationObserver {
+    // After every whole timestep, with the particle as it is at the end of it
+    fn on_step(&mut self, _timestep: usize, _particle: &TestParticle) {}
+
+    // When the particle comes within CLOSE_APPROACH_FACTOR collision radii of a body, once per
+    // pass, with its distance from the body and the particle as they are then
+    fn on_close_approach(&mut self, _timestep: usize, _body: usize, _distance: f32, _particle: &TestParticle) {}
+
+    // When the particle stops at a body
+    fn on_collision(&mut self, _timestep: usize, _body: usize) {}
+
+    // When the simulation runs out of timesteps without the particle stopping at any body
+    fn on_escape(&mut self, _timestep: usize, _particle: &TestParticle) {}
+}
+
+// The observer of simulations nobody observes
+impl SimulationObserver for () {}
+
+// Tells when a particle checked after every substep starts a close approach, counting each pass
+// the same way as TrajectoryStats
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ApproachDetector {
+    approaching: bool,
+}
+
+impl ApproachDetector {
+    // The body the particle just came close to and its distance, if it did
+    pub fn check(&mut self, particle: &TestParticle, scene: &Scene, collision_threshold: f32) -> Option<(usize, f32)> {
+        let nearest = scene.bodies_around(particle).iter()
+            .map(|body| body.distance(particle.pos))
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b));
+        let approach = nearest.filter(|&(_, distance)| distance < CLOSE_APPROACH_FACTOR * collision_threshold);
+        let started = approach.filter(|_| !self.approaching);
+        self.approaching = approach.is_some();
+        started
+    }
+}
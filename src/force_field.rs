@@ -0,0 +1,606 @@
+use std::f64::consts::PI;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use rhai::{Array, CallFnOptions, Dynamic, Engine, Scope, AST, FLOAT, INT};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::physics::Vec2;
+use crate::precise::DVec2;
+
+// Operations one evaluation of a script may run before it is stopped, so a runaway loop can't
+// hang the simulation
+const MAX_SCRIPT_OPERATIONS: u64 = 100_000;
+// Name and parameter count of the function a force script has to define
+const SCRIPT_FUNCTION: &str = "a";
+const SCRIPT_PARAMETERS: usize = 3;
+
+// Acceleration on the test particle on top of the scene's gravity and drag, from its position,
+// its velocity and the simulated time since launch. Evaluated in f64 so the double precision
+// simulation gets the same field as the f32 one.
+pub trait ForceField: Send + Sync {
+    fn acceleration(&self, pos: DVec2, vel: DVec2, t: f64) -> DVec2;
+}
+
+// How a body's mass is spread out. Points are the usual wells. Rings, discs and segments lie in
+// the plane around the body's position and pull with the exact inverse-square field of their
+// shape. Plummer and King bodies are spherical star clusters centered on it, whose pull fades to
+// zero at the center instead of diverging. Black holes pull harder than points close in and
+// capture particles at their horizon. In scene files:
+//
+//     shape = { type = "ring", radius = 80.0 }
+//     shape = { type = "disc", radius = 40.0 }
+//     shape = { type = "segment", length = 200.0, angle = 30.0 }
+//     shape = { type = "plummer", radius = 50.0 }
+//     shape = { type = "king", core_radius = 20.0, tidal_radius = 150.0 }
+//     shape = { type = "black_hole", schwarzschild_radius = 15.0 }
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum BodyShape {
+    #[default]
+    Point,
+    // Thin circle of the given radius
+    Ring { radius: f32 },
+    // Filled circle of uniform density
+    Disc { radius: f32 },
+    // Straight rod of uniform density through the body's position, at `angle` degrees
+    // counterclockwise from +x as seen on screen
+    Segment { length: f32, angle: f32 },
+    // Plummer sphere with scale radius `radius`
+    Plummer { radius: f32 },
+    // King-like cluster: dense core of radius `core_radius`, cut off at `tidal_radius`
+    King { core_radius: f32, tidal_radius: f32 },
+    // Pseudo-Newtonian black hole with its horizon at `schwarzschild_radius`
+    #[serde(rename = "black_hole")]
+    BlackHole { schwarzschild_radius: f32 },
+}
+
+impl BodyShape {
+    pub fn is_point(&self) -> bool {
+        *self == BodyShape::Point
+    }
+
+    // Field of a body of this shape centered at `center`, pulling with `strength` (G times its
+    // mass, or whatever stands in for it). None for points, which the callers sum themselves so
+    // point-mass scenes simulate exactly as before.
+    fn field(&self, center: DVec2, strength: f64) -> Option<ShapeField> {
+        Some(match *self {
+            BodyShape::Point => return None,
+            BodyShape::Ring { radius } => ShapeField::Ring(Ring { center, radius: radius as f64, strength }),
+            BodyShape::Disc { radius } => ShapeField::Disc(Disc { center, radius: radius as f64, strength }),
+            BodyShape::Segment { length, angle } => ShapeField::Segment(Segment {
+                center,
+                half_length: length as f64 / 2.0,
+                direction: screen_direction(angle as f64),
+                strength,
+            }),
+            BodyShape::Plummer { radius } => ShapeField::Plummer(Plummer { center, radius: radius as f64, strength }),
+            BodyShape::King { core_radius, tidal_radius } => ShapeField::King(King {
+                center,
+                core_radius: core_radius as f64,
+                tidal_radius: tidal_radius as f64,
+                strength,
+            }),
+            BodyShape::BlackHole { schwarzschild_radius } => ShapeField::BlackHole(BlackHole {
+                center,
+                schwarzschild_radius: schwarzschild_radius as f64,
+                strength,
+            }),
+        })
+    }
+
+    // Acceleration of the body's field on a particle at `pos`, see `field`
+    pub fn field_at(&self, center: DVec2, strength: f64, pos: DVec2) -> Option<DVec2> {
+        self.field(center, strength).map(|field| field.acceleration(pos, DVec2::new(0.0, 0.0), 0.0))
+    }
+
+    // Potential energy of the same field at `pos`, the counterpart of -strength / r for a point
+    pub fn potential_at(&self, center: DVec2, strength: f64, pos: DVec2) -> Option<f64> {
+        self.field(center, strength).map(|field| field.potential(pos))
+    }
+
+    // Shapes that make no sense, like a ring without a radius, or None if it's fine
+    pub fn problem(&self) -> Option<String> {
+        let positive = |value: f32, name: &str| (value <= 0.0).then(|| format!("{} {} must be positive", self.name(), name));
+        match *self {
+            BodyShape::Point => None,
+            BodyShape::Ring { radius } | BodyShape::Disc { radius } | BodyShape::Plummer { radius } => positive(radius, "radius"),
+            BodyShape::Segment { length, .. } => positive(length, "length"),
+            BodyShape::BlackHole { schwarzschild_radius } => positive(schwarzschild_radius, "schwarzschild radius"),
+            BodyShape::King { core_radius, tidal_radius } => positive(core_radius, "core radius").or_else(|| {
+                (tidal_radius <= core_radius).then(|| "king tidal radius must be larger than its core radius".to_string())
+            }),
+        }
+    }
+
+    // Distance from `offset` (relative to the body's position) to the nearest point of the
+    // shape, 0 inside a disc. Particles collide with bodies by this distance; clusters count as
+    // their center, which particles can otherwise pass straight through, and black holes as their
+    // horizon.
+    pub fn distance(&self, offset: DVec2) -> f64 {
+        match *self {
+            BodyShape::Point | BodyShape::Plummer { .. } | BodyShape::King { .. } => offset.length(),
+            BodyShape::Ring { radius } => (offset.length() - radius as f64).abs(),
+            BodyShape::Disc { radius } => (offset.length() - radius as f64).max(0.0),
+            BodyShape::BlackHole { schwarzschild_radius } => (offset.length() - schwarzschild_radius as f64).max(0.0),
+            BodyShape::Segment { length, angle } => {
+                let direction = screen_direction(angle as f64);
+                let half_length = length as f64 / 2.0;
+                let along = (offset.x * direction.x + offset.y * direction.y).clamp(-half_length, half_length);
+                offset.distance(&(direction * along))
+            }
+        }
+    }
+
+    // Point of the shape nearest to `offset` by `distance`, relative to the body's position, and
+    // the unit normal pointing from it toward the side `offset` is on (outward for a disc or a
+    // horizon, even from inside). Particles bounce off this surface. A particle exactly on a point
+    // or a segment gets the normal along +x or across the segment.
+    pub fn surface(&self, offset: DVec2) -> (DVec2, DVec2) {
+        let unit = |v: DVec2, fallback: DVec2| {
+            let length = v.length();
+            if length > 0.0 { v / length } else { fallback }
+        };
+        let radial = unit(offset, DVec2::new(1.0, 0.0));
+        match *self {
+            BodyShape::Point | BodyShape::Plummer { .. } | BodyShape::King { .. } => (DVec2::new(0.0, 0.0), radial),
+            BodyShape::Ring { radius } => {
+                let side = if offset.length() < radius as f64 { -1.0 } else { 1.0 };
+                (radial * radius as f64, radial * side)
+            }
+            BodyShape::Disc { radius } | BodyShape::BlackHole { schwarzschild_radius: radius } => (radial * radius as f64, radial),
+            BodyShape::Segment { length, angle } => {
+                let direction = screen_direction(angle as f64);
+                let half_length = length as f64 / 2.0;
+                let along = (offset.x * direction.x + offset.y * direction.y).clamp(-half_length, half_length);
+                let point = direction * along;
+                (point, unit(offset - point, DVec2::new(-direction.y, direction.x)))
+            }
+        }
+    }
+
+    // Radius of the circle around the body's position that contains the whole shape as drawn.
+    // Clusters are drawn as points and black holes as their horizon.
+    pub fn extent(&self) -> f32 {
+        match *self {
+            BodyShape::Point | BodyShape::Plummer { .. } | BodyShape::King { .. } => 0.0,
+            BodyShape::Ring { radius } | BodyShape::Disc { radius } => radius,
+            BodyShape::BlackHole { schwarzschild_radius } => schwarzschild_radius,
+            BodyShape::Segment { length, .. } => length / 2.0,
+        }
+    }
+
+    // Ends of a segment relative to the body's position, None for the other shapes
+    pub fn ends(&self) -> Option<(Vec2, Vec2)> {
+        let BodyShape::Segment { length, angle } = *self else {
+            return None;
+        };
+        let half = (screen_direction(angle as f64) * (length as f64 / 2.0)).to_vec2();
+        Some((half * -1.0, half))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BodyShape::Point => "point",
+            BodyShape::Ring { .. } => "ring",
+            BodyShape::Disc { .. } => "disc",
+            BodyShape::Segment { .. } => "segment",
+            BodyShape::Plummer { .. } => "plummer",
+            BodyShape::King { .. } => "king",
+            BodyShape::BlackHole { .. } => "black hole",
+        }
+    }
+
+    pub fn is_black_hole(&self) -> bool {
+        matches!(self, BodyShape::BlackHole { .. })
+    }
+}
+
+enum ShapeField {
+    Ring(Ring),
+    Disc(Disc),
+    Segment(Segment),
+    Plummer(Plummer),
+    King(King),
+    BlackHole(BlackHole),
+}
+
+impl ShapeField {
+    fn potential(&self, pos: DVec2) -> f64 {
+        match self {
+            ShapeField::Ring(ring) => ring.potential(pos),
+            ShapeField::Disc(disc) => disc.potential(pos),
+            ShapeField::Segment(segment) => segment.potential(pos),
+            ShapeField::Plummer(plummer) => plummer.potential(pos),
+            ShapeField::King(king) => king.potential(pos),
+            ShapeField::BlackHole(black_hole) => black_hole.potential(pos),
+        }
+    }
+}
+
+impl ForceField for ShapeField {
+    fn acceleration(&self, pos: DVec2, vel: DVec2, t: f64) -> DVec2 {
+        match self {
+            ShapeField::Ring(ring) => ring.acceleration(pos, vel, t),
+            ShapeField::Disc(disc) => disc.acceleration(pos, vel, t),
+            ShapeField::Segment(segment) => segment.acceleration(pos, vel, t),
+            ShapeField::Plummer(plummer) => plummer.acceleration(pos, vel, t),
+            ShapeField::King(king) => king.acceleration(pos, vel, t),
+            ShapeField::BlackHole(black_hole) => black_hole.acceleration(pos, vel, t),
+        }
+    }
+}
+
+// Unit vector at `degrees` counterclockwise from +x as seen on screen, where y points down
+fn screen_direction(degrees: f64) -> DVec2 {
+    let radians = degrees.to_radians();
+    DVec2::new(radians.cos(), -radians.sin())
+}
+
+// Complete elliptic integrals of the first and second kind, K(m) and E(m) with parameter
+// m = k², by the arithmetic-geometric mean
+fn complete_elliptic(m: f64) -> (f64, f64) {
+    if m >= 1.0 {
+        return (f64::INFINITY, 1.0);
+    }
+    let (mut a, mut b, mut c) = (1.0, (1.0 - m).sqrt(), m.sqrt());
+    let mut weight = 0.5;
+    let mut sum = weight * c * c;
+    for _ in 0..32 {
+        if c.abs() <= f64::EPSILON * a {
+            break;
+        }
+        c = (a - b) / 2.0;
+        (a, b) = ((a + b) / 2.0, (a * b).sqrt());
+        weight *= 2.0;
+        sum += weight * c * c;
+    }
+    let k = PI / (2.0 * a);
+    (k, k * (1.0 - sum))
+}
+
+// Outward acceleration `radial` at `pos`, pointing away from `center`
+fn radial_field(center: DVec2, pos: DVec2, radial: impl Fn(f64) -> f64) -> DVec2 {
+    let offset = pos - center;
+    let r = offset.length();
+    if r == 0.0 {
+        return DVec2::new(0.0, 0.0);
+    }
+    offset * (radial(r) / r)
+}
+
+// Thin ring of radius `radius` around `center`. In its own plane the field is
+// (strength / πr)·(E(m)/(R - r) - K(m)/(R + r)) outward, with m = 4Rr/(R + r)²: a pull toward
+// the ring from outside, and toward the nearest side of it from inside.
+pub struct Ring {
+    pub center: DVec2,
+    pub radius: f64,
+    pub strength: f64,
+}
+
+impl Ring {
+    // -2·strength·K(m) / π(R + r)
+    pub fn potential(&self, pos: DVec2) -> f64 {
+        let (a, r) = (self.radius, pos.distance(&self.center));
+        let (k, _) = complete_elliptic(4.0 * a * r / ((a + r) * (a + r)));
+        -2.0 * self.strength * k / (PI * (a + r))
+    }
+}
+
+impl ForceField for Ring {
+    fn acceleration(&self, pos: DVec2, _vel: DVec2, _t: f64) -> DVec2 {
+        let a = self.radius;
+        radial_field(self.center, pos, |r| {
+            let (k, e) = complete_elliptic(4.0 * a * r / ((a + r) * (a + r)));
+            self.strength / (PI * r) * (e / (a - r) - k / (a + r))
+        })
+    }
+}
+
+// Uniform disc of radius `radius` around `center`. With σ = strength / πR², the field in its
+// plane is 4σ·(E(u) - K(u)) outward outside it (u = R²/r²) and 4σ·(R/r)·(E(m) - K(m)) inside
+// (m = r²/R²), always toward the center.
+pub struct Disc {
+    pub center: DVec2,
+    pub radius: f64,
+    pub strength: f64,
+}
+
+impl Disc {
+    // -4σ·r·(E(u) - (1 - u)·K(u)) outside, -4σ·R·E(m) inside
+    pub fn potential(&self, pos: DVec2) -> f64 {
+        let (radius, r) = (self.radius, pos.distance(&self.center));
+        let density = self.strength / (PI * radius * radius);
+        if r >= radius {
+            let u = radius * radius / (r * r);
+            let (k, e) = complete_elliptic(u);
+            -4.0 * density * r * (e - (1.0 - u) * k)
+        } else {
+            let (_, e) = complete_elliptic(r * r / (radius * radius));
+            -4.0 * density * radius * e
+        }
+    }
+}
+
+impl ForceField for Disc {
+    fn acceleration(&self, pos: DVec2, _vel: DVec2, _t: f64) -> DVec2 {
+        let radius = self.radius;
+        let density = self.strength / (PI * radius * radius);
+        radial_field(self.center, pos, |r| {
+            if r >= radius {
+                let (k, e) = complete_elliptic(radius * radius / (r * r));
+                4.0 * density * (e - k)
+            } else {
+                let (k, e) = complete_elliptic(r * r / (radius * radius));
+                4.0 * density * radius / r * (e - k)
+            }
+        })
+    }
+}
+
+// Rod from center - direction·half_length to center + direction·half_length, `direction` being
+// a unit vector. With the rod along x from -h to h and λ = strength / 2h, the field at (x, y) is
+// λ·(1/r₋ - 1/r₊) along it and -(λ/y)·((h - x)/r₊ + (h + x)/r₋) across it, r± being the
+// distances to the two ends.
+pub struct Segment {
+    pub center: DVec2,
+    pub half_length: f64,
+    pub direction: DVec2,
+    pub strength: f64,
+}
+
+impl Segment {
+    // Position relative to the rod in its own frame, and the distances r₊ and r₋ to its ends
+    fn local(&self, pos: DVec2) -> (f64, f64, f64, f64) {
+        let offset = pos - self.center;
+        let (dir, h) = (self.direction, self.half_length);
+        let x = offset.x * dir.x + offset.y * dir.y;
+        let y = offset.y * dir.x - offset.x * dir.y;
+        (x, y, ((h - x) * (h - x) + y * y).sqrt(), ((h + x) * (h + x) + y * y).sqrt())
+    }
+
+    // -λ·ln((r₊ + r₋ + 2h) / (r₊ + r₋ - 2h))
+    pub fn potential(&self, pos: DVec2) -> f64 {
+        let (_, _, r_plus, r_minus) = self.local(pos);
+        let h = self.half_length;
+        let density = self.strength / (2.0 * h);
+        -density * ((r_plus + r_minus + 2.0 * h) / (r_plus + r_minus - 2.0 * h)).ln()
+    }
+}
+
+impl ForceField for Segment {
+    fn acceleration(&self, pos: DVec2, _vel: DVec2, _t: f64) -> DVec2 {
+        let (x, y, r_plus, r_minus) = self.local(pos);
+        let (dir, h) = (self.direction, self.half_length);
+        let density = self.strength / (2.0 * h);
+        let along = density * (1.0 / r_minus - 1.0 / r_plus);
+        // On the rod's line the pull is along it by symmetry
+        let across = if y == 0.0 { 0.0 } else { -density / y * ((h - x) / r_plus + (h + x) / r_minus) };
+        // Back from the rod's frame, whose y axis is the direction turned by 90°
+        let normal = DVec2::new(-dir.y, dir.x);
+        dir * along + normal * across
+    }
+}
+
+// Plummer sphere of scale radius b: strength / r² far out, but -strength·r / (r² + b²)^(3/2)
+// throughout, so the pull grows linearly from zero at the center
+pub struct Plummer {
+    pub center: DVec2,
+    pub radius: f64,
+    pub strength: f64,
+}
+
+impl Plummer {
+    // -strength / √(r² + b²)
+    pub fn potential(&self, pos: DVec2) -> f64 {
+        let r = pos.distance(&self.center);
+        -self.strength / (r * r + self.radius * self.radius).sqrt()
+    }
+}
+
+impl ForceField for Plummer {
+    fn acceleration(&self, pos: DVec2, _vel: DVec2, _t: f64) -> DVec2 {
+        let b2 = self.radius * self.radius;
+        radial_field(self.center, pos, |r| {
+            let s2 = r * r + b2;
+            -self.strength * r / (s2 * s2.sqrt())
+        })
+    }
+}
+
+// Cluster with the density ρ ∝ (1 + r²/rc²)^(-3/2) that King's models follow through their core,
+// truncated at the tidal radius rt. King's exact profile needs a differential equation solved per
+// model; this one has a closed form. With x = r/rc and g(x) = asinh(x) - x/√(1 + x²), the mass
+// within r is a fraction g(x)/g(rt/rc) of the total, and beyond rt the cluster pulls like a point.
+pub struct King {
+    pub center: DVec2,
+    pub core_radius: f64,
+    pub tidal_radius: f64,
+    pub strength: f64,
+}
+
+impl King {
+    fn enclosed(x: f64) -> f64 {
+        x.asinh() - x / (1.0 + x * x).sqrt()
+    }
+
+    // -strength·g(x)/(g(c)·r) - strength/(rc·g(c))·(1/√(1 + x²) - 1/√(1 + c²)) inside, with
+    // c = rt/rc, and -strength / r outside
+    pub fn potential(&self, pos: DVec2) -> f64 {
+        let r = pos.distance(&self.center);
+        if r >= self.tidal_radius {
+            return -self.strength / r;
+        }
+        let (x, c) = (r / self.core_radius, self.tidal_radius / self.core_radius);
+        let total = Self::enclosed(c);
+        let inner = if r == 0.0 { 0.0 } else { self.strength * Self::enclosed(x) / (total * r) };
+        let outer = self.strength / (self.core_radius * total) * (1.0 / (1.0 + x * x).sqrt() - 1.0 / (1.0 + c * c).sqrt());
+        -inner - outer
+    }
+}
+
+impl ForceField for King {
+    fn acceleration(&self, pos: DVec2, _vel: DVec2, _t: f64) -> DVec2 {
+        let total = Self::enclosed(self.tidal_radius / self.core_radius);
+        radial_field(self.center, pos, |r| {
+            let fraction = if r >= self.tidal_radius { 1.0 } else { Self::enclosed(r / self.core_radius) / total };
+            -self.strength * fraction / (r * r)
+        })
+    }
+}
+
+// Paczyński–Wiita black hole: the potential -strength / (r - rs) mimics the strong-field
+// orbits of a Schwarzschild black hole, with an innermost stable circular orbit at 3rs and
+// unstable orbits down to 2rs, and diverges at the horizon rs where particles are captured
+pub struct BlackHole {
+    pub center: DVec2,
+    pub schwarzschild_radius: f64,
+    pub strength: f64,
+}
+
+impl BlackHole {
+    // -strength / (r - rs) outside the horizon; nothing inside it survives to feel it
+    pub fn potential(&self, pos: DVec2) -> f64 {
+        let r = pos.distance(&self.center);
+        if r <= self.schwarzschild_radius {
+            return f64::NEG_INFINITY;
+        }
+        -self.strength / (r - self.schwarzschild_radius)
+    }
+}
+
+impl ForceField for BlackHole {
+    // -strength / (r - rs)² toward the center. Zero inside the horizon, which the integrators
+    // can only sample mid-step before the particle is captured, so it stays finite there.
+    fn acceleration(&self, pos: DVec2, _vel: DVec2, _t: f64) -> DVec2 {
+        radial_field(self.center, pos, |r| {
+            let gap = r - self.schwarzschild_radius;
+            if gap <= 0.0 { 0.0 } else { -self.strength / (gap * gap) }
+        })
+    }
+}
+
+// Force field defined by a Rhai script with a function `a(pos, vel, t)` returning the
+// acceleration, e.g.
+//
+//     fn a(pos, vel, t) {
+//         let center = vec2(300.0, 300.0);
+//         let r = pos - center;
+//         vec2(-r.y, r.x) * 0.5
+//     }
+//
+// Vectors have `x` and `y`, the operators +, - and * / by a number, and `length()`,
+// `normalize()` and `dot(other)`. The function may also return an array `[ax, ay]`.
+#[derive(Clone)]
+pub struct ScriptedForce {
+    source: String,
+    engine: Arc<Engine>,
+    ast: Arc<AST>,
+    // Whether an evaluation error was reported yet, so a failing script doesn't flood the terminal
+    reported: Arc<AtomicBool>,
+}
+
+impl ScriptedForce {
+    pub fn compile(source: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let engine = script_engine();
+        let ast = engine.compile(source)?;
+        let defines_function = ast.iter_functions()
+            .any(|function| function.name == SCRIPT_FUNCTION && function.params.len() == SCRIPT_PARAMETERS);
+        if !defines_function {
+            return Err("no function a(pos, vel, t) defined".into());
+        }
+        Ok(Self { source: source.to_string(), engine: Arc::new(engine), ast: Arc::new(ast), reported: Arc::new(AtomicBool::new(false)) })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn evaluate(&self, pos: DVec2, vel: DVec2, t: f64) -> Result<DVec2, Box<dyn std::error::Error>> {
+        // Top-level statements run once when compiling is enough; only the function is called
+        let options = CallFnOptions::new().eval_ast(false);
+        let result: Dynamic = self.engine.call_fn_with_options(options, &mut Scope::new(), &self.ast, SCRIPT_FUNCTION, (pos, vel, t as FLOAT))?;
+        if let Some(vector) = result.clone().try_cast::<DVec2>() {
+            return Ok(vector);
+        }
+        let components: Vec<f64> = result.try_cast::<Array>()
+            .ok_or("a(pos, vel, t) must return a vector or an array [ax, ay]")?
+            .into_iter()
+            .map(number)
+            .collect::<Option<_>>()
+            .ok_or("a(pos, vel, t) returned an array with something other than numbers")?;
+        match components[..] {
+            [x, y] => Ok(DVec2::new(x, y)),
+            _ => Err("a(pos, vel, t) must return an array of two numbers".into()),
+        }
+    }
+}
+
+impl ForceField for ScriptedForce {
+    // A failing script adds no force, and its first error is printed
+    fn acceleration(&self, pos: DVec2, vel: DVec2, t: f64) -> DVec2 {
+        self.evaluate(pos, vel, t).unwrap_or_else(|e| {
+            if !self.reported.swap(true, Ordering::Relaxed) {
+                eprintln!("Error in force script: {}", e);
+            }
+            DVec2::new(0.0, 0.0)
+        })
+    }
+}
+
+fn number(value: Dynamic) -> Option<f64> {
+    value.as_float().ok().or_else(|| value.as_int().ok().map(|int| int as f64))
+}
+
+// Engine with the vector type and its operations registered, and a cap on operations per call
+fn script_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_SCRIPT_OPERATIONS);
+    engine.register_type_with_name::<DVec2>("Vec2")
+        .register_get("x", |v: &mut DVec2| v.x)
+        .register_get("y", |v: &mut DVec2| v.y)
+        .register_fn("vec2", |x: FLOAT, y: FLOAT| DVec2::new(x, y))
+        .register_fn("vec2", |x: INT, y: INT| DVec2::new(x as f64, y as f64))
+        .register_fn("+", |a: DVec2, b: DVec2| a + b)
+        .register_fn("-", |a: DVec2, b: DVec2| a - b)
+        .register_fn("-", |a: DVec2| a * -1.0)
+        .register_fn("*", |a: DVec2, s: FLOAT| a * s)
+        .register_fn("*", |s: FLOAT, a: DVec2| a * s)
+        .register_fn("*", |a: DVec2, s: INT| a * s as f64)
+        .register_fn("/", |a: DVec2, s: FLOAT| a / s)
+        .register_fn("length", |v: &mut DVec2| v.length())
+        .register_fn("normalize", |v: &mut DVec2| {
+            let length = v.length();
+            if length == 0.0 { *v } else { *v / length }
+        })
+        .register_fn("dot", |a: &mut DVec2, b: DVec2| a.x * b.x + a.y * b.y)
+        .register_fn("to_string", |v: &mut DVec2| format!("({}, {})", v.x, v.y));
+    engine
+}
+
+// Scripts are stored and compared by their source
+impl PartialEq for ScriptedForce {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl fmt::Debug for ScriptedForce {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ScriptedForce").field(&self.source).finish()
+    }
+}
+
+impl Serialize for ScriptedForce {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+impl<'de> Deserialize<'de> for ScriptedForce {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        ScriptedForce::compile(&source).map_err(serde::de::Error::custom)
+    }
+}
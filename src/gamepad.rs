@@ -0,0 +1,78 @@
+use gravity_wells::physics::Vec2;
+use serde::{Deserialize, Serialize};
+
+// Stick deflection ignored around the center, so a resting stick doesn't drift
+#[cfg(feature = "gamepad")]
+const DEAD_ZONE: f32 = 0.15;
+
+// What the first connected gamepad asks for this frame. Without the `gamepad` feature no gamepad
+// is ever connected and every frame is neutral.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct GamepadFrame {
+    pub connected: bool,
+    // Left stick, -1 to 1 with x right and y down like the screen
+    pub pan: Vec2,
+    // Right trigger minus left trigger
+    pub zoom: f32,
+    // Right stick, when pushed past the dead zone
+    pub aim: Option<Vec2>,
+    // South button (A on Xbox layouts) pressed this frame
+    pub launch: bool,
+}
+
+pub struct Gamepad {
+    #[cfg(feature = "gamepad")]
+    gilrs: Option<gilrs::Gilrs>,
+}
+
+#[cfg(feature = "gamepad")]
+fn stick(x: f32, y: f32) -> Option<Vec2> {
+    // gilrs points y up
+    let deflection = Vec2::new(x, -y);
+    (deflection.length() > DEAD_ZONE).then(|| deflection.clamp_length(1.0))
+}
+
+impl Gamepad {
+    #[cfg(feature = "gamepad")]
+    pub fn new() -> Self {
+        let gilrs = gilrs::Gilrs::new()
+            .map_err(|e| eprintln!("Gamepad input unavailable: {}", e))
+            .ok();
+        Self { gilrs }
+    }
+
+    #[cfg(not(feature = "gamepad"))]
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    #[cfg(feature = "gamepad")]
+    pub fn poll(&mut self) -> GamepadFrame {
+        use gilrs::{Axis, Button, EventType};
+
+        let Some(gilrs) = &mut self.gilrs else {
+            return GamepadFrame::default();
+        };
+        // Draining the events keeps the gamepad state current
+        let mut launch = false;
+        while let Some(event) = gilrs.next_event() {
+            launch |= matches!(event.event, EventType::ButtonPressed(Button::South, _));
+        }
+        let Some((_, pad)) = gilrs.gamepads().find(|(_, pad)| pad.is_connected()) else {
+            return GamepadFrame::default();
+        };
+        let trigger = |button| pad.button_data(button).map_or(0.0, |data| data.value());
+        GamepadFrame {
+            connected: true,
+            pan: stick(pad.value(Axis::LeftStickX), pad.value(Axis::LeftStickY)).unwrap_or_default(),
+            zoom: trigger(Button::RightTrigger2) - trigger(Button::LeftTrigger2),
+            aim: stick(pad.value(Axis::RightStickX), pad.value(Axis::RightStickY)),
+            launch,
+        }
+    }
+
+    #[cfg(not(feature = "gamepad"))]
+    pub fn poll(&mut self) -> GamepadFrame {
+        GamepadFrame::default()
+    }
+}
@@ -0,0 +1,207 @@
+use gravity_wells::physics::Vec2;
+use macroquad::miniquad::{BlendFactor, BlendState, Equation};
+use macroquad::prelude::{
+    clear_background, draw_circle, draw_circle_lines, draw_line, draw_texture_ex, gl_use_default_material, gl_use_material,
+    load_material, render_target, set_camera, set_default_camera, vec2, Camera2D, Color, DrawTextureParams, FilterMode,
+    Material, MaterialParams, PipelineParams, RenderTarget, ShaderSource, UniformDesc, UniformType, BLANK, WHITE,
+};
+
+// The glow is blurred at a fraction of the window's resolution, which widens it for free
+const GLOW_DOWNSCALE: f32 = 2.0;
+// Horizontal and vertical blur passes; each one spreads the glow further
+const BLUR_PASSES: usize = 2;
+
+const VERTEX_SHADER: &str = "#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+attribute vec4 color0;
+
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1);
+    color = color0 / 255.0;
+    uv = texcoord;
+}
+";
+
+// Nine tap Gaussian blur along Direction (one texel long), read in five samples by letting linear
+// filtering blend neighbouring texels
+const BLUR_SHADER: &str = "#version 100
+precision mediump float;
+
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform sampler2D Texture;
+uniform vec2 Direction;
+
+void main() {
+    vec4 sum = texture2D(Texture, uv) * 0.2270270;
+    sum += (texture2D(Texture, uv + Direction * 1.3846154) + texture2D(Texture, uv - Direction * 1.3846154)) * 0.3162162;
+    sum += (texture2D(Texture, uv + Direction * 3.2307692) + texture2D(Texture, uv - Direction * 3.2307692)) * 0.0702703;
+    gl_FragColor = sum * color;
+}
+";
+
+// Adds the blurred layer over the screen, brightened since blurring spreads it thin
+const COMPOSITE_SHADER: &str = "#version 100
+precision mediump float;
+
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform sampler2D Texture;
+
+void main() {
+    gl_FragColor = vec4(texture2D(Texture, uv).rgb * color.rgb * 2.5, 1.0);
+}
+";
+
+// Something that glows, in screen coordinates
+enum GlowShape {
+    Circle { center: Vec2, radius: f32, color: Color },
+    Ring { center: Vec2, radius: f32, width: f32, color: Color },
+    Line { from: Vec2, to: Vec2, width: f32, color: Color },
+}
+
+// Offscreen layers the glow is drawn and blurred in, at GLOW_DOWNSCALE of the window size
+struct GlowTargets {
+    size: (u32, u32),
+    layer: RenderTarget,
+    scratch: RenderTarget,
+}
+
+impl GlowTargets {
+    fn new(size: (u32, u32)) -> Self {
+        let target = || {
+            let target = render_target(size.0, size.1);
+            target.texture.set_filter(FilterMode::Linear);
+            target
+        };
+        Self { size, layer: target(), scratch: target() }
+    }
+}
+
+// Camera drawing into `target` with y down like the screen, (0, 0) to (width, height) covering it
+fn target_camera(target: &RenderTarget, width: f32, height: f32) -> Camera2D {
+    Camera2D {
+        zoom: vec2(2.0 / width, 2.0 / height),
+        target: vec2(width / 2.0, height / 2.0),
+        render_target: Some(target.clone()),
+        ..Default::default()
+    }
+}
+
+// Bloom over the bodies and live trails. The viewer hands over what should glow while it draws
+// the frame, then `draw` renders it again into an offscreen layer, blurs it and adds it on top.
+pub struct Glow {
+    pub enabled: bool,
+    shapes: Vec<GlowShape>,
+    // Blur and composite materials, None if the shaders didn't compile
+    materials: Option<(Material, Material)>,
+    targets: Option<GlowTargets>,
+}
+
+impl Glow {
+    pub fn new() -> Self {
+        let blur = load_material(ShaderSource::Glsl { vertex: VERTEX_SHADER, fragment: BLUR_SHADER }, MaterialParams {
+            uniforms: vec![UniformDesc::new("Direction", UniformType::Float2)],
+            ..Default::default()
+        });
+        let composite = load_material(ShaderSource::Glsl { vertex: VERTEX_SHADER, fragment: COMPOSITE_SHADER }, MaterialParams {
+            pipeline_params: PipelineParams {
+                color_blend: Some(BlendState::new(Equation::Add, BlendFactor::One, BlendFactor::One)),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let materials = match (blur, composite) {
+            (Ok(blur), Ok(composite)) => Some((blur, composite)),
+            (Err(e), _) | (_, Err(e)) => {
+                eprintln!("Glow unavailable: {:?}", e);
+                None
+            }
+        };
+        Self { enabled: false, shapes: Vec::new(), materials, targets: None }
+    }
+
+    pub fn available(&self) -> bool {
+        self.materials.is_some()
+    }
+
+    fn collecting(&self) -> bool {
+        self.enabled && self.available()
+    }
+
+    pub fn circle(&mut self, center: Vec2, radius: f32, color: Color) {
+        if self.collecting() {
+            self.shapes.push(GlowShape::Circle { center, radius, color });
+        }
+    }
+
+    pub fn ring(&mut self, center: Vec2, radius: f32, width: f32, color: Color) {
+        if self.collecting() {
+            self.shapes.push(GlowShape::Ring { center, radius, width, color });
+        }
+    }
+
+    pub fn line(&mut self, from: Vec2, to: Vec2, width: f32, color: Color) {
+        if self.collecting() {
+            self.shapes.push(GlowShape::Line { from, to, width, color });
+        }
+    }
+
+    // Blurs the shapes handed over since the last call and adds them over a view of `width` x
+    // `height` on the screen
+    pub fn draw(&mut self, width: f32, height: f32) {
+        let shapes = std::mem::take(&mut self.shapes);
+        let Some((blur, composite)) = self.materials.as_ref().filter(|_| self.enabled && !shapes.is_empty()) else {
+            return;
+        };
+        let size = ((width / GLOW_DOWNSCALE).max(1.0) as u32, (height / GLOW_DOWNSCALE).max(1.0) as u32);
+        let targets = match self.targets.take() {
+            Some(targets) if targets.size == size => targets,
+            _ => GlowTargets::new(size),
+        };
+
+        set_camera(&target_camera(&targets.layer, width, height));
+        clear_background(BLANK);
+        for shape in &shapes {
+            match *shape {
+                GlowShape::Circle { center, radius, color } => draw_circle(center.x, center.y, radius, color),
+                GlowShape::Ring { center, radius, width, color } => draw_circle_lines(center.x, center.y, radius, width, color),
+                GlowShape::Line { from, to, width, color } => draw_line(from.x, from.y, to.x, to.y, width, color),
+            }
+        }
+
+        // Blur back and forth between the layer and the scratch target, ending in the layer
+        let (layer_width, layer_height) = (size.0 as f32, size.1 as f32);
+        let full_size = DrawTextureParams { dest_size: Some(vec2(layer_width, layer_height)), ..Default::default() };
+        gl_use_material(blur);
+        for _ in 0..BLUR_PASSES {
+            for (from, to, direction) in [
+                (&targets.layer, &targets.scratch, vec2(1.0 / layer_width, 0.0)),
+                (&targets.scratch, &targets.layer, vec2(0.0, 1.0 / layer_height)),
+            ] {
+                blur.set_uniform("Direction", direction);
+                set_camera(&target_camera(to, layer_width, layer_height));
+                clear_background(BLANK);
+                draw_texture_ex(&from.texture, 0.0, 0.0, WHITE, full_size.clone());
+            }
+        }
+
+        set_default_camera();
+        gl_use_material(composite);
+        draw_texture_ex(&targets.layer.texture, 0.0, 0.0, WHITE, DrawTextureParams {
+            dest_size: Some(vec2(width, height)),
+            ..Default::default()
+        });
+        gl_use_default_material();
+        self.targets = Some(targets);
+    }
+}
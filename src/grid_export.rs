@@ -0,0 +1,110 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use exr::prelude::{AnyChannel, AnyChannels, Encoding, FlatSamples, Image, Layer, LayerAttributes, Text, WritableImage};
+
+use crate::image_gen::MapParams;
+use crate::outcome::OutcomeGrid;
+
+// Value of pixels that never collided in 16-bit collision time PNGs
+pub const ESCAPED_TIMESTEP: u16 = u16::MAX;
+
+// Writes the raw per-pixel data of a map for analysis elsewhere, in the format given by the
+// extension: .exr for float channels, .png for 16-bit grayscale collision timesteps, .npy for a
+// pair of NumPy arrays next to each other
+pub fn save_grid_data(path: &str, grid: &OutcomeGrid, params: &MapParams) -> Result<(), Box<dyn std::error::Error>> {
+    let extension = Path::new(path).extension().and_then(|ext| ext.to_str()).map(|ext| ext.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("exr") => save_exr(path, grid, params)?,
+        Some("png") => save_png16(path, grid, params)?,
+        // Reports the two files it writes itself
+        Some("npy") => return save_npy(path, grid, params),
+        _ => return Err(format!("unsupported grid data format for {}, expected .exr, .png or .npy", path).into()),
+    }
+    println!("Grid data saved to {}", path);
+    Ok(())
+}
+
+// OpenEXR with two float channels: "time", the simulated time at the start of the timestep the
+// particle collided in (infinite for pixels that never collided), and "body", the index of the
+// body hit (-1 if none)
+fn save_exr(path: &str, grid: &OutcomeGrid, params: &MapParams) -> Result<(), Box<dyn std::error::Error>> {
+    let dt = params.simulation.dt;
+    let times = grid.cells.iter().map(|cell| cell.map_or(f32::INFINITY, |(_, timestep)| timestep as f32 * dt)).collect();
+    let bodies = grid.cells.iter().map(|cell| cell.map_or(-1.0, |(index, _)| index as f32)).collect();
+    let channels = vec![
+        AnyChannel::new("time", FlatSamples::F32(times)),
+        AnyChannel::new("body", FlatSamples::F32(bodies)),
+    ];
+    let mut attributes = LayerAttributes::named("collisions");
+    attributes.comments = Some(Text::from(params.label().as_str()));
+    let layer = Layer::new(
+        (grid.width as usize, grid.height as usize),
+        attributes,
+        Encoding::FAST_LOSSLESS,
+        AnyChannels::sort(channels.into()),
+    );
+    Image::from_layer(layer).write().to_file(path)?;
+    Ok(())
+}
+
+// 16-bit grayscale PNG of the timestep each pixel collided at, ESCAPED_TIMESTEP for pixels that
+// never collided. Maps with more timesteps than fit are clamped just below it. The timestep
+// length is stored in a text chunk, so times can be recovered exactly.
+fn save_png16(path: &str, grid: &OutcomeGrid, params: &MapParams) -> Result<(), Box<dyn std::error::Error>> {
+    let writer = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(writer, grid.width, grid.height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Sixteen);
+    encoder.add_text_chunk("Timesteps".to_string(), grid.timesteps.to_string())?;
+    encoder.add_text_chunk("Timestep dt".to_string(), params.simulation.dt.to_string())?;
+    encoder.add_text_chunk("Escaped value".to_string(), ESCAPED_TIMESTEP.to_string())?;
+    encoder.add_text_chunk("View".to_string(), params.label())?;
+    let mut writer = encoder.write_header()?;
+    // PNG samples are big-endian
+    let data: Vec<u8> = grid.cells.iter()
+        .flat_map(|cell| cell.map_or(ESCAPED_TIMESTEP, |(_, timestep)| timestep.min(ESCAPED_TIMESTEP as usize - 1) as u16).to_be_bytes())
+        .collect();
+    writer.write_image_data(&data)?;
+    Ok(())
+}
+
+// Writes one array in NumPy's .npy format (version 1.0): a magic string, a little-endian header
+// length, then a Python dict literal describing the array, padded so the data starts on a
+// 64-byte boundary, followed by the raw row-major data
+fn write_npy(path: &Path, descr: &str, width: u32, height: u32, data: &[u8]) -> std::io::Result<()> {
+    let mut header = format!("{{'descr': '{}', 'fortran_order': False, 'shape': ({}, {}), }}", descr, height, width);
+    // Magic (6 bytes), version (2) and header length (2) come first, and the header ends in a newline
+    let unpadded = 10 + header.len() + 1;
+    header.push_str(&" ".repeat(unpadded.next_multiple_of(64) - unpadded));
+    header.push('\n');
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(b"\x93NUMPY\x01\x00")?;
+    writer.write_all(&(header.len() as u16).to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+    writer.write_all(data)?;
+    writer.flush()
+}
+
+// Two arrays of shape (height, width) next to `path`: <stem>_body.npy with the index of the body
+// each pixel hit as int32 (-1 if none), and <stem>_time.npy with the simulated time at the start
+// of the timestep it collided in as float32 (infinite if it never did), as in the EXR channels
+fn save_npy(path: &str, grid: &OutcomeGrid, params: &MapParams) -> Result<(), Box<dyn std::error::Error>> {
+    let path = Path::new(path);
+    let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("grid");
+    let dt = params.simulation.dt;
+    let bodies: Vec<u8> = grid.cells.iter()
+        .flat_map(|cell| cell.map_or(-1, |(index, _)| index as i32).to_le_bytes())
+        .collect();
+    let times: Vec<u8> = grid.cells.iter()
+        .flat_map(|cell| cell.map_or(f32::INFINITY, |(_, timestep)| timestep as f32 * dt).to_le_bytes())
+        .collect();
+    let body_path = path.with_file_name(format!("{}_body.npy", stem));
+    let time_path = path.with_file_name(format!("{}_time.npy", stem));
+    write_npy(&body_path, "<i4", grid.width, grid.height, &bodies)?;
+    write_npy(&time_path, "<f4", grid.width, grid.height, &times)?;
+    println!("Grid data saved to {} and {}", body_path.display(), time_path.display());
+    Ok(())
+}
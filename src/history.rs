@@ -0,0 +1,74 @@
+use std::collections::VecDeque;
+
+use gravity_wells::deep_zoom::DeepPoint;
+use gravity_wells::physics::Vec2;
+use gravity_wells::scene::Scene;
+use gravity_wells::simulation::SimulationParams;
+use serde::{Deserialize, Serialize};
+
+// Edits kept for undo; the oldest are dropped beyond this
+const UNDO_LEVELS: usize = 50;
+
+// The viewer's settings an undo step restores
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ViewSettings {
+    pub initial_velocity: Vec2,
+    pub camera: DeepPoint,
+    pub zoom_factor: f32,
+    pub use_runge_kutta: bool,
+    pub sim_params: SimulationParams,
+    pub scene: Scene,
+}
+
+// Undo stack of the viewer's settings. Consecutive frames that change them, like holding a key,
+// dragging a slider or the camera gliding after a drag, make up one edit, so a single undo goes
+// back to before all of it.
+pub struct History {
+    undo: VecDeque<ViewSettings>,
+    current: ViewSettings,
+    editing: bool,
+    // The settings the viewer opened with, kept after the undo stack drops them
+    initial: ViewSettings,
+}
+
+impl History {
+    pub fn new(settings: ViewSettings) -> Self {
+        Self { undo: VecDeque::new(), current: settings.clone(), editing: false, initial: settings }
+    }
+
+    pub fn initial(&self) -> &ViewSettings {
+        &self.initial
+    }
+
+    // Call with the settings after every frame that changed them
+    pub fn record(&mut self, settings: ViewSettings) {
+        if settings == self.current {
+            return;
+        }
+        let previous = std::mem::replace(&mut self.current, settings);
+        if !self.editing {
+            self.undo.push_back(previous);
+            if self.undo.len() > UNDO_LEVELS {
+                self.undo.pop_front();
+            }
+        }
+        self.editing = true;
+    }
+
+    // Call after frames that changed nothing, ending the edit in progress
+    pub fn settle(&mut self) {
+        self.editing = false;
+    }
+
+    // Settings from before the latest edit, which become the current ones
+    pub fn undo(&mut self) -> Option<ViewSettings> {
+        let settings = self.undo.pop_back()?;
+        self.current = settings.clone();
+        self.editing = false;
+        Some(settings)
+    }
+
+    pub fn len(&self) -> usize {
+        self.undo.len()
+    }
+}
@@ -0,0 +1,172 @@
+use macroquad::prelude::*;
+
+use crate::keybindings::Keybindings;
+
+// Key bindings listed in the help panel
+pub const CONTROLS: &[&str] = &[
+    "Arrow keys: Change initial velocity",
+    "Home/End (hold): Fire thrust prograde/retrograde",
+    "PageUp/PageDown: Increase/decrease thrust",
+    "WASD / right or middle drag: Move camera",
+    "Q/E: Zoom out/in, Mouse wheel: Zoom at cursor",
+    "+/-: Change step size",
+    "Space: Toggle integration method",
+    "Enter: Recalculate image",
+    "T/U/K (+Shift): Timesteps/substeps/collision radius",
+    "C: Pin/unpin map for comparison",
+    "X: Toggle difference view",
+    "Left click: Start simulation (Shift: add another)",
+    "Click a body: Select, then wheel or [/] to change its mass",
+    "M: Cycle single/ensemble launch, Ctrl+M: Sound effects",
+    "Backspace: Clear simulations",
+    "Ctrl+Z: Undo, Ctrl+R: Reset velocity/camera/integrator",
+    "L: Long exposure, Shift+L: Save exposure",
+    "Escape: Cancel map generation",
+    "V: Record GIF, Shift+V: Record PNG frames",
+    "Y: Export trajectory CSV, Shift+Y: JSON",
+    "F3: Energy drift of each integrator, Shift+F3: Save CSV",
+    "F4: Fitted Kepler orbit, F5: Zero-velocity curve",
+    "F6: Phase space panel (x, y, r), F7: Escaped pixel coloring",
+    "F8: World grid and scale bar, Shift+F8: Minimap",
+    "F9: Save SVG figure",
+    "F10: Basin boundaries, Shift+F10: Save GeoJSON",
+    "F11: Save raw grid (EXR), Shift+F11: 16-bit PNG",
+    "F12: Time-reversal check of the latest launch",
+    "Shift+F12: Annotated screenshot, Ctrl+F12: Screenshot",
+    "G: Trail coloring (solid/speed/time), Shift+G: Glow",
+    "O: Toggle hover preview, I: Pixel inspector",
+    "F: Velocity/acceleration arrows, Shift+F: Arrow scale",
+    "P/N: Pause/step simulation, ,/.: Slower/faster",
+    "R: Replay mode, [/]: Scrub trajectory",
+    "H: HUD full/compact/hidden, Shift+H: Move HUD",
+    "1-5: Preset scenes, Z: Random scene",
+    "B: Bookmark view, J: Jump to next bookmark",
+    "Ctrl+B: Starfield background",
+    "Tab: Toggle control panel",
+    "F2: Export 4K image, Shift+F2: Export 8K image",
+    "F1: Toggle this help",
+];
+
+const PANEL_MARGIN: f32 = 8.0;
+const PANEL_PADDING: f32 = 6.0;
+const FONT_SIZE: f32 = 16.0;
+const LINE_HEIGHT: f32 = 18.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HudMode {
+    Full,
+    // Single line with the most important settings
+    Compact,
+    Hidden,
+}
+
+impl HudMode {
+    pub fn next(self) -> Self {
+        match self {
+            HudMode::Full => HudMode::Compact,
+            HudMode::Compact => HudMode::Hidden,
+            HudMode::Hidden => HudMode::Full,
+        }
+    }
+}
+
+// Window corner the status panel is anchored to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HudCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl HudCorner {
+    pub fn next(self) -> Self {
+        match self {
+            HudCorner::TopLeft => HudCorner::TopRight,
+            HudCorner::TopRight => HudCorner::BottomRight,
+            HudCorner::BottomRight => HudCorner::BottomLeft,
+            HudCorner::BottomLeft => HudCorner::TopLeft,
+        }
+    }
+
+    fn is_left(self) -> bool {
+        matches!(self, HudCorner::TopLeft | HudCorner::BottomLeft)
+    }
+
+    fn is_top(self) -> bool {
+        matches!(self, HudCorner::TopLeft | HudCorner::TopRight)
+    }
+}
+
+pub struct HudLine {
+    pub text: String,
+    pub color: Color,
+}
+
+impl HudLine {
+    pub fn new(text: impl Into<String>, color: Color) -> Self {
+        Self { text: text.into(), color }
+    }
+}
+
+// On-screen status overlay and help panel
+pub struct Hud {
+    pub mode: HudMode,
+    pub corner: HudCorner,
+    pub show_help: bool,
+}
+
+impl Hud {
+    pub fn new() -> Self {
+        Self { mode: HudMode::Full, corner: HudCorner::TopLeft, show_help: false }
+    }
+
+    // H cycles the HUD mode, Shift+H moves it to the next corner, F1 toggles the help panel
+    pub fn handle_input(&mut self, bindings: &Keybindings, shift_down: bool) {
+        if bindings.key_pressed(KeyCode::H) {
+            if shift_down {
+                self.corner = self.corner.next();
+            } else {
+                self.mode = self.mode.next();
+            }
+        }
+        if bindings.key_pressed(KeyCode::F1) {
+            self.show_help = !self.show_help;
+        }
+    }
+
+    pub fn draw(&self, status_lines: &[HudLine], compact_line: &HudLine) {
+        match self.mode {
+            HudMode::Full => draw_panel(status_lines, self.corner),
+            HudMode::Compact => draw_panel(std::slice::from_ref(compact_line), self.corner),
+            HudMode::Hidden => {}
+        }
+
+        // The help panel sits on the opposite side from the status panel so they never overlap
+        if self.show_help {
+            let help_corner = if self.corner.is_left() { HudCorner::TopRight } else { HudCorner::TopLeft };
+            let mut lines = vec![HudLine::new("Controls:", WHITE)];
+            lines.extend(CONTROLS.iter().map(|line| HudLine::new(format!("• {}", line), WHITE)));
+            draw_panel(&lines, help_corner);
+        }
+    }
+}
+
+// Draws lines of text on a translucent background anchored to a corner of the window
+pub fn draw_panel(lines: &[HudLine], corner: HudCorner) {
+    if lines.is_empty() {
+        return;
+    }
+    let width = lines.iter()
+        .map(|line| measure_text(&line.text, None, FONT_SIZE as u16, 1.0).width)
+        .fold(0.0, f32::max) + 2.0 * PANEL_PADDING;
+    let height = lines.len() as f32 * LINE_HEIGHT + 2.0 * PANEL_PADDING;
+
+    let x = if corner.is_left() { PANEL_MARGIN } else { screen_width() - PANEL_MARGIN - width };
+    let y = if corner.is_top() { PANEL_MARGIN } else { screen_height() - PANEL_MARGIN - height };
+    draw_rectangle(x, y, width, height, Color::new(0.0, 0.0, 0.0, 0.6));
+    for (i, line) in lines.iter().enumerate() {
+        let baseline = y + PANEL_PADDING + LINE_HEIGHT * (i as f32 + 1.0) - 4.0;
+        draw_text(&line.text, x + PANEL_PADDING, baseline, FONT_SIZE, line.color);
+    }
+}
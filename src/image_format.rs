@@ -0,0 +1,115 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use image::codecs::bmp::BmpEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::RgbImage;
+
+use crate::image_gen::MapParams;
+use crate::metadata::save_png_with_metadata;
+use crate::scene::Scene;
+
+pub const DEFAULT_QUALITY: u8 = 90;
+
+// File formats map images can be saved in. Only PNG carries the map's parameters in its
+// metadata, so only PNGs can be reopened with --from-png.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RasterFormat {
+    #[default]
+    Png,
+    // Lossless, the only WebP mode the encoder supports
+    WebP,
+    Jpeg,
+    Bmp,
+}
+
+impl RasterFormat {
+    pub const ALL: [RasterFormat; 4] = [RasterFormat::Png, RasterFormat::WebP, RasterFormat::Jpeg, RasterFormat::Bmp];
+
+    // Identifier used on the command line, which is also the file extension
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            RasterFormat::Png => "png",
+            RasterFormat::WebP => "webp",
+            RasterFormat::Jpeg => "jpg",
+            RasterFormat::Bmp => "bmp",
+        }
+    }
+
+    // Format for a path's extension, if it is one of the supported ones
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+        if extension == "jpeg" {
+            return Some(RasterFormat::Jpeg);
+        }
+        extension.parse().ok()
+    }
+}
+
+impl std::str::FromStr for RasterFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "jpeg" {
+            return Ok(RasterFormat::Jpeg);
+        }
+        RasterFormat::ALL.iter()
+            .find(|format| format.short_name() == s)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = RasterFormat::ALL.iter().map(|format| format.short_name()).collect();
+                format!("unknown image format '{}', expected one of: {}", s, names.join(", "))
+            })
+    }
+}
+
+// How a rendered image is written: the format, or None to go by the file extension (PNG if it
+// isn't a supported one), and the JPEG quality from 1 to 100
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImageOutput {
+    pub format: Option<RasterFormat>,
+    pub quality: u8,
+}
+
+impl Default for ImageOutput {
+    fn default() -> Self {
+        Self { format: None, quality: DEFAULT_QUALITY }
+    }
+}
+
+impl ImageOutput {
+    pub fn format_for(&self, path: &str) -> RasterFormat {
+        self.format.or_else(|| RasterFormat::from_path(path)).unwrap_or_default()
+    }
+
+    // File extension of outputs whose name the program picks, e.g. animation frames
+    pub fn extension(&self) -> &'static str {
+        self.format.unwrap_or_default().short_name()
+    }
+
+    // Saves `img` to `path`; PNGs get the map parameters and scene as metadata
+    pub fn save(&self, img: &RgbImage, path: &str, params: &MapParams, scene: &Scene) -> Result<(), Box<dyn std::error::Error>> {
+        match self.format_for(path) {
+            RasterFormat::Png => save_png_with_metadata(img, path, params, scene),
+            format => self.save_plain(img, path, format),
+        }
+    }
+
+    // Saves `img` to `path` without metadata
+    pub fn save_image(&self, img: &RgbImage, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.save_plain(img, path, self.format_for(path))
+    }
+
+    fn save_plain(&self, img: &RgbImage, path: &str, format: RasterFormat) -> Result<(), Box<dyn std::error::Error>> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        match format {
+            RasterFormat::Png => img.write_to(&mut writer, image::ImageFormat::Png)?,
+            RasterFormat::WebP => img.write_with_encoder(WebPEncoder::new_lossless(&mut writer))?,
+            RasterFormat::Jpeg => img.write_with_encoder(JpegEncoder::new_with_quality(&mut writer, self.quality.clamp(1, 100)))?,
+            RasterFormat::Bmp => img.write_with_encoder(BmpEncoder::new(&mut writer))?,
+        }
+        Ok(())
+    }
+}
@@ -1,80 +1,919 @@
-use image::{ImageBuffer, Rgb};
+use image::{ImageBuffer, Rgb, RgbImage};
 use rayon::prelude::*;
 use indicatif::ProgressBar;
+use std::borrow::Cow;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
-use crate::physics::{Vec2, StationaryBody};
-use crate::simulation::{run_simulation_with_time, IntegrationMethod};
-use crate::config::{IMAGE_SIZE, DEFAULT_NON_COLLISION_COLOR};
+use crate::physics::{Vec2, Body};
+use crate::simulation::{run_simulation_with_stats, run_simulation_with_time, IntegrationMethod, SimulationParams};
+use crate::escape::{escaped_color, EscapeColoring};
+use crate::config::{BODY_STROKE_WIDTH, DEFAULT_NON_COLLISION_COLOR, HORIZON_TINT, IMAGE_SIZE};
+use crate::force_field::BodyShape;
+use crate::outcome::OutcomeGrid;
+use crate::scene::Scene;
+use crate::axes::{format_length, scale_bar, WorldGrid, GRID_SPACING};
+use crate::render::{draw_text, fill_circle, fill_near, fill_rect, hsv_to_rgb, rgb_to_hsv, text_width};
+use crate::image_format::ImageOutput;
+use crate::progress::CancelToken;
+use crate::barnes_hut::{BARNES_HUT_BODIES, OPENING_ANGLE};
+use crate::batch::{run_simulation_batch, LANES};
+use crate::precise::run_simulation_with_time_f64;
+use crate::camera::Camera;
+use crate::deep_zoom::{DeepPoint, DeepView, DEEP_ZOOM_THRESHOLD};
+use serde::{Deserialize, Serialize};
 
+// Full set of parameters that determine a generated basin map
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MapParams {
+    // Size of the map in pixels
+    pub width: u32,
+    pub height: u32,
+    pub initial_velocity: Vec2,
+    pub camera_offset: Vec2,
+    pub zoom_factor: f32,
+    pub integration_method: IntegrationMethod,
+    pub simulation: SimulationParams,
+    // Scene::cache_key of the bodies and gravitational constant the map was generated for
+    pub scene_key: u64,
+    // camera_offset at full precision for views zoomed past DEEP_ZOOM_THRESHOLD
+    #[serde(default)]
+    pub deep_camera: Option<DeepPoint>,
+    // How pixels that never collide are colored
+    #[serde(default)]
+    pub escape_coloring: EscapeColoring,
+}
+
+impl MapParams {
+    // The view the viewer starts in (camera at the origin, zoom 1, at rest) for a window
+    // IMAGE_SIZE pixels wide with the aspect ratio of width x height
+    pub fn initial_view(scene: &Scene, simulation: SimulationParams, width: u32, height: u32) -> MapParams {
+        MapParams {
+            width: IMAGE_SIZE,
+            height: ((IMAGE_SIZE as u64 * height as u64) / width.max(1) as u64).max(1) as u32,
+            initial_velocity: Vec2::new(0.0, 0.0),
+            camera_offset: Vec2::new(0.0, 0.0),
+            zoom_factor: 1.0,
+            integration_method: IntegrationMethod::RungeKutta4,
+            simulation: simulation.resolved(scene, 1.0),
+            scene_key: scene.cache_key(),
+            deep_camera: None,
+            escape_coloring: EscapeColoring::Uniform,
+        }
+    }
+
+    // Pixel/world transform of the map
+    pub fn camera(&self) -> Camera {
+        Camera::new(self.camera_offset, self.zoom_factor, Vec2::new(self.width as f32, self.height as f32))
+    }
+
+    // The camera at the highest precision available
+    pub fn precise_camera(&self) -> DeepPoint {
+        self.deep_camera.unwrap_or_else(|| DeepPoint::from_vec2(self.camera_offset))
+    }
+
+    // Moves the camera, keeping the full precision position only when the zoom needs it
+    pub fn with_camera(self, camera: DeepPoint) -> MapParams {
+        let deep_camera = (self.zoom_factor >= DEEP_ZOOM_THRESHOLD).then_some(camera);
+        MapParams { camera_offset: camera.to_vec2(), deep_camera, ..self }
+    }
+
+    // Cache filename for the map image; every parameter is encoded so stale images are never reused
+    pub fn filename(&self) -> String {
+        format!("gravity_wells_{}_{}x{}_{:.1}_{:.1}_{:.1}_{:.1}_{:.2}_{}_{}_{:.1}_{:.4}_{:016x}{}{}{}{}.png",
+                self.integration_method.short_name(),
+                self.width, self.height,
+                self.initial_velocity.x, self.initial_velocity.y,
+                self.camera_offset.x, self.camera_offset.y,
+                self.zoom_factor,
+                self.simulation.timesteps, self.simulation.substeps, self.simulation.collision_threshold,
+                self.simulation.dt, self.scene_key,
+                if self.simulation.double_precision { "_f64" } else { "" },
+                self.barnes_hut_tag(),
+                self.deep_camera.map_or(String::new(), |camera| format!("_deep{:016x}", camera.cache_key())),
+                self.escape_coloring_tag())
+    }
+
+    fn escape_coloring_tag(&self) -> String {
+        if self.tracks_escapes() {
+            format!("_{}", self.escape_coloring.short_name())
+        } else {
+            String::new()
+        }
+    }
+
+    // Whether pixels that never collide are shaded by statistics of their trajectory. The
+    // statistics are gathered by the f32 integrator only, so f64 and deep zoom maps keep
+    // escaped pixels uniform.
+    pub fn tracks_escapes(&self) -> bool {
+        self.escape_coloring != EscapeColoring::Uniform && !self.simulation.double_precision && self.deep_camera.is_none()
+    }
+
+    // Maps made with non-default Barnes-Hut settings are cached separately
+    fn barnes_hut_tag(&self) -> String {
+        let SimulationParams { opening_angle, barnes_hut_bodies, .. } = self.simulation;
+        if opening_angle == OPENING_ANGLE && barnes_hut_bodies == BARNES_HUT_BODIES {
+            String::new()
+        } else {
+            format!("_bh{:.2}_{}", opening_angle, barnes_hut_bodies)
+        }
+    }
+
+    pub fn grid_filename(&self) -> String {
+        grid_filename(&self.filename())
+    }
+
+    // The same view of the world rendered at a different resolution
+    pub fn at_resolution(&self, width: u32, height: u32) -> MapParams {
+        let scale = width as f32 / self.width as f32;
+        MapParams { width, height, zoom_factor: self.zoom_factor * scale, ..*self }.with_camera(self.precise_camera())
+    }
+
+    // Short human readable summary, used for on-screen labels
+    pub fn label(&self) -> String {
+        format!("{} v=({:.1}, {:.1}) zoom {}x{}",
+                self.integration_method.name(),
+                self.initial_velocity.x, self.initial_velocity.y,
+                zoom_text(self.zoom_factor),
+                if self.simulation.double_precision { " f64" } else { "" })
+    }
+}
+
+// Zoom factor for display; deep zooms are too long to read in full
+pub fn zoom_text(zoom_factor: f32) -> String {
+    if zoom_factor >= DEEP_ZOOM_THRESHOLD {
+        format!("{:.3e}", zoom_factor)
+    } else {
+        format!("{:.2}", zoom_factor)
+    }
+}
+
+// Returns the filename of the map for these parameters, generating it first if it (or its raw outcome grid)
+// isn't cached on disk
+pub fn ensure_map_image(
+    scene: &Scene,
+    params: &MapParams,
+    progress: &(impl Fn(usize, usize) + Sync),
+    cancel: &CancelToken,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let filename = params.filename();
+    if std::path::Path::new(&filename).exists() && std::path::Path::new(&params.grid_filename()).exists() {
+        println!("Found existing image: {}", filename);
+    } else {
+        println!("Generating new {}x{} image with velocity ({:.1}, {:.1}), camera ({:.1}, {:.1}), zoom {}...",
+                 params.width, params.height,
+                 params.initial_velocity.x, params.initial_velocity.y,
+                 params.camera_offset.x, params.camera_offset.y, zoom_text(params.zoom_factor));
+        generate_gravity_wells_image(scene, params, &filename, &ImageOutput::default(), progress, cancel)?;
+    }
+    Ok(filename)
+}
+
+// The image is saved in the format `output` picks for `filename`, with the parameters in its
+// metadata if that is PNG. `progress` is called with the number of pixels done and the total as
+// generation proceeds. Cancelling `cancel` aborts the generation with an error and nothing is saved.
 pub fn generate_gravity_wells_image(
-    stationary_bodies: &[StationaryBody], 
-    initial_velocity: Vec2,
-    camera_offset: Vec2,
-    zoom_factor: f32,
+    scene: &Scene,
+    params: &MapParams,
     filename: &str,
-    integration_method: IntegrationMethod
+    output: &ImageOutput,
+    progress: &(impl Fn(usize, usize) + Sync),
+    cancel: &CancelToken,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Generating gravity wells image using {:?} integration...", 
-             match integration_method {
-                 IntegrationMethod::Euler => "Euler",
-                 IntegrationMethod::RungeKutta4 => "Runge-Kutta 4",
-             });
+    println!("Generating gravity wells image using {} integration...", params.integration_method.name());
     
-    let num_pixels = (IMAGE_SIZE * IMAGE_SIZE) as usize;
-    let mut pixels = vec![Rgb(DEFAULT_NON_COLLISION_COLOR); num_pixels];
+    let grid = compute_outcome_grid_with_progress(scene, params, progress, cancel).ok_or("generation cancelled")?;
+    let img = render_outcome_grid(&grid, &scene.bodies);
+
+    output.save(&img, filename, params, scene)?;
+    println!("Gravity wells image saved to {}", filename);
+
+    // Keep the raw outcomes next to the image so they can be analysed later
+    let grid_path = grid_filename(filename);
+    grid.save(&grid_path)?;
+    println!("Raw outcome grid saved to {}", grid_path);
     
-    let bar = ProgressBar::new(num_pixels as u64);
+    Ok(())
+}
+
+// Filename of the raw outcome grid stored alongside a map image
+pub fn grid_filename(image_filename: &str) -> String {
+    std::path::Path::new(image_filename).with_extension("grid").to_string_lossy().into_owned()
+}
+
+// Progress callback that draws a progress bar in the terminal
+pub fn terminal_progress() -> impl Fn(usize, usize) + Sync {
+    let bar = ProgressBar::new(0);
+    move |done, total| {
+        bar.set_length(total as u64);
+        bar.set_position(done as u64);
+        if done == total {
+            bar.finish();
+        }
+    }
+}
+
+// Counts finished pixels from the parallel loops, calling `progress` every PROGRESS_INTERVAL
+// pixels and once at the end
+const PROGRESS_INTERVAL: usize = 1000;
+
+pub fn count_pixel(counter: &AtomicUsize, total: usize, progress: &(impl Fn(usize, usize) + Sync)) {
+    let done = counter.fetch_add(1, Ordering::Relaxed) + 1;
+    if done.is_multiple_of(PROGRESS_INTERVAL) || done == total {
+        progress(done, total);
+    }
+}
+
+// Prepared deep zoom for views past DEEP_ZOOM_THRESHOLD. Its linearized dynamics don't cover
+// force fields, oblate, moving or dynamic bodies, the post-Newtonian correction, bounces or walls, so
+// those scenes simulate every pixel from its f32 or f64 start position instead, which blurs at
+// the deepest zooms.
+fn deep_view(scene: &Scene, params: &MapParams) -> Option<DeepView> {
+    params.deep_camera
+        .filter(|_| !scene.has_force_fields() && !scene.has_oblate_bodies() && !scene.is_relativistic() && !scene.has_moving_bodies())
+        .filter(|_| !scene.bounces() && scene.walls.is_none() && !scene.is_dynamic())
+        .map(|camera| DeepView::new(scene, params, camera))
+}
+
+// Runs the simulation for every pixel and records which body it hit and when, with progress
+// shown in the terminal
+pub fn compute_outcome_grid(scene: &Scene, params: &MapParams) -> OutcomeGrid {
+    compute_outcome_grid_with_progress(scene, params, &terminal_progress(), &CancelToken::new()).unwrap()
+}
+
+// Returns None if `cancel` was cancelled before every pixel was simulated
+pub fn compute_outcome_grid_with_progress(
+    scene: &Scene,
+    params: &MapParams,
+    progress: &(impl Fn(usize, usize) + Sync),
+    cancel: &CancelToken,
+) -> Option<OutcomeGrid> {
+    let MapParams { width, height, initial_velocity, integration_method, simulation, .. } = *params;
+    let camera = params.camera();
+    let num_pixels = (width * height) as usize;
+    let mut cells = vec![None; num_pixels];
     let counter = AtomicUsize::new(0);
+    let deep_view = deep_view(scene, params);
+
+    // Escape coloring needs statistics along every trajectory, which the SIMD batches don't
+    // gather, so those maps are simulated pixel by pixel
+    if params.tracks_escapes() {
+        let mut shades = vec![f32::NAN; num_pixels];
+        cells.par_iter_mut().zip(shades.par_iter_mut()).enumerate().for_each(|(i, (cell, shade))| {
+            if cancel.is_cancelled() {
+                return;
+            }
+            let px = (i % width as usize) as f32;
+            let py = (i / width as usize) as f32;
+            let world_pos = camera.screen_to_world(Vec2::new(px, py));
+            let (outcome, stats) = run_simulation_with_stats(world_pos, initial_velocity, scene, integration_method, &simulation);
+            *cell = outcome;
+            if outcome.is_none() {
+                *shade = stats.shade(params.escape_coloring, scene, &simulation).unwrap_or(f32::NAN);
+            }
+            count_pixel(&counter, num_pixels, progress);
+        });
+        if cancel.is_cancelled() {
+            return None;
+        }
+        return Some(OutcomeGrid::new(width, height, simulation.timesteps, cells).with_escape_shades(shades));
+    }
     
-    pixels.par_iter_mut().enumerate().for_each(|(i, pixel)| {
-        let px = (i % IMAGE_SIZE as usize) as u32;
-        let py = (i / IMAGE_SIZE as usize) as u32;
-
-        // Transform pixel coordinates to world coordinates accounting for camera and zoom
-        let world_pos = Vec2::new(
-            (px as f32) / zoom_factor - camera_offset.x,
-            (py as f32) / zoom_factor - camera_offset.y
-        );
-        
-        if let Some((collision_index, collision_time)) = run_simulation_with_time(world_pos, initial_velocity, stationary_bodies, integration_method) {
-            // Color with intensity based on collision time
-            let body_color = stationary_bodies[collision_index].color;
-            
-            // Calculate intensity: 1.0 for immediate collision, fading to 0.0 for max timesteps
-            let max_time = crate::simulation::SIMULATION_TIMESTEPS as f32;
-            let intensity = (1.0 - (collision_time as f32 / max_time)).max(0.0);
-            
-            // Apply intensity to the body's color, with minimum intensity to keep it visible
-            let min_intensity = 0.15; // Minimum visibility
-            let max_intensity = 0.85; // Maximum intensity for pixels (less than full)
-            let final_intensity = intensity * (max_intensity - min_intensity) + min_intensity;
-            
-            let colored_pixel = [
-                (body_color[0] as f32 * final_intensity) as u8,
-                (body_color[1] as f32 * final_intensity) as u8,
-                (body_color[2] as f32 * final_intensity) as u8,
-            ];
-            *pixel = Rgb(colored_pixel);
-        }
-        // Otherwise keep default dark color
-        
-        // Update progress bar occasionally
-        let count = counter.fetch_add(1, Ordering::Relaxed);
-        if count % 1000 == 0 {
-            bar.set_position(count as u64);
+    // Pixels are simulated in batches of LANES consecutive pixels, advanced together with SIMD
+    cells.par_chunks_mut(LANES).enumerate().for_each(|(chunk, batch)| {
+        // Skip the remaining pixels once cancelled; each costs a full simulation
+        if cancel.is_cancelled() {
+            return;
+        }
+        if let Some(deep_view) = &deep_view {
+            for (offset, cell) in batch.iter_mut().enumerate() {
+                let i = chunk * LANES + offset;
+                *cell = deep_view.outcome_at((i % width as usize) as f64, (i / width as usize) as f64);
+                count_pixel(&counter, num_pixels, progress);
+            }
+            return;
+        }
+        // The SIMD batches are f32 only, so double precision simulates pixel by pixel
+        if simulation.double_precision {
+            for (offset, cell) in batch.iter_mut().enumerate() {
+                let i = chunk * LANES + offset;
+                let px = (i % width as usize) as f64;
+                let py = (i / width as usize) as f64;
+                let world_pos = camera.screen_to_world_f64(px, py);
+                *cell = run_simulation_with_time_f64(world_pos, initial_velocity, scene, integration_method, &simulation);
+                count_pixel(&counter, num_pixels, progress);
+            }
+            return;
+        }
+        let start_positions: Vec<Vec2> = (0..batch.len())
+            .map(|offset| {
+                let i = chunk * LANES + offset;
+                let px = (i % width as usize) as u32;
+                let py = (i / width as usize) as u32;
+                camera.screen_to_world(Vec2::new(px as f32, py as f32))
+            })
+            .collect();
+
+        let outcomes = run_simulation_batch(&start_positions, initial_velocity, scene, integration_method, &simulation);
+        for (cell, outcome) in batch.iter_mut().zip(outcomes) {
+            *cell = outcome;
+            count_pixel(&counter, num_pixels, progress);
         }
     });
-    
-    bar.finish();
 
-    let mut img = ImageBuffer::new(IMAGE_SIZE, IMAGE_SIZE);
-    for (px, py, pixel) in img.enumerate_pixels_mut() {
-        *pixel = pixels[(py * IMAGE_SIZE + px) as usize];
+    if cancel.is_cancelled() {
+        return None;
     }
+    Some(OutcomeGrid::new(width, height, simulation.timesteps, cells))
+}
 
-    img.save(filename)?;
-    println!("Gravity wells image saved to {}", filename);
-    
-    Ok(())
+// Intensity range of collided pixels: the minimum keeps slow collisions visible, the maximum
+// stays below full so the bodies themselves stand out
+pub const MIN_INTENSITY: f32 = 0.15;
+pub const MAX_INTENSITY: f32 = 0.85;
+
+// Colors each pixel by the body it hit, brighter for faster collisions. Escaped pixels are
+// shaded by their trajectory statistics when the grid has them.
+pub fn render_outcome_grid(grid: &OutcomeGrid, stationary_bodies: &[Body]) -> RgbImage {
+    ImageBuffer::from_fn(grid.width, grid.height, |px, py| {
+        match (grid.get(px, py), grid.escape_shade(px, py)) {
+            (None, Some(shade)) => Rgb(escaped_color(shade)),
+            (outcome, _) => Rgb(outcome_color(outcome, grid.timesteps, stationary_bodies)),
+        }
+    })
+}
+
+// Color pixels that hit `body` are shaded from: its own color, tinted for black holes so captures
+// by a horizon stand out from collisions with a surface
+pub fn capture_color(body: &Body) -> [u8; 3] {
+    if !body.shape.is_black_hole() {
+        return body.color;
+    }
+    std::array::from_fn(|c| ((body.color[c] as u16 + HORIZON_TINT[c] as u16) / 2) as u8)
+}
+
+// Color of a single outcome: the body's color with intensity based on collision time, or the
+// default dark color if the particle never collided
+pub fn outcome_color(outcome: Option<(usize, usize)>, timesteps: usize, stationary_bodies: &[Body]) -> [u8; 3] {
+    let Some((collision_index, collision_time)) = outcome else {
+        return DEFAULT_NON_COLLISION_COLOR;
+    };
+    let body_color = capture_color(&stationary_bodies[collision_index]);
+
+    // Calculate intensity: 1.0 for immediate collision, fading to 0.0 for max timesteps
+    let max_time = timesteps as f32;
+    let intensity = (1.0 - (collision_time as f32 / max_time)).max(0.0);
+
+    // Apply intensity to the body's color, with minimum intensity to keep it visible
+    let final_intensity = intensity * (MAX_INTENSITY - MIN_INTENSITY) + MIN_INTENSITY;
+
+    [
+        (body_color[0] as f32 * final_intensity) as u8,
+        (body_color[1] as f32 * final_intensity) as u8,
+        (body_color[2] as f32 * final_intensity) as u8,
+    ]
+}
+
+// How collided pixels of exported maps are colored
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ColorEncoding {
+    // The body's color, brighter for faster collisions, with supersamples averaged together
+    #[default]
+    BodyTime,
+    // Hue from the body hit by most supersamples, brightness from their collision time, and
+    // saturation from the share of supersamples that agree, so uncertain pixels fade to gray
+    Combined,
+}
+
+impl ColorEncoding {
+    pub const ALL: [ColorEncoding; 2] = [ColorEncoding::BodyTime, ColorEncoding::Combined];
+
+    // Identifier used on the command line and in export filenames
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            ColorEncoding::BodyTime => "body-time",
+            ColorEncoding::Combined => "combined",
+        }
+    }
+}
+
+impl std::str::FromStr for ColorEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ColorEncoding::ALL.iter()
+            .find(|encoding| encoding.short_name() == s)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = ColorEncoding::ALL.iter().map(|encoding| encoding.short_name()).collect();
+                format!("unknown color encoding '{}', expected one of: {}", s, names.join(", "))
+            })
+    }
+}
+
+// Colors of the bodies' basins in exported maps
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BasinPalette {
+    // Each body's own color
+    #[default]
+    Scene,
+    // The Okabe-Ito palette, whose colors stay distinct under the common kinds of color
+    // blindness, assigned in body order
+    OkabeIto,
+}
+
+// Okabe-Ito's colors without its black, which would vanish among escaped pixels
+const OKABE_ITO: [[u8; 3]; 7] = [
+    [230, 159, 0], [86, 180, 233], [0, 158, 115], [240, 228, 66], [0, 114, 178], [213, 94, 0], [204, 121, 167],
+];
+
+impl BasinPalette {
+    pub const ALL: [BasinPalette; 2] = [BasinPalette::Scene, BasinPalette::OkabeIto];
+
+    // Identifier used on the command line and in export filenames
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            BasinPalette::Scene => "scene",
+            BasinPalette::OkabeIto => "okabe-ito",
+        }
+    }
+
+    // The scene with its bodies in the palette's colors, so the map, the bodies drawn over it and
+    // the legend all agree
+    pub fn recolor<'a>(&self, scene: &'a Scene) -> Cow<'a, Scene> {
+        match self {
+            BasinPalette::Scene => Cow::Borrowed(scene),
+            BasinPalette::OkabeIto => {
+                let mut recolored = scene.clone();
+                for (i, body) in recolored.bodies.iter_mut().enumerate() {
+                    body.color = OKABE_ITO[i % OKABE_ITO.len()];
+                }
+                Cow::Owned(recolored)
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for BasinPalette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BasinPalette::ALL.iter()
+            .find(|palette| palette.short_name() == s)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = BasinPalette::ALL.iter().map(|palette| palette.short_name()).collect();
+                format!("unknown palette '{}', expected one of: {}", s, names.join(", "))
+            })
+    }
+}
+
+// Texture laid over a body's basin in exports, so basins can be told apart without color, e.g.
+// printed in grayscale
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BasinPattern {
+    Plain,
+    Diagonal,
+    AntiDiagonal,
+    Horizontal,
+    Vertical,
+    Dots,
+    Crosshatch,
+    Grid,
+}
+
+// Basin of a pixel without a body, in the per-pixel basins of render_supersampled
+pub const NO_BASIN: u8 = u8::MAX;
+
+impl BasinPattern {
+    // Patterns in the order bodies get them, repeating for scenes with more bodies
+    const CYCLE: [BasinPattern; 8] = [
+        BasinPattern::Plain, BasinPattern::Diagonal, BasinPattern::AntiDiagonal, BasinPattern::Dots,
+        BasinPattern::Horizontal, BasinPattern::Vertical, BasinPattern::Crosshatch, BasinPattern::Grid,
+    ];
+
+    pub fn for_body(index: usize) -> Self {
+        BasinPattern::CYCLE[index % BasinPattern::CYCLE.len()]
+    }
+
+    // Whether the pattern marks pixel (x, y), for lines `spacing` pixels apart and `width` wide.
+    // Dots sit on a staggered grid, every other row shifted by half the spacing.
+    pub fn covers(self, x: u32, y: u32, spacing: u32, width: u32) -> bool {
+        let (x, y, spacing, width) = (x as i64, y as i64, spacing.max(2) as i64, width as i64);
+        let on_line = |offset: i64| offset.rem_euclid(spacing) < width;
+        match self {
+            BasinPattern::Plain => false,
+            BasinPattern::Diagonal => on_line(x + y),
+            BasinPattern::AntiDiagonal => on_line(x - y),
+            BasinPattern::Horizontal => on_line(y),
+            BasinPattern::Vertical => on_line(x),
+            BasinPattern::Crosshatch => on_line(x + y) || on_line(x - y),
+            BasinPattern::Grid => on_line(x) || on_line(y),
+            BasinPattern::Dots => {
+                let row = y.div_euclid(spacing);
+                let dx = (x + row * spacing / 2).rem_euclid(spacing) - spacing / 2;
+                let dy = y.rem_euclid(spacing) - spacing / 2;
+                dx * dx + dy * dy <= width * width
+            }
+        }
+    }
+
+    // Blends the pattern into `color` at (x, y): darker over light colors and lighter over dark
+    // ones, so it shows in grayscale too
+    pub fn mark(self, color: [u8; 3], x: u32, y: u32, spacing: u32, width: u32) -> [u8; 3] {
+        if !self.covers(x, y, spacing, width) {
+            return color;
+        }
+        let luma = 0.299 * color[0] as f32 + 0.587 * color[1] as f32 + 0.114 * color[2] as f32;
+        let ink = if luma > 110.0 { 0.0 } else { 255.0 };
+        color.map(|c| (c as f32 * 0.4 + ink * 0.6).round() as u8)
+    }
+}
+
+// Optional decorations drawn on top of exported maps, how the map itself is colored and the
+// format it is saved in
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ExportOverlays {
+    pub bodies: bool,
+    pub legend: bool,
+    // World coordinate grid with labelled lines and a scale bar
+    pub grid: bool,
+    pub encoding: ColorEncoding,
+    pub palette: BasinPalette,
+    // Hatch or stipple pattern over each body's basin
+    pub patterns: bool,
+    pub output: ImageOutput,
+}
+
+// Outcome of one supersample, with the shade of its trajectory if it escaped
+type Sample = (Option<(usize, usize)>, Option<f32>);
+
+// The body hit by most of a pixel's supersamples, or the body count if most escaped, and how
+// many samples that is
+fn majority(samples: &[Sample], body_count: usize) -> (usize, usize) {
+    let mut counts = vec![0usize; body_count + 1];
+    for (outcome, _) in samples {
+        counts[outcome.map_or(body_count, |(index, _)| index)] += 1;
+    }
+    let (majority, &count) = counts.iter().enumerate().max_by_key(|&(_, count)| *count).unwrap();
+    (majority, count)
+}
+
+// Color of a pixel from its supersamples under the combined encoding. Escaped samples count as a
+// group of their own, colored by the average of their shades.
+fn combined_color(samples: &[Sample], timesteps: usize, stationary_bodies: &[Body]) -> [u8; 3] {
+    let (majority, count) = majority(samples, stationary_bodies.len());
+    let coverage = count as f32 / samples.len() as f32;
+
+    if majority == stationary_bodies.len() {
+        let mut sum = [0.0f32; 3];
+        for (_, shade) in samples.iter().filter(|(outcome, _)| outcome.is_none()) {
+            let color = shade.map_or(DEFAULT_NON_COLLISION_COLOR, escaped_color);
+            for c in 0..3 {
+                sum[c] += color[c] as f32;
+            }
+        }
+        return sum.map(|c| (c / count as f32) as u8);
+    }
+
+    let mean_time = samples.iter()
+        .filter_map(|(outcome, _)| outcome.filter(|(index, _)| *index == majority))
+        .map(|(_, time)| time as f32)
+        .sum::<f32>() / count as f32;
+    let intensity = (1.0 - mean_time / timesteps as f32).max(0.0) * (MAX_INTENSITY - MIN_INTENSITY) + MIN_INTENSITY;
+    let [hue, saturation, _] = rgb_to_hsv(capture_color(&stationary_bodies[majority]));
+    hsv_to_rgb([hue, saturation * coverage, intensity])
+}
+
+// Renders `params` with `supersample`² simulations per pixel averaged together, so exports
+// have smooth basin boundaries. Colors are computed directly instead of through an OutcomeGrid
+// to keep memory bounded at large sizes. Also returns the basin of each pixel, the body hit by
+// most of its supersamples (NO_BASIN if most escaped).
+pub fn render_supersampled(
+    scene: &Scene,
+    params: &MapParams,
+    supersample: u32,
+    encoding: ColorEncoding,
+    progress: &(impl Fn(usize, usize) + Sync),
+) -> (RgbImage, Vec<u8>) {
+    let MapParams { width, height, initial_velocity, integration_method, simulation, .. } = *params;
+    let camera = params.camera();
+    let samples = supersample.max(1);
+    let num_pixels = (width * height) as usize;
+    let mut pixels = vec![([0u8; 3], NO_BASIN); num_pixels];
+    let counter = AtomicUsize::new(0);
+    let deep_view = deep_view(scene, params);
+
+    pixels.par_iter_mut().enumerate().for_each(|(i, (pixel, basin))| {
+        let px = (i % width as usize) as f32;
+        let py = (i / width as usize) as f32;
+        let mut sum = [0.0f32; 3];
+        let mut outcomes = Vec::with_capacity((samples * samples) as usize);
+        for sy in 0..samples {
+            for sx in 0..samples {
+                let mut escape_shade = None;
+                let outcome = if let Some(deep_view) = &deep_view {
+                    deep_view.outcome_at(px as f64 + sx as f64 / samples as f64, py as f64 + sy as f64 / samples as f64)
+                } else if simulation.double_precision {
+                    let sub_x = px as f64 + sx as f64 / samples as f64;
+                    let sub_y = py as f64 + sy as f64 / samples as f64;
+                    let world_pos = camera.screen_to_world_f64(sub_x, sub_y);
+                    run_simulation_with_time_f64(world_pos, initial_velocity, scene, integration_method, &simulation)
+                } else {
+                    let sub_x = px + sx as f32 / samples as f32;
+                    let sub_y = py + sy as f32 / samples as f32;
+                    let world_pos = camera.screen_to_world(Vec2::new(sub_x, sub_y));
+                    if params.tracks_escapes() {
+                        let (outcome, stats) = run_simulation_with_stats(world_pos, initial_velocity, scene, integration_method, &simulation);
+                        escape_shade = stats.shade(params.escape_coloring, scene, &simulation).filter(|_| outcome.is_none());
+                        outcome
+                    } else {
+                        run_simulation_with_time(world_pos, initial_velocity, scene, integration_method, &simulation)
+                    }
+                };
+                outcomes.push((outcome, escape_shade));
+                if encoding == ColorEncoding::Combined {
+                    continue;
+                }
+                let color = match escape_shade {
+                    Some(shade) => escaped_color(shade),
+                    None => outcome_color(outcome, simulation.timesteps, &scene.bodies),
+                };
+                for c in 0..3 {
+                    sum[c] += color[c] as f32;
+                }
+            }
+        }
+        let count = (samples * samples) as f32;
+        *pixel = match encoding {
+            ColorEncoding::BodyTime => [(sum[0] / count) as u8, (sum[1] / count) as u8, (sum[2] / count) as u8],
+            ColorEncoding::Combined => combined_color(&outcomes, simulation.timesteps, &scene.bodies),
+        };
+        let (body, _) = majority(&outcomes, scene.bodies.len());
+        *basin = if body < scene.bodies.len() { body.min(NO_BASIN as usize - 1) as u8 } else { NO_BASIN };
+        count_pixel(&counter, num_pixels, progress);
+    });
+
+    let (colors, basins): (Vec<[u8; 3]>, Vec<u8>) = pixels.into_iter().unzip();
+    (ImageBuffer::from_raw(width, height, colors.into_iter().flatten().collect()).unwrap(), basins)
+}
+
+// Lays each body's pattern over its basin, given the basin of every pixel. Sizes are multiplied
+// by `scale`.
+pub fn draw_basin_patterns(img: &mut RgbImage, basins: &[u8], scale: f32) {
+    let spacing = (8.0 * scale).round().max(4.0) as u32;
+    let line_width = (1.5 * scale).round().max(1.0) as u32;
+    let width = img.width();
+    for (i, pixel) in img.pixels_mut().enumerate() {
+        if basins[i] != NO_BASIN {
+            let (x, y) = (i as u32 % width, i as u32 / width);
+            pixel.0 = BasinPattern::for_body(basins[i] as usize).mark(pixel.0, x, y, spacing, line_width);
+        }
+    }
+}
+
+// Renders the view in `view` at `width` x `height` with supersampling and overlays and saves it
+// in the format of `overlays.output`
+pub fn export_image(
+    scene: &Scene,
+    view: &MapParams,
+    width: u32,
+    height: u32,
+    supersample: u32,
+    overlays: ExportOverlays,
+    progress: &(impl Fn(usize, usize) + Sync),
+) -> Result<String, Box<dyn std::error::Error>> {
+    let params = view.at_resolution(width, height);
+    let scene = &*overlays.palette.recolor(scene);
+    println!("Exporting {}x{} image with {}x{} supersampling...", width, height, supersample, supersample);
+    let (mut img, basins) = render_supersampled(scene, &params, supersample, overlays.encoding, progress);
+
+    // Overlays are scaled with the export so they look the same as on screen
+    let scale = width as f32 / view.width as f32;
+    if overlays.patterns {
+        draw_basin_patterns(&mut img, &basins, scale);
+    }
+    if overlays.bodies {
+        draw_bodies(&mut img, &scene.bodies, &params, scale);
+    }
+    if overlays.grid {
+        draw_world_grid(&mut img, &params, scale);
+    }
+    if overlays.legend {
+        draw_legend_strip(&mut img, scene, &params, overlays, scale);
+    }
+
+    let output = overlays.output;
+    let map_filename = std::path::Path::new(&params.filename()).with_extension(output.extension()).to_string_lossy().into_owned();
+    let mut prefix = String::from("export_");
+    if overlays.encoding != ColorEncoding::BodyTime {
+        prefix += &format!("{}_", overlays.encoding.short_name());
+    }
+    if overlays.palette != BasinPalette::Scene {
+        prefix += &format!("{}_", overlays.palette.short_name());
+    }
+    if overlays.patterns {
+        prefix += "patterns_";
+    }
+    let filename = format!("{}{}", prefix, map_filename);
+    output.save(&img, &filename, &params, scene)?;
+    println!("Exported image saved to {}", filename);
+    Ok(filename)
+}
+
+// Draws the bodies into a map image the way the viewer does: filled in their color with a dark
+// outline, which is `scale` times two pixels wide. Rings and segments are drawn as lines.
+pub fn draw_bodies(img: &mut RgbImage, stationary_bodies: &[Body], params: &MapParams, scale: f32) {
+    let zoom = params.zoom_factor;
+    let camera = params.camera();
+    for body in stationary_bodies {
+        let center = camera.world_to_screen(body.pos);
+        match body.shape {
+            BodyShape::Ring { .. } | BodyShape::Segment { .. } => {
+                let reach = body.shape.extent() * zoom;
+                let (min, max) = (center - Vec2::new(reach, reach), center + Vec2::new(reach, reach));
+                let distance = |p: Vec2| body.shape.distance(((p - center) / zoom).into()) as f32 * zoom;
+                let half_width = BODY_STROKE_WIDTH / 2.0 * scale;
+                fill_near(img, min, max, half_width + 2.0 * scale, [0, 0, 0], distance);
+                fill_near(img, min, max, half_width, body.color, distance);
+            }
+            shape => {
+                let radius = match shape {
+                    BodyShape::Disc { radius } | BodyShape::BlackHole { schwarzschild_radius: radius } => radius,
+                    _ => body.radius,
+                } * zoom;
+                fill_circle(img, center, radius + 2.0 * scale, [0, 0, 0]);
+                fill_circle(img, center, radius, body.color);
+            }
+        }
+    }
+}
+
+// Text with a one pixel dark shadow, readable over any part of the map
+fn draw_shadowed_text(img: &mut RgbImage, text: &str, x: f32, y: f32, size: f32) {
+    draw_text(img, text, x + 1.0, y + 1.0, size, [0, 0, 0]);
+    draw_text(img, text, x, y, size, [230, 230, 230]);
+}
+
+// World coordinate gridlines labelled along the top and left edges, with the axes through the
+// origin drawn stronger, and a scale bar in the top right corner. Sizes are multiplied by `scale`.
+fn draw_world_grid(img: &mut RgbImage, params: &MapParams, scale: f32) {
+    let (width, height) = (img.width(), img.height());
+    let grid = WorldGrid::new(&params.camera(), GRID_SPACING * scale);
+    let thickness = scale.round().max(1.0) as u32;
+    let text_size = 12.0 * scale;
+    let pad = 3.0 * scale;
+    let opacity = |value: f32| if grid.is_axis(value) { 0.6 } else { 0.25 };
+    for &(x, value) in &grid.columns {
+        fill_rect(img, x as u32, 0, thickness, height, [255, 255, 255], opacity(value));
+    }
+    for &(y, value) in &grid.rows {
+        fill_rect(img, 0, y as u32, width, thickness, [255, 255, 255], opacity(value));
+    }
+    for &(x, value) in &grid.columns {
+        draw_shadowed_text(img, &grid.label(value), x + pad, pad, text_size);
+    }
+    for &(y, value) in &grid.rows {
+        draw_shadowed_text(img, &grid.label(value), pad, y + pad, text_size);
+    }
+
+    // Scale bar with end ticks, labelled above, kept below the row of column labels
+    let (length, pixels) = scale_bar(width as f32, params.zoom_factor);
+    let margin = 12.0 * scale;
+    let bar_thickness = (3.0 * scale).round().max(1.0) as u32;
+    let right = width as f32 - margin;
+    let label_y = margin + text_size + pad;
+    let bar_y = label_y + text_size + 2.0 * pad;
+    let tick_height = 4 * bar_thickness;
+    fill_rect(img, (right - pixels) as u32, bar_y as u32, pixels as u32, bar_thickness, [255, 255, 255], 1.0);
+    for x in [right - pixels, right - bar_thickness as f32] {
+        fill_rect(img, x as u32, (bar_y - tick_height as f32 / 2.0) as u32, bar_thickness, tick_height, [255, 255, 255], 1.0);
+    }
+    let label = format_length(length, length);
+    let label_x = right - pixels / 2.0 - text_width(&label, text_size) / 2.0;
+    draw_shadowed_text(img, &label, label_x, label_y, text_size);
+}
+
+// Legend composited along the bottom edge of an export, explaining the color mapping: a swatch
+// per body (with its pattern, if the basins have them) and for escaped pixels, the brightness
+// ramp of collision time and, for the combined encoding, the saturation ramp of supersample
+// agreement. Sizes are multiplied by `scale`.
+fn draw_legend_strip(img: &mut RgbImage, scene: &Scene, params: &MapParams, overlays: ExportOverlays, scale: f32) {
+    let encoding = overlays.encoding;
+    let text_size = 14.0 * scale;
+    let row_height = (20.0 * scale).round() as u32;
+    let margin = (8.0 * scale).round() as u32;
+    let swatch = (12.0 * scale).round() as u32;
+    let ramp_width = ((160.0 * scale).round() as u32).min(img.width() / 3);
+    let rows = if encoding == ColorEncoding::Combined { 3 } else { 2 };
+    let height = rows * row_height + 2 * margin;
+    if height >= img.height() / 2 || ramp_width == 0 {
+        return;
+    }
+    let top = img.height() - height;
+    let width = img.width();
+    fill_rect(img, 0, top, width, height, [0, 0, 0], 0.8);
+
+    // Row of body swatches, ending with escaped pixels; whatever doesn't fit is left off
+    let row_y = |row: u32| top + margin + row * row_height;
+    let swatch_top = |row: u32| row_y(row) + (row_height - swatch) / 2;
+    let text_top = |row: u32| row_y(row) as f32 + (row_height as f32 - text_size) / 2.0;
+    let escaped = if params.tracks_escapes() {
+        format!("escaped ({})", params.escape_coloring.name())
+    } else {
+        "escaped".to_string()
+    };
+    let mut entries: Vec<(String, [u8; 3], BasinPattern)> = scene.bodies.iter().enumerate()
+        .map(|(i, body)| {
+            let label = if body.shape.is_black_hole() { format!("body {} (horizon)", i) } else { format!("body {}", i) };
+            let pattern = if overlays.patterns { BasinPattern::for_body(i) } else { BasinPattern::Plain };
+            (label, capture_color(body).map(|c| (c as f32 * MAX_INTENSITY) as u8), pattern)
+        })
+        .collect();
+    let escaped_swatch = if params.tracks_escapes() { escaped_color(0.5) } else { DEFAULT_NON_COLLISION_COLOR };
+    entries.push((escaped, escaped_swatch, BasinPattern::Plain));
+    // Swatch patterns are finer than the map's so a few lines fit in each
+    let (pattern_spacing, pattern_width) = ((4.0 * scale).round().max(3.0) as u32, (scale.round() as u32).max(1));
+    let mut x = margin as f32;
+    for (label, color, pattern) in entries {
+        let entry_width = swatch as f32 + 4.0 * scale + text_width(&label, text_size);
+        if x + entry_width > (width - margin) as f32 {
+            break;
+        }
+        for sy in 0..swatch {
+            for sx in 0..swatch {
+                let marked = pattern.mark(color, sx, sy, pattern_spacing, pattern_width);
+                img.put_pixel(x as u32 + sx, swatch_top(0) + sy, Rgb(marked));
+            }
+        }
+        draw_text(img, &label, x + swatch as f32 + 4.0 * scale, text_top(0), text_size, [220, 220, 220]);
+        x += entry_width + 12.0 * scale;
+    }
+
+    // Labelled ramps: a caption, then the ramp with its end values on either side. The ramps
+    // line up after the widest caption and start label.
+    let captions = ["collision time", "supersample agreement"];
+    let caption_width = captions[..rows as usize - 1].iter().map(|caption| text_width(caption, text_size)).fold(0.0, f32::max);
+    let ramp_x = (margin as f32 + caption_width + 8.0 * scale + text_width("100%", text_size) + 4.0 * scale) as u32;
+    let mut ramp = |row: u32, start: &str, end: &str, color_at: &dyn Fn(f32) -> [u8; 3]| {
+        draw_text(img, captions[row as usize - 1], margin as f32, text_top(row), text_size, [220, 220, 220]);
+        let start_x = ramp_x as f32 - 4.0 * scale - text_width(start, text_size);
+        draw_text(img, start, start_x, text_top(row), text_size, [160, 160, 160]);
+        for i in 0..ramp_width {
+            fill_rect(img, ramp_x + i, swatch_top(row), 1, swatch, color_at(i as f32 / (ramp_width - 1) as f32), 1.0);
+        }
+        draw_text(img, end, (ramp_x + ramp_width) as f32 + 4.0 * scale, text_top(row), text_size, [160, 160, 160]);
+    };
+    let timesteps = params.simulation.timesteps;
+    ramp(1, "0", &timesteps.to_string(), &|t| {
+        let intensity = MAX_INTENSITY - (MAX_INTENSITY - MIN_INTENSITY) * t;
+        [(255.0 * intensity) as u8; 3]
+    });
+    if encoding == ColorEncoding::Combined {
+        let color = scene.bodies.first().map_or([255, 255, 255], capture_color);
+        let [hue, saturation, _] = rgb_to_hsv(color);
+        ramp(2, "100%", "0%", &|t| hsv_to_rgb([hue, saturation * (1.0 - t), MAX_INTENSITY]));
+    }
+}
+
+// Summary of how two outcome grids differ
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DifferenceStats {
+    pub changed_body: usize,
+    pub earlier: usize,
+    pub later: usize,
+}
+
+// Highlights pixels whose captured body changed between two grids (white) and shows the signed change
+// in collision time for pixels that kept their body (red = hit earlier in `after`, blue = hit later)
+pub fn render_difference_image(before: &OutcomeGrid, after: &OutcomeGrid) -> (RgbImage, DifferenceStats) {
+    let width = before.width.min(after.width);
+    let height = before.height.min(after.height);
+    let max_time = before.timesteps.max(after.timesteps) as f32;
+    let mut stats = DifferenceStats::default();
+
+    let img = ImageBuffer::from_fn(width, height, |px, py| {
+        match (before.get(px, py), after.get(px, py)) {
+            (Some((body_a, time_a)), Some((body_b, time_b))) if body_a == body_b => {
+                let delta = time_b as f32 - time_a as f32;
+                // Square root keeps small timing shifts visible next to large ones
+                let strength = (delta.abs() / max_time).sqrt().min(1.0);
+                let level = (40.0 + 215.0 * strength) as u8;
+                if delta < 0.0 {
+                    stats.earlier += 1;
+                    Rgb([level, 20, 20])
+                } else if delta > 0.0 {
+                    stats.later += 1;
+                    Rgb([20, 20, level])
+                } else {
+                    Rgb(DEFAULT_NON_COLLISION_COLOR)
+                }
+            }
+            (None, None) => Rgb([0, 0, 0]),
+            _ => {
+                stats.changed_body += 1;
+                Rgb([255, 255, 255])
+            }
+        }
+    });
+    (img, stats)
 }
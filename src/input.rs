@@ -0,0 +1,297 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Lines, Write};
+
+use macroquad::input as live;
+use macroquad::prelude::{KeyCode, MouseButton};
+use serde::{Deserialize, Serialize};
+
+use crate::gamepad::GamepadFrame;
+use crate::history::ViewSettings;
+use crate::keybindings::NAMED_KEYS;
+
+// Keys recorded besides those an action can be bound to
+const MODIFIER_KEYS: &[KeyCode] = &[
+    KeyCode::LeftShift, KeyCode::RightShift, KeyCode::LeftControl, KeyCode::RightControl,
+    KeyCode::LeftAlt, KeyCode::RightAlt,
+];
+const MOUSE_BUTTONS: [(&str, MouseButton); 3] = [("Left", MouseButton::Left), ("Right", MouseButton::Right), ("Middle", MouseButton::Middle)];
+
+// The viewer's input for one frame. Everything in the viewer reads its input through this module
+// instead of from macroquad, so a session can be recorded and played back frame by frame.
+#[derive(Clone, Debug, Default)]
+struct Frame {
+    time: f64,
+    frame_time: f32,
+    mouse: (f32, f32),
+    wheel: (f32, f32),
+    keys_down: HashSet<KeyCode>,
+    keys_pressed: HashSet<KeyCode>,
+    buttons_down: Vec<MouseButton>,
+    buttons_pressed: Vec<MouseButton>,
+    gamepad: GamepadFrame,
+    // The control panel's edits made during the frame
+    settings: Option<ViewSettings>,
+}
+
+// A frame as a line of the recording, naming keys and buttons as keybindings do
+#[derive(Serialize, Deserialize)]
+struct RecordedFrame {
+    time: f64,
+    frame_time: f32,
+    mouse: (f32, f32),
+    #[serde(default, skip_serializing_if = "is_still")]
+    wheel: (f32, f32),
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    keys_down: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    keys_pressed: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    buttons_down: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    buttons_pressed: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    gamepad: Option<GamepadFrame>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    settings: Option<ViewSettings>,
+}
+
+fn is_still(wheel: &(f32, f32)) -> bool {
+    *wheel == (0.0, 0.0)
+}
+
+// First line of a recording: what the session started from
+#[derive(Serialize, Deserialize)]
+pub struct RecordingHeader {
+    // Seed of the viewer's random numbers (random scenes, ensemble launches)
+    pub seed: u64,
+    pub screen: (f32, f32),
+    pub settings: ViewSettings,
+}
+
+fn key_name(key: KeyCode) -> String {
+    format!("{:?}", key)
+}
+
+fn key_named(name: &str) -> Option<KeyCode> {
+    NAMED_KEYS.iter().chain(MODIFIER_KEYS).copied().find(|&key| key_name(key) == name)
+}
+
+fn button_name(button: MouseButton) -> String {
+    MOUSE_BUTTONS.iter().find(|(_, b)| *b == button).map_or_else(String::new, |(name, _)| name.to_string())
+}
+
+fn button_named(name: &str) -> Option<MouseButton> {
+    MOUSE_BUTTONS.iter().find(|(n, _)| *n == name).map(|(_, button)| *button)
+}
+
+impl Frame {
+    fn capture() -> Self {
+        let buttons = |pressed: fn(MouseButton) -> bool| MOUSE_BUTTONS.iter().map(|(_, b)| *b).filter(|&b| pressed(b)).collect();
+        Self {
+            time: macroquad::time::get_time(),
+            frame_time: macroquad::time::get_frame_time(),
+            mouse: live::mouse_position(),
+            wheel: live::mouse_wheel(),
+            keys_down: live::get_keys_down(),
+            keys_pressed: live::get_keys_pressed(),
+            buttons_down: buttons(live::is_mouse_button_down),
+            buttons_pressed: buttons(live::is_mouse_button_pressed),
+            gamepad: GamepadFrame::default(),
+            settings: None,
+        }
+    }
+
+    fn to_recorded(&self) -> RecordedFrame {
+        // Only keys that can be replayed are written
+        let keys = |keys: &HashSet<KeyCode>| {
+            let mut names: Vec<String> = keys.iter().filter(|&&key| key_named(&key_name(key)).is_some()).map(|&key| key_name(key)).collect();
+            names.sort();
+            names
+        };
+        RecordedFrame {
+            time: self.time,
+            frame_time: self.frame_time,
+            mouse: self.mouse,
+            wheel: self.wheel,
+            keys_down: keys(&self.keys_down),
+            keys_pressed: keys(&self.keys_pressed),
+            buttons_down: self.buttons_down.iter().map(|&b| button_name(b)).collect(),
+            buttons_pressed: self.buttons_pressed.iter().map(|&b| button_name(b)).collect(),
+            gamepad: self.gamepad.connected.then_some(self.gamepad),
+            settings: self.settings.clone(),
+        }
+    }
+
+    fn from_recorded(recorded: RecordedFrame) -> Self {
+        Self {
+            time: recorded.time,
+            frame_time: recorded.frame_time,
+            mouse: recorded.mouse,
+            wheel: recorded.wheel,
+            keys_down: recorded.keys_down.iter().filter_map(|name| key_named(name)).collect(),
+            keys_pressed: recorded.keys_pressed.iter().filter_map(|name| key_named(name)).collect(),
+            buttons_down: recorded.buttons_down.iter().filter_map(|name| button_named(name)).collect(),
+            buttons_pressed: recorded.buttons_pressed.iter().filter_map(|name| button_named(name)).collect(),
+            gamepad: recorded.gamepad.unwrap_or_default(),
+            settings: recorded.settings,
+        }
+    }
+}
+
+enum Mode {
+    Live,
+    // Each frame is written once the next one begins, so it includes the panel's edits
+    Recording { writer: BufWriter<File>, path: String },
+    Replaying { lines: Lines<BufReader<File>>, path: String },
+}
+
+struct InputState {
+    mode: Mode,
+    // None before the first frame
+    frame: Option<Frame>,
+}
+
+thread_local! {
+    static INPUT: RefCell<InputState> = const { RefCell::new(InputState { mode: Mode::Live, frame: None }) };
+}
+
+// Starts writing every frame's input to `path`, after a header with what the session starts from
+pub fn start_recording(path: &str, header: &RecordingHeader) -> Result<(), Box<dyn Error>> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "{}", serde_json::to_string(header)?)?;
+    INPUT.with_borrow_mut(|input| input.mode = Mode::Recording { writer, path: path.to_string() });
+    Ok(())
+}
+
+// Starts playing back the recording at `path` instead of reading the mouse and keyboard,
+// returning its header
+pub fn start_replay(path: &str) -> Result<RecordingHeader, Box<dyn Error>> {
+    let mut lines = BufReader::new(File::open(path)?).lines();
+    let header = match lines.next() {
+        Some(line) => serde_json::from_str(&line?)?,
+        None => return Err(format!("{} is empty", path).into()),
+    };
+    INPUT.with_borrow_mut(|input| input.mode = Mode::Replaying { lines, path: path.to_string() });
+    Ok(header)
+}
+
+pub fn is_replaying() -> bool {
+    INPUT.with_borrow(|input| matches!(input.mode, Mode::Replaying { .. }))
+}
+
+fn write_frame(writer: &mut BufWriter<File>, frame: &Frame) -> Result<(), Box<dyn Error>> {
+    writeln!(writer, "{}", serde_json::to_string(&frame.to_recorded())?)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn read_frame(line: std::io::Result<String>) -> Result<Frame, Box<dyn Error>> {
+    Ok(Frame::from_recorded(serde_json::from_str(&line?)?))
+}
+
+// Reads the input for a new frame: the mouse and keyboard, or the next recorded frame. A replay
+// that runs out hands control back to the mouse and keyboard.
+pub fn begin_frame() {
+    INPUT.with_borrow_mut(|input| {
+        let next = match &mut input.mode {
+            Mode::Live => Some(Frame::capture()),
+            Mode::Recording { writer, path } => {
+                if let Some(Err(e)) = input.frame.as_ref().map(|frame| write_frame(writer, frame)) {
+                    eprintln!("Error writing input recording {}: {}", path, e);
+                    input.mode = Mode::Live;
+                }
+                Some(Frame::capture())
+            }
+            Mode::Replaying { lines, path } => match lines.next().map(read_frame) {
+                Some(Ok(frame)) => Some(frame),
+                Some(Err(e)) => {
+                    eprintln!("Error reading input recording {}: {}", path, e);
+                    None
+                }
+                None => {
+                    println!("Finished replaying {}", path);
+                    None
+                }
+            },
+        };
+        input.frame = Some(next.unwrap_or_else(|| {
+            input.mode = Mode::Live;
+            Frame::capture()
+        }));
+    });
+}
+
+// The gamepad's frame, which the recording keeps alongside the mouse and keyboard
+pub fn gamepad(polled: GamepadFrame) -> GamepadFrame {
+    INPUT.with_borrow_mut(|input| {
+        let replaying = matches!(input.mode, Mode::Replaying { .. });
+        match &mut input.frame {
+            Some(frame) if !replaying => {
+                frame.gamepad = polled;
+                polled
+            }
+            Some(frame) => frame.gamepad,
+            None => polled,
+        }
+    })
+}
+
+// Notes the settings after the control panel changed them, for the recording
+pub fn record_settings(settings: ViewSettings) {
+    INPUT.with_borrow_mut(|input| {
+        if let Some(frame) = &mut input.frame {
+            frame.settings = Some(settings);
+        }
+    });
+}
+
+// Settings the control panel was changed to during the replayed frame
+pub fn replayed_settings() -> Option<ViewSettings> {
+    INPUT.with_borrow_mut(|input| match (&input.mode, &mut input.frame) {
+        (Mode::Replaying { .. }, Some(frame)) => frame.settings.take(),
+        _ => None,
+    })
+}
+
+// Reads the current frame; before the first one every input is at rest
+fn with_frame<T>(read: impl FnOnce(&Frame) -> T) -> T {
+    INPUT.with_borrow(|input| match &input.frame {
+        Some(frame) => read(frame),
+        None => read(&Frame::default()),
+    })
+}
+
+pub fn is_key_down(key: KeyCode) -> bool {
+    with_frame(|frame| frame.keys_down.contains(&key))
+}
+
+pub fn is_key_pressed(key: KeyCode) -> bool {
+    with_frame(|frame| frame.keys_pressed.contains(&key))
+}
+
+pub fn is_mouse_button_down(button: MouseButton) -> bool {
+    with_frame(|frame| frame.buttons_down.contains(&button))
+}
+
+pub fn is_mouse_button_pressed(button: MouseButton) -> bool {
+    with_frame(|frame| frame.buttons_pressed.contains(&button))
+}
+
+pub fn mouse_position() -> (f32, f32) {
+    with_frame(|frame| frame.mouse)
+}
+
+pub fn mouse_wheel() -> (f32, f32) {
+    with_frame(|frame| frame.wheel)
+}
+
+pub fn get_time() -> f64 {
+    with_frame(|frame| frame.time)
+}
+
+pub fn get_frame_time() -> f32 {
+    with_frame(|frame| frame.frame_time)
+}
@@ -0,0 +1,91 @@
+use crate::physics::{total_energy, Body, TestParticle, Vec2};
+use crate::scene::Scene;
+
+// Zero-velocity curves: the particle's kinetic energy can't go negative, so it can only reach
+// points where the potential lies below its energy. The scene's bodies don't move, so this is
+// the restricted problem in a frame with zero rotation rate, where the Jacobi constant
+// C = 2U - v² (with U = -Φ the effective potential) reduces to -2E.
+
+// Contour lines of the potential are drawn this many per doubling of its depth
+const CONTOURS_PER_OCTAVE: f32 = 2.0;
+const CONTOUR_COLOR: [u8; 4] = [255, 255, 255, 60];
+const ZERO_VELOCITY_COLOR: [u8; 4] = [255, 220, 60, 255];
+// Tint over the region the particle can never reach
+const FORBIDDEN_COLOR: [u8; 4] = [0, 0, 0, 120];
+
+// Gravitational potential per unit mass at a point
+pub fn potential(pos: Vec2, scene: &Scene) -> f32 {
+    bodies_potential(pos, &scene.bodies, scene.gravitational_constant)
+}
+
+// Same for any set of bodies, e.g. a scene's at some time after launch
+pub fn bodies_potential(pos: Vec2, bodies: &[Body], g: f32) -> f32 {
+    bodies.iter()
+        .map(|body| {
+            let strength = g * body.mass;
+            if let Some(potential) = body.shape.potential_at(body.pos.into(), strength as f64, pos.into()) {
+                return potential as f32;
+            }
+            let dist = pos.distance(&body.pos);
+            if dist == 0.0 {
+                return 0.0;
+            }
+            let potential = -g * body.mass / dist;
+            if body.j2 != 0.0 {
+                return potential + body.oblateness_potential(g * body.mass, pos);
+            }
+            potential
+        })
+        .sum()
+}
+
+pub fn jacobi_constant(particle: &TestParticle, scene: &Scene) -> f32 {
+    -2.0 * total_energy(particle, scene) / particle.mass
+}
+
+// Index of the logarithmically spaced contour band a potential falls in
+fn contour_band(potential: f32) -> i32 {
+    (potential.abs().max(f32::MIN_POSITIVE).log2() * CONTOURS_PER_OCTAVE).floor() as i32
+}
+
+// RGBA overlay of the view: faint contours of the potential, and for a given Jacobi constant the
+// zero-velocity curve with the forbidden region beyond it shaded. `world_at` maps a pixel to
+// world coordinates.
+pub fn contour_overlay(
+    scene: &Scene,
+    width: u32,
+    height: u32,
+    world_at: impl Fn(f32, f32) -> Vec2,
+    jacobi: Option<f32>,
+) -> Vec<u8> {
+    let potentials: Vec<f32> = (0..height)
+        .flat_map(|py| (0..width).map(move |px| (px, py)))
+        .map(|(px, py)| potential(world_at(px as f32 + 0.5, py as f32 + 0.5), scene))
+        .collect();
+    // Reachable where -2Φ >= C
+    let reachable = |p: f32| jacobi.is_none_or(|c| -2.0 * p >= c);
+
+    let mut pixels = vec![0u8; (width * height * 4) as usize];
+    for py in 0..height {
+        for px in 0..width {
+            let index = (py * width + px) as usize;
+            let here = potentials[index];
+            // A line is drawn wherever the pixel differs from its right or lower neighbour
+            let neighbours = [
+                (px + 1 < width).then(|| potentials[index + 1]),
+                (py + 1 < height).then(|| potentials[index + width as usize]),
+            ];
+            let color = if neighbours.iter().flatten().any(|&other| reachable(other) != reachable(here)) {
+                ZERO_VELOCITY_COLOR
+            } else if !reachable(here) {
+                FORBIDDEN_COLOR
+            } else if neighbours.iter().flatten().any(|&other| contour_band(other) != contour_band(here)) {
+                CONTOUR_COLOR
+            } else {
+                continue;
+            };
+            pixels[index * 4..index * 4 + 4].copy_from_slice(&color);
+        }
+    }
+    pixels
+}
@@ -0,0 +1,91 @@
+use std::f32::consts::PI;
+
+use crate::physics::{calculate_gravitational_force, TestParticle, Vec2};
+use crate::scene::Scene;
+
+// Share of the summed pull of all bodies that one body needs before the particle counts as
+// orbiting it alone
+pub const KEPLER_DOMINANCE: f32 = 0.9;
+
+// Osculating two-body orbit: the conic the particle would follow if the dominant body were the
+// only one, fitted to its current position and velocity
+#[derive(Clone, Copy, Debug)]
+pub struct OrbitalElements {
+    pub body: usize,
+    // Position of the dominant body, one focus of the conic
+    pub focus: Vec2,
+    // Negative for hyperbolic orbits, infinite for parabolic ones
+    pub semi_major_axis: f32,
+    pub eccentricity: f32,
+    // Direction of periapsis from the focus, in radians
+    pub argument_of_periapsis: f32,
+    pub semi_latus_rectum: f32,
+    // Time for one revolution, for bound orbits only
+    pub period: Option<f32>,
+}
+
+impl OrbitalElements {
+    // Fits the elements relative to the body dominating the particle's acceleration, or returns
+    // None when no single body dominates
+    pub fn fit(particle: &TestParticle, scene: &Scene) -> Option<Self> {
+        let pulls: Vec<f32> = scene.bodies.iter()
+            .map(|body| calculate_gravitational_force(particle, body, scene.gravitational_constant).length())
+            .collect();
+        let total: f32 = pulls.iter().sum();
+        let (index, &pull) = pulls.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1))?;
+        let body = &scene.bodies[index];
+        let mu = scene.gravitational_constant * body.mass;
+        if total <= 0.0 || pull < KEPLER_DOMINANCE * total || mu <= 0.0 {
+            return None;
+        }
+
+        let r = particle.pos - body.pos;
+        let v = particle.vel;
+        let dist = r.length();
+        if dist == 0.0 {
+            return None;
+        }
+        let speed_sq = v.dot(&v);
+        let radial = r.dot(&v);
+        let angular_momentum = r.x * v.y - r.y * v.x;
+        let energy = 0.5 * speed_sq - mu / dist;
+        let eccentricity_vector = (r * (speed_sq - mu / dist) - v * radial) / mu;
+        let eccentricity = eccentricity_vector.length();
+        let semi_major_axis = if energy == 0.0 { f32::INFINITY } else { -mu / (2.0 * energy) };
+        let period = (eccentricity < 1.0 && semi_major_axis > 0.0)
+            .then(|| 2.0 * PI * (semi_major_axis.powi(3) / mu).sqrt());
+
+        Some(Self {
+            body: index,
+            focus: body.pos,
+            semi_major_axis,
+            eccentricity,
+            argument_of_periapsis: eccentricity_vector.angle(),
+            semi_latus_rectum: angular_momentum * angular_momentum / mu,
+            period,
+        })
+    }
+
+    // Points along the conic in world coordinates: the whole ellipse for bound orbits, otherwise
+    // the branch out to `max_radius` from the focus. Radial orbits have no extent to draw.
+    pub fn conic_points(&self, segments: usize, max_radius: f32) -> Vec<Vec2> {
+        if self.semi_latus_rectum <= f32::EPSILON {
+            return Vec::new();
+        }
+        // True anomalies where the conic reaches infinity, kept slightly inside
+        let limit = if self.eccentricity < 1.0 { PI } else { (-1.0 / self.eccentricity).acos() * 0.999 };
+        (0..=segments).filter_map(|i| {
+            let anomaly = -limit + 2.0 * limit * i as f32 / segments as f32;
+            let radius = self.semi_latus_rectum / (1.0 + self.eccentricity * anomaly.cos());
+            let angle = anomaly + self.argument_of_periapsis;
+            (radius <= max_radius).then(|| self.focus + Vec2::new(angle.cos(), angle.sin()) * radius)
+        }).collect()
+    }
+
+    // One-line summary for the HUD
+    pub fn describe(&self) -> String {
+        let shape = if self.eccentricity < 1.0 { "ellipse" } else { "hyperbola" };
+        let period = self.period.map_or(String::new(), |period| format!(", T={:.2}", period));
+        format!("Kepler ({} around body {}): a={:.1}, e={:.3}{}", shape, self.body, self.semi_major_axis, self.eccentricity, period)
+    }
+}
@@ -0,0 +1,198 @@
+use macroquad::prelude::*;
+use serde::Deserialize;
+
+use crate::input::{is_key_down, is_key_pressed, is_mouse_button_down, is_mouse_button_pressed};
+
+// Keys that can be named in a `keybindings` table, by their KeyCode name ("W", "Up", "Space",
+// "Key1", "F5", "LeftBracket", ...). Digits can also be given as "1".
+pub const NAMED_KEYS: &[KeyCode] = &[
+    KeyCode::A, KeyCode::B, KeyCode::C, KeyCode::D, KeyCode::E, KeyCode::F, KeyCode::G, KeyCode::H,
+    KeyCode::I, KeyCode::J, KeyCode::K, KeyCode::L, KeyCode::M, KeyCode::N, KeyCode::O, KeyCode::P,
+    KeyCode::Q, KeyCode::R, KeyCode::S, KeyCode::T, KeyCode::U, KeyCode::V, KeyCode::W, KeyCode::X,
+    KeyCode::Y, KeyCode::Z,
+    KeyCode::Key0, KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4,
+    KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+    KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+    KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+    KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right,
+    KeyCode::Space, KeyCode::Enter, KeyCode::Tab, KeyCode::Backspace, KeyCode::Escape,
+    KeyCode::Insert, KeyCode::Delete, KeyCode::Home, KeyCode::End, KeyCode::PageUp, KeyCode::PageDown,
+    KeyCode::Minus, KeyCode::Equal, KeyCode::Comma, KeyCode::Period, KeyCode::Slash, KeyCode::Backslash,
+    KeyCode::Semicolon, KeyCode::Apostrophe, KeyCode::GraveAccent, KeyCode::LeftBracket, KeyCode::RightBracket,
+    KeyCode::Kp0, KeyCode::Kp1, KeyCode::Kp2, KeyCode::Kp3, KeyCode::Kp4,
+    KeyCode::Kp5, KeyCode::Kp6, KeyCode::Kp7, KeyCode::Kp8, KeyCode::Kp9,
+    KeyCode::KpAdd, KeyCode::KpSubtract, KeyCode::KpMultiply, KeyCode::KpDivide, KeyCode::KpEnter, KeyCode::KpDecimal,
+];
+
+// A key or mouse button an action is bound to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Input {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+impl Input {
+    pub fn is_pressed(self) -> bool {
+        match self {
+            Input::Key(key) => is_key_pressed(key),
+            Input::Mouse(button) => is_mouse_button_pressed(button),
+        }
+    }
+
+    pub fn is_down(self) -> bool {
+        match self {
+            Input::Key(key) => is_key_down(key),
+            Input::Mouse(button) => is_mouse_button_down(button),
+        }
+    }
+}
+
+impl std::str::FromStr for Input {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mouse = [("MouseLeft", MouseButton::Left), ("MouseRight", MouseButton::Right), ("MouseMiddle", MouseButton::Middle)];
+        if let Some((_, button)) = mouse.iter().find(|(name, _)| name.eq_ignore_ascii_case(s)) {
+            return Ok(Input::Mouse(*button));
+        }
+        let name = if s.len() == 1 && s.as_bytes()[0].is_ascii_digit() { format!("Key{}", s) } else { s.to_string() };
+        NAMED_KEYS.iter()
+            .find(|key| format!("{:?}", key).eq_ignore_ascii_case(&name))
+            .map(|&key| Input::Key(key))
+            .ok_or_else(|| format!("unknown key '{}', expected a key name like W, Up, Space or F5, or MouseLeft, MouseRight or MouseMiddle", s))
+    }
+}
+
+// Keys of the viewer's main controls, read from the `keybindings` table of a scene (or any other
+// TOML or JSON) file so they can follow other keyboard layouts, e.g. for AZERTY:
+//
+//     [keybindings]
+//     pan_up = "Z"
+//     pan_left = "Q"
+//     zoom_out = "A"
+//
+// Actions left out keep their default. Every other control keeps its key, unless an action is
+// bound to it, in which case the action takes the key over.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Keybindings {
+    #[serde(deserialize_with = "input")]
+    pub pan_up: Input,
+    #[serde(deserialize_with = "input")]
+    pub pan_down: Input,
+    #[serde(deserialize_with = "input")]
+    pub pan_left: Input,
+    #[serde(deserialize_with = "input")]
+    pub pan_right: Input,
+    #[serde(deserialize_with = "input")]
+    pub zoom_in: Input,
+    #[serde(deserialize_with = "input")]
+    pub zoom_out: Input,
+    // Nudge the initial velocity by the step size
+    #[serde(deserialize_with = "input")]
+    pub velocity_up: Input,
+    #[serde(deserialize_with = "input")]
+    pub velocity_down: Input,
+    #[serde(deserialize_with = "input")]
+    pub velocity_left: Input,
+    #[serde(deserialize_with = "input")]
+    pub velocity_right: Input,
+    #[serde(deserialize_with = "input")]
+    pub toggle_integrator: Input,
+    #[serde(deserialize_with = "input")]
+    pub recalculate: Input,
+    // Launch a particle from the point under the mouse
+    #[serde(deserialize_with = "input")]
+    pub launch: Input,
+}
+
+fn input<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Input, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    name.parse().map_err(serde::de::Error::custom)
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Self {
+            pan_up: Input::Key(KeyCode::W),
+            pan_down: Input::Key(KeyCode::S),
+            pan_left: Input::Key(KeyCode::A),
+            pan_right: Input::Key(KeyCode::D),
+            zoom_in: Input::Key(KeyCode::E),
+            zoom_out: Input::Key(KeyCode::Q),
+            velocity_up: Input::Key(KeyCode::Up),
+            velocity_down: Input::Key(KeyCode::Down),
+            velocity_left: Input::Key(KeyCode::Left),
+            velocity_right: Input::Key(KeyCode::Right),
+            toggle_integrator: Input::Key(KeyCode::Space),
+            recalculate: Input::Key(KeyCode::Enter),
+            launch: Input::Mouse(MouseButton::Left),
+        }
+    }
+}
+
+// The part of a scene file the bindings are read from; the rest is the scene's
+#[derive(Deserialize)]
+struct BindingsFile {
+    #[serde(default)]
+    keybindings: Keybindings,
+}
+
+impl Keybindings {
+    // Reads the `keybindings` table of a TOML (by extension) or JSON file; a file without one
+    // leaves every default in place
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(path)?;
+        let file: BindingsFile = if path.ends_with(".toml") {
+            toml::from_str(&contents)?
+        } else {
+            serde_json::from_str(&contents)?
+        };
+        if let Some(problem) = file.keybindings.problem() {
+            return Err(problem.into());
+        }
+        Ok(file.keybindings)
+    }
+
+    fn actions(&self) -> [(&'static str, Input); 13] {
+        [
+            ("pan_up", self.pan_up),
+            ("pan_down", self.pan_down),
+            ("pan_left", self.pan_left),
+            ("pan_right", self.pan_right),
+            ("zoom_in", self.zoom_in),
+            ("zoom_out", self.zoom_out),
+            ("velocity_up", self.velocity_up),
+            ("velocity_down", self.velocity_down),
+            ("velocity_left", self.velocity_left),
+            ("velocity_right", self.velocity_right),
+            ("toggle_integrator", self.toggle_integrator),
+            ("recalculate", self.recalculate),
+            ("launch", self.launch),
+        ]
+    }
+
+    // Two actions bound to the same input, if any are
+    fn problem(&self) -> Option<String> {
+        let actions = self.actions();
+        actions.iter().enumerate().find_map(|(i, (name, input))| {
+            actions[i + 1..].iter()
+                .find(|(_, other)| other == input)
+                .map(|(other, _)| format!("{} and {} are bound to the same input", name, other))
+        })
+    }
+
+    // is_key_pressed for the viewer's other controls, which give way to any action bound to the
+    // same key
+    pub fn key_pressed(&self, key: KeyCode) -> bool {
+        is_key_pressed(key) && !self.claims(key)
+    }
+
+    pub fn key_down(&self, key: KeyCode) -> bool {
+        is_key_down(key) && !self.claims(key)
+    }
+
+    fn claims(&self, key: KeyCode) -> bool {
+        self.actions().iter().any(|(_, input)| *input == Input::Key(key))
+    }
+}
@@ -0,0 +1,46 @@
+pub mod physics;
+pub mod camera;
+pub mod simulation;
+pub mod config;
+pub mod image_gen;
+pub mod outcome;
+pub mod render;
+pub mod scene;
+pub mod bookmarks;
+pub mod session;
+pub mod metadata;
+pub mod animation;
+pub mod zoom_video;
+pub mod recording;
+pub mod trajectory;
+pub mod sweep;
+pub mod stream;
+pub mod server;
+pub mod progress;
+pub mod threads;
+pub mod batch;
+pub mod precise;
+pub mod deep_zoom;
+pub mod barnes_hut;
+pub mod energy_drift;
+pub mod validation;
+pub mod kepler;
+pub mod jacobi;
+pub mod escape;
+pub mod axes;
+pub mod svg;
+pub mod contours;
+pub mod grid_export;
+pub mod image_format;
+pub mod thrust;
+pub mod force_field;
+pub mod motion;
+pub mod probability;
+pub mod uncertainty;
+pub mod nbody;
+pub mod stability;
+pub mod parameter_map;
+pub mod bifurcation;
+pub mod overview;
+pub mod events;
+pub mod screenshot;
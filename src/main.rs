@@ -1,184 +1,2045 @@
-mod physics;
-mod simulation;
-mod config;
-mod image_gen;
+mod cli;
+mod gamepad;
+mod glow;
+mod history;
+mod hud;
+mod input;
+mod keybindings;
+mod minimap;
+mod panel;
+mod plot;
+mod render_queue;
+mod sound;
+mod stability_view;
+mod starfield;
 
+use std::borrow::Cow;
+use std::time::SystemTime;
+
+use ::rand::rngs::StdRng;
+use ::rand::{Rng, SeedableRng};
+use clap::Parser;
 use macroquad::prelude::*;
-use physics::Vec2;
-use simulation::{LiveSimulationState, IntegrationMethod};
-use config::{IMAGE_SIZE, create_stationary_bodies};
-use image_gen::generate_gravity_wells_image;
+use gravity_wells::camera::Camera;
+use gravity_wells::physics::{calculate_acceleration, TestParticle, Vec2};
+use gravity_wells::simulation::{
+    preview_trajectory, sample_ensemble, CollisionPolicy, EnsembleSimulation, EnsembleSpread, LiveSimulationState, IntegrationMethod, SimulationParams,
+    TEST_PARTICLE_MASS, TEST_PARTICLE_RADIUS,
+};
+use gravity_wells::config::{body_radius, BODY_STROKE_WIDTH, IMAGE_SIZE, RANDOM_SCENE_BODIES};
+use gravity_wells::force_field::BodyShape;
+use gravity_wells::thrust::{Thrust, ThrustDirection, THRUST_STEP};
+use gravity_wells::image_gen::{
+    compute_outcome_grid, export_image, render_difference_image, terminal_progress, zoom_text, DifferenceStats, ExportOverlays, MapParams,
+};
+use gravity_wells::outcome::OutcomeGrid;
+use gravity_wells::render::{gradient_color, DensityBuffer};
+use gravity_wells::scene::{Scene, ScenePreset, SceneWatcher};
+use gravity_wells::bookmarks::{load_bookmarks, save_bookmarks, Bookmark, BOOKMARKS_FILE};
+use gravity_wells::session::{SavedTrajectory, Session, SESSION_FILE};
+use gravity_wells::metadata::load_png_metadata;
+use gravity_wells::animation::{render_animation, AnimationSettings};
+use gravity_wells::zoom_video::{render_zoom_video, ZoomVideoSettings};
+use gravity_wells::recording::{Recording, RecordingFormat};
+use gravity_wells::screenshot::{caption_lines, next_screenshot_filename, save_screenshot};
+use gravity_wells::trajectory::{record_trajectory, save_trajectory};
+use gravity_wells::svg::{save_svg, SvgTrajectory};
+use gravity_wells::contours::{extract_boundaries, save_geojson, BasinBoundary};
+use gravity_wells::grid_export::save_grid_data;
+use gravity_wells::jacobi::{contour_overlay, jacobi_constant};
+use gravity_wells::escape::EscapeColoring;
+use gravity_wells::events::SimulationObserver;
+use gravity_wells::axes::{format_length, scale_bar, WorldGrid, GRID_SPACING};
+use gravity_wells::kepler::{OrbitalElements, KEPLER_DOMINANCE};
+use gravity_wells::energy_drift::{compare_integrators, save_energy_drift, EnergyDrift};
+use gravity_wells::validation::{time_reversal, Reversibility};
+use gravity_wells::sweep::{render_sweep, SweepAxis, SweepSettings};
+use gravity_wells::stream::run_stream;
+use gravity_wells::server::serve;
+use gravity_wells::progress::GenerationProgress;
+use gravity_wells::parameter_map::{compute_parameter_grid, render_labeled_map, ParameterMap};
+use gravity_wells::bifurcation::{compute_bifurcation, render_bifurcation};
+use gravity_wells::probability::{compute_probability_grid, render_probability_grid, ProbabilitySettings, DEFAULT_NOISE};
+use gravity_wells::uncertainty::{estimate_uncertainty_exponent, save_uncertainty, UncertaintySettings};
+use gravity_wells::deep_zoom::{DeepPoint, DEEP_ZOOM_THRESHOLD, MAX_ZOOM};
+use gravity_wells::threads::RenderPoolBuilder;
+use gamepad::Gamepad;
+use glow::Glow;
+use history::{History, ViewSettings};
+use hud::{Hud, HudLine};
+use input::{
+    get_frame_time, get_time, is_key_down, is_key_pressed, is_mouse_button_down, is_mouse_button_pressed, mouse_position, mouse_wheel,
+    RecordingHeader,
+};
+use minimap::{Minimap, MINIMAP_SIZE};
+use plot::{padded_range, Plot};
+use sound::{SoundCue, SoundEffects};
+use starfield::draw_starfield;
+use panel::{ControlPanel, PanelChanges};
+use render_queue::{FinishedJob, RenderQueue};
+
+// A generated map loaded into GPU memory together with the parameters and raw outcomes it was rendered with
+struct LoadedMap {
+    params: MapParams,
+    texture: Texture2D,
+    grid: OutcomeGrid,
+}
+
+// Progress bar, percentage and ETA of the map being generated, drawn at the top center of the window
+fn draw_generation_progress(progress: &GenerationProgress, view_width: f32) {
+    const BAR_WIDTH: f32 = 260.0;
+    const BAR_HEIGHT: f32 = 10.0;
+    let x = (view_width - BAR_WIDTH) / 2.0;
+    let y = 36.0;
+    let fraction = progress.fraction();
+    let eta = progress.eta().map_or("estimating...".to_string(), |eta| format!("ETA {:.0}s", eta.as_secs_f32().ceil()));
+    let label = format!("Generating map: {:.0}%  {}", fraction * 100.0, eta);
+
+    draw_rectangle(x - 8.0, y - 24.0, BAR_WIDTH + 16.0, BAR_HEIGHT + 32.0, Color::new(0.0, 0.0, 0.0, 0.7));
+    draw_text(&label, x, y - 6.0, 18.0, WHITE);
+    draw_rectangle(x, y, BAR_WIDTH, BAR_HEIGHT, DARKGRAY);
+    draw_rectangle(x, y, BAR_WIDTH * fraction, BAR_HEIGHT, SKYBLUE);
+}
+
+fn texture_from_image(img: &image::RgbImage) -> Texture2D {
+    let rgba = image::DynamicImage::ImageRgb8(img.clone()).to_rgba8();
+    Texture2D::from_rgba8(rgba.width() as u16, rgba.height() as u16, rgba.as_raw())
+}
+
+// How close (in pixels) the mouse has to be to grab the comparison divider
+const DIVIDER_GRAB_DISTANCE: f32 = 6.0;
+
+// Trail colors cycled through for simultaneous live simulations
+const TRAIL_COLORS: [Color; 6] = [YELLOW, ORANGE, PINK, LIME, SKYBLUE, VIOLET];
+// Screen pixels a live particle moves between the points kept of its trail. Changing the zoom
+// clears the live simulations, so the spacing is fixed at launch.
+const TRAIL_POINT_SPACING: f32 = 2.0;
+
+// A running live simulation together with the color its trail is drawn in
+struct LiveTrail {
+    sim: LiveSimulationState,
+    color: Color,
+    // The trail's recorded_points as of the latest deposit into the long exposure
+    exposed_points: usize,
+    events: TrailEvents,
+}
+
+// What the status line tells of a live launch besides its outcome, and the sounds it has made
+// since the last frame
+#[derive(Default)]
+struct TrailEvents {
+    close_approaches: usize,
+    sounds: Vec<SoundCue>,
+}
+
+impl SimulationObserver for TrailEvents {
+    fn on_close_approach(&mut self, _timestep: usize, _body: usize, _distance: f32, particle: &TestParticle) {
+        self.close_approaches += 1;
+        self.sounds.push(SoundCue::CloseApproach { speed: particle.vel.length() });
+    }
+
+    fn on_collision(&mut self, _timestep: usize, body: usize) {
+        self.sounds.push(SoundCue::Collision { body });
+    }
+}
+
+// Screenshot saved with the parameter readout and time burned into a corner, or as drawn
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Screenshot {
+    Annotated,
+    Plain,
+}
+
+// What a left click launches
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum LaunchMode {
+    Single,
+    // Monte Carlo cloud scattered in position around the click
+    EnsemblePosition,
+    // Monte Carlo cloud launched from the click with jittered velocities
+    EnsembleVelocity,
+}
+
+impl LaunchMode {
+    fn next(self) -> Self {
+        match self {
+            LaunchMode::Single => LaunchMode::EnsemblePosition,
+            LaunchMode::EnsemblePosition => LaunchMode::EnsembleVelocity,
+            LaunchMode::EnsembleVelocity => LaunchMode::Single,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            LaunchMode::Single => "Single particle",
+            LaunchMode::EnsemblePosition => "Ensemble (position disc)",
+            LaunchMode::EnsembleVelocity => "Ensemble (velocity jitter)",
+        }
+    }
+}
+
+// How live trajectory segments are colored
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TrailColoring {
+    Solid,
+    Speed,
+    Time,
+}
+
+impl TrailColoring {
+    fn next(self) -> Self {
+        match self {
+            TrailColoring::Solid => TrailColoring::Speed,
+            TrailColoring::Speed => TrailColoring::Time,
+            TrailColoring::Time => TrailColoring::Solid,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            TrailColoring::Solid => "solid",
+            TrailColoring::Speed => "by speed",
+            TrailColoring::Time => "by time",
+        }
+    }
+}
+
+const ENSEMBLE_SIZE: usize = 500;
+// Radius in screen pixels of the disc ensemble particles start in
+const ENSEMBLE_SCREEN_RADIUS: f32 = 6.0;
+
+// Screen pixels per unit of velocity / acceleration for the vector overlays at scale 1
+const VELOCITY_ARROW_SCALE: f32 = 0.5;
+const ACCELERATION_ARROW_SCALE: f32 = 0.05;
+const MAX_ARROW_LENGTH: f32 = 250.0;
+const VECTOR_SCALES: [f32; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+// Length in screen pixels of the exhaust drawn behind a firing particle
+const EXHAUST_LENGTH: f32 = 12.0;
+
+fn draw_arrow(from: Vec2, vector: Vec2, color: Color) {
+    let arrow = vector.clamp_length(MAX_ARROW_LENGTH);
+    let length = arrow.length();
+    if length < 1.0 {
+        return;
+    }
+    let dir = arrow.normalize();
+    let tip = from + arrow;
+    draw_line(from.x, from.y, tip.x, tip.y, 2.0, color);
+
+    // Arrow head: two short strokes angled back from the tip
+    let head = 8.0_f32.min(length * 0.5);
+    let side = dir.perp();
+    for sign in [-1.0, 1.0] {
+        let end = tip - dir * head + side * (head * 0.5 * sign);
+        draw_line(tip.x, tip.y, end.x, end.y, 2.0, color);
+    }
+}
+
+// Hover preview: number of timesteps simulated and minimum seconds between recomputations
+const PREVIEW_TIMESTEPS: usize = 200;
+const PREVIEW_INTERVAL: f64 = 0.05;
+
+// Tooltip with the raw outcome of the map pixel under the mouse
+fn draw_pixel_inspector(map: &LoadedMap, mx: f32, my: f32) {
+    let params = &map.params;
+    let world_pos = params.camera().screen_to_world(Vec2::new(mx, my));
+    let outcome = match map.grid.get(mx as u32, my as u32) {
+        Some((body, timestep)) => format!("Hits body {} at timestep {} (t = {:.2})",
+                                          body, timestep, timestep as f32 * params.simulation.dt),
+        None => format!("No collision within {} timesteps (escaped)", map.grid.timesteps),
+    };
+    let lines = [
+        format!("Pixel ({}, {})", mx as u32, my as u32),
+        format!("World ({:.2}, {:.2})", world_pos.x, world_pos.y),
+        outcome,
+    ];
+
+    let width = lines.iter().map(|line| measure_text(line, None, 16, 1.0).width).fold(0.0, f32::max) + 12.0;
+    let height = lines.len() as f32 * 18.0 + 6.0;
+    // Keep the tooltip inside the window
+    let x = (mx + 14.0).min(screen_width() - width);
+    let y = (my + 14.0).min(screen_height() - height);
+    draw_rectangle(x, y, width, height, Color::new(0.0, 0.0, 0.0, 0.8));
+    draw_rectangle_lines(x, y, width, height, 1.0, GRAY);
+    for (i, line) in lines.iter().enumerate() {
+        draw_text(line, x + 6.0, y + 18.0 * (i + 1) as f32, 16.0, WHITE);
+    }
+}
+
+// Camera offset, zoom, scene, Jacobi constant bits and size the zero-velocity overlay was built for
+type JacobiOverlayKey = (Vec2, f32, u64, Option<u32>, u32, u32);
+
+// Kepler overlay: segments of the drawn conic, and how far out hyperbolic branches are drawn in
+// view sizes
+const KEPLER_SEGMENTS: usize = 360;
+const KEPLER_VIEW_EXTENT: f32 = 3.0;
+
+// World coordinate grid over the map, labelled along the top and left edges, with a scale bar
+// in the top right corner; the same layout as the grid drawn into exports
+fn draw_world_grid(camera: &Camera) {
+    let (view_width, view_height, zoom_factor) = (camera.viewport.x, camera.viewport.y, camera.zoom);
+    let grid = WorldGrid::new(camera, GRID_SPACING);
+    let line_color = |value: f32| Color::new(1.0, 1.0, 1.0, if grid.is_axis(value) { 0.6 } else { 0.25 });
+    for &(x, value) in &grid.columns {
+        draw_line(x, 0.0, x, view_height, 1.0, line_color(value));
+        draw_text(&grid.label(value), x + 3.0, 14.0, 14.0, LIGHTGRAY);
+    }
+    for &(y, value) in &grid.rows {
+        draw_line(0.0, y, view_width, y, 1.0, line_color(value));
+        draw_text(&grid.label(value), 3.0, y + 14.0, 14.0, LIGHTGRAY);
+    }
+
+    let (length, pixels) = scale_bar(view_width, zoom_factor);
+    let right = view_width - 12.0;
+    let bar_y = 50.0;
+    draw_line(right - pixels, bar_y, right, bar_y, 3.0, WHITE);
+    for x in [right - pixels, right] {
+        draw_line(x, bar_y - 6.0, x, bar_y + 6.0, 2.0, WHITE);
+    }
+    let label = format_length(length, length);
+    let label_width = measure_text(&label, None, 14, 1.0).width;
+    draw_text(&label, right - pixels / 2.0 - label_width / 2.0, bar_y - 8.0, 14.0, WHITE);
+}
+
+// Energy drift panel in the bottom right corner, plotting log10 of each integrator's relative
+// energy error against time
+const DRIFT_PANEL_WIDTH: f32 = 320.0;
+const DRIFT_PANEL_HEIGHT: f32 = 170.0;
+// Errors below this are drawn at the bottom of the plot
+const DRIFT_MIN_ERROR: f32 = 1e-9;
+const DRIFT_COLORS: [Color; 2] = [ORANGE, SKYBLUE];
+
+fn draw_energy_drift(drifts: &[EnergyDrift], bottom: f32) {
+    let end_time = drifts.iter().filter_map(|drift| drift.samples.last()).map(|sample| sample.0).fold(0.0, f32::max);
+    let min_decade = DRIFT_MIN_ERROR.log10().floor();
+    let max_decade = drifts.iter().map(|drift| drift.max_error()).fold(DRIFT_MIN_ERROR * 10.0, f32::max).log10().ceil();
+    let plot = Plot::new(screen_width() - DRIFT_PANEL_WIDTH - 8.0, bottom - DRIFT_PANEL_HEIGHT, DRIFT_PANEL_WIDTH, DRIFT_PANEL_HEIGHT,
+                         (0.0, end_time), (min_decade, max_decade), "Energy error |dE/E0|");
+
+    // A gridline and label every few decades
+    let step = ((max_decade - min_decade) / 4.0).ceil().max(1.0);
+    let decades: Vec<f32> = (0..).map(|i| min_decade + step * i as f32).take_while(|&decade| decade <= max_decade).collect();
+    plot.y_ticks(&decades, |decade| format!("1e{}", decade));
+    plot.x_ticks(&[0.0, end_time], |t| format!("t={:.2}", t));
+
+    for (i, drift) in drifts.iter().enumerate() {
+        let color = DRIFT_COLORS[i % DRIFT_COLORS.len()];
+        plot.line(drift.samples.iter().map(|&(t, _, error)| (t, error.abs().max(DRIFT_MIN_ERROR).log10())), 1.5, color);
+        plot.legend(i, &format!("{}: max {:.1e}", drift.integration_method.name(), drift.max_error()), color);
+    }
+}
+
+// Phase-space panel in the bottom left corner
+const PHASE_PANEL_WIDTH: f32 = 300.0;
+const PHASE_PANEL_HEIGHT: f32 = 200.0;
+
+// Coordinates the phase-space panel plots the live particles in
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PhaseView {
+    Off,
+    X,
+    Y,
+    // Distance from, and velocity away from, the body nearest the launch point
+    Radial,
+}
+
+impl PhaseView {
+    fn next(self) -> Self {
+        match self {
+            PhaseView::Off => PhaseView::X,
+            PhaseView::X => PhaseView::Y,
+            PhaseView::Y => PhaseView::Radial,
+            PhaseView::Radial => PhaseView::Off,
+        }
+    }
+
+    fn axis_names(self) -> &'static str {
+        match self {
+            PhaseView::Off => "",
+            PhaseView::X => "x, vx",
+            PhaseView::Y => "y, vy",
+            PhaseView::Radial => "r, vr",
+        }
+    }
+
+    // Phase-space coordinates of a state, with `center` the body radial coordinates refer to
+    fn coordinates(self, pos: Vec2, vel: Vec2, center: Vec2) -> (f32, f32) {
+        match self {
+            PhaseView::Off | PhaseView::X => (pos.x, vel.x),
+            PhaseView::Y => (pos.y, vel.y),
+            PhaseView::Radial => {
+                let offset = pos - center;
+                let dir = offset.normalize();
+                (offset.length(), vel.dot(&dir))
+            }
+        }
+    }
+}
+
+// Plots every live trajectory in phase space, up to the replayed timestep when replaying
+fn draw_phase_space(view: PhaseView, trails: &[LiveTrail], replay_timestep: Option<usize>, bottom: f32) {
+    // Radial coordinates are taken about the body nearest each launch point
+    let center = |trail: &LiveTrail| {
+        let start = trail.sim.trajectory_history[0].pos;
+        trail.sim.scene.bodies.iter()
+            .map(|body| body.pos)
+            .min_by(|a, b| a.distance(&start).total_cmp(&b.distance(&start)))
+            .unwrap_or(Vec2::new(0.0, 0.0))
+    };
+    let paths: Vec<Vec<(f32, f32)>> = trails.iter().map(|trail| {
+        let shown = replay_timestep.map_or(trail.sim.trajectory_history.len(), |t| trail.sim.history_len_at(t));
+        let center = center(trail);
+        trail.sim.trajectory_history[..shown].iter()
+            .map(|point| view.coordinates(point.pos, point.vel, center))
+            .collect()
+    }).collect();
+
+    let plot = Plot::new(8.0, bottom - PHASE_PANEL_HEIGHT, PHASE_PANEL_WIDTH, PHASE_PANEL_HEIGHT,
+                         padded_range(paths.iter().flatten().map(|p| p.0)),
+                         padded_range(paths.iter().flatten().map(|p| p.1)),
+                         "Phase space");
+    plot.default_ticks();
+    plot.corner_label(view.axis_names(), SKYBLUE);
+    for (trail, path) in trails.iter().zip(&paths) {
+        plot.line(path.iter().copied(), 1.5, trail.color);
+        if let Some(&(x, y)) = path.last() {
+            plot.marker(x, y, trail.color);
+        }
+    }
+}
+
+// Drag panning: the camera keeps gliding after release, losing this fraction of its speed per second
+const PAN_FRICTION: f32 = 5.0;
+// Glide speed (screen pixels per second) below which the camera stops
+const PAN_MIN_SPEED: f32 = 5.0;
+
+// Launch speed with the gamepad's right stick fully deflected, the range of the panel's sliders
+const GAMEPAD_MAX_SPEED: f32 = 100.0;
+// Zoom change per frame with a trigger fully pulled, as with Q/E
+const GAMEPAD_ZOOM_RATE: f32 = 0.01;
+// Size of the crosshair gamepad launches start from
+const GAMEPAD_CROSSHAIR_SIZE: f32 = 8.0;
+
+// Mass change of the selected body per wheel notch or bracket press
+const BODY_MASS_STEP: f32 = 1.1;
+// Clicks this close (screen pixels) to a small body still select it
+const BODY_PICK_RADIUS: f32 = 8.0;
+
+// Replay timeline bar along the bottom of the window
+const TIMELINE_MARGIN: f32 = 10.0;
+// Distance of the timeline from the bottom of the window
+const TIMELINE_BOTTOM_OFFSET: f32 = 56.0;
+const TIMELINE_HEIGHT: f32 = 8.0;
 
 fn window_conf() -> Conf {
     Conf {
         window_title: "Gravity Wells Visualization".to_owned(),
         window_width: IMAGE_SIZE as i32,
         window_height: IMAGE_SIZE as i32,
-        window_resizable: false,
+        window_resizable: true,
         ..Default::default()
     }
 }
 
-#[macroquad::main(window_conf)]
-async fn main() {
-    // Create stationary bodies from configuration
-    let stationary_bodies = create_stationary_bodies();
-    let mut use_runge_kutta = true; // Default to RK4 for better accuracy
-    let mut initial_velocity = Vec2::new(0.0, 0.0); // Starting with zero velocity
-    let mut step_size = 2.0; // Configurable step size for velocity and camera movement
-    let mut camera_offset = Vec2::new(0.0, 0.0); // Camera position offset
-    let mut zoom_factor = 1.0; // Zoom level
-    let mut needs_recalculation = true; // Flag to track when we need to regenerate image
+fn main() {
+    // Parse arguments before opening the window so --help and argument errors work without a display
+    let cli = cli::Cli::parse();
+    if cli.self_test {
+        let passed = gravity_wells::validation::print_report(&gravity_wells::validation::run_all());
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+    let pool = RenderPoolBuilder::new().threads(cli.threads).background(cli.background);
+    if let Err(e) = pool.build_global() {
+        eprintln!("Error configuring the thread pool: {}", e);
+    }
+    match &cli.command {
+        Some(cli::Command::Animate(args)) => {
+            run_animation(&cli, args);
+            return;
+        }
+        Some(cli::Command::ZoomVideo(args)) => {
+            run_zoom_video(&cli, args);
+            return;
+        }
+        Some(cli::Command::Trajectory(args)) => {
+            export_trajectory(&cli, args);
+            return;
+        }
+        Some(cli::Command::Sweep(args)) => {
+            run_sweep(&cli, args);
+            return;
+        }
+        Some(cli::Command::Stream(args)) => {
+            stream_launches(&cli, args);
+            return;
+        }
+        Some(cli::Command::Serve(args)) => {
+            run_server(&cli, args);
+            return;
+        }
+        Some(cli::Command::Boundaries(args)) => {
+            export_boundaries(&cli, args);
+            return;
+        }
+        Some(cli::Command::GridData(args)) => {
+            export_grid_data(&cli, args);
+            return;
+        }
+        Some(cli::Command::Probability(args)) => {
+            render_probability_map(&cli, args);
+            return;
+        }
+        Some(cli::Command::Uncertainty(args)) => {
+            estimate_uncertainty(&cli, args);
+            return;
+        }
+        Some(cli::Command::Polar(args)) => {
+            render_polar_map(&cli, args);
+            return;
+        }
+        Some(cli::Command::MassMap(args)) => {
+            render_mass_map(&cli, args);
+            return;
+        }
+        Some(cli::Command::Bifurcation(args)) => {
+            render_bifurcation_plot(&cli, args);
+            return;
+        }
+        Some(cli::Command::Stability(args)) => {
+            let conf = stability_view::window_conf(args.size);
+            macroquad::Window::from_config(conf, stability_view::run_stability_explorer(args.size, args.output.clone()));
+            return;
+        }
+        None => {}
+    }
+    if let Some((width, height)) = cli.export {
+        export_default_view(&cli, width, height);
+        return;
+    }
+    macroquad::Window::from_config(window_conf(), run_viewer(cli));
+}
+
+// Headless --export: renders the initial view (camera at the origin, zoom 1, at rest) with the
+// requested aspect ratio
+fn export_default_view(cli: &cli::Cli, width: u32, height: u32) {
+    let scene = cli.requested_scene().unwrap_or_default();
+    let view = cli.initial_view(&scene, width, height);
+    let overlays = ExportOverlays { bodies: cli.export_bodies, legend: cli.export_legend, grid: cli.export_grid, encoding: cli.color_encoding, palette: cli.palette, patterns: cli.export_patterns, output: cli.image_output() };
+    if let Err(e) = export_image(&scene, &view, width, height, cli.supersample, overlays, &terminal_progress()) {
+        eprintln!("Error exporting image: {}", e);
+        std::process::exit(1);
+    }
+}
+
+// `animate` subcommand: sweeps one parameter starting from the initial view
+fn run_animation(cli: &cli::Cli, args: &cli::AnimateArgs) {
+    let scene = cli.requested_scene().unwrap_or_default();
+    let (width, height) = args.size;
+    let base = cli.initial_view(&scene, width, height).at_resolution(width, height);
+    let settings = AnimationSettings {
+        parameter: args.parameter,
+        from: args.from,
+        to: args.to,
+        frames: args.frames.max(1),
+        body: args.body,
+        speed: args.speed,
+        frame_delay_ms: args.frame_delay,
+        draw_bodies: cli.export_bodies,
+        image_output: cli.image_output(),
+    };
+    if let Err(e) = render_animation(&base, &scene, &settings, &args.output, args.gif) {
+        eprintln!("Error rendering animation: {}", e);
+        std::process::exit(1);
+    }
+}
+
+// `zoom-video` subcommand: zooms from the start zoom into the requested point
+fn run_zoom_video(cli: &cli::Cli, args: &cli::ZoomVideoArgs) {
+    let scene = cli.requested_scene().unwrap_or_default();
+    let (width, height) = args.size;
+    let base = cli.initial_view(&scene, width, height).at_resolution(width, height);
+    let settings = ZoomVideoSettings {
+        target: gravity_wells::precise::DVec2::new(args.x, args.y),
+        start_zoom: args.start_zoom,
+        end_zoom: args.end_zoom,
+        frames: args.frames.max(1),
+        draw_bodies: cli.export_bodies,
+        image_output: cli.image_output(),
+    };
+    if let Err(e) = render_zoom_video(&base, &scene, &settings, &args.output) {
+        eprintln!("Error rendering zoom video: {}", e);
+        std::process::exit(1);
+    }
+}
+
+// `trajectory` subcommand: simulates one launch and writes every timestep to a file
+fn export_trajectory(cli: &cli::Cli, args: &cli::TrajectoryArgs) {
+    let scene = cli.requested_scene().unwrap_or_default();
+    if args.energy_drift {
+        let drifts = compare_integrators(Vec2::new(args.x, args.y), Vec2::new(args.vx, args.vy), &scene, &cli.simulation_params());
+        if let Err(e) = save_energy_drift(&args.output, &drifts) {
+            eprintln!("Error saving energy drift: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let method = if args.euler { IntegrationMethod::Euler } else { IntegrationMethod::RungeKutta4 };
+    let params = cli.simulation_params();
+    let samples = record_trajectory(Vec2::new(args.x, args.y), Vec2::new(args.vx, args.vy), &scene, method, &params,
+                                    &cli.scheduled_burns(&params));
+    let result = if args.output.to_ascii_lowercase().ends_with(".svg") {
+        // Drawn over the initial view, the same one the viewer opens with
+        let view = cli.initial_view(&scene, IMAGE_SIZE, IMAGE_SIZE);
+        let trajectory = SvgTrajectory { points: samples.iter().map(|s| Vec2::new(s.x, s.y)).collect(), color: [255, 255, 0] };
+        save_svg(&args.output, &scene, &view, &[trajectory], None)
+    } else {
+        save_trajectory(&args.output, &samples)
+    };
+    if let Err(e) = result {
+        eprintln!("Error saving trajectory: {}", e);
+        std::process::exit(1);
+    }
+}
+
+// `boundaries` subcommand: generates the initial view at the requested size and velocity, then
+// traces its basin boundaries
+fn export_boundaries(cli: &cli::Cli, args: &cli::BoundariesArgs) {
+    let scene = cli.requested_scene().unwrap_or_default();
+    let (width, height) = args.size;
+    let mut view = cli.initial_view(&scene, width, height).at_resolution(width, height);
+    view.initial_velocity = Vec2::new(args.vx, args.vy);
+    println!("Generating {}x{} map...", width, height);
+    let grid = compute_outcome_grid(&scene, &view);
+    let result = if args.output.to_ascii_lowercase().ends_with(".svg") {
+        save_svg(&args.output, &scene, &view, &[], Some(&grid))
+    } else {
+        save_geojson(&args.output, &extract_boundaries(&grid), &grid, &view)
+    };
+    if let Err(e) = result {
+        eprintln!("Error saving boundaries: {}", e);
+        std::process::exit(1);
+    }
+}
+
+// `grid-data` subcommand: generates the initial view at the requested size and velocity and
+// writes its raw outcomes
+fn export_grid_data(cli: &cli::Cli, args: &cli::GridDataArgs) {
+    let scene = cli.requested_scene().unwrap_or_default();
+    let (width, height) = args.size;
+    let mut view = cli.initial_view(&scene, width, height).at_resolution(width, height);
+    view.initial_velocity = Vec2::new(args.vx, args.vy);
+    println!("Generating {}x{} map...", width, height);
+    let grid = compute_outcome_grid(&scene, &view);
+    if let Err(e) = save_grid_data(&args.output, &grid, &view) {
+        eprintln!("Error saving grid data: {}", e);
+        std::process::exit(1);
+    }
+}
+
+// `probability` subcommand: the initial view simulated `samples` times per pixel under noise or
+// with jittered starts
+fn render_probability_map(cli: &cli::Cli, args: &cli::ProbabilityArgs) {
+    let scene = cli.requested_scene().unwrap_or_default();
+    let (width, height) = args.size;
+    let mut view = cli.initial_view(&scene, width, height).at_resolution(width, height);
+    view.initial_velocity = Vec2::new(args.vx, args.vy);
+    let settings = ProbabilitySettings {
+        samples: args.samples.max(1),
+        noise: args.noise.unwrap_or(if args.jitter { 0.0 } else { DEFAULT_NOISE }),
+        jitter: args.jitter,
+        seed: cli.seed.unwrap_or_default(),
+    };
+    println!("Generating {}x{} map with {} samples per pixel...", width, height, settings.samples);
+    let grid = compute_probability_grid(&scene, &view, &settings, &terminal_progress());
+    let image = render_probability_grid(&grid, &scene.bodies, args.coloring);
+    match cli.image_output().save_image(&image, &args.output) {
+        Ok(()) => println!("Probability map saved to {}", args.output),
+        Err(e) => {
+            eprintln!("Error saving probability map: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// `uncertainty` subcommand: prints the uncertain fraction at every separation and the fit
+fn estimate_uncertainty(cli: &cli::Cli, args: &cli::UncertaintyArgs) {
+    let scene = cli.requested_scene().unwrap_or_default();
+    let mut view = cli.initial_view(&scene, IMAGE_SIZE, IMAGE_SIZE);
+    view.initial_velocity = Vec2::new(args.vx, args.vy);
+    if !(args.largest > 0.0 && args.smallest > 0.0) {
+        eprintln!("Separations must be positive");
+        std::process::exit(1);
+    }
+    let settings = UncertaintySettings {
+        pairs: args.pairs.max(1),
+        largest: args.largest,
+        smallest: args.smallest,
+        steps: args.steps.max(1),
+        seed: cli.seed.unwrap_or_default(),
+    };
+    println!("Comparing {} launch pairs at {} separations...", settings.pairs, settings.steps);
+    let estimate = estimate_uncertainty_exponent(&scene, &view, &settings, &terminal_progress());
+    println!("{:>12} {:>10} {:>10}", "separation", "uncertain", "fraction");
+    for point in &estimate.points {
+        println!("{:>12.3e} {:>10} {:>10.4}", point.separation, point.uncertain, point.fraction);
+    }
+    match (estimate.exponent, estimate.boundary_dimension) {
+        (Some(exponent), Some(dimension)) => {
+            println!("Uncertainty exponent {:.3}, boundary dimension {:.3}", exponent, dimension)
+        }
+        _ => println!("Too few uncertain pairs to fit an exponent; try more pairs or larger separations"),
+    }
+    if let Some(output) = &args.output {
+        match save_uncertainty(output, &estimate) {
+            Ok(()) => println!("Uncertainty estimate saved to {}", output),
+            Err(e) => {
+                eprintln!("Error saving uncertainty estimate: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+// `polar` subcommand: every launch angle and speed from one start, with labeled axes
+fn render_polar_map(cli: &cli::Cli, args: &cli::PolarArgs) {
+    let scene = cli.requested_scene().unwrap_or_default();
+    let (width, height) = args.size;
+    let map = ParameterMap::Polar { start: Vec2::new(args.x, args.y), min_speed: args.min_speed, max_speed: args.max_speed };
+    println!("Generating {}x{} polar map from ({}, {})...", width, height, args.x, args.y);
+    render_parameter_map(cli, &scene, &map, args.size, args.euler, &args.output);
+}
+
+// `mass-map` subcommand: one launch over the masses of two bodies
+fn render_mass_map(cli: &cli::Cli, args: &cli::MassMapArgs) {
+    let scene = cli.requested_scene().unwrap_or_default();
+    let (width, height) = args.size;
+    let map = ParameterMap::Masses {
+        bodies: [args.body_x, args.body_y],
+        x_masses: (args.mass_x[0], args.mass_x[1]),
+        y_masses: (args.mass_y[0], args.mass_y[1]),
+        start: Vec2::new(args.x, args.y),
+        velocity: Vec2::new(args.vx, args.vy),
+    };
+    if let Some(problem) = map.problem(&scene) {
+        eprintln!("Error: {}", problem);
+        std::process::exit(1);
+    }
+    println!("Generating {}x{} map over the masses of bodies {} and {}...", width, height, args.body_x, args.body_y);
+    render_parameter_map(cli, &scene, &map, args.size, args.euler, &args.output);
+}
+
+// Simulates a parameter map and saves it with labeled axes
+fn render_parameter_map(cli: &cli::Cli, scene: &Scene, map: &ParameterMap, (width, height): (u32, u32), euler: bool, output: &str) {
+    let method = if euler { IntegrationMethod::Euler } else { IntegrationMethod::RungeKutta4 };
+    let grid = compute_parameter_grid(scene, map, width, height, method, &cli.simulation_params(), &terminal_progress());
+    let image = render_labeled_map(&grid, scene, map);
+    match cli.image_output().save_image(&image, output) {
+        Ok(()) => println!("Parameter map saved to {}", output),
+        Err(e) => {
+            eprintln!("Error saving parameter map: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// `bifurcation` subcommand: one launch's collision time over a single swept parameter
+fn render_bifurcation_plot(cli: &cli::Cli, args: &cli::BifurcationArgs) {
+    let scene = cli.requested_scene().unwrap_or_default();
+    let mut base = cli.initial_view(&scene, IMAGE_SIZE, IMAGE_SIZE);
+    base.initial_velocity = Vec2::new(args.vx, args.vy);
+    if args.euler {
+        base.integration_method = IntegrationMethod::Euler;
+    }
+    let axis = SweepAxis { parameter: args.parameter, from: args.from, to: args.to, steps: args.steps.max(1) };
+    println!("Sweeping {} over {} values from ({}, {})...", args.parameter.short_name(), axis.steps, args.x, args.y);
+    let result = compute_bifurcation(&base, &scene, Vec2::new(args.x, args.y), axis, args.body, &terminal_progress())
+        .and_then(|bifurcation| {
+            let image = render_bifurcation(&bifurcation, &scene.bodies, args.height);
+            cli.image_output().save_image(&image, &args.output)
+        });
+    match result {
+        Ok(()) => println!("Bifurcation plot saved to {}", args.output),
+        Err(e) => {
+            eprintln!("Error rendering bifurcation plot: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// `sweep` subcommand: renders the initial view for every cell of the parameter grid
+fn run_sweep(cli: &cli::Cli, args: &cli::SweepArgs) {
+    let scene = cli.requested_scene().unwrap_or_default();
+    let (width, height) = args.cell_size;
+    let base = cli.initial_view(&scene, width, height).at_resolution(width, height);
+    let settings = SweepSettings {
+        columns: SweepAxis { parameter: args.parameter, from: args.from, to: args.to, steps: args.steps.max(1) },
+        rows: args.rows_parameter.map(|parameter| SweepAxis {
+            parameter,
+            from: args.rows_from,
+            to: args.rows_to,
+            steps: args.rows_steps.max(1),
+        }),
+        body: args.body,
+    };
+    let result = render_sweep(&base, &scene, &settings).and_then(|montage| cli.image_output().save_image(&montage, &args.output));
+    match result {
+        Ok(()) => println!("Sweep montage saved to {}", args.output),
+        Err(e) => {
+            eprintln!("Error rendering sweep: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// `stream` subcommand: stdout only carries results, so messages go to stderr
+fn stream_launches(cli: &cli::Cli, args: &cli::StreamArgs) {
+    let scene = cli.requested_scene().unwrap_or_default();
+    let method = if args.euler { IntegrationMethod::Euler } else { IntegrationMethod::RungeKutta4 };
+    if let Err(e) = run_stream(std::io::stdin().lock(), std::io::stdout().lock(), &scene, method, &cli.simulation_params()) {
+        eprintln!("Error streaming launches: {}", e);
+        std::process::exit(1);
+    }
+}
+
+// `serve` subcommand: requests default to the initial view of the chosen scene
+fn run_server(cli: &cli::Cli, args: &cli::ServeArgs) {
+    let scene = cli.requested_scene().unwrap_or_default();
+    let base = cli.initial_view(&scene, IMAGE_SIZE, IMAGE_SIZE);
+    if let Err(e) = serve(&args.address, args.websocket.as_deref(), &scene, &base, &cli.simulation_params()) {
+        eprintln!("Error running server: {}", e);
+        std::process::exit(1);
+    }
+}
+
+// Widths of the F2 / Shift+F2 exports; the height follows the window's aspect ratio
+const EXPORT_WIDTH_4K: u32 = 3840;
+const EXPORT_WIDTH_8K: u32 = 7680;
+
+async fn run_viewer(cli: cli::Cli) {
+    // Bodies and gravitational constant, starting from the configuration and editable in the control panel
+    let mut scene = Scene::default();
+    let mut use_runge_kutta = true; // Default to RK4 for better accuracy
+    let mut initial_velocity = Vec2::new(0.0, 0.0); // Starting with zero velocity
+    let mut step_size = 2.0; // Configurable step size for velocity and camera movement
+    let mut camera = DeepPoint::from_vec2(Vec2::new(0.0, 0.0)); // Camera position offset, at full precision for deep zooms
+    let mut camera_offset = camera.to_vec2();
+    let mut zoom_factor: f32 = 1.0; // Zoom level
+    let mut sim_params = cli.simulation_params(); // Simulation length, substeps and collision threshold
+    let mut thrust_magnitude = cli.thrust; // Acceleration of the engine fired with Home/End
+    let mut escape_coloring = cli.escape_coloring;
+    let mut needs_recalculation = true; // Flag to track when we need to regenerate image
+    let bindings = cli.keybindings();
+
+    // Helper function to bundle the current map parameters
+    let get_map_params = |size: (u32, u32), use_rk4: bool, vel: Vec2, cam: DeepPoint, zoom: f32, sim: SimulationParams, scene: &Scene,
+                          coloring: EscapeColoring| -> MapParams {
+        MapParams {
+            width: size.0,
+            height: size.1,
+            initial_velocity: vel,
+            camera_offset: cam.to_vec2(),
+            zoom_factor: zoom,
+            integration_method: if use_rk4 { IntegrationMethod::RungeKutta4 } else { IntegrationMethod::Euler },
+            simulation: sim.resolved(scene, zoom),
+            scene_key: scene.cache_key(),
+            deep_camera: None,
+            escape_coloring: coloring,
+        }.with_camera(cam)
+    };
+
+    let mut current_map: Option<LoadedMap> = None;
+    let mut render_queue = RenderQueue::new();
+    let mut live_simulations: Vec<LiveTrail> = Vec::new();
+    let mut launch_mode = LaunchMode::Single;
+    let mut trail_coloring = TrailColoring::Solid;
+    let mut show_vectors = false;
+    // Ghost of the two-body orbit fitted to the latest particle when one body dominates its pull
+    let mut show_kepler = false;
+    let mut phase_view = PhaseView::Off;
+    // Zero-velocity curve of the latest launch and contours of the potential, rebuilt whenever
+    // the view, scene or launch changes
+    let mut show_jacobi = false;
+    let mut show_grid = false;
+    // Parallax stars behind the map
+    let mut show_starfield = false;
+    // Overview of the whole scene, rendered when first shown and again when the scene changes
+    let mut show_minimap = false;
+    let mut minimap: Option<Minimap> = None;
+    // Body picked by clicking it, whose mass the mouse wheel and [/] then change
+    let mut selected_body: Option<usize> = None;
+    // Basin boundaries traced from the current map, redone whenever another map is loaded
+    let mut show_boundaries = false;
+    let mut boundaries: Option<(MapParams, Vec<BasinBoundary>)> = None;
+    let mut jacobi_overlay: Option<(JacobiOverlayKey, Texture2D)> = None;
+    let mut vector_scale_index = 2; // Index into VECTOR_SCALES, starting at 1x
+
+    // Faint preview of the trajectory starting under the mouse, recomputed at most every PREVIEW_INTERVAL
+    let mut show_hover_preview = true;
+    let mut hover_preview: Vec<Vec2> = Vec::new();
+    let mut hover_preview_pixel: Option<(i32, i32)> = None;
+    let mut hover_preview_time = 0.0;
+    let mut show_inspector = false;
+    let mut hud = Hud::new();
+
+    // Right/middle mouse drag panning; the last mouse position while dragging and the glide velocity after release
+    let mut pan_drag: Option<Vec2> = None;
+    let mut pan_velocity = Vec2::default();
+    let mut panel = ControlPanel::new();
+
+    // Saved views, persisted across sessions; the index is the bookmark last jumped to
+    let mut bookmarks = load_bookmarks(BOOKMARKS_FILE).unwrap_or_else(|e| {
+        eprintln!("Error loading bookmarks: {}", e);
+        Vec::new()
+    });
+    let mut bookmark_index: Option<usize> = None;
+    let mut ensemble: Option<EnsembleSimulation> = None;
+    let mut gamepad = Gamepad::new();
+    let mut sound_effects = SoundEffects::load().await;
+    let mut glow = Glow::new();
+
+    // Long exposure: every trajectory drawn while active is accumulated into a density image
+    let mut exposure: Option<DensityBuffer> = None;
+    let mut exposure_texture: Option<Texture2D> = None;
+    let mut exposure_count = 0;
+    // Screen recording started with V (GIF) or Shift+V (frame sequence)
+    let mut recording: Option<Recording> = None;
+    let mut recording_count = 0;
+    let mut trajectory_count = 0;
+    // Energy error of the latest launch under every integrator, shown with F3, and the launch it
+    // was computed for
+    let mut show_energy_drift = false;
+    let mut energy_drift: Option<((Vec2, Vec2), Vec<EnergyDrift>)> = None;
+    let mut energy_drift_count = 0;
+    // The latest launch run forward and back again, shown with F12, and the launch it was run for
+    let mut show_reversal = false;
+    let mut reversal: Option<((Vec2, Vec2), Reversibility)> = None;
+    // Screenshot asked for with Shift+F12 (annotated) or Ctrl+F12, taken once the frame is drawn
+    let mut screenshot_request: Option<Screenshot> = None;
+    let mut screenshot_count = 0;
+    let mut svg_count = 0;
+    let mut boundaries_count = 0;
+    let mut grid_data_count = 0;
+    let mut selected_px: Option<u32> = None;
+    let mut selected_py: Option<u32> = None;
+
+    // Playback control for the live simulation
+    let mut simulation_paused = false;
+    let mut playback_speed: f32 = 1.0;
+
+    // Replay mode: timestep shown instead of the running particles
+    let mut replay_timestep: Option<usize> = None;
+    let mut dragging_timeline = false;
+
+    // Split-screen comparison: a pinned map is shown left of the divider, the current map right of it
+    let mut pinned_map: Option<LoadedMap> = None;
+    let mut divider_x = IMAGE_SIZE as f32 / 2.0;
+    let mut dragging_divider = false;
+
+    // Difference view between the pinned and current maps, rebuilt whenever either side changes
+    let mut show_difference = false;
+    let mut difference_view: Option<(Texture2D, DifferenceStats)> = None;
+
+    // Restore the previous session unless --fresh was given; it is written back when the window
+    // closes. Recorded sessions always start fresh, so they play back from the same start.
+    if !cli.fresh && cli.record_input.is_none() && cli.replay_input.is_none() {
+        match Session::load(SESSION_FILE) {
+            Ok(Some(session)) => {
+                camera = session.deep_camera.unwrap_or_else(|| DeepPoint::from_vec2(session.camera_offset));
+                camera_offset = camera.to_vec2();
+                zoom_factor = session.zoom_factor;
+                initial_velocity = session.initial_velocity;
+                use_runge_kutta = session.integration_method == IntegrationMethod::RungeKutta4;
+                sim_params = session.sim_params.unwrap_or(sim_params);
+                scene = session.scene;
+                selected_px = session.selected_pixel.map(|(px, _)| px);
+                selected_py = session.selected_pixel.map(|(_, py)| py);
+                for saved in &session.trajectories {
+                    let mut sim = LiveSimulationState::new(saved.start_pos, saved.initial_velocity, scene.clone(),
+                                                           session.integration_method, sim_params.resolved(&scene, zoom_factor));
+                    sim.burns = saved.burns.clone();
+                    sim.record_spacing = TRAIL_POINT_SPACING / zoom_factor;
+                    sim.point_limit = cli.trail_points;
+                    let mut events = TrailEvents::default();
+                    while sim.current_timestep < saved.timestep && !sim.is_finished() {
+                        sim.step_observed(&mut events);
+                    }
+                    // Catching up is silent
+                    events.sounds.clear();
+                    live_simulations.push(LiveTrail {
+                        sim,
+                        color: TRAIL_COLORS[live_simulations.len() % TRAIL_COLORS.len()],
+                        exposed_points: 0,
+                        events,
+                    });
+                }
+                println!("Restored session from {}", SESSION_FILE);
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Error restoring session: {}", e),
+        }
+    }
+
+    // A PNG given on the command line replaces the restored session with the view it was rendered from
+    if let Some(path) = &cli.from_png {
+        match load_png_metadata(path) {
+            Ok((params, loaded_scene)) => {
+                // The PNG may have been exported at a different resolution than the window
+                let initial = MapParams::initial_view(&loaded_scene, params.simulation, params.width, params.height);
+                let params = params.at_resolution(initial.width, initial.height);
+                camera = params.precise_camera();
+                camera_offset = params.camera_offset;
+                zoom_factor = params.zoom_factor;
+                initial_velocity = params.initial_velocity;
+                use_runge_kutta = params.integration_method == IntegrationMethod::RungeKutta4;
+                sim_params = params.simulation;
+                escape_coloring = params.escape_coloring;
+                scene = loaded_scene;
+                live_simulations.clear();
+                selected_px = None;
+                selected_py = None;
+                println!("Restored parameters from {}", path);
+            }
+            Err(e) => eprintln!("Error reading parameters from {}: {}", path, e),
+        }
+    }
+
+    // A scene given on the command line replaces the built-in (or restored) scene; scene files are watched for edits.
+    // Restored trajectories were run in the session's scene, so they go unless it is the same one.
+    if let Some(requested) = cli.requested_scene() {
+        if requested != scene && !live_simulations.is_empty() {
+            println!("Dropped {} restored trajectories run in the session's scene", live_simulations.len());
+            live_simulations.clear();
+        }
+        scene = requested;
+    }
+    let mut scene_watcher = cli.scene.as_deref().map(SceneWatcher::new);
+    let random_scene_bodies = cli.random_scene.unwrap_or(RANDOM_SCENE_BODIES);
+
+    // Random numbers come from a seed a recording keeps, so random scenes and ensembles replay the same
+    let mut seed = ::rand::random::<u64>();
+    if let Some(path) = &cli.replay_input {
+        match input::start_replay(path) {
+            Ok(header) => {
+                seed = header.seed;
+                initial_velocity = header.settings.initial_velocity;
+                camera = header.settings.camera;
+                camera_offset = camera.to_vec2();
+                zoom_factor = header.settings.zoom_factor;
+                use_runge_kutta = header.settings.use_runge_kutta;
+                sim_params = header.settings.sim_params;
+                scene = header.settings.scene;
+                request_new_screen_size(header.screen.0, header.screen.1);
+                println!("Replaying {}", path);
+            }
+            Err(e) => {
+                eprintln!("Error reading input recording {}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    } else if let Some(path) = &cli.record_input {
+        let settings = ViewSettings { initial_velocity, camera, zoom_factor, use_runge_kutta, sim_params, scene: scene.clone() };
+        if let Err(e) = input::start_recording(path, &RecordingHeader { seed, screen: (screen_width(), screen_height()), settings }) {
+            eprintln!("Error recording input to {}: {}", path, e);
+            std::process::exit(1);
+        }
+        println!("Recording input to {}", path);
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    // Edits of the settings, undone with Ctrl+Z back to the ones the viewer opened with
+    let mut history = History::new(ViewSettings { initial_velocity, camera, zoom_factor, use_runge_kutta, sim_params, scene: scene.clone() });
+    prevent_quit();
+
+    loop {
+        input::begin_frame();
+        if is_quit_requested() {
+            let session = Session {
+                camera_offset,
+                deep_camera: (zoom_factor >= DEEP_ZOOM_THRESHOLD).then_some(camera),
+                zoom_factor,
+                initial_velocity,
+                integration_method: if use_runge_kutta { IntegrationMethod::RungeKutta4 } else { IntegrationMethod::Euler },
+                sim_params: Some(sim_params),
+                scene: scene.clone(),
+                selected_pixel: selected_px.zip(selected_py),
+                trajectories: live_simulations.iter().map(|trail| {
+                    let start = trail.sim.trajectory_history[0];
+                    SavedTrajectory {
+                        start_pos: start.pos,
+                        initial_velocity: start.vel,
+                        timestep: trail.sim.current_timestep,
+                        burns: trail.sim.burns.clone(),
+                    }
+                }).collect(),
+            };
+            match session.save(SESSION_FILE) {
+                Ok(()) => println!("Session saved to {}", SESSION_FILE),
+                Err(e) => eprintln!("Error saving session: {}", e),
+            }
+            break;
+        }
+
+        clear_background(BLACK);
+
+        // The map fills the window, which can be resized at any time
+        let view_width = screen_width();
+        let view_height = screen_height();
+        let timeline_y = view_height - TIMELINE_BOTTOM_OFFSET;
+        if let Some(map) = &current_map {
+            if map.params.width != view_width as u32 || map.params.height != view_height as u32 {
+                needs_recalculation = true;
+            }
+        }
+        if let Some(buffer) = &mut exposure {
+            if buffer.width != view_width as u32 || buffer.height != view_height as u32 {
+                *buffer = DensityBuffer::new(view_width as u32, view_height as u32);
+                exposure_texture = None;
+            }
+        }
+
+        // Handle step size changes
+        if bindings.key_pressed(KeyCode::Equal) || bindings.key_pressed(KeyCode::KpAdd) {
+            step_size = (step_size * 1.2_f32).min(50.0); // Increase by 20%, max 50
+        }
+        if bindings.key_pressed(KeyCode::Minus) || bindings.key_pressed(KeyCode::KpSubtract) {
+            step_size = (step_size * 0.8_f32).max(0.1); // Decrease by 20%, min 0.1
+        }
+
+        // Handle camera movement with WASD. Pan and zoom keys are ignored while Ctrl is held, since
+        // they can be bound to the letter of a Ctrl shortcut, like Z for Ctrl+Z on AZERTY.
+        let shift_down = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+        let ctrl_down = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+        let held = |input: keybindings::Input| !ctrl_down && input.is_down();
+        let mut camera_changed = false;
+        if held(bindings.pan_up) {
+            camera = camera.offset(0.0, -step_size as f64);
+            camera_changed = true;
+        }
+        if held(bindings.pan_down) {
+            camera = camera.offset(0.0, step_size as f64);
+            camera_changed = true;
+        }
+        if held(bindings.pan_left) {
+            camera = camera.offset(-step_size as f64, 0.0);
+            camera_changed = true;
+        }
+        if held(bindings.pan_right) {
+            camera = camera.offset(step_size as f64, 0.0);
+            camera_changed = true;
+        }
+
+        // Handle zoom with Q and E
+        if held(bindings.zoom_out) {
+            zoom_factor = (zoom_factor * 0.99).max(0.1); // Zoom out, min 0.1x
+            camera_changed = true;
+        }
+        if held(bindings.zoom_in) {
+            zoom_factor = (zoom_factor * 1.01).min(MAX_ZOOM); // Zoom in
+            camera_changed = true;
+        }
+
+        // Handle the gamepad: the left stick pans at up to the WASD speed and the triggers zoom
+        let pad = input::gamepad(gamepad.poll());
+        if pad.pan != Vec2::default() {
+            camera = camera.offset((pad.pan.x * step_size) as f64, (pad.pan.y * step_size) as f64);
+            camera_changed = true;
+        }
+        if pad.zoom != 0.0 {
+            zoom_factor = (zoom_factor * (1.0 + GAMEPAD_ZOOM_RATE * pad.zoom)).clamp(0.1, MAX_ZOOM);
+            camera_changed = true;
+        }
+
+        // Handle key presses for changing initial velocity
+        let mut velocity_changed = false;
+        for (input, direction) in [
+            (bindings.velocity_up, Vec2::new(0.0, -1.0)),
+            (bindings.velocity_down, Vec2::new(0.0, 1.0)),
+            (bindings.velocity_left, Vec2::new(-1.0, 0.0)),
+            (bindings.velocity_right, Vec2::new(1.0, 0.0)),
+        ] {
+            if input.is_pressed() {
+                initial_velocity += direction * step_size;
+                velocity_changed = true;
+            }
+        }
+        // The right stick sets the velocity outright, its deflection scaled to GAMEPAD_MAX_SPEED,
+        // and leaves it there when released
+        if let Some(aim) = pad.aim {
+            let velocity = aim * GAMEPAD_MAX_SPEED;
+            if velocity != initial_velocity {
+                initial_velocity = velocity;
+                velocity_changed = true;
+            }
+        }
+
+        // Handle Home/End for firing the engine of the live particles prograde/retrograde while
+        // held, and PageUp/PageDown for its thrust
+        if bindings.key_pressed(KeyCode::PageUp) {
+            thrust_magnitude *= THRUST_STEP;
+        }
+        if bindings.key_pressed(KeyCode::PageDown) {
+            thrust_magnitude /= THRUST_STEP;
+        }
+        let manual_thrust = if bindings.key_down(KeyCode::Home) {
+            Some(Thrust::new(thrust_magnitude, ThrustDirection::Prograde))
+        } else if bindings.key_down(KeyCode::End) {
+            Some(Thrust::new(thrust_magnitude, ThrustDirection::Retrograde))
+        } else {
+            None
+        };
+
+        // Handle key presses for switching integration method
+        if bindings.toggle_integrator.is_pressed() {
+            use_runge_kutta = !use_runge_kutta;
+            velocity_changed = true; // This will also trigger recalculation
+        }
+
+        // Handle T/U/K (with Shift to decrease) for simulation length, substeps and collision threshold
+        let mut simulation_changed = false;
+        if bindings.key_pressed(KeyCode::T) {
+            sim_params.timesteps = if shift_down {
+                (sim_params.timesteps * 2 / 3).max(100)
+            } else {
+                (sim_params.timesteps * 3 / 2).min(100_000)
+            };
+            simulation_changed = true;
+        }
+        if bindings.key_pressed(KeyCode::U) {
+            sim_params.substeps = if shift_down {
+                (sim_params.substeps - 1).max(1)
+            } else {
+                (sim_params.substeps + 1).min(200)
+            };
+            simulation_changed = true;
+        }
+        if bindings.key_pressed(KeyCode::K) {
+            sim_params.collision_threshold = if shift_down {
+                (sim_params.collision_threshold - 1.0).max(1.0)
+            } else {
+                (sim_params.collision_threshold + 1.0).min(100.0)
+            };
+            simulation_changed = true;
+        }
+
+        // Handle P (pause), N (single step while paused) and ,/. (slower/faster playback)
+        if bindings.key_pressed(KeyCode::P) {
+            simulation_paused = !simulation_paused;
+        }
+        if bindings.key_pressed(KeyCode::Comma) {
+            playback_speed = (playback_speed / 2.0).max(1.0 / 16.0);
+        }
+        if bindings.key_pressed(KeyCode::Period) {
+            playback_speed = (playback_speed * 2.0).min(16.0);
+        }
+        let single_step = simulation_paused && bindings.key_pressed(KeyCode::N);
+
+        // Handle L for toggling long exposure mode, Shift+L for saving the exposure as a PNG
+        if bindings.key_pressed(KeyCode::L) {
+            if shift_down {
+                if let Some(buffer) = &exposure {
+                    let filename = format!("long_exposure_{}.png", exposure_count);
+                    match buffer.save_png(&filename) {
+                        Ok(()) => exposure_count += 1,
+                        Err(e) => eprintln!("Error saving long exposure: {}", e),
+                    }
+                }
+            } else if exposure.is_some() {
+                exposure = None;
+                exposure_texture = None;
+            } else {
+                exposure = Some(DensityBuffer::new(view_width as u32, view_height as u32));
+                // Only trajectory segments from now on are accumulated
+                for trail in &mut live_simulations {
+                    trail.exposed_points = trail.sim.recorded_points;
+                }
+            }
+        }
+
+        // Handle V for starting/stopping a GIF recording of the window, Shift+V for a PNG frame sequence
+        if bindings.key_pressed(KeyCode::V) {
+            if let Some(finished) = recording.take() {
+                save_recording(finished, &mut recording_count);
+            } else {
+                let format = if shift_down { RecordingFormat::Frames } else { RecordingFormat::Gif };
+                recording = Some(Recording::new(format));
+            }
+        }
+
+        // Handle F for toggling the velocity/acceleration overlay, Shift+F for cycling its scale
+        if bindings.key_pressed(KeyCode::F) {
+            if shift_down {
+                vector_scale_index = (vector_scale_index + 1) % VECTOR_SCALES.len();
+            } else {
+                show_vectors = !show_vectors;
+            }
+        }
+
+        // Handle F4 for the osculating Kepler orbit of the latest trajectory
+        if bindings.key_pressed(KeyCode::F4) {
+            show_kepler = !show_kepler;
+        }
+
+        // Handle F7 for cycling how pixels that never collide are colored
+        if bindings.key_pressed(KeyCode::F7) {
+            escape_coloring = escape_coloring.next();
+            needs_recalculation = true;
+        }
+
+        // Handle F6 for cycling the phase-space panel
+        if bindings.key_pressed(KeyCode::F6) {
+            phase_view = phase_view.next();
+        }
+
+        // Handle F8 for the world coordinate grid and scale bar, Shift+F8 for the minimap
+        if bindings.key_pressed(KeyCode::F8) {
+            if shift_down {
+                show_minimap = !show_minimap;
+            } else {
+                show_grid = !show_grid;
+            }
+        }
+
+        // Handle F11 for saving the raw collision times and bodies of the current map as OpenEXR,
+        // Shift+F11 for the collision timesteps as a 16-bit PNG
+        if bindings.key_pressed(KeyCode::F11) {
+            if let Some(map) = &current_map {
+                let filename = format!("grid_data_{}.{}", grid_data_count, if shift_down { "png" } else { "exr" });
+                match save_grid_data(&filename, &map.grid, &map.params) {
+                    Ok(()) => grid_data_count += 1,
+                    Err(e) => eprintln!("Error saving grid data: {}", e),
+                }
+            }
+        }
+
+        // Handle F10 for the basin boundary overlay, Shift+F10 for saving the boundaries as GeoJSON
+        let f10_pressed = bindings.key_pressed(KeyCode::F10);
+        if f10_pressed && !shift_down {
+            show_boundaries = !show_boundaries;
+        }
+        if let Some(map) = &current_map {
+            if (show_boundaries || f10_pressed) && boundaries.as_ref().is_none_or(|(params, _)| *params != map.params) {
+                boundaries = Some((map.params, extract_boundaries(&map.grid)));
+            }
+            if let (true, true, Some((_, traced))) = (f10_pressed, shift_down, &boundaries) {
+                let filename = format!("boundaries_{}.geojson", boundaries_count);
+                match save_geojson(&filename, traced, &map.grid, &map.params) {
+                    Ok(()) => boundaries_count += 1,
+                    Err(e) => eprintln!("Error saving boundaries: {}", e),
+                }
+            }
+        }
+
+        // Handle F5 for the zero-velocity curve and potential contours
+        if bindings.key_pressed(KeyCode::F5) {
+            show_jacobi = !show_jacobi;
+        }
+
+        // Handle H/F1 for the HUD layout and help panel
+        hud.handle_input(&bindings, shift_down);
+
+        // Handle Tab for the control panel; its sliders feed into the same change flags as the keys
+        panel.handle_input(&bindings);
+        let (mx, my) = mouse_position();
+        let mouse = Vec2::new(mx, my);
+        // The minimap takes clicks and scrolls over it like the panel does; a click centers the view
+        // on the point clicked
+        let minimap_bottom = timeline_y - 8.0;
+        if show_minimap {
+            match &mut minimap {
+                Some(minimap) => minimap.update(&scene),
+                None => minimap = Some(Minimap::new(&scene)),
+            }
+        }
+        let minimap_under_mouse = minimap.as_ref().filter(|minimap| show_minimap && minimap.contains(mouse, minimap_bottom));
+        if let Some(minimap) = minimap_under_mouse.filter(|_| is_mouse_button_pressed(MouseButton::Left)) {
+            let target = minimap.world_at(mouse, minimap_bottom);
+            camera = DeepPoint::from_vec2(Vec2::new(view_width, view_height) / (2.0 * zoom_factor) - target);
+            camera_changed = true;
+        }
+        let mouse_over_panel = panel.wants_mouse() || minimap_under_mouse.is_some();
+        let panel_changes = if input::is_replaying() {
+            // The panel still takes the clicks over it, but its edits come from the recording
+            let (mut velocity, mut zoom, mut runge_kutta, mut params, mut edited) = (initial_velocity, zoom_factor, use_runge_kutta, sim_params, scene.clone());
+            panel.draw(&mut velocity, &mut zoom, &mut runge_kutta, &mut params, &mut edited);
+            match input::replayed_settings() {
+                Some(settings) => {
+                    let before = ViewSettings { initial_velocity, camera, zoom_factor, use_runge_kutta, sim_params, scene: scene.clone() };
+                    let changes = PanelChanges::between(&before, &settings);
+                    initial_velocity = settings.initial_velocity;
+                    zoom_factor = settings.zoom_factor;
+                    use_runge_kutta = settings.use_runge_kutta;
+                    sim_params = settings.sim_params;
+                    scene = settings.scene;
+                    changes
+                }
+                None => PanelChanges::default(),
+            }
+        } else {
+            let changes = panel.draw(&mut initial_velocity, &mut zoom_factor, &mut use_runge_kutta, &mut sim_params, &mut scene);
+            if changes.any() {
+                input::record_settings(ViewSettings { initial_velocity, camera, zoom_factor, use_runge_kutta, sim_params, scene: scene.clone() });
+            }
+            changes
+        };
+        velocity_changed |= panel_changes.velocity;
+        camera_changed |= panel_changes.camera;
+        simulation_changed |= panel_changes.simulation || panel_changes.scene;
+
+        // Handle clicks on a body for selecting it, or deselecting it when it already is. The
+        // click doesn't launch a particle.
+        let body_clicked = is_mouse_button_pressed(MouseButton::Left) && !mouse_over_panel && {
+            let view = Camera::new(camera_offset, zoom_factor, Vec2::new(view_width, view_height));
+            let clicked = scene.bodies.iter().position(|body| {
+                let on_screen = view.world_to_screen(body.pos);
+                mouse.distance(&on_screen) <= (body.radius * zoom_factor).max(BODY_PICK_RADIUS)
+            });
+            if let Some(index) = clicked {
+                selected_body = if selected_body == Some(index) { None } else { Some(index) };
+            }
+            clicked.is_some()
+        };
 
-    // Helper function to get current image filename
-    let get_image_filename = |use_rk4: bool, vel: Vec2, cam: Vec2, zoom: f32| -> String {
-        format!("gravity_wells_{}_{:.1}_{:.1}_{:.1}_{:.1}_{:.2}.png", 
-                if use_rk4 { "rk4" } else { "euler" },
-                vel.x, vel.y, cam.x, cam.y, zoom)
-    };
+        // Handle the mouse wheel for changing the selected body's mass, and [/] when not replaying;
+        // its radius follows the mass as with the panel's slider
+        let (_, wheel) = mouse_wheel();
+        let mut mass_steps = 0;
+        if !mouse_over_panel {
+            mass_steps += wheel.signum() as i32;
+        }
+        if replay_timestep.is_none() {
+            mass_steps += bindings.key_pressed(KeyCode::RightBracket) as i32 - bindings.key_pressed(KeyCode::LeftBracket) as i32;
+        }
+        if let (Some(body), true) = (selected_body.and_then(|index| scene.bodies.get_mut(index)), mass_steps != 0) {
+            body.mass *= BODY_MASS_STEP.powi(mass_steps);
+            body.radius = body_radius(body.mass);
+            simulation_changed = true;
+        }
 
-    let mut current_image_filename = get_image_filename(use_runge_kutta, initial_velocity, camera_offset, zoom_factor);
-    let mut texture_option: Option<Texture2D> = None;
-    let mut live_simulation: Option<LiveSimulationState> = None;
-    let mut selected_px: Option<u32> = None;
-    let mut selected_py: Option<u32> = None;
+        // Handle the mouse wheel for zooming around the cursor: the world point under the mouse stays put
+        if wheel != 0.0 && !mouse_over_panel && selected_body.is_none() {
+            let (mx, my) = mouse_position();
+            let factor = if wheel > 0.0 { 1.1 } else { 1.0 / 1.1 };
+            let old_zoom = zoom_factor as f64;
+            zoom_factor = (zoom_factor * factor).clamp(0.1, MAX_ZOOM);
+            let new_zoom = zoom_factor as f64;
+            camera = camera.offset(mx as f64 / new_zoom - mx as f64 / old_zoom, my as f64 / new_zoom - my as f64 / old_zoom);
+            camera_changed = true;
+        }
 
-    loop {
-        clear_background(BLACK);
+        // Handle dragging with the right or middle mouse button to pan, with some inertia after release
+        let pan_button_down = is_mouse_button_down(MouseButton::Right) || is_mouse_button_down(MouseButton::Middle);
+        match pan_drag {
+            Some(last) if pan_button_down => {
+                let delta = mouse - last;
+                if delta.length() > 0.0 {
+                    camera = camera.offset(delta.x as f64 / zoom_factor as f64, delta.y as f64 / zoom_factor as f64);
+                    camera_changed = true;
+                }
+                pan_velocity = delta / get_frame_time().max(1e-3);
+                pan_drag = Some(mouse);
+            }
+            Some(_) => pan_drag = None,
+            None if pan_button_down && !mouse_over_panel => {
+                pan_drag = Some(mouse);
+                pan_velocity = Vec2::default();
+            }
+            None => {
+                if pan_velocity.length() > PAN_MIN_SPEED {
+                    let pan = pan_velocity * get_frame_time();
+                    camera = camera.offset(pan.x as f64 / zoom_factor as f64, pan.y as f64 / zoom_factor as f64);
+                    pan_velocity = pan_velocity * (1.0 - PAN_FRICTION * get_frame_time()).max(0.0);
+                    camera_changed = true;
+                } else {
+                    pan_velocity = Vec2::default();
+                }
+            }
+        }
+        // Everything else reads the camera at f32 precision
+        camera_offset = camera.to_vec2();
 
-        // Handle step size changes
-        if is_key_pressed(KeyCode::Equal) || is_key_pressed(KeyCode::KpAdd) {
-            step_size = (step_size * 1.2_f32).min(50.0); // Increase by 20%, max 50
+
+        // Reload the scene file when it changes on disk
+        if let Some(watcher) = &mut scene_watcher {
+            match watcher.poll() {
+                Some(Ok(reloaded)) => {
+                    println!("Reloaded scene from {}", watcher.path);
+                    scene = reloaded;
+                    simulation_changed = true;
+                }
+                Some(Err(e)) => eprintln!("Error reloading scene {}: {}", watcher.path, e),
+                None => {}
+            }
         }
-        if is_key_pressed(KeyCode::Minus) || is_key_pressed(KeyCode::KpSubtract) {
-            step_size = (step_size * 0.8_f32).max(0.1); // Decrease by 20%, min 0.1
+
+        // Handle number keys for switching to a preset scene
+        const PRESET_KEYS: [KeyCode; 5] = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5];
+        for (key, preset) in PRESET_KEYS.iter().zip(ScenePreset::ALL) {
+            if bindings.key_pressed(*key) {
+                scene = preset.scene();
+                println!("Switched to preset scene: {}", preset.name());
+                simulation_changed = true;
+            }
         }
 
-        // Handle camera movement with WASD
-        let mut camera_changed = false;
-        if is_key_down(KeyCode::W) {
-            camera_offset.y -= step_size;
-            camera_changed = true;
+        // Handle Z for generating a new random scene
+        if bindings.key_pressed(KeyCode::Z) && !ctrl_down {
+            let seed = rng.random::<u64>();
+            scene = Scene::random(seed, random_scene_bodies);
+            println!("Generated random scene with {} bodies (seed {})", random_scene_bodies, seed);
+            simulation_changed = true;
         }
-        if is_key_down(KeyCode::S) {
-            camera_offset.y += step_size;
-            camera_changed = true;
+
+        // Handle Ctrl+B for the starfield background
+        if ctrl_down && is_key_pressed(KeyCode::B) {
+            show_starfield = !show_starfield;
         }
-        if is_key_down(KeyCode::A) {
-            camera_offset.x -= step_size;
-            camera_changed = true;
+
+        // Handle B for bookmarking the current view and J for jumping to the next bookmark
+        if bindings.key_pressed(KeyCode::B) && !ctrl_down {
+            bookmarks.push(Bookmark {
+                name: format!("Bookmark {}", bookmarks.len() + 1),
+                camera_offset,
+                deep_camera: (zoom_factor >= DEEP_ZOOM_THRESHOLD).then_some(camera),
+                zoom_factor,
+                initial_velocity,
+                integration_method: if use_runge_kutta { IntegrationMethod::RungeKutta4 } else { IntegrationMethod::Euler },
+                scene: scene.clone(),
+            });
+            match save_bookmarks(BOOKMARKS_FILE, &bookmarks) {
+                Ok(()) => println!("Saved bookmark {} to {}", bookmarks.len(), BOOKMARKS_FILE),
+                Err(e) => eprintln!("Error saving bookmarks: {}", e),
+            }
+            bookmark_index = Some(bookmarks.len() - 1);
         }
-        if is_key_down(KeyCode::D) {
-            camera_offset.x += step_size;
+        if bindings.key_pressed(KeyCode::J) && !bookmarks.is_empty() {
+            let index = bookmark_index.map_or(0, |i| (i + 1) % bookmarks.len());
+            let bookmark = &bookmarks[index];
+            camera = bookmark.deep_camera.unwrap_or_else(|| DeepPoint::from_vec2(bookmark.camera_offset));
+            camera_offset = camera.to_vec2();
+            zoom_factor = bookmark.zoom_factor;
+            initial_velocity = bookmark.initial_velocity;
+            use_runge_kutta = bookmark.integration_method == IntegrationMethod::RungeKutta4;
+            scene = bookmark.scene.clone();
+            bookmark_index = Some(index);
             camera_changed = true;
+            simulation_changed = true;
         }
 
-        // Handle zoom with Q and E
-        if is_key_down(KeyCode::Q) {
-            zoom_factor = (zoom_factor * 0.99).max(0.1); // Zoom out, min 0.1x
-            camera_changed = true;
+        // Handle Ctrl+Z for undoing the latest edit of the settings and Ctrl+R for resetting the
+        // velocity, camera, zoom and integrator to the ones the viewer opened with (itself undoable),
+        // keeping any edits to the scene and simulation settings
+        if ctrl_down && is_key_pressed(KeyCode::Z) {
+            match history.undo() {
+                Some(settings) => {
+                    initial_velocity = settings.initial_velocity;
+                    camera = settings.camera;
+                    camera_offset = camera.to_vec2();
+                    zoom_factor = settings.zoom_factor;
+                    use_runge_kutta = settings.use_runge_kutta;
+                    sim_params = settings.sim_params;
+                    scene = settings.scene;
+                    println!("Undid the latest edit ({} more to undo)", history.len());
+                    camera_changed = true;
+                    simulation_changed = true;
+                }
+                None => println!("Nothing to undo"),
+            }
         }
-        if is_key_down(KeyCode::E) {
-            zoom_factor = (zoom_factor * 1.01).min(10.0); // Zoom in, max 10x
+        if ctrl_down && is_key_pressed(KeyCode::R) {
+            let settings = history.initial();
+            initial_velocity = settings.initial_velocity;
+            camera = settings.camera;
+            camera_offset = camera.to_vec2();
+            zoom_factor = settings.zoom_factor;
+            use_runge_kutta = settings.use_runge_kutta;
             camera_changed = true;
+            velocity_changed = true;
         }
 
-        // Handle key presses for changing initial velocity
-        let mut velocity_changed = false;
-        if is_key_pressed(KeyCode::Up) {
-            initial_velocity.y -= step_size;
-            velocity_changed = true;
+        // Handle I for toggling the pixel inspector
+        if bindings.key_pressed(KeyCode::I) {
+            show_inspector = !show_inspector;
         }
-        if is_key_pressed(KeyCode::Down) {
-            initial_velocity.y += step_size;
-            velocity_changed = true;
+
+        // Handle O for toggling the hover preview
+        if bindings.key_pressed(KeyCode::O) {
+            show_hover_preview = !show_hover_preview;
         }
-        if is_key_pressed(KeyCode::Left) {
-            initial_velocity.x -= step_size;
-            velocity_changed = true;
+
+        // Handle G for cycling the trajectory coloring, Shift+G for the glow
+        if bindings.key_pressed(KeyCode::G) {
+            if !shift_down {
+                trail_coloring = trail_coloring.next();
+            } else if glow.available() {
+                glow.enabled = !glow.enabled;
+            } else {
+                println!("Glow is unavailable, its shaders didn't compile");
+            }
         }
-        if is_key_pressed(KeyCode::Right) {
-            initial_velocity.x += step_size;
-            velocity_changed = true;
+
+        // Handle M for cycling what a click launches, Ctrl+M for turning the sound effects on and off
+        if bindings.key_pressed(KeyCode::M) && !ctrl_down {
+            launch_mode = launch_mode.next();
+        }
+        if ctrl_down && is_key_pressed(KeyCode::M) {
+            if sound_effects.available() {
+                sound_effects.enabled = !sound_effects.enabled;
+            } else {
+                println!("Sound effects need the viewer built with the `audio` feature");
+            }
         }
 
-        // Handle key presses for switching integration method
-        if is_key_pressed(KeyCode::Space) {
-            use_runge_kutta = !use_runge_kutta;
-            velocity_changed = true; // This will also trigger recalculation
+        // Handle Y for exporting the latest trajectory at full resolution as CSV, Shift+Y as JSON
+        if bindings.key_pressed(KeyCode::Y) {
+            if let Some(trail) = live_simulations.last() {
+                let start = trail.sim.trajectory_history[0];
+                let samples = record_trajectory(start.pos, start.vel, &trail.sim.scene, trail.sim.integration_method, &trail.sim.params,
+                                                &trail.sim.burns);
+                let filename = format!("trajectory_{}.{}", trajectory_count, if shift_down { "json" } else { "csv" });
+                match save_trajectory(&filename, &samples) {
+                    Ok(()) => trajectory_count += 1,
+                    Err(e) => eprintln!("Error saving trajectory: {}", e),
+                }
+            }
+        }
+
+        // Handle F9 for saving the trajectories, bodies and basin boundaries of the view as an SVG.
+        // With a map loaded its view is used, so the boundaries line up with the trajectories.
+        if bindings.key_pressed(KeyCode::F9) {
+            let view = current_map.as_ref().map_or_else(
+                || get_map_params((view_width as u32, view_height as u32), use_runge_kutta, initial_velocity, camera, zoom_factor, sim_params, &scene, escape_coloring),
+                |map| map.params);
+            let trajectories: Vec<SvgTrajectory> = live_simulations.iter()
+                .map(|trail| SvgTrajectory {
+                    points: trail.sim.trajectory_history.iter().map(|point| point.pos).collect(),
+                    color: [trail.color.r, trail.color.g, trail.color.b].map(|c| (c * 255.0) as u8),
+                })
+                .collect();
+            let filename = format!("figure_{}.svg", svg_count);
+            match save_svg(&filename, &scene, &view, &trajectories, current_map.as_ref().map(|map| &map.grid)) {
+                Ok(()) => svg_count += 1,
+                Err(e) => eprintln!("Error saving SVG: {}", e),
+            }
+        }
+
+        // Handle F3 for the energy drift panel of the latest trajectory, Shift+F3 for saving it as CSV.
+        // The comparison is rerun only while it's needed and the latest launch has changed.
+        let f3_pressed = bindings.key_pressed(KeyCode::F3);
+        if f3_pressed && !shift_down {
+            show_energy_drift = !show_energy_drift;
+        }
+        let latest_launch = live_simulations.last().map(|trail| (trail.sim.trajectory_history[0].pos, trail.sim.trajectory_history[0].vel));
+        if (show_energy_drift || f3_pressed) && latest_launch != energy_drift.as_ref().map(|(launch, _)| *launch) {
+            energy_drift = live_simulations.last().map(|trail| {
+                let start = trail.sim.trajectory_history[0];
+                ((start.pos, start.vel), compare_integrators(start.pos, start.vel, &trail.sim.scene, &trail.sim.params))
+            });
+        }
+        if f3_pressed && shift_down {
+            if let Some((_, drifts)) = &energy_drift {
+                let filename = format!("energy_drift_{}.csv", energy_drift_count);
+                match save_energy_drift(&filename, drifts) {
+                    Ok(()) => energy_drift_count += 1,
+                    Err(e) => eprintln!("Error saving energy drift: {}", e),
+                }
+            }
+        }
+
+        // Handle F12 for the time-reversal check of the latest trajectory, rerun like the energy
+        // drift only while shown and when the latest launch has changed. Shift+F12 saves a
+        // screenshot with the parameters and time burned in, Ctrl+F12 a plain one.
+        if bindings.key_pressed(KeyCode::F12) {
+            if shift_down {
+                screenshot_request = Some(Screenshot::Annotated);
+            } else if ctrl_down {
+                screenshot_request = Some(Screenshot::Plain);
+            } else {
+                show_reversal = !show_reversal;
+            }
+        }
+        if show_reversal && latest_launch != reversal.as_ref().map(|(launch, _)| *launch) {
+            reversal = live_simulations.last().map(|trail| {
+                let start = trail.sim.trajectory_history[0];
+                ((start.pos, start.vel), time_reversal(start.pos, start.vel, &trail.sim.scene, trail.sim.integration_method, &trail.sim.params))
+            });
+        }
+
+        // Handle Backspace for clearing all live simulations
+        if bindings.key_pressed(KeyCode::Backspace) {
+            live_simulations.clear();
+            ensemble = None;
+            replay_timestep = None;
+            selected_px = None;
+            selected_py = None;
         }
 
-        // Mark for recalculation if any parameters changed
-        if velocity_changed || camera_changed {
+        // Handle R to enter/leave replay mode and [/] (Shift for faster) to scrub through the trajectories
+        let last_timestep = live_simulations.iter().map(|trail| trail.sim.current_timestep).max();
+        if bindings.key_pressed(KeyCode::R) && !ctrl_down {
+            replay_timestep = match (replay_timestep, last_timestep) {
+                (None, Some(last)) => Some(last),
+                _ => None,
+            };
+        }
+        if let (Some(timestep), Some(last)) = (replay_timestep, last_timestep) {
+            let scrub_step = if shift_down { 50 } else { 5 };
+            if bindings.key_down(KeyCode::LeftBracket) {
+                replay_timestep = Some(timestep.saturating_sub(scrub_step));
+            }
+            if bindings.key_down(KeyCode::RightBracket) {
+                replay_timestep = Some((timestep + scrub_step).min(last));
+            }
+
+            // Clicking or dragging on the timeline bar jumps to that point
+            let (mx, my) = mouse_position();
+            let timeline_width = view_width - 2.0 * TIMELINE_MARGIN;
+            if is_mouse_button_pressed(MouseButton::Left) && !mouse_over_panel && (my - timeline_y - TIMELINE_HEIGHT / 2.0).abs() <= TIMELINE_HEIGHT {
+                dragging_timeline = true;
+            }
+            if dragging_timeline {
+                let fraction = ((mx - TIMELINE_MARGIN) / timeline_width).clamp(0.0, 1.0);
+                replay_timestep = Some((fraction * last as f32).round() as usize);
+            }
+        }
+        if !is_mouse_button_down(MouseButton::Left) {
+            dragging_timeline = false;
+        }
+
+        // The exposure is in screen space, so it no longer lines up once the camera moves
+        if camera_changed {
+            if let Some(buffer) = &mut exposure {
+                buffer.clear();
+                exposure_texture = None;
+            }
+        }
+
+        // Mark for recalculation if any parameters changed; a map still generating for the old
+        // parameters is no longer wanted
+        if velocity_changed || camera_changed || simulation_changed {
+            history.record(ViewSettings { initial_velocity, camera, zoom_factor, use_runge_kutta, sim_params, scene: scene.clone() });
             needs_recalculation = true;
+            render_queue.cancel_all();
             // Clear current simulation when parameters change
-            live_simulation = None;
+            live_simulations.clear();
+            hover_preview_pixel = None;
+            ensemble = None;
+            replay_timestep = None;
             selected_px = None;
             selected_py = None;
+            // A new scene may not have the selected body
+            selected_body = selected_body.filter(|&index| index < scene.bodies.len());
+        } else {
+            history.settle();
         }
 
-        // Handle Enter key for manual recalculation
-        if is_key_pressed(KeyCode::Enter) && needs_recalculation {
-            let new_image_filename = get_image_filename(use_runge_kutta, initial_velocity, camera_offset, zoom_factor);
-            
-            // Only regenerate if this specific configuration doesn't exist
-            if !std::path::Path::new(&new_image_filename).exists() {
-                let integration_method = if use_runge_kutta {
-                    IntegrationMethod::RungeKutta4
-                } else {
-                    IntegrationMethod::Euler
-                };
-                
-                println!("Generating new image with velocity ({:.1}, {:.1}), camera ({:.1}, {:.1}), zoom {:.2}...", 
-                        initial_velocity.x, initial_velocity.y, camera_offset.x, camera_offset.y, zoom_factor);
-                if let Err(e) = generate_gravity_wells_image(&stationary_bodies, initial_velocity, camera_offset, zoom_factor, &new_image_filename, integration_method) {
+        // Handle C key for pinning the current map for split-screen comparison
+        if bindings.key_pressed(KeyCode::C) {
+            if pinned_map.is_some() {
+                pinned_map = None;
+            } else if let Some(map) = current_map.take() {
+                // The pinned map keeps its texture; the right half waits for the next recalculation
+                pinned_map = Some(map);
+                needs_recalculation = true;
+            }
+            difference_view = None;
+        }
+
+        // Handle X key for toggling the difference view
+        if bindings.key_pressed(KeyCode::X) {
+            show_difference = !show_difference;
+        }
+
+        // Handle F2 for exporting the current view at 4K (Shift: 8K) with supersampling, bodies and legend,
+        // plus the grid when it is shown
+        if bindings.key_pressed(KeyCode::F2) {
+            let view = get_map_params((view_width as u32, view_height as u32), use_runge_kutta, initial_velocity, camera, zoom_factor, sim_params, &scene, escape_coloring);
+            let width = if shift_down { EXPORT_WIDTH_8K } else { EXPORT_WIDTH_4K };
+            let height = (view_height * width as f32 / view_width).round() as u32;
+            let overlays = ExportOverlays { bodies: true, legend: true, grid: show_grid, encoding: cli.color_encoding, palette: cli.palette, patterns: cli.export_patterns, output: cli.image_output() };
+            if let Err(e) = export_image(&scene, &view, width, height, cli.supersample, overlays, &terminal_progress()) {
+                eprintln!("Error exporting image: {}", e);
+            }
+        }
+
+        // Handle Escape for aborting the map being generated along with anything queued
+        if bindings.key_pressed(KeyCode::Escape) {
+            render_queue.cancel_all();
+        }
+
+        // Handle Enter key for manual recalculation; the map is generated (or found in the cache) on a
+        // background thread so the window keeps running meanwhile. Pressing it again while a map
+        // generates replaces that job with the latest settings.
+        if bindings.recalculate.is_pressed() && needs_recalculation {
+            let params = get_map_params((view_width as u32, view_height as u32), use_runge_kutta, initial_velocity, camera, zoom_factor, sim_params, &scene, escape_coloring);
+            render_queue.request(&scene, params);
+        }
+
+        // Pick up the map once its generation has finished
+        if let Some(finished) = render_queue.poll() {
+            let loaded = match finished {
+                FinishedJob::Cancelled => {
+                    println!("Map generation cancelled");
+                    None
+                }
+                FinishedJob::Generated { params, filename } => match OutcomeGrid::load(&params.grid_filename()) {
+                    Ok(grid) => match load_texture(&filename).await {
+                        Ok(texture) => Some(LoadedMap { params, texture, grid }),
+                        Err(e) => {
+                            eprintln!("Error loading map texture: {}", e);
+                            None
+                        }
+                    },
+                    Err(e) => {
+                        eprintln!("Error loading outcome grid: {}", e);
+                        None
+                    }
+                },
+                FinishedJob::Failed(e) => {
                     eprintln!("Error generating image: {}", e);
-                    next_frame().await;
-                    continue;
+                    None
                 }
-            } else {
-                println!("Found existing image: {}", new_image_filename);
+            };
+            if let Some(map) = loaded {
+                // Settings may have changed while the map generated; it is shown anyway, but only
+                // counts as up to date if it still matches them
+                let current = get_map_params((view_width as u32, view_height as u32), use_runge_kutta, initial_velocity, camera, zoom_factor, sim_params, &scene, escape_coloring);
+                needs_recalculation = map.params != current;
+                current_map = Some(map);
+                difference_view = None;
             }
-            
-            // Load the new texture
-            current_image_filename = new_image_filename;
-            texture_option = Some(load_texture(&current_image_filename).await.unwrap());
-            needs_recalculation = false;
         }
 
-        // Load initial texture if none exists
-        if texture_option.is_none() && !needs_recalculation {
-            if std::path::Path::new(&current_image_filename).exists() {
-                texture_option = Some(load_texture(&current_image_filename).await.unwrap());
-            } else {
-                needs_recalculation = true;
+        // Build the difference texture lazily once both maps are available
+        if show_difference && difference_view.is_none() {
+            if let (Some(pinned), Some(map)) = (&pinned_map, &current_map) {
+                let (img, stats) = render_difference_image(&pinned.grid, &map.grid);
+                difference_view = Some((texture_from_image(&img), stats));
+            }
+        }
+        let difference_active = show_difference && difference_view.is_some();
+
+        // Osculating orbit of the latest particle at the shown (or replayed) timestep
+        let kepler_orbit = live_simulations.last().filter(|_| show_kepler).and_then(|trail| {
+            let (pos, vel) = match replay_timestep {
+                Some(timestep) => {
+                    let point = trail.sim.point_at(timestep);
+                    (point.pos, point.vel)
+                }
+                None => (trail.sim.particle.pos, trail.sim.particle.vel),
+            };
+            let particle = TestParticle::new(pos, vel, TEST_PARTICLE_MASS, TEST_PARTICLE_RADIUS);
+            OrbitalElements::fit(&particle, &trail.sim.scene)
+        });
+
+        // Handle dragging the comparison divider
+        if pinned_map.is_some() && !difference_active {
+            let (mx, _) = mouse_position();
+            if is_mouse_button_pressed(MouseButton::Left) && !mouse_over_panel && (mx - divider_x).abs() <= DIVIDER_GRAB_DISTANCE {
+                dragging_divider = true;
+            }
+            if dragging_divider {
+                divider_x = mx.clamp(0.0, view_width);
             }
         }
+        if !is_mouse_button_down(MouseButton::Left) {
+            dragging_divider = false;
+        }
+
+        if show_starfield {
+            draw_starfield(&Camera::new(camera_offset, zoom_factor, Vec2::new(view_width, view_height)), scene.seed.unwrap_or(0));
+        }
+
+        // Draw the pinned comparison map left of the divider
+        if let (Some(pinned), false) = (&pinned_map, difference_active) {
+            draw_texture_ex(&pinned.texture, 0.0, 0.0, WHITE, DrawTextureParams {
+                dest_size: Some(macroquad::math::Vec2::new(divider_x, pinned.params.height as f32)),
+                source: Some(Rect::new(0.0, 0.0, divider_x, pinned.params.height as f32)),
+                ..Default::default()
+            });
+        }
 
         // Draw gravity wells image if available
-        if let Some(texture) = &texture_option {
-            draw_texture(texture, 0.0, 0.0, WHITE);
+        if let Some(map) = &current_map {
+            if let (Some((texture, _)), true) = (&difference_view, difference_active) {
+                draw_texture(texture, 0.0, 0.0, WHITE);
+            } else if pinned_map.is_some() {
+                let right_width = (map.params.width as f32 - divider_x).max(0.0);
+                draw_texture_ex(&map.texture, divider_x, 0.0, WHITE, DrawTextureParams {
+                    dest_size: Some(macroquad::math::Vec2::new(right_width, map.params.height as f32)),
+                    source: Some(Rect::new(divider_x, 0.0, right_width, map.params.height as f32)),
+                    ..Default::default()
+                });
+            } else {
+                draw_texture(&map.texture, 0.0, 0.0, WHITE);
+            }
 
             // Transform stationary bodies for camera and zoom
-            let transform_point = |p: Vec2| -> Vec2 {
-                Vec2::new(
-                    (p.x + camera_offset.x) * zoom_factor,
-                    (p.y + camera_offset.y) * zoom_factor
-                )
-            };
+            let screen_camera = Camera::new(camera_offset, zoom_factor, Vec2::new(view_width, view_height));
+
+            // Walls, if the scene has a box
+            if let Some(walls) = &scene.walls {
+                let (min, max) = (screen_camera.world_to_screen(walls.min), screen_camera.world_to_screen(walls.max));
+                let color = if walls.is_periodic() { Color::new(0.6, 0.8, 1.0, 0.6) } else { Color::new(1.0, 1.0, 1.0, 0.6) };
+                draw_rectangle_lines(min.x, min.y, max.x - min.x, max.y - min.y, 2.0, color);
+            }
 
-            // Draw stationary bodies on top with camera transformation
-            for body in &stationary_bodies {
-                let transformed_pos = transform_point(body.pos);
-                if transformed_pos.x >= -body.radius && transformed_pos.x < IMAGE_SIZE as f32 + body.radius &&
-                   transformed_pos.y >= -body.radius && transformed_pos.y < IMAGE_SIZE as f32 + body.radius {
+            // Faint lines along the paths of moving bodies
+            for motion in &scene.motions {
+                let Some(body) = scene.bodies.get(motion.body) else { continue };
+                let color = Color::from_rgba(body.color[0], body.color[1], body.color[2], 90);
+                let points: Vec<Vec2> = motion.path.outline().into_iter()
+                    .map(|offset| screen_camera.world_to_screen(body.pos + offset.to_vec2()))
+                    .collect();
+                for pair in points.windows(2) {
+                    draw_line(pair[0].x, pair[0].y, pair[1].x, pair[1].y, 1.0, color);
+                }
+            }
+
+            // Draw bodies on top with camera transformation. Moving bodies are shown where they are
+            // at the latest simulation's time, or at the replayed timestep, and the bodies of a
+            // dynamic scene where the latest simulation has pulled them to.
+            let body_time = live_simulations.last().map_or(0.0, |trail| {
+                replay_timestep.map_or(trail.sim.particle.time, |timestep| timestep as f32 * trail.sim.params.dt)
+            });
+            let bodies = match live_simulations.last() {
+                Some(trail) if scene.is_dynamic() => Cow::Borrowed(&trail.sim.scene.bodies[..]),
+                _ => scene.bodies_at(body_time),
+            };
+            for (index, body) in bodies.iter().enumerate() {
+                let transformed_pos = screen_camera.world_to_screen(body.pos);
+                let reach = body.radius.max(body.shape.extent() * zoom_factor);
+                if screen_camera.is_visible(transformed_pos, reach) {
+                    // The selected body is ringed in white
+                    if selected_body == Some(index) {
+                        let ring = (body.radius * zoom_factor).max(BODY_PICK_RADIUS) + 6.0;
+                        draw_circle_lines(transformed_pos.x, transformed_pos.y, ring, 2.0, WHITE);
+                    }
                     let color = Color::from_rgba(body.color[0], body.color[1], body.color[2], 255);
-                    
-                    // Draw a dark outline first for better visibility
-                    draw_circle(transformed_pos.x, transformed_pos.y, body.radius * zoom_factor + 2.0, BLACK);
-                    
-                    // Draw the body with full color intensity
-                    draw_circle(transformed_pos.x, transformed_pos.y, body.radius * zoom_factor, color);
+                    let outlined = BODY_STROKE_WIDTH + 4.0;
+                    match body.shape {
+                        BodyShape::Ring { radius } => {
+                            draw_circle_lines(transformed_pos.x, transformed_pos.y, radius * zoom_factor, outlined, BLACK);
+                            draw_circle_lines(transformed_pos.x, transformed_pos.y, radius * zoom_factor, BODY_STROKE_WIDTH, color);
+                            glow.ring(transformed_pos, radius * zoom_factor, BODY_STROKE_WIDTH, color);
+                        }
+                        BodyShape::Segment { .. } => {
+                            if let Some((start, end)) = body.shape.ends() {
+                                let (start, end) = (screen_camera.world_to_screen(body.pos + start), screen_camera.world_to_screen(body.pos + end));
+                                draw_line(start.x, start.y, end.x, end.y, outlined, BLACK);
+                                draw_line(start.x, start.y, end.x, end.y, BODY_STROKE_WIDTH, color);
+                                glow.line(start, end, BODY_STROKE_WIDTH, color);
+                            }
+                        }
+                        shape => {
+                            let radius = match shape {
+                                BodyShape::Disc { radius } | BodyShape::BlackHole { schwarzschild_radius: radius } => radius,
+                                _ => body.radius,
+                            } * zoom_factor;
+
+                            // Draw a dark outline first for better visibility
+                            draw_circle(transformed_pos.x, transformed_pos.y, radius + 2.0, BLACK);
+
+                            // Draw the body with full color intensity
+                            draw_circle(transformed_pos.x, transformed_pos.y, radius, color);
+                            glow.circle(transformed_pos, radius, color);
+
+                            // Oblate bodies show their pole as a dark line across them
+                            if body.is_oblate() {
+                                let pole = body.pole() * radius;
+                                let (from, to) = (transformed_pos - pole, transformed_pos + pole);
+                                draw_line(from.x, from.y, to.x, to.y, 2.0, BLACK);
+                            }
+                        }
+                    }
+                }
+            }
+
+            // Draw the accumulated long exposure on top of the map
+            if let Some(texture) = &exposure_texture {
+                draw_texture(texture, 0.0, 0.0, WHITE);
+            }
+
+            // Draw the hover preview, recomputing it when the mouse has moved to another pixel
+            if show_hover_preview {
+                let (mx, my) = mouse_position();
+                let pixel = (mx as i32, my as i32);
+                let inside = mx >= 0.0 && mx < view_width && my >= 0.0 && my < view_height;
+                if !inside {
+                    hover_preview.clear();
+                    hover_preview_pixel = None;
+                } else if hover_preview_pixel != Some(pixel) && get_time() - hover_preview_time >= PREVIEW_INTERVAL {
+                    let world_pos = screen_camera.screen_to_world(Vec2::new(mx, my));
+                    hover_preview = preview_trajectory(world_pos, initial_velocity, &scene, &sim_params.resolved(&scene, zoom_factor), PREVIEW_TIMESTEPS);
+                    hover_preview_pixel = Some(pixel);
+                    hover_preview_time = get_time();
+                }
+                for segment in hover_preview.windows(2) {
+                    let p1 = screen_camera.world_to_screen(segment[0]);
+                    let p2 = screen_camera.world_to_screen(segment[1]);
+                    draw_line(p1.x, p1.y, p2.x, p2.y, 1.0, Color::new(1.0, 1.0, 1.0, 0.35));
+                }
+            }
+
+            // Draw the zero-velocity curve of the latest launch over the map
+            if show_jacobi {
+                let jacobi = live_simulations.last().map(|trail| {
+                    let start = trail.sim.trajectory_history[0];
+                    jacobi_constant(&TestParticle::new(start.pos, start.vel, TEST_PARTICLE_MASS, TEST_PARTICLE_RADIUS), &scene)
+                });
+                let (width, height) = (view_width as u32, view_height as u32);
+                let key = (camera_offset, zoom_factor, scene.cache_key(), jacobi.map(f32::to_bits), width, height);
+                if jacobi_overlay.as_ref().is_none_or(|(cached, _)| *cached != key) {
+                    let pixels = contour_overlay(&scene, width, height,
+                        |px, py| screen_camera.screen_to_world(Vec2::new(px, py)), jacobi);
+                    let texture = Texture2D::from_rgba8(width as u16, height as u16, &pixels);
+                    texture.set_filter(FilterMode::Nearest);
+                    jacobi_overlay = Some((key, texture));
+                }
+                if let Some((_, texture)) = &jacobi_overlay {
+                    draw_texture(texture, 0.0, 0.0, WHITE);
+                }
+            }
+
+            // Basin outlines in the color of their body, over the map they were traced from
+            if let (true, Some((_, traced))) = (show_boundaries, &boundaries) {
+                for boundary in traced {
+                    let Some(body) = boundary.body.and_then(|index| scene.bodies.get(index)) else {
+                        continue;
+                    };
+                    let color = Color::from_rgba(body.color[0], body.color[1], body.color[2], 230);
+                    for segment in boundary.points.windows(2) {
+                        draw_line(segment[0].x, segment[0].y, segment[1].x, segment[1].y, 1.0, color);
+                    }
+                }
+            }
+
+            if show_grid {
+                draw_world_grid(&screen_camera);
+            }
+
+            // Draw the fitted Kepler orbit behind the numerical trajectories, out to a few view
+            // widths from its focus
+            if let Some(orbit) = &kepler_orbit {
+                let max_radius = KEPLER_VIEW_EXTENT * view_width.max(view_height) / zoom_factor;
+                let points = orbit.conic_points(KEPLER_SEGMENTS, max_radius);
+                for segment in points.windows(2) {
+                    let p1 = screen_camera.world_to_screen(segment[0]);
+                    let p2 = screen_camera.world_to_screen(segment[1]);
+                    draw_line(p1.x, p1.y, p2.x, p2.y, 1.5, Color::new(0.7, 0.9, 1.0, 0.45));
+                }
+            }
+
+            // The backward run of the time-reversal check, and how far from the launch it ended
+            if let (true, Some((_, run))) = (show_reversal && !live_simulations.is_empty(), &reversal) {
+                let color = Color::new(1.0, 0.55, 0.9, 0.7);
+                for segment in run.backward.windows(2) {
+                    let p1 = screen_camera.world_to_screen(segment[0]);
+                    let p2 = screen_camera.world_to_screen(segment[1]);
+                    draw_line(p1.x, p1.y, p2.x, p2.y, 1.5, color);
+                }
+                if let (Some(&start), Some(&end)) = (run.forward.first(), run.backward.last()) {
+                    let (start, end) = (screen_camera.world_to_screen(start), screen_camera.world_to_screen(end));
+                    draw_line(start.x, start.y, end.x, end.y, 1.0, WHITE);
+                    draw_circle_lines(end.x, end.y, 4.0, 1.5, color);
                 }
             }
 
@@ -190,20 +2051,28 @@ async fn main() {
                 draw_rectangle_lines(x, y, highlight_size, highlight_size, 2.0, WHITE);
             }
 
-            // Handle mouse clicks (account for camera transformation)
-            if is_mouse_button_pressed(MouseButton::Left) {
-                let (mx, my) = mouse_position();
-                if mx >= 0.0 && mx < IMAGE_SIZE as f32 && my >= 0.0 && my < IMAGE_SIZE as f32 {
+            // Handle mouse clicks (account for camera transformation), and the gamepad's launch
+            // button, which launches from the crosshair in the middle of the view
+            let view_center = (view_width / 2.0, view_height / 2.0);
+            if pad.connected {
+                let (cx, cy) = view_center;
+                draw_line(cx - GAMEPAD_CROSSHAIR_SIZE, cy, cx + GAMEPAD_CROSSHAIR_SIZE, cy, 1.0, WHITE);
+                draw_line(cx, cy - GAMEPAD_CROSSHAIR_SIZE, cx, cy + GAMEPAD_CROSSHAIR_SIZE, 1.0, WHITE);
+            }
+            let launch_point = if bindings.launch.is_pressed() && !mouse_over_panel && !dragging_divider && !dragging_timeline && !body_clicked {
+                Some(mouse_position())
+            } else {
+                Some(view_center).filter(|_| pad.launch)
+            };
+            if let Some((mx, my)) = launch_point {
+                if mx >= 0.0 && mx < view_width && my >= 0.0 && my < view_height {
                     let px = mx as u32;
                     let py = my as u32;
                     selected_px = Some(px);
                     selected_py = Some(py);
 
                     // Transform mouse position back to world coordinates
-                    let world_pos = Vec2::new(
-                        mx / zoom_factor - camera_offset.x,
-                        my / zoom_factor - camera_offset.y
-                    );
+                    let world_pos = screen_camera.screen_to_world(Vec2::new(mx, my));
                     
                     let integration_method = if use_runge_kutta {
                         IntegrationMethod::RungeKutta4
@@ -211,103 +2080,438 @@ async fn main() {
                         IntegrationMethod::Euler
                     };
                     
-                    live_simulation = Some(LiveSimulationState::new(
-                        world_pos,
-                        initial_velocity,
-                        stationary_bodies.clone(),
-                        integration_method,
-                    ));
+                    if launch_mode != LaunchMode::Single {
+                        let spread = if launch_mode == LaunchMode::EnsemblePosition {
+                            EnsembleSpread { position_radius: ENSEMBLE_SCREEN_RADIUS / zoom_factor, velocity_jitter: 0.0 }
+                        } else {
+                            EnsembleSpread { position_radius: 0.0, velocity_jitter: step_size }
+                        };
+                        let particles = sample_ensemble(world_pos, initial_velocity, ENSEMBLE_SIZE, spread, &mut rng);
+                        ensemble = Some(EnsembleSimulation::new(particles, scene.clone(), integration_method, sim_params.resolved(&scene, zoom_factor)));
+                    } else {
+                        // Shift-click adds another simulation instead of replacing the existing ones
+                        if !shift_down {
+                            live_simulations.clear();
+                        }
+                        let mut sim = LiveSimulationState::new(world_pos, initial_velocity, scene.clone(), integration_method, sim_params.resolved(&scene, zoom_factor));
+                        sim.record_spacing = TRAIL_POINT_SPACING / zoom_factor;
+                        sim.point_limit = cli.trail_points;
+                        sim.burns = cli.scheduled_burns(&sim_params);
+                        live_simulations.push(LiveTrail {
+                            sim,
+                            color: TRAIL_COLORS[live_simulations.len() % TRAIL_COLORS.len()],
+                            exposed_points: 0,
+                            events: TrailEvents::default(),
+                        });
+                        replay_timestep = None;
+                    }
                 }
             }
 
-            // Update and draw live simulation
-            if let Some(sim) = &mut live_simulation {
-                // if !sim.is_finished() {
-                //     sim.step();
-                // }
-                sim.step();
+            // Update and draw live simulations
+            let mut sound_cues = Vec::new();
+            for trail in &mut live_simulations {
+                let sim = &mut trail.sim;
+
+                // Integrate by real elapsed time so slow frames don't slow down simulated time.
+                // The simulations are frozen while replaying their history.
+                if replay_timestep.is_none() {
+                    sim.manual_thrust = manual_thrust;
+                    if single_step {
+                        sim.step_observed(&mut trail.events);
+                    } else if !simulation_paused {
+                        sim.advance_observed(get_frame_time(), playback_speed, &mut trail.events);
+                    }
+                }
+                sound_cues.append(&mut trail.events.sounds);
+
+                // Deposit newly recorded trajectory segments into the long exposure. They are the
+                // latest points, unless the trail was thinned out since, which can drop some of them.
+                if let Some(buffer) = &mut exposure {
+                    let len = sim.trajectory_history.len();
+                    let new_points = (sim.recorded_points - trail.exposed_points).min(len - 1);
+                    for i in len - new_points..len {
+                        buffer.add_segment(screen_camera.world_to_screen(sim.trajectory_history[i - 1].pos),
+                                           screen_camera.world_to_screen(sim.trajectory_history[i].pos));
+                    }
+                    trail.exposed_points = sim.recorded_points;
+                }
 
-                // Draw trajectory with camera transformation
+                // Draw trajectory with camera transformation, dimming the part after the replay position
+                let replay_len = replay_timestep.map_or(sim.trajectory_history.len(), |t| sim.history_len_at(t));
+                let max_speed = sim.trajectory_history.iter().map(|p| p.vel.length()).fold(0.0, f32::max).max(f32::EPSILON);
                 for i in 1..sim.trajectory_history.len() {
-                    let p1 = transform_point(sim.trajectory_history[i - 1]);
-                    let p2 = transform_point(sim.trajectory_history[i]);
+                    let p1 = screen_camera.world_to_screen(sim.trajectory_history[i - 1].pos);
+                    let p2 = screen_camera.world_to_screen(sim.trajectory_history[i].pos);
+                    // Nor across the box where periodic walls wrapped the particle around
+                    let (from, to) = (sim.trajectory_history[i - 1].pos, sim.trajectory_history[i].pos);
+                    let wrapped = sim.scene.walls.is_some_and(|walls| walls.nearest_image(to.into(), from.into()).to_vec2() != to);
                     
                     // Only draw if both points are visible
-                    if p1.x >= -50.0 && p1.x < IMAGE_SIZE as f32 + 50.0 &&
-                       p1.y >= -50.0 && p1.y < IMAGE_SIZE as f32 + 50.0 &&
-                       p2.x >= -50.0 && p2.x < IMAGE_SIZE as f32 + 50.0 &&
-                       p2.y >= -50.0 && p2.y < IMAGE_SIZE as f32 + 50.0 {
-                        draw_line(p1.x, p1.y, p2.x, p2.y, 2.0, YELLOW);
+                    if !wrapped &&
+                       p1.x >= -50.0 && p1.x < view_width + 50.0 &&
+                       p1.y >= -50.0 && p1.y < view_height + 50.0 &&
+                       p2.x >= -50.0 && p2.x < view_width + 50.0 &&
+                       p2.y >= -50.0 && p2.y < view_height + 50.0 {
+                        let point = sim.trajectory_history[i];
+                        let mut color = match trail_coloring {
+                            TrailColoring::Solid => trail.color,
+                            TrailColoring::Speed => {
+                                let [r, g, b] = gradient_color(point.vel.length() / max_speed);
+                                Color::new(r, g, b, 1.0)
+                            }
+                            TrailColoring::Time => {
+                                let [r, g, b] = gradient_color(point.timestep as f32 / sim.params.timesteps as f32);
+                                Color::new(r, g, b, 1.0)
+                            }
+                        };
+                        if i >= replay_len {
+                            color.a = 0.25;
+                        }
+                        draw_line(p1.x, p1.y, p2.x, p2.y, 2.0, color);
+                        glow.line(p1, p2, 2.0, color);
                     }
                 }
 
-                // Draw current particle position with camera transformation
-                let particle_pos = transform_point(sim.particle.pos);
-                if particle_pos.x >= 0.0 && particle_pos.x < IMAGE_SIZE as f32 &&
-                   particle_pos.y >= 0.0 && particle_pos.y < IMAGE_SIZE as f32 {
-                    let particle_color = if sim.collision_body_index.is_some() {
+                // Draw current (or replayed) particle position with camera transformation
+                let (shown_pos, shown_vel, at_end) = match replay_timestep {
+                    Some(timestep) => {
+                        let point = sim.point_at(timestep);
+                        (point.pos, point.vel, replay_len == sim.trajectory_history.len())
+                    }
+                    None => (sim.particle.pos, sim.particle.vel, true),
+                };
+                let particle_pos = screen_camera.world_to_screen(shown_pos);
+                if screen_camera.is_visible(particle_pos, 0.0) {
+                    let particle_color = if sim.collision_body_index.is_some() && at_end {
                         RED
                     } else {
-                        YELLOW
+                        trail.color
                     };
                     draw_circle(particle_pos.x, particle_pos.y, 3.0, particle_color);
+                    glow.circle(particle_pos, 3.0, particle_color);
+
+                    // Exhaust behind a particle whose engine is firing
+                    if let Some(thrust) = sim.last_thrust().filter(|_| replay_timestep.is_none() && !sim.is_finished()) {
+                        let exhaust = particle_pos - thrust.acceleration(shown_vel).normalize() * EXHAUST_LENGTH;
+                        draw_line(particle_pos.x, particle_pos.y, exhaust.x, exhaust.y, 3.0, ORANGE);
+                    }
+
+                    // Velocity (green) and net gravitational acceleration (red) arrows
+                    if show_vectors {
+                        let scale = VECTOR_SCALES[vector_scale_index];
+                        let probe = sim.scene.launch_particle(shown_pos, shown_vel);
+                        let acceleration = calculate_acceleration(&probe, &sim.scene);
+                        draw_arrow(particle_pos, shown_vel * (VELOCITY_ARROW_SCALE * scale), GREEN);
+                        draw_arrow(particle_pos, acceleration * (ACCELERATION_ARROW_SCALE * scale), RED);
+                    }
+                }
+            }
+            sound_effects.play(&sound_cues);
+
+            // Update and draw the ensemble, coloring captured particles by the body that caught them
+            if let Some(cloud) = &mut ensemble {
+                let previous_positions: Vec<Vec2> = cloud.particles.iter().map(|p| p.pos).collect();
+                if single_step {
+                    cloud.step();
+                } else if !simulation_paused {
+                    cloud.advance(get_frame_time(), playback_speed);
+                }
+
+                if let Some(buffer) = &mut exposure {
+                    for (previous, particle) in previous_positions.iter().zip(&cloud.particles) {
+                        buffer.add_segment(screen_camera.world_to_screen(*previous), screen_camera.world_to_screen(particle.pos));
+                    }
                 }
+
+                for (particle, collision) in cloud.particles.iter().zip(&cloud.collisions) {
+                    let p = screen_camera.world_to_screen(particle.pos);
+                    let color = match collision {
+                        Some(index) => {
+                            let c = cloud.scene.bodies[*index].color;
+                            Color::from_rgba(c[0], c[1], c[2], 255)
+                        }
+                        None => WHITE,
+                    };
+                    draw_circle(p.x, p.y, 1.5, color);
+                }
+            }
+
+            // Refresh the tone mapped exposure for the next frame
+            if let Some(buffer) = &exposure {
+                if !buffer.is_empty() {
+                    let img = buffer.tone_map();
+                    exposure_texture = Some(Texture2D::from_rgba8(img.width() as u16, img.height() as u16, img.as_raw()));
+                }
+            }
+
+            glow.draw(view_width, view_height);
+
+            // Draw the replay timeline
+            if let (Some(timestep), Some(last)) = (replay_timestep, last_timestep) {
+                let timeline_width = view_width - 2.0 * TIMELINE_MARGIN;
+                let fraction = timestep as f32 / last.max(1) as f32;
+                draw_rectangle(TIMELINE_MARGIN, timeline_y, timeline_width, TIMELINE_HEIGHT, Color::new(0.0, 0.0, 0.0, 0.6));
+                draw_rectangle(TIMELINE_MARGIN, timeline_y, timeline_width * fraction, TIMELINE_HEIGHT, YELLOW);
+                draw_rectangle_lines(TIMELINE_MARGIN, timeline_y, timeline_width, TIMELINE_HEIGHT, 1.0, WHITE);
             }
+        } else if pinned_map.is_some() {
+            draw_text("Press ENTER to generate comparison image", divider_x + 10.0, view_height / 2.0, 20.0, WHITE);
         } else {
             // Show message when no image is loaded
-            draw_text("Press ENTER to generate gravity wells image", 10.0, IMAGE_SIZE as f32 / 2.0, 24.0, WHITE);
+            draw_text("Press ENTER to generate gravity wells image", 10.0, view_height / 2.0, 24.0, WHITE);
+        }
+
+        // Inspect whichever map is shown under the mouse
+        if show_inspector {
+            let (mx, my) = mouse_position();
+            let inspected = match &pinned_map {
+                Some(pinned) if !difference_active && mx < divider_x => Some(pinned),
+                _ => current_map.as_ref(),
+            };
+            if let Some(map) = inspected {
+                if mx >= 0.0 && mx < view_width && my >= 0.0 && my < view_height {
+                    draw_pixel_inspector(map, mx, my);
+                }
+            }
+        }
+
+        // Draw the divider and labels for both sides of the comparison
+        if let (Some((_, stats)), true) = (&difference_view, difference_active) {
+            let summary = format!("Difference: {} pixels changed body, {} hit earlier (red), {} hit later (blue)",
+                                  stats.changed_body, stats.earlier, stats.later);
+            draw_text(&summary, 10.0, view_height - 12.0, 16.0, WHITE);
+        } else if let Some(pinned) = &pinned_map {
+            draw_line(divider_x, 0.0, divider_x, view_height, 2.0, WHITE);
+            draw_text(&format!("Pinned: {}", pinned.params.label()), 10.0, view_height - 30.0, 16.0, WHITE);
+            if let Some(map) = &current_map {
+                draw_text(&format!("Current: {}", map.params.label()), 10.0, view_height - 12.0, 16.0, WHITE);
+            }
         }
 
-        // Draw instructions and status
+        // Draw status HUD and help panel
         let integration_name = if use_runge_kutta { "Runge-Kutta 4" } else { "Euler" };
-        let mut y_offset = 20.0;
-        
-        draw_text("Controls:", 10.0, y_offset, 18.0, WHITE);
-        y_offset += 20.0;
-        
-        draw_text("• Arrow keys: Change initial velocity", 10.0, y_offset, 14.0, WHITE);
-        y_offset += 16.0;
-        draw_text("• WASD: Move camera", 10.0, y_offset, 14.0, WHITE);
-        y_offset += 16.0;
-        draw_text("• Q/E: Zoom out/in", 10.0, y_offset, 14.0, WHITE);
-        y_offset += 16.0;
-        draw_text("• +/-: Change step size", 10.0, y_offset, 14.0, WHITE);
-        y_offset += 16.0;
-        draw_text("• Space: Toggle integration method", 10.0, y_offset, 14.0, WHITE);
-        y_offset += 16.0;
-        draw_text("• Enter: Recalculate image", 10.0, y_offset, 14.0, WHITE);
-        y_offset += 16.0;
-        draw_text("• Left click: Start simulation", 10.0, y_offset, 14.0, WHITE);
-        y_offset += 20.0;
-        
-        // Show current settings
-        draw_text(&format!("Step Size: {:.1}", step_size), 10.0, y_offset, 16.0, SKYBLUE);
-        y_offset += 18.0;
-        draw_text(&format!("Initial Velocity: ({:.1}, {:.1})", initial_velocity.x, initial_velocity.y), 10.0, y_offset, 16.0, SKYBLUE);
-        y_offset += 18.0;
-        draw_text(&format!("Camera: ({:.1}, {:.1})", camera_offset.x, camera_offset.y), 10.0, y_offset, 16.0, SKYBLUE);
-        y_offset += 18.0;
-        draw_text(&format!("Zoom: {:.2}x", zoom_factor), 10.0, y_offset, 16.0, SKYBLUE);
-        y_offset += 18.0;
-        draw_text(&format!("Integration: {}", integration_name), 10.0, y_offset, 16.0, SKYBLUE);
-        y_offset += 18.0;
+        let mut status_lines = vec![
+            HudLine::new(format!("Step Size: {:.1}", step_size), SKYBLUE),
+            HudLine::new(format!("Initial Velocity: ({:.1}, {:.1})", initial_velocity.x, initial_velocity.y), SKYBLUE),
+            HudLine::new(format!("Thrust: {:.1}{}", thrust_magnitude,
+                                 live_simulations.last().map_or(String::new(), |trail| format!("  Delta-v used: {:.1}", trail.sim.delta_v()))),
+                         if manual_thrust.is_some() { ORANGE } else { SKYBLUE }),
+            HudLine::new(format!("Camera: ({:.1}, {:.1})", camera_offset.x, camera_offset.y), SKYBLUE),
+            HudLine::new(format!("Zoom: {}x", zoom_text(zoom_factor)), SKYBLUE),
+            HudLine::new(format!("Integration: {}{}", integration_name,
+                                 if sim_params.double_precision { " (f64 maps)" } else { "" }), SKYBLUE),
+            HudLine::new(format!("G: {:.1}  Bodies: {}{}{}{}{}{}{}{}{}{}", scene.gravitational_constant, scene.bodies.len(),
+                                 scene.seed.map_or(String::new(), |seed| format!("  Seed: {}", seed)),
+                                 if scene.drag.is_active() {
+                                     format!("  Drag: {}, {}", scene.drag.linear, scene.drag.quadratic)
+                                 } else {
+                                     String::new()
+                                 },
+                                 if scene.is_charged() {
+                                     format!("  Charge: {} (k {})", scene.particle_charge, scene.coulomb_constant)
+                                 } else {
+                                     String::new()
+                                 },
+                                 scene.speed_of_light.map_or(String::new(), |c| format!("  c: {:.0}", c)),
+                                 scene.restitution.map_or(String::new(), |restitution| format!("  Bounce: {:.2}", restitution)),
+                                 scene.walls.map_or(String::new(), |walls| format!("  Walls: {}", if walls.is_periodic() { "periodic" } else { "reflecting" })),
+                                 if scene.is_dynamic() {
+                                     format!("  Dynamic (particle mass {}{})", scene.particle_mass, if scene.accretion { ", accretion" } else { "" })
+                                 } else {
+                                     String::new()
+                                 },
+                                 if scene.is_magnetized() {
+                                     format!("  B: {}", scene.magnetic_field)
+                                 } else {
+                                     String::new()
+                                 },
+                                 if scene.external_field.is_active() {
+                                     let field = &scene.external_field;
+                                     format!("  Field: {:.1}, {:.1}{}", field.acceleration.x, field.acceleration.y,
+                                             if field.tidal != [0.0; 3] { " + tidal" } else { "" })
+                                 } else {
+                                     String::new()
+                                 }), SKYBLUE),
+            HudLine::new(format!("Timesteps: {}  Substeps: {}  dt: {:.4}  Collision radius: {}",
+                                 sim_params.timesteps, sim_params.substeps, sim_params.dt,
+                                 match sim_params.collision_policy {
+                                     CollisionPolicy::World => format!("{:.1}", sim_params.collision_threshold),
+                                     CollisionPolicy::Screen => format!("{:.1}px ({:.3})", sim_params.collision_threshold,
+                                                                        sim_params.resolved(&scene, zoom_factor).collision_threshold),
+                                     CollisionPolicy::BodyRadius => format!("body radius ({:.1})", sim_params.resolved(&scene, zoom_factor).collision_threshold),
+                                 }), SKYBLUE),
+        ];
         
-        if needs_recalculation {
-            draw_text("Parameters changed - press ENTER to recalculate", 10.0, y_offset, 16.0, YELLOW);
-            y_offset += 18.0;
+        if let Some(index) = bookmark_index {
+            status_lines.push(HudLine::new(format!("{} ({} of {})", bookmarks[index].name, index + 1, bookmarks.len()), SKYBLUE));
+        }
+
+        if let Some(index) = selected_body {
+            let body = &scene.bodies[index];
+            status_lines.push(HudLine::new(
+                format!("Body {} selected: mass {:.0}, radius {:.1} (wheel or [/] to change)", index, body.mass, body.radius),
+                WHITE));
+        }
+
+        if let Some(status) = render_queue.status() {
+            status_lines.push(HudLine::new(status, YELLOW));
+        } else if needs_recalculation {
+            status_lines.push(HudLine::new("Parameters changed - press ENTER to recalculate", YELLOW));
         }
         
-        if let Some(sim) = &live_simulation {
+        let exposure_status = if exposure.is_some() { ", long exposure on" } else { "" };
+        let vector_status = if show_vectors { format!(", arrows {}x", VECTOR_SCALES[vector_scale_index]) } else { String::new() };
+        status_lines.push(HudLine::new(
+            format!("Launch: {}, trails {}{}{}", launch_mode.name(), trail_coloring.name(), vector_status, exposure_status),
+            SKYBLUE));
+
+        if escape_coloring != EscapeColoring::Uniform {
+            let unsupported = if sim_params.double_precision || zoom_factor >= DEEP_ZOOM_THRESHOLD { " (f32 maps only)" } else { "" };
+            status_lines.push(HudLine::new(format!("Escaped pixels: {}{}", escape_coloring.name(), unsupported), SKYBLUE));
+        }
+
+        if let Some(cloud) = &ensemble {
+            let fractions = cloud.capture_fractions();
+            let captured: f32 = fractions.iter().sum();
+            let per_body: Vec<String> = fractions.iter().enumerate()
+                .map(|(i, fraction)| format!("body {}: {:.1}%", i, fraction * 100.0))
+                .collect();
+            status_lines.push(HudLine::new(
+                format!("Ensemble t={}: {}, free: {:.1}%", cloud.current_timestep, per_body.join(", "), (1.0 - captured) * 100.0),
+                WHITE));
+        }
+
+        if show_jacobi {
+            if let Some(trail) = live_simulations.last() {
+                let start = trail.sim.trajectory_history[0];
+                let launch = TestParticle::new(start.pos, start.vel, TEST_PARTICLE_MASS, TEST_PARTICLE_RADIUS);
+                let now = TestParticle::new(trail.sim.particle.pos, trail.sim.particle.vel, TEST_PARTICLE_MASS, TEST_PARTICLE_RADIUS);
+                status_lines.push(HudLine::new(
+                    format!("Jacobi constant C = -2E = {:.1} (now {:.1})", jacobi_constant(&launch, &scene), jacobi_constant(&now, &scene)),
+                    Color::new(1.0, 0.86, 0.24, 1.0)));
+            }
+        }
+
+        if let (true, Some((_, run))) = (show_reversal && !live_simulations.is_empty(), &reversal) {
+            status_lines.push(HudLine::new(
+                format!("Time reversal ({}): back after t={:.2} each way, {:.2e} from the start",
+                        run.integration_method.name(), run.duration, run.return_error),
+                Color::new(1.0, 0.55, 0.9, 1.0)));
+        }
+
+        if show_kepler && !live_simulations.is_empty() {
+            match &kepler_orbit {
+                Some(orbit) => status_lines.push(HudLine::new(orbit.describe(), Color::new(0.7, 0.9, 1.0, 1.0))),
+                None => status_lines.push(HudLine::new(
+                    format!("Kepler: no body provides {:.0}% of the pull", KEPLER_DOMINANCE * 100.0), GRAY)),
+            }
+        }
+
+        // Status of the most recently launched simulation
+        if let Some(trail) = live_simulations.last() {
+            let sim = &trail.sim;
             let status = if let Some(collision_index) = sim.collision_body_index {
                 format!("Collided with body {} at timestep {}", collision_index, sim.current_timestep)
-            } else if sim.current_timestep >= simulation::SIMULATION_TIMESTEPS {
+            } else if sim.current_timestep >= sim.params.timesteps {
                 "No collision - simulation ended".to_string()
             } else {
                 format!("Simulating... timestep {}", sim.current_timestep)
             };
-            draw_text(&status, 10.0, y_offset, 16.0, WHITE);
+            let status = match sim.particle.bounces {
+                0 => status,
+                1 => format!("{} after 1 bounce", status),
+                bounces => format!("{} after {} bounces", status, bounces),
+            };
+            let status = match trail.events.close_approaches {
+                0 => status,
+                1 => format!("{}, 1 close approach", status),
+                approaches => format!("{}, {} close approaches", status, approaches),
+            };
+            let status = if let Some(timestep) = replay_timestep {
+                let point = sim.point_at(timestep);
+                format!("Replay: timestep {} of {}, speed {:.1}", timestep, last_timestep.unwrap_or(0), point.vel.length())
+            } else if simulation_paused {
+                format!("{} [paused, {}x]", status, playback_speed)
+            } else {
+                format!("{} [{}x]", status, playback_speed)
+            };
+            status_lines.push(HudLine::new(status, WHITE));
+
+            if live_simulations.len() > 1 {
+                let collided = live_simulations.iter().filter(|trail| trail.sim.collision_body_index.is_some()).count();
+                status_lines.push(HudLine::new(format!("{} simulations, {} collided", live_simulations.len(), collided), WHITE));
+            }
+        }
+
+        if !hud.show_help {
+            status_lines.push(HudLine::new("F1: Controls", GRAY));
+        }
+
+        let compact_line = HudLine::new(
+            format!("v=({:.1}, {:.1})  zoom {:.2}x  {}  T={}{}",
+                    initial_velocity.x, initial_velocity.y, zoom_factor, integration_name, sim_params.timesteps,
+                    if needs_recalculation { "  [ENTER to recalculate]" } else { "" }),
+            if needs_recalculation { YELLOW } else { SKYBLUE });
+        hud.draw(&status_lines, &compact_line);
+
+        if phase_view != PhaseView::Off && !live_simulations.is_empty() {
+            draw_phase_space(phase_view, &live_simulations, replay_timestep, timeline_y - 8.0);
+        }
+
+        // The energy drift panel stacks above the minimap, which shares its corner
+        if let Some(minimap) = minimap.as_ref().filter(|_| show_minimap) {
+            minimap.draw(&Camera::new(camera_offset, zoom_factor, Vec2::new(view_width, view_height)), minimap_bottom);
+        }
+        if show_energy_drift {
+            if let Some((_, drifts)) = energy_drift.as_ref().filter(|_| !live_simulations.is_empty()) {
+                let bottom = if show_minimap { minimap_bottom - MINIMAP_SIZE - 8.0 } else { timeline_y - 8.0 };
+                draw_energy_drift(drifts, bottom);
+            }
+        }
+
+        if let Some(progress) = render_queue.progress() {
+            draw_generation_progress(progress, view_width);
+        }
+
+        if let Some(screenshot) = screenshot_request.take() {
+            let caption = (screenshot == Screenshot::Annotated).then(|| {
+                let view = get_map_params((view_width as u32, view_height as u32), use_runge_kutta, initial_velocity, camera, zoom_factor, sim_params, &scene, escape_coloring);
+                caption_lines(&view, &scene, SystemTime::now())
+            });
+            let filename = next_screenshot_filename(&mut screenshot_count);
+            let screen = get_screen_data();
+            match save_screenshot(&filename, screen.width as u32, screen.height as u32, screen.bytes, caption.as_deref()) {
+                Ok(()) => println!("Screenshot saved to {}", filename),
+                Err(e) => eprintln!("Error saving screenshot: {}", e),
+            }
+        }
+
+        // Capture the finished frame, then draw the recording indicator so it stays out of the recording
+        if let Some(active) = &mut recording {
+            if active.wants_frame(get_time()) {
+                let screen = get_screen_data();
+                active.capture(get_time(), screen.width as u32, screen.height as u32, screen.bytes);
+            }
+            if active.is_full() {
+                save_recording(recording.take().unwrap(), &mut recording_count);
+            } else {
+                draw_circle(view_width - 70.0, 20.0, 6.0, RED);
+                draw_text(&format!("REC {}", active.frame_count()), view_width - 58.0, 26.0, 18.0, RED);
+            }
         }
 
         next_frame().await;
     }
 }
+
+// Encodes a finished recording on a background thread, since GIF encoding takes a while
+fn save_recording(recording: Recording, recording_count: &mut usize) {
+    let name = format!("recording_{}", recording_count);
+    *recording_count += 1;
+    println!("Saving recording of {} frames...", recording.frame_count());
+    std::thread::spawn(move || match recording.save(&name) {
+        Ok(path) => println!("Recording saved to {}", path),
+        Err(e) => eprintln!("Error saving recording: {}", e),
+    });
+}
@@ -0,0 +1,92 @@
+use std::fs::File;
+use std::io::BufWriter;
+
+use image::RgbImage;
+
+use crate::escape::EscapeColoring;
+use crate::image_gen::MapParams;
+use crate::physics::Vec2;
+use crate::scene::Scene;
+use crate::simulation::{IntegrationMethod, SimulationParams};
+
+// tEXt chunk keywords. Values are JSON so they round-trip exactly; they stay readable with
+// any PNG metadata viewer.
+const SOFTWARE_KEY: &str = "Software";
+const SCENE_KEY: &str = "Scene";
+const VELOCITY_KEY: &str = "Initial velocity";
+const CAMERA_KEY: &str = "Camera offset";
+const ZOOM_KEY: &str = "Zoom";
+const INTEGRATOR_KEY: &str = "Integrator";
+const SIMULATION_KEY: &str = "Simulation";
+const DEEP_CAMERA_KEY: &str = "Deep camera offset";
+const ESCAPE_COLORING_KEY: &str = "Escape coloring";
+
+// Saves a map as a PNG with every parameter needed to regenerate it stored in tEXt chunks
+pub fn save_png_with_metadata(
+    img: &RgbImage,
+    filename: &str,
+    params: &MapParams,
+    scene: &Scene,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let writer = BufWriter::new(File::create(filename)?);
+    let mut encoder = png::Encoder::new(writer, img.width(), img.height());
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let chunks = [
+        (SOFTWARE_KEY, "gravity-wells".to_string()),
+        (SCENE_KEY, serde_json::to_string(scene)?),
+        (VELOCITY_KEY, serde_json::to_string(&params.initial_velocity)?),
+        (CAMERA_KEY, serde_json::to_string(&params.camera_offset)?),
+        (ZOOM_KEY, serde_json::to_string(&params.zoom_factor)?),
+        (INTEGRATOR_KEY, serde_json::to_string(&params.integration_method)?),
+        (SIMULATION_KEY, serde_json::to_string(&params.simulation)?),
+    ];
+    for (keyword, text) in chunks {
+        encoder.add_text_chunk(keyword.to_string(), text)?;
+    }
+    if let Some(camera) = &params.deep_camera {
+        encoder.add_text_chunk(DEEP_CAMERA_KEY.to_string(), serde_json::to_string(camera)?)?;
+    }
+    if params.escape_coloring != EscapeColoring::Uniform {
+        encoder.add_text_chunk(ESCAPE_COLORING_KEY.to_string(), serde_json::to_string(&params.escape_coloring)?)?;
+    }
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(img.as_raw())?;
+    Ok(())
+}
+
+// Reads back the parameters stored by save_png_with_metadata, giving the map parameters (at the
+// PNG's size) and the scene it was generated from
+pub fn load_png_metadata(filename: &str) -> Result<(MapParams, Scene), Box<dyn std::error::Error>> {
+    let decoder = png::Decoder::new(File::open(filename)?);
+    let reader = decoder.read_info()?;
+    let info = reader.info();
+    let text = |keyword: &str| -> Result<&str, Box<dyn std::error::Error>> {
+        info.uncompressed_latin1_text.iter()
+            .find(|chunk| chunk.keyword == keyword)
+            .map(|chunk| chunk.text.as_str())
+            .ok_or_else(|| format!("{} has no '{}' metadata", filename, keyword).into())
+    };
+
+    let scene: Scene = serde_json::from_str(text(SCENE_KEY)?)?;
+    let initial_velocity: Vec2 = serde_json::from_str(text(VELOCITY_KEY)?)?;
+    let camera_offset: Vec2 = serde_json::from_str(text(CAMERA_KEY)?)?;
+    let zoom_factor: f32 = serde_json::from_str(text(ZOOM_KEY)?)?;
+    let integration_method: IntegrationMethod = serde_json::from_str(text(INTEGRATOR_KEY)?)?;
+    let simulation: SimulationParams = serde_json::from_str(text(SIMULATION_KEY)?)?;
+    let params = MapParams {
+        width: info.width,
+        height: info.height,
+        initial_velocity,
+        camera_offset,
+        zoom_factor,
+        integration_method,
+        simulation,
+        scene_key: scene.cache_key(),
+        deep_camera: text(DEEP_CAMERA_KEY).ok().map(serde_json::from_str).transpose()?,
+        escape_coloring: text(ESCAPE_COLORING_KEY).ok().map(serde_json::from_str).transpose()?.unwrap_or_default(),
+    };
+    Ok((params, scene))
+}
@@ -0,0 +1,69 @@
+use macroquad::prelude::*;
+
+use gravity_wells::camera::Camera;
+use gravity_wells::overview::{overview_camera, render_strongest_attractor};
+use gravity_wells::physics::Vec2;
+use gravity_wells::scene::Scene;
+
+use crate::texture_from_image;
+
+// Side of the square overview in the bottom right corner
+pub const MINIMAP_SIZE: f32 = 180.0;
+const MINIMAP_MARGIN: f32 = 8.0;
+// Smallest size the viewport outline shrinks to when zoomed far in
+const MIN_OUTLINE_SIZE: f32 = 4.0;
+
+// Overview of the whole scene, colored by the strongest attractor, with the part the view shows
+// outlined. Clicking it centers the view on the clicked point.
+pub struct Minimap {
+    camera: Camera,
+    texture: Texture2D,
+    // Scene::cache_key the overview was rendered for
+    scene_key: u64,
+}
+
+impl Minimap {
+    pub fn new(scene: &Scene) -> Self {
+        let camera = overview_camera(scene, MINIMAP_SIZE as u32);
+        let texture = texture_from_image(&render_strongest_attractor(scene, &camera));
+        Self { camera, texture, scene_key: scene.cache_key() }
+    }
+
+    // Renders the overview again if the scene has changed since
+    pub fn update(&mut self, scene: &Scene) {
+        if scene.cache_key() != self.scene_key {
+            *self = Minimap::new(scene);
+        }
+    }
+
+    // Top-left corner of the overview on screen, sitting on `bottom`
+    fn origin(bottom: f32) -> Vec2 {
+        Vec2::new(screen_width() - MINIMAP_SIZE - MINIMAP_MARGIN, bottom - MINIMAP_SIZE)
+    }
+
+    pub fn contains(&self, mouse: Vec2, bottom: f32) -> bool {
+        let p = mouse - Minimap::origin(bottom);
+        p.x >= 0.0 && p.x < MINIMAP_SIZE && p.y >= 0.0 && p.y < MINIMAP_SIZE
+    }
+
+    // World point under the mouse
+    pub fn world_at(&self, mouse: Vec2, bottom: f32) -> Vec2 {
+        self.camera.screen_to_world(mouse - Minimap::origin(bottom))
+    }
+
+    pub fn draw(&self, view: &Camera, bottom: f32) {
+        let origin = Minimap::origin(bottom);
+        draw_texture(&self.texture, origin.x, origin.y, WHITE);
+        draw_rectangle_lines(origin.x, origin.y, MINIMAP_SIZE, MINIMAP_SIZE, 1.0, GRAY);
+
+        // The view's corners in overview pixels, kept inside the overview when the view strays
+        // past it so the outline still points the way back
+        let top_left = self.camera.world_to_screen(view.screen_to_world(Vec2::new(0.0, 0.0)));
+        let bottom_right = self.camera.world_to_screen(view.screen_to_world(view.viewport));
+        let clamp = |p: Vec2| Vec2::new(p.x.clamp(0.0, MINIMAP_SIZE), p.y.clamp(0.0, MINIMAP_SIZE));
+        let (min, max) = (clamp(top_left), clamp(bottom_right));
+        let size = Vec2::new((max.x - min.x).max(MIN_OUTLINE_SIZE), (max.y - min.y).max(MIN_OUTLINE_SIZE));
+        let center = (min + max) / 2.0 + origin;
+        draw_rectangle_lines(center.x - size.x / 2.0, center.y - size.y / 2.0, size.x, size.y, 1.5, WHITE);
+    }
+}
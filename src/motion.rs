@@ -0,0 +1,154 @@
+use std::f64::consts::TAU;
+
+use serde::{Deserialize, Serialize};
+
+use crate::precise::DVec2;
+
+// Position a moving body passes through at time `t` of a keyframed path
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    pub t: f32,
+    pub x: f32,
+    pub y: f32,
+}
+
+// Prescribed path of a body, as an offset from its position in the scene as a function of the
+// simulated time since launch. Bodies on paths don't feel gravity themselves, they just drag
+// their field along, e.g. a moon circling a planet. In scene files:
+//
+//     [[motions]]
+//     body = 1
+//     type = "circle"
+//     radius = 120.0
+//     period = 8.0
+//
+// Angles are in degrees counterclockwise from +x as seen on screen, and positive periods turn
+// counterclockwise.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum BodyPath {
+    // Uniform motion around a circle centered on the body's position, starting `phase` around it
+    Circle {
+        radius: f32,
+        period: f32,
+        #[serde(default)]
+        phase: f32,
+    },
+    // Ellipse centered on the body's position with its major axis at `angle`, traced at a
+    // uniform rate of its parametric angle, starting at `phase`
+    Ellipse {
+        semi_major: f32,
+        semi_minor: f32,
+        period: f32,
+        #[serde(default)]
+        angle: f32,
+        #[serde(default)]
+        phase: f32,
+    },
+    // Straight lines between keyframes in order of time, holding the first and last offsets
+    // outside them, or starting over after the last if `repeat` is set
+    Keyframes {
+        keyframes: Vec<Keyframe>,
+        #[serde(default)]
+        repeat: bool,
+    },
+}
+
+impl BodyPath {
+    // Offset from the body's position at time `t`, in f64 so the double precision simulation
+    // gets the same path as the f32 one
+    pub fn offset(&self, t: f64) -> DVec2 {
+        match self {
+            BodyPath::Circle { radius, period, phase } => {
+                let angle = phase.to_radians() as f64 + TAU * t / *period as f64;
+                DVec2::new(angle.cos(), -angle.sin()) * *radius as f64
+            }
+            BodyPath::Ellipse { semi_major, semi_minor, period, angle, phase } => {
+                let parameter = phase.to_radians() as f64 + TAU * t / *period as f64;
+                let (along, across) = (*semi_major as f64 * parameter.cos(), *semi_minor as f64 * parameter.sin());
+                let tilt = angle.to_radians() as f64;
+                // Rotated counterclockwise on screen, where y points down
+                DVec2::new(along * tilt.cos() - across * tilt.sin(), -(along * tilt.sin() + across * tilt.cos()))
+            }
+            BodyPath::Keyframes { keyframes, repeat } => {
+                let (Some(first), Some(last)) = (keyframes.first(), keyframes.last()) else {
+                    return DVec2::new(0.0, 0.0);
+                };
+                let (start, end) = (first.t as f64, last.t as f64);
+                let t = if *repeat && end > start { start + (t - start).rem_euclid(end - start) } else { t };
+                let point = |keyframe: &Keyframe| DVec2::new(keyframe.x as f64, keyframe.y as f64);
+                let next = keyframes.partition_point(|keyframe| (keyframe.t as f64) <= t);
+                if next == 0 {
+                    return point(first);
+                }
+                if next == keyframes.len() {
+                    return point(last);
+                }
+                let (a, b) = (&keyframes[next - 1], &keyframes[next]);
+                let fraction = (t - a.t as f64) / (b.t as f64 - a.t as f64);
+                point(a) + (point(b) - point(a)) * fraction
+            }
+        }
+    }
+
+    // Velocity along the path at time `t`, by a central difference of the offset
+    pub fn velocity(&self, t: f64) -> DVec2 {
+        const STEP: f64 = 1e-4;
+        (self.offset(t + STEP) - self.offset(t - STEP)) / (2.0 * STEP)
+    }
+
+    // Points along one full trip around the path, or the whole of a keyframed one, for drawing it
+    pub fn outline(&self) -> Vec<DVec2> {
+        match self {
+            BodyPath::Circle { period, .. } | BodyPath::Ellipse { period, .. } => {
+                (0..=64).map(|i| self.offset(*period as f64 * i as f64 / 64.0)).collect()
+            }
+            BodyPath::Keyframes { keyframes, .. } => {
+                keyframes.iter().map(|keyframe| DVec2::new(keyframe.x as f64, keyframe.y as f64)).collect()
+            }
+        }
+    }
+
+    // Paths that make no sense, like a circle with no period, or None if it's fine
+    pub fn problem(&self) -> Option<String> {
+        match self {
+            BodyPath::Circle { period, .. } | BodyPath::Ellipse { period, .. } if *period == 0.0 => {
+                Some("period can't be zero".to_string())
+            }
+            BodyPath::Keyframes { keyframes, .. } if keyframes.is_empty() => Some("no keyframes".to_string()),
+            BodyPath::Keyframes { keyframes, .. } if keyframes.windows(2).any(|pair| pair[1].t <= pair[0].t) => {
+                Some("keyframe times must increase".to_string())
+            }
+            _ => None,
+        }
+    }
+
+    // Every number describing the path, tagged with its kind, for hashing scenes
+    pub fn parameters(&self) -> Vec<f32> {
+        match self {
+            BodyPath::Circle { radius, period, phase } => vec![1.0, *radius, *period, *phase],
+            BodyPath::Ellipse { semi_major, semi_minor, period, angle, phase } => {
+                vec![2.0, *semi_major, *semi_minor, *period, *angle, *phase]
+            }
+            BodyPath::Keyframes { keyframes, repeat } => [3.0, if *repeat { 1.0 } else { 0.0 }].into_iter()
+                .chain(keyframes.iter().flat_map(|keyframe| [keyframe.t, keyframe.x, keyframe.y]))
+                .collect(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BodyPath::Circle { .. } => "circle",
+            BodyPath::Ellipse { .. } => "ellipse",
+            BodyPath::Keyframes { .. } => "keyframes",
+        }
+    }
+}
+
+// Path followed by the body with index `body` in the scene
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BodyMotion {
+    pub body: usize,
+    #[serde(flatten)]
+    pub path: BodyPath,
+}
@@ -0,0 +1,227 @@
+use crate::physics::{calculate_acceleration, calculate_gravitational_force, Body, TestParticle, Vec2};
+use crate::scene::Scene;
+use crate::simulation::IntegrationMethod;
+
+// Acceleration of every body of a dynamic scene: the pull of every other body, shaped like it
+// pulls on the particle, plus the particle's own as a point of mass `particle_mass`. Bodies pass
+// straight through each other unless the scene has accretion.
+fn body_accelerations(bodies: &[Body], particle: &TestParticle, scene: &Scene) -> Vec<Vec2> {
+    let g = scene.gravitational_constant;
+    bodies.iter().enumerate().map(|(i, body)| {
+        let mut acceleration = mutual_acceleration(bodies, i, g);
+        let offset = particle.pos - body.pos;
+        let dist = offset.length();
+        if scene.particle_mass != 0.0 && dist > 0.0 {
+            acceleration += offset * (g * scene.particle_mass / (dist * dist * dist));
+        }
+        acceleration
+    }).collect()
+}
+
+// Pull of every other body on body `index`
+fn mutual_acceleration(bodies: &[Body], index: usize, g: f32) -> Vec2 {
+    let body = &bodies[index];
+    // Unit mass standing in for the body, so the force on it is its acceleration
+    let probe = TestParticle::new(body.pos, body.vel, 1.0, body.radius);
+    let mut acceleration = Vec2::new(0.0, 0.0);
+    for (j, other) in bodies.iter().enumerate() {
+        if index != j {
+            acceleration += calculate_gravitational_force(&probe, other, g);
+        }
+    }
+    acceleration
+}
+
+// Accelerations of the whole system with the bodies at the given state: the bodies' and the
+// particle's, the particle feeling everything it does in a stationary scene plus `extra`
+fn system_accelerations(
+    scene: &mut Scene,
+    bodies: &[Body],
+    particle: &TestParticle,
+    extra: &impl Fn(&TestParticle) -> Vec2,
+) -> (Vec<Vec2>, Vec2) {
+    scene.bodies.copy_from_slice(bodies);
+    let particle_acceleration = calculate_acceleration(particle, scene) + extra(particle);
+    (body_accelerations(bodies, particle, scene), particle_acceleration)
+}
+
+// Advances the bodies of a dynamic scene in place together with the particle by one substep of
+// length dt, as one system so the particle and the bodies see each other where they are at every
+// stage. Runge-Kutta 4 follows the system-aware integrator of the example explorer's
+// update_bodies; `extra` is any acceleration on the particle besides the scene's, e.g. thrust.
+// Bodies of an accreting scene that touch by the end of the step are merged.
+pub fn step_system(
+    scene: &mut Scene,
+    particle: &mut TestParticle,
+    integration_method: IntegrationMethod,
+    dt: f32,
+    extra: impl Fn(&TestParticle) -> Vec2,
+) {
+    let original_bodies = scene.bodies.clone();
+    let original_particle = *particle;
+    match integration_method {
+        IntegrationMethod::Euler => {
+            let (body_accelerations, acceleration) = system_accelerations(scene, &original_bodies, &original_particle, &extra);
+            for (body, body_acceleration) in scene.bodies.iter_mut().zip(body_accelerations) {
+                body.vel += body_acceleration * dt;
+                body.pos += body.vel * dt;
+            }
+            particle.vel += acceleration * dt;
+            particle.pos += particle.vel * dt;
+        }
+        IntegrationMethod::RungeKutta4 => {
+            let n = original_bodies.len();
+            let mut k_vel = [vec![Vec2::new(0.0, 0.0); n], vec![Vec2::new(0.0, 0.0); n], vec![Vec2::new(0.0, 0.0); n], vec![Vec2::new(0.0, 0.0); n]];
+            let mut k_pos = k_vel.clone();
+            let mut k_particle = [(Vec2::new(0.0, 0.0), Vec2::new(0.0, 0.0)); 4];
+            let mut temp_bodies = original_bodies.clone();
+            let mut temp_particle = original_particle;
+
+            // Stage k is evaluated at the state advanced by `fraction` of the previous stage's step
+            for (stage, fraction) in [0.0, 0.5, 0.5, 1.0].into_iter().enumerate() {
+                if stage > 0 {
+                    for (i, body) in temp_bodies.iter_mut().enumerate() {
+                        body.pos = original_bodies[i].pos + k_pos[stage - 1][i] * fraction;
+                        body.vel = original_bodies[i].vel + k_vel[stage - 1][i] * fraction;
+                    }
+                    let (k_vel_particle, k_pos_particle) = k_particle[stage - 1];
+                    temp_particle.time = original_particle.time + dt * fraction;
+                    temp_particle.pos = original_particle.pos + k_pos_particle * fraction;
+                    temp_particle.vel = original_particle.vel + k_vel_particle * fraction;
+                }
+                let (body_accelerations, acceleration) = system_accelerations(scene, &temp_bodies, &temp_particle, &extra);
+                for (i, body_acceleration) in body_accelerations.into_iter().enumerate() {
+                    k_vel[stage][i] = body_acceleration * dt;
+                    k_pos[stage][i] = temp_bodies[i].vel * dt;
+                }
+                k_particle[stage] = (acceleration * dt, temp_particle.vel * dt);
+            }
+
+            // Final update
+            for (i, body) in scene.bodies.iter_mut().enumerate() {
+                body.pos = original_bodies[i].pos + (k_pos[0][i] + k_pos[1][i] * 2.0 + k_pos[2][i] * 2.0 + k_pos[3][i]) / 6.0;
+                body.vel = original_bodies[i].vel + (k_vel[0][i] + k_vel[1][i] * 2.0 + k_vel[2][i] * 2.0 + k_vel[3][i]) / 6.0;
+            }
+            let [(v1, p1), (v2, p2), (v3, p3), (v4, p4)] = k_particle;
+            particle.vel = original_particle.vel + (v1 + v2 * 2.0 + v3 * 2.0 + v4) / 6.0;
+            particle.pos = original_particle.pos + (p1 + p2 * 2.0 + p3 * 2.0 + p4) / 6.0;
+        }
+    }
+    particle.time += dt;
+    if scene.accretion {
+        merge_touching_bodies(scene);
+    }
+}
+
+// Merges every pair of point bodies whose drawn disks overlap into one body at their center of
+// mass, with their combined mass, charge and momentum. The heavier of the two carries on with its
+// index, color and shape, and its disk grows to the pair's combined area, which for the default
+// radii is the radius of the combined mass.
+pub fn merge_touching_bodies(scene: &mut Scene) {
+    while let Some((i, j)) = touching_pair(&scene.bodies) {
+        if scene.merged_indices.is_empty() {
+            scene.merged_indices = (0..scene.bodies.len()).collect();
+        }
+        let (kept, absorbed) = if scene.bodies[j].mass > scene.bodies[i].mass { (j, i) } else { (i, j) };
+        let other = scene.bodies[absorbed];
+        let body = &mut scene.bodies[kept];
+        let mass = body.mass + other.mass;
+        if mass > 0.0 {
+            body.pos = (body.pos * body.mass + other.pos * other.mass) / mass;
+            body.vel = (body.vel * body.mass + other.vel * other.mass) / mass;
+        }
+        body.mass = mass;
+        body.charge += other.charge;
+        body.radius = (body.radius * body.radius + other.radius * other.radius).sqrt();
+        scene.bodies.remove(absorbed);
+        scene.merged_indices.remove(absorbed);
+    }
+}
+
+// First pair of point bodies whose disks overlap
+fn touching_pair(bodies: &[Body]) -> Option<(usize, usize)> {
+    (0..bodies.len()).flat_map(|i| (i + 1..bodies.len()).map(move |j| (i, j))).find(|&(i, j)| {
+        let (a, b) = (&bodies[i], &bodies[j]);
+        a.shape.is_point() && b.shape.is_point() && a.pos.distance(&b.pos) < a.radius + b.radius
+    })
+}
+
+// Merges the particle into body `index` of an accreting scene once it has come to rest there: the
+// body takes on its mass `particle_mass` and its momentum, and grows to match
+pub fn accrete_particle(scene: &mut Scene, index: usize, particle: &TestParticle) {
+    let particle_mass = scene.particle_mass;
+    if !scene.accretion || particle_mass <= 0.0 {
+        return;
+    }
+    let body = &mut scene.bodies[index];
+    let mass = body.mass + particle_mass;
+    if body.mass > 0.0 {
+        body.radius *= (mass / body.mass).sqrt();
+        body.pos = (body.pos * body.mass + particle.pos * particle_mass) / mass;
+        body.vel = (body.vel * body.mass + particle.vel * particle_mass) / mass;
+    }
+    body.mass = mass;
+}
+
+// Advances bodies that only pull on each other by one Runge-Kutta 4 step of length dt, with no
+// particle among them, as the stability explorer's three bodies do
+pub fn step_bodies(bodies: &mut [Body], g: f32, dt: f32) {
+    let original_bodies = bodies.to_vec();
+    let n = bodies.len();
+    let mut k_vel = [vec![Vec2::new(0.0, 0.0); n], vec![Vec2::new(0.0, 0.0); n], vec![Vec2::new(0.0, 0.0); n], vec![Vec2::new(0.0, 0.0); n]];
+    let mut k_pos = k_vel.clone();
+    let mut temp_bodies = original_bodies.clone();
+
+    for (stage, fraction) in [0.0, 0.5, 0.5, 1.0].into_iter().enumerate() {
+        if stage > 0 {
+            for (i, body) in temp_bodies.iter_mut().enumerate() {
+                body.pos = original_bodies[i].pos + k_pos[stage - 1][i] * fraction;
+                body.vel = original_bodies[i].vel + k_vel[stage - 1][i] * fraction;
+            }
+        }
+        for i in 0..n {
+            k_vel[stage][i] = mutual_acceleration(&temp_bodies, i, g) * dt;
+            k_pos[stage][i] = temp_bodies[i].vel * dt;
+        }
+    }
+
+    for (i, body) in bodies.iter_mut().enumerate() {
+        body.pos = original_bodies[i].pos + (k_pos[0][i] + k_pos[1][i] * 2.0 + k_pos[2][i] * 2.0 + k_pos[3][i]) / 6.0;
+        body.vel = original_bodies[i].vel + (k_vel[0][i] + k_vel[1][i] * 2.0 + k_vel[2][i] * 2.0 + k_vel[3][i]) / 6.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::G;
+
+    fn momentum(scene: &Scene) -> Vec2 {
+        scene.bodies.iter().fold(Vec2::new(0.0, 0.0), |sum, body| sum + body.vel * body.mass)
+    }
+
+    // Two bodies falling into each other past a third merge into one, keeping the scene's mass and
+    // momentum, and the merged body answers for the heavier one's original index
+    #[test]
+    fn accretion_conserves_mass_and_momentum() {
+        let bodies = vec![
+            Body::new(Vec2::new(500.0, 100.0), 1000.0, 10.0, [255, 0, 0]),
+            Body::new(Vec2::new(100.0, 300.0), 30000.0, 15.0, [0, 255, 0]).with_velocity(Vec2::new(40.0, 5.0)),
+            Body::new(Vec2::new(200.0, 300.0), 10000.0, 12.0, [0, 0, 255]).with_velocity(Vec2::new(-60.0, 0.0)),
+        ];
+        let mut scene = Scene { stationary: false, accretion: true, ..Scene::new(bodies, G) };
+        let mass: f32 = scene.bodies.iter().map(|body| body.mass).sum();
+        let initial_momentum = momentum(&scene);
+        let mut particle = scene.launch_particle(Vec2::new(500.0, 500.0), Vec2::new(0.0, 0.0));
+        for _ in 0..400 {
+            step_system(&mut scene, &mut particle, IntegrationMethod::RungeKutta4, 0.002, |_| Vec2::new(0.0, 0.0));
+        }
+
+        assert_eq!(scene.bodies.len(), 2);
+        assert_eq!(scene.merged_indices, vec![0, 1]);
+        assert_eq!(scene.bodies[1].color, [0, 255, 0]);
+        assert!((scene.bodies.iter().map(|body| body.mass).sum::<f32>() - mass).abs() < 1e-3);
+        let drift = momentum(&scene) - initial_momentum;
+        assert!(drift.length() < 1e-4 * initial_momentum.length(), "momentum drifted by {:?}", drift);
+    }
+}
@@ -0,0 +1,191 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+// Magic bytes at the start of every raw outcome grid file
+const GRID_MAGIC: &[u8; 4] = b"GWOG";
+// Marker stored in place of a body index for pixels that never collided
+const NO_COLLISION: u32 = u32::MAX;
+// Magic bytes before the optional escape shades at the end of a grid file
+const SHADES_MAGIC: &[u8; 4] = b"GWES";
+
+// Raw per-pixel simulation result of a basin map: which body was hit and at which timestep
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutcomeGrid {
+    pub width: u32,
+    pub height: u32,
+    // Number of timesteps each pixel was simulated for, i.e. the upper bound on collision times
+    pub timesteps: usize,
+    pub cells: Vec<Option<(usize, usize)>>,
+    // Shade of every pixel that never collided under the map's escape coloring, NaN for the
+    // others; empty when escaped pixels are colored uniformly
+    pub escape_shades: Vec<f32>,
+}
+
+impl OutcomeGrid {
+    pub fn new(width: u32, height: u32, timesteps: usize, cells: Vec<Option<(usize, usize)>>) -> Self {
+        assert_eq!(cells.len(), (width * height) as usize, "outcome grid size mismatch");
+        Self { width, height, timesteps, cells, escape_shades: Vec::new() }
+    }
+
+    pub fn with_escape_shades(self, escape_shades: Vec<f32>) -> Self {
+        assert_eq!(escape_shades.len(), self.cells.len(), "escape shade count mismatch");
+        Self { escape_shades, ..self }
+    }
+
+    // Shade of an escaped pixel, if the map tracked one
+    pub fn escape_shade(&self, px: u32, py: u32) -> Option<f32> {
+        if px < self.width && py < self.height {
+            self.escape_shades.get((py * self.width + px) as usize).copied().filter(|shade| !shade.is_nan())
+        } else {
+            None
+        }
+    }
+
+    pub fn get(&self, px: u32, py: u32) -> Option<(usize, usize)> {
+        if px < self.width && py < self.height {
+            self.cells[(py * self.width + px) as usize]
+        } else {
+            None
+        }
+    }
+
+    // Binary layout: magic, width, height, timesteps, then (body index, collision timestep) per pixel, all little-endian u32.
+    // Escape shades follow as a second magic and one little-endian f32 per pixel when present.
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(GRID_MAGIC)?;
+        writer.write_all(&self.width.to_le_bytes())?;
+        writer.write_all(&self.height.to_le_bytes())?;
+        writer.write_all(&(self.timesteps as u32).to_le_bytes())?;
+        for cell in &self.cells {
+            let (body, time) = match cell {
+                Some((body, time)) => (*body as u32, *time as u32),
+                None => (NO_COLLISION, 0),
+            };
+            writer.write_all(&body.to_le_bytes())?;
+            writer.write_all(&time.to_le_bytes())?;
+        }
+        if !self.escape_shades.is_empty() {
+            writer.write_all(SHADES_MAGIC)?;
+            for shade in &self.escape_shades {
+                writer.write_all(&shade.to_le_bytes())?;
+            }
+        }
+        writer.flush()
+    }
+
+    pub fn load(path: &str) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != GRID_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} is not an outcome grid", path)));
+        }
+
+        let width = read_u32(&mut reader)?;
+        let height = read_u32(&mut reader)?;
+        let timesteps = read_u32(&mut reader)? as usize;
+        // The header's size must fit the file, so a corrupt one can't overflow or allocate
+        // more than the file holds
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("{} has a corrupt grid size", path));
+        let num_cells = width.checked_mul(height).ok_or_else(invalid)? as usize;
+        let cells_len = (num_cells as u64).checked_mul(8).ok_or_else(invalid)?;
+        if cells_len > file_len.saturating_sub(16) {
+            return Err(invalid());
+        }
+        let mut cells = Vec::with_capacity(num_cells);
+        for _ in 0..num_cells {
+            let body = read_u32(&mut reader)?;
+            let time = read_u32(&mut reader)?;
+            cells.push(if body == NO_COLLISION { None } else { Some((body as usize, time as usize)) });
+        }
+
+        // Grids saved before escape shades existed simply end here
+        let mut escape_shades = Vec::new();
+        let mut magic = [0u8; 4];
+        if reader.read_exact(&mut magic).is_ok() && &magic == SHADES_MAGIC {
+            escape_shades.reserve(num_cells);
+            for _ in 0..num_cells {
+                escape_shades.push(f32::from_bits(read_u32(&mut reader)?));
+            }
+        }
+        Ok(Self { width, height, timesteps, cells, escape_shades })
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Path in the system temp directory that's unique to this test run
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("gravity-wells-{}-{}.gwog", std::process::id(), name))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn small_grid() -> OutcomeGrid {
+        OutcomeGrid::new(3, 2, 500, vec![Some((0, 12)), None, Some((2, 499)), None, Some((1, 0)), None])
+    }
+
+    #[test]
+    fn round_trips_without_escape_shades() {
+        let path = temp_path("plain");
+        let grid = small_grid();
+        grid.save(&path).unwrap();
+        let loaded = OutcomeGrid::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), grid);
+    }
+
+    #[test]
+    fn round_trips_with_escape_shades() {
+        let path = temp_path("shaded");
+        let shades = vec![f32::NAN, 0.25, f32::NAN, 1.0, f32::NAN, 0.0];
+        let grid = small_grid().with_escape_shades(shades.clone());
+        grid.save(&path).unwrap();
+        let loaded = OutcomeGrid::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+        // NaN never equals itself, so the shades are compared bit for bit
+        assert_eq!(loaded.cells, grid.cells);
+        assert_eq!((loaded.width, loaded.height, loaded.timesteps), (3, 2, 500));
+        let bits = |shades: &[f32]| shades.iter().map(|shade| shade.to_bits()).collect::<Vec<_>>();
+        assert_eq!(bits(&loaded.escape_shades), bits(&shades));
+    }
+
+    // Loads a file holding just a header with the given size and `payload` bytes of cells
+    fn load_header(name: &str, width: u32, height: u32, payload: usize) -> io::Result<OutcomeGrid> {
+        let path = temp_path(name);
+        let mut bytes = GRID_MAGIC.to_vec();
+        for value in [width, height, 500] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.resize(bytes.len() + payload, 0);
+        std::fs::write(&path, bytes).unwrap();
+        let loaded = OutcomeGrid::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        loaded
+    }
+
+    #[test]
+    fn rejects_overflowing_size() {
+        let error = load_header("overflow", u32::MAX, u32::MAX, 16).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_size_larger_than_file() {
+        let error = load_header("truncated", 1000, 1000, 16).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,55 @@
+use image::{ImageBuffer, Rgb, RgbImage};
+
+use crate::camera::Camera;
+use crate::config::{DEFAULT_NON_COLLISION_COLOR, IMAGE_SIZE};
+use crate::physics::Vec2;
+use crate::scene::Scene;
+
+// Room left around the bodies in the overview
+const OVERVIEW_PADDING: f32 = 1.5;
+// Share of the body color a pixel gets when its strongest pull barely beats the others
+const MIN_SHARE_INTENSITY: f32 = 0.25;
+
+// Square camera `size` pixels across framing every body, and at least the area the viewer starts
+// in, for the overview map
+pub fn overview_camera(scene: &Scene, size: u32) -> Camera {
+    let (min, max) = scene.bodies.iter().fold((Vec2::new(0.0, 0.0), Vec2::new(IMAGE_SIZE as f32, IMAGE_SIZE as f32)), |(min, max), body| {
+        let reach = Vec2::new(body.radius, body.radius);
+        let (low, high) = (body.pos - reach, body.pos + reach);
+        (Vec2::new(min.x.min(low.x), min.y.min(low.y)), Vec2::new(max.x.max(high.x), max.y.max(high.y)))
+    });
+    let center = (min + max) / 2.0;
+    let side = (max.x - min.x).max(max.y - min.y) * OVERVIEW_PADDING;
+    let zoom = size as f32 / side;
+    Camera::new(Vec2::new(side, side) / 2.0 - center, zoom, Vec2::new(size as f32, size as f32))
+}
+
+// Cheap stand-in for a basin map: every pixel takes the color of the body pulling hardest on a
+// particle at rest there, brighter the larger that body's share of the total pull. Bodies
+// themselves are drawn in full color.
+pub fn render_strongest_attractor(scene: &Scene, camera: &Camera) -> RgbImage {
+    let (width, height) = (camera.viewport.x as u32, camera.viewport.y as u32);
+    ImageBuffer::from_fn(width, height, |px, py| {
+        let p = camera.screen_to_world(Vec2::new(px as f32 + 0.5, py as f32 + 0.5));
+        let mut total = 0.0;
+        let mut strongest: Option<(usize, f32)> = None;
+        for (index, body) in scene.bodies.iter().enumerate() {
+            let distance = p.distance(&body.pos);
+            if distance <= body.radius {
+                return Rgb(body.color);
+            }
+            let pull = scene.gravitational_constant.abs() * body.mass.abs() / (distance * distance);
+            total += pull;
+            if strongest.is_none_or(|(_, most)| pull > most) {
+                strongest = Some((index, pull));
+            }
+        }
+        match strongest.filter(|_| total > 0.0) {
+            Some((index, pull)) => {
+                let intensity = MIN_SHARE_INTENSITY + (1.0 - MIN_SHARE_INTENSITY) * pull / total;
+                Rgb(scene.bodies[index].color.map(|c| (c as f32 * intensity) as u8))
+            }
+            None => Rgb(DEFAULT_NON_COLLISION_COLOR),
+        }
+    })
+}
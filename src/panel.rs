@@ -0,0 +1,155 @@
+use std::ops::Range;
+
+use macroquad::prelude::*;
+use macroquad::ui::{hash, root_ui, widgets, Id, Ui};
+use gravity_wells::physics::Vec2;
+use gravity_wells::simulation::{CollisionPolicy, SimulationParams};
+use gravity_wells::config::body_radius;
+use gravity_wells::scene::Scene;
+
+use crate::history::ViewSettings;
+use crate::input::mouse_position;
+use crate::keybindings::Keybindings;
+
+const PANEL_WIDTH: f32 = 300.0;
+const PANEL_HEIGHT: f32 = 420.0;
+const PANEL_MARGIN: f32 = 8.0;
+
+const INTEGRATORS: &[&str] = &["Euler", "Runge-Kutta 4"];
+// In the order of CollisionPolicy::ALL
+const COLLISION_POLICIES: &[&str] = &["World units", "Screen pixels", "Body radius"];
+
+// Which groups of settings were edited in the panel this frame
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PanelChanges {
+    pub velocity: bool,
+    pub camera: bool,
+    pub simulation: bool,
+    pub scene: bool,
+}
+
+impl PanelChanges {
+    pub fn any(&self) -> bool {
+        self.velocity || self.camera || self.simulation || self.scene
+    }
+
+    // The groups that differ between two sets of settings, for panel edits replayed from a recording
+    pub fn between(before: &ViewSettings, after: &ViewSettings) -> Self {
+        Self {
+            velocity: before.initial_velocity != after.initial_velocity || before.use_runge_kutta != after.use_runge_kutta,
+            camera: before.zoom_factor != after.zoom_factor || before.camera != after.camera,
+            simulation: before.sim_params != after.sim_params,
+            scene: before.scene != after.scene,
+        }
+    }
+}
+
+// Slider window for fine-tuning the parameters that are otherwise only reachable through key repeats
+pub struct ControlPanel {
+    pub visible: bool,
+}
+
+impl ControlPanel {
+    pub fn new() -> Self {
+        Self { visible: false }
+    }
+
+    // Tab toggles the panel
+    pub fn handle_input(&mut self, bindings: &Keybindings) {
+        if bindings.key_pressed(KeyCode::Tab) {
+            self.visible = !self.visible;
+        }
+    }
+
+    // Whether the mouse is over the panel, in which case clicks belong to the panel and not the map
+    pub fn wants_mouse(&self) -> bool {
+        self.visible && root_ui().is_mouse_over(mouse_position().into())
+    }
+
+    pub fn draw(
+        &self,
+        initial_velocity: &mut Vec2,
+        zoom_factor: &mut f32,
+        use_runge_kutta: &mut bool,
+        sim_params: &mut SimulationParams,
+        scene: &mut Scene,
+    ) -> PanelChanges {
+        let mut changes = PanelChanges::default();
+        if !self.visible {
+            return changes;
+        }
+
+        let position = vec2(screen_width() - PANEL_WIDTH - PANEL_MARGIN, PANEL_MARGIN);
+        widgets::Window::new(hash!(), position, vec2(PANEL_WIDTH, PANEL_HEIGHT))
+            .label("Control panel (Tab)")
+            .ui(&mut root_ui(), |ui| {
+                changes.velocity |= slider(ui, hash!(), "Velocity x", -100.0..100.0, &mut initial_velocity.x);
+                changes.velocity |= slider(ui, hash!(), "Velocity y", -100.0..100.0, &mut initial_velocity.y);
+                changes.camera |= slider(ui, hash!(), "Zoom", 0.1..10.0, zoom_factor);
+
+                let mut integrator = *use_runge_kutta as usize;
+                widgets::ComboBox::new(hash!(), INTEGRATORS).label("Integrator").ui(ui, &mut integrator);
+                if (integrator == 1) != *use_runge_kutta {
+                    *use_runge_kutta = integrator == 1;
+                    changes.velocity = true;
+                }
+
+                let mut timesteps = sim_params.timesteps as f32;
+                if slider(ui, hash!(), "Timesteps", 100.0..20000.0, &mut timesteps) {
+                    let timesteps = timesteps.round().max(1.0) as usize;
+                    if timesteps != sim_params.timesteps {
+                        sim_params.timesteps = timesteps;
+                        changes.simulation = true;
+                    }
+                }
+
+                let mut policy = CollisionPolicy::ALL.iter().position(|&policy| policy == sim_params.collision_policy).unwrap_or(0);
+                widgets::ComboBox::new(hash!(), COLLISION_POLICIES).label("Collision radius").ui(ui, &mut policy);
+                if CollisionPolicy::ALL[policy] != sim_params.collision_policy {
+                    sim_params.collision_policy = CollisionPolicy::ALL[policy];
+                    changes.simulation = true;
+                }
+
+                ui.separator();
+                changes.scene |= slider(ui, hash!(), "G", 1.0..500.0, &mut scene.gravitational_constant);
+                changes.scene |= slider(ui, hash!(), "Drag k1", 0.0..1.0, &mut scene.drag.linear);
+                changes.scene |= slider(ui, hash!(), "Drag k2", 0.0..0.01, &mut scene.drag.quadratic);
+                changes.scene |= slider(ui, hash!(), "Particle charge", -1.0..1.0, &mut scene.particle_charge);
+                changes.scene |= slider(ui, hash!(), "Magnetic field", -2.0..2.0, &mut scene.magnetic_field);
+                // Only relativistic scenes have a speed of light to change
+                if let Some(c) = &mut scene.speed_of_light {
+                    changes.scene |= slider(ui, hash!(), "Speed of light", 300.0..10000.0, c);
+                }
+                // Likewise only scenes that bounce particles have a restitution
+                if let Some(restitution) = &mut scene.restitution {
+                    changes.scene |= slider(ui, hash!(), "Restitution", 0.0..1.0, restitution);
+                }
+                changes.scene |= slider(ui, hash!(), "Field x", -50.0..50.0, &mut scene.external_field.acceleration.x);
+                changes.scene |= slider(ui, hash!(), "Field y", -50.0..50.0, &mut scene.external_field.acceleration.y);
+                for (i, body) in scene.bodies.iter_mut().enumerate() {
+                    let label = if body.shape.is_point() { format!("Body {}", i) } else { format!("Body {} ({})", i, body.shape.name()) };
+                    ui.tree_node(hash!("body", i), &label, |ui| {
+                        changes.scene |= slider(ui, hash!("body x", i), "x", -600.0..1200.0, &mut body.pos.x);
+                        changes.scene |= slider(ui, hash!("body y", i), "y", -600.0..1200.0, &mut body.pos.y);
+                        if slider(ui, hash!("body mass", i), "Mass", 0.0..200000.0, &mut body.mass) {
+                            body.radius = body_radius(body.mass);
+                            changes.scene = true;
+                        }
+                        changes.scene |= slider(ui, hash!("body charge", i), "Charge", -200000.0..200000.0, &mut body.charge);
+                        if body.shape.is_point() {
+                            changes.scene |= slider(ui, hash!("body j2", i), "J2", -0.5..0.5, &mut body.j2);
+                            changes.scene |= slider(ui, hash!("body pole", i), "Pole angle", -180.0..180.0, &mut body.pole_angle);
+                        }
+                    });
+                }
+            });
+        changes
+    }
+}
+
+// Slider that reports whether it changed the value
+fn slider(ui: &mut Ui, id: Id, label: &str, range: Range<f32>, value: &mut f32) -> bool {
+    let before = *value;
+    ui.slider(id, label, range, value);
+    *value != before
+}
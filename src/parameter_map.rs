@@ -0,0 +1,191 @@
+use std::borrow::Cow;
+use std::f32::consts::TAU;
+use std::sync::atomic::AtomicUsize;
+
+use image::{imageops, Rgb, RgbImage};
+use rayon::prelude::*;
+
+use crate::config::body_radius;
+use crate::image_gen::{count_pixel, render_outcome_grid};
+use crate::outcome::OutcomeGrid;
+use crate::physics::Vec2;
+use crate::render::{draw_text, fill_rect, text_width};
+use crate::scene::Scene;
+use crate::simulation::{run_simulation_with_time, IntegrationMethod, SimulationParams};
+
+// Room around a labeled map for the tick labels and axis names
+const AXIS_MARGIN: u32 = 8;
+const TICK_LENGTH: u32 = 4;
+const LABEL_SIZE: f32 = 13.0;
+// Tick marks along each axis, including both ends
+const TICKS: usize = 5;
+const BACKGROUND: [u8; 3] = [24, 24, 24];
+const LABEL_COLOR: [u8; 3] = [220, 220, 220];
+
+// What the pixel axes of a parameter map stand for. Unlike a basin map, where every pixel is a
+// different starting point, every pixel launches from the same point and only the launch itself
+// or the scene changes across the map.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParameterMap {
+    // Launch angle from 0 to 360 degrees across (from +x toward +y, as sweeps and animations
+    // measure it), speed from `max_speed` at the top down to `min_speed`: capture "shotgun" diagrams
+    Polar { start: Vec2, min_speed: f32, max_speed: f32 },
+    // Mass of body `bodies[0]` across from `x_masses.0` to `x_masses.1`, and of `bodies[1]` from
+    // `y_masses.1` at the top down to `y_masses.0`, for one fixed launch. The bodies stay where
+    // they are, with radii following their masses as in sweeps.
+    Masses { bodies: [usize; 2], x_masses: (f32, f32), y_masses: (f32, f32), start: Vec2, velocity: Vec2 },
+}
+
+// Range of one axis of a parameter map and what to call it
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParameterAxis {
+    pub name: String,
+    // Value at the left or top edge
+    pub from: f32,
+    // Value at the right or bottom edge
+    pub to: f32,
+}
+
+impl ParameterAxis {
+    fn value_at(&self, t: f32) -> f32 {
+        self.from + (self.to - self.from) * t
+    }
+}
+
+impl ParameterMap {
+    // Horizontal and vertical axis
+    pub fn axes(&self) -> (ParameterAxis, ParameterAxis) {
+        match *self {
+            ParameterMap::Polar { min_speed, max_speed, .. } => (
+                ParameterAxis { name: "angle (deg)".to_string(), from: 0.0, to: 360.0 },
+                ParameterAxis { name: "speed".to_string(), from: max_speed, to: min_speed },
+            ),
+            ParameterMap::Masses { bodies, x_masses, y_masses, .. } => (
+                ParameterAxis { name: format!("mass of body {}", bodies[0]), from: x_masses.0, to: x_masses.1 },
+                ParameterAxis { name: format!("mass of body {}", bodies[1]), from: y_masses.1, to: y_masses.0 },
+            ),
+        }
+    }
+
+    // Why the map can't be made for this scene, if it can't
+    pub fn problem(&self, scene: &Scene) -> Option<String> {
+        match *self {
+            ParameterMap::Polar { .. } => None,
+            ParameterMap::Masses { bodies, .. } => {
+                if let Some(body) = bodies.iter().find(|&&body| body >= scene.bodies.len()) {
+                    Some(format!("body {} does not exist, the scene has {} bodies", body, scene.bodies.len()))
+                } else if bodies[0] == bodies[1] {
+                    Some("the two axes need different bodies".to_string())
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    // Scene, start position and initial velocity of pixel (px, py) of a map `width` x `height`.
+    // The angle wraps around, so its last column stops one step short of 360 degrees.
+    pub fn launch_at<'a>(&self, scene: &'a Scene, px: u32, py: u32, width: u32, height: u32) -> (Cow<'a, Scene>, Vec2, Vec2) {
+        let (columns, rows) = self.axes();
+        let u = px as f32 / (width.max(2) - 1) as f32;
+        let v = py as f32 / (height.max(2) - 1) as f32;
+        match *self {
+            ParameterMap::Polar { start, .. } => {
+                let angle = TAU * px as f32 / width.max(1) as f32;
+                (Cow::Borrowed(scene), start, Vec2::new(angle.cos(), angle.sin()) * rows.value_at(v))
+            }
+            ParameterMap::Masses { bodies, start, velocity, .. } => {
+                let mut scene = scene.clone();
+                for (index, mass) in bodies.into_iter().zip([columns.value_at(u), rows.value_at(v)]) {
+                    let body = &mut scene.bodies[index];
+                    body.mass = mass;
+                    body.radius = body_radius(mass);
+                }
+                (Cow::Owned(scene), start, velocity)
+            }
+        }
+    }
+}
+
+// Simulates the launch of every pixel of a `width` x `height` parameter map, in the scene as that
+// pixel changes it
+pub fn compute_parameter_grid(
+    scene: &Scene,
+    map: &ParameterMap,
+    width: u32,
+    height: u32,
+    integration_method: IntegrationMethod,
+    simulation: &SimulationParams,
+    progress: &(impl Fn(usize, usize) + Sync),
+) -> OutcomeGrid {
+    let num_pixels = (width * height) as usize;
+    let counter = AtomicUsize::new(0);
+    let cells = (0..num_pixels).into_par_iter().map(|i| {
+        let (scene, start, velocity) = map.launch_at(scene, i as u32 % width, i as u32 / width, width, height);
+        let outcome = run_simulation_with_time(start, velocity, &scene, integration_method, simulation);
+        count_pixel(&counter, num_pixels, progress);
+        outcome
+    }).collect();
+    OutcomeGrid::new(width, height, simulation.timesteps, cells)
+}
+
+// Tick label of an axis value, with as few decimals as the range allows
+fn tick_label(axis: &ParameterAxis, value: f32) -> String {
+    if (axis.to - axis.from).abs() >= 10.0 {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+// The map colored like a basin map, framed with its axes
+pub fn render_labeled_map(grid: &OutcomeGrid, scene: &Scene, map: &ParameterMap) -> RgbImage {
+    let (columns, rows) = map.axes();
+    frame_with_axes(&render_outcome_grid(grid, &scene.bodies), &columns, &rows)
+}
+
+// A plot framed with ticks, values and axis names along the bottom and left edges
+pub fn frame_with_axes(plot: &RgbImage, columns: &ParameterAxis, rows: &ParameterAxis) -> RgbImage {
+    let ticks = |axis: &ParameterAxis| -> Vec<(f32, String)> {
+        (0..TICKS).map(|i| {
+            let t = i as f32 / (TICKS - 1) as f32;
+            (t, tick_label(axis, axis.value_at(t)))
+        }).collect()
+    };
+    let (column_ticks, row_ticks) = (ticks(columns), ticks(rows));
+
+    // The row values and axis name sit left of the map, the column values and name below it
+    let row_label_width = row_ticks.iter().map(|(_, label)| text_width(label, LABEL_SIZE)).fold(0.0, f32::max).ceil() as u32;
+    let line_height = LABEL_SIZE.ceil() as u32 + 2;
+    let left = AXIS_MARGIN + line_height + AXIS_MARGIN + row_label_width + TICK_LENGTH + 2;
+    let top = AXIS_MARGIN + line_height / 2;
+    let right_margin = AXIS_MARGIN + text_width(&column_ticks[TICKS - 1].1, LABEL_SIZE).ceil() as u32 / 2;
+    let bottom = TICK_LENGTH + 2 + line_height + AXIS_MARGIN / 2 + line_height + AXIS_MARGIN;
+    let (width, height) = plot.dimensions();
+    let mut img = RgbImage::from_pixel(left + width + right_margin, top + height + bottom, Rgb(BACKGROUND));
+    imageops::replace(&mut img, plot, left as i64, top as i64);
+
+    for (t, label) in &column_ticks {
+        let x = left as f32 + t * (width - 1) as f32;
+        fill_rect(&mut img, x as u32, top + height, 1, TICK_LENGTH, LABEL_COLOR, 1.0);
+        draw_text(&mut img, label, x - text_width(label, LABEL_SIZE) / 2.0, (top + height + TICK_LENGTH + 2) as f32, LABEL_SIZE, LABEL_COLOR);
+    }
+    for (t, label) in &row_ticks {
+        let y = top as f32 + t * (height - 1) as f32;
+        fill_rect(&mut img, left - TICK_LENGTH, y as u32, TICK_LENGTH, 1, LABEL_COLOR, 1.0);
+        let x = (left - TICK_LENGTH - 2) as f32 - text_width(label, LABEL_SIZE);
+        draw_text(&mut img, label, x, y - LABEL_SIZE / 2.0, LABEL_SIZE, LABEL_COLOR);
+    }
+
+    let column_name_x = left as f32 + (width as f32 - text_width(&columns.name, LABEL_SIZE)) / 2.0;
+    let column_name_y = top + height + TICK_LENGTH + 2 + line_height + AXIS_MARGIN / 2;
+    draw_text(&mut img, &columns.name, column_name_x, column_name_y as f32, LABEL_SIZE, LABEL_COLOR);
+    // The row name is drawn sideways, reading upward, by drawing it flat and rotating it into place
+    let name_width = text_width(&rows.name, LABEL_SIZE).ceil() as u32;
+    let mut name = RgbImage::from_pixel(name_width.max(1), line_height, Rgb(BACKGROUND));
+    draw_text(&mut name, &rows.name, 0.0, 0.0, LABEL_SIZE, LABEL_COLOR);
+    let name = imageops::rotate270(&name);
+    let name_y = top as i64 + (height as i64 - name.height() as i64) / 2;
+    imageops::replace(&mut img, &name, AXIS_MARGIN as i64, name_y);
+    img
+}
@@ -1,8 +1,19 @@
-use std::ops::{Add, Sub, Mul, Div};
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
 
-pub const G: f32 = 100.0; // Gravitational constant
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+use crate::force_field::BodyShape;
+use crate::precise::DVec2;
+use crate::scene::Scene;
+
+pub const G: f32 = 100.0; // Default gravitational constant
+// Default Coulomb constant, the same as G so a unit of charge pushes as hard as a unit of mass pulls
+pub const COULOMB_CONSTANT: f32 = 100.0;
+// Bounces after which a particle counts as having come to rest on the body it last hit. With a
+// restitution below 1 the bounces get ever smaller and closer together, so this ends them.
+pub const MAX_BOUNCES: u32 = 64;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
 pub struct Vec2 {
     pub x: f32,
     pub y: f32,
@@ -29,6 +40,44 @@ impl Vec2 {
     pub fn distance(&self, other: &Self) -> f32 {
         (*self - *other).length()
     }
+
+    pub fn dot(&self, other: &Self) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    // Point a fraction t of the way from self to other
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        Self {
+            x: self.x + (other.x - self.x) * t,
+            y: self.y + (other.y - self.y) * t,
+        }
+    }
+
+    // Rotated a quarter turn, from +x toward +y
+    pub fn perp(&self) -> Self {
+        Self { x: -self.y, y: self.x }
+    }
+
+    // Rotated by `angle` radians, from +x toward +y
+    pub fn rotate(&self, angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self { x: self.x * cos - self.y * sin, y: self.x * sin + self.y * cos }
+    }
+
+    // Direction in radians from +x toward +y, in (-π, π]
+    pub fn angle(&self) -> f32 {
+        self.y.atan2(self.x)
+    }
+
+    // Shortened to at most `max` long, keeping its direction
+    pub fn clamp_length(&self, max: f32) -> Self {
+        let len = self.length();
+        if len > max {
+            *self * (max / len)
+        } else {
+            *self
+        }
+    }
 }
 
 impl Add for Vec2 {
@@ -45,6 +94,25 @@ impl Sub for Vec2 {
     }
 }
 
+impl AddAssign for Vec2 {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Vec2 {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Neg for Vec2 {
+    type Output = Self;
+    fn neg(self) -> Self::Output {
+        Self { x: -self.x, y: -self.y }
+    }
+}
+
 impl Mul<f32> for Vec2 {
     type Output = Self;
     fn mul(self, rhs: f32) -> Self::Output {
@@ -59,17 +127,275 @@ impl Div<f32> for Vec2 {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
-pub struct StationaryBody {
+impl Mul<Vec2> for f32 {
+    type Output = Vec2;
+    fn mul(self, rhs: Vec2) -> Self::Output {
+        rhs * self
+    }
+}
+
+// The viewer draws with macroquad's own vector type
+impl From<Vec2> for macroquad::math::Vec2 {
+    fn from(v: Vec2) -> Self {
+        macroquad::math::Vec2::new(v.x, v.y)
+    }
+}
+
+impl From<macroquad::math::Vec2> for Vec2 {
+    fn from(v: macroquad::math::Vec2) -> Self {
+        Vec2::new(v.x, v.y)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Body {
     pub pos: Vec2,
     pub mass: f32,
     pub radius: f32,
     pub color: [u8; 3],
+    // Electric charge, repelling a particle of the same sign
+    #[serde(default)]
+    pub charge: f32,
+    // How the mass and charge are spread around `pos`; a point unless given
+    #[serde(default)]
+    pub shape: BodyShape,
+    // Oblateness J2 of a point body, flattened along a pole lying in the plane at `pole_angle`
+    // degrees counterclockwise from +x as seen on screen. 0 for a sphere.
+    #[serde(default)]
+    pub j2: f32,
+    #[serde(default)]
+    pub pole_angle: f32,
+    // Velocity of the body, which only moves in scenes whose bodies aren't stationary
+    #[serde(default)]
+    pub vel: Vec2,
 }
 
-impl StationaryBody {
+impl Body {
     pub fn new(pos: Vec2, mass: f32, radius: f32, color: [u8; 3]) -> Self {
-        Self { pos, mass, radius, color }
+        Self { pos, mass, radius, color, charge: 0.0, shape: BodyShape::Point, j2: 0.0, pole_angle: 0.0, vel: Vec2::new(0.0, 0.0) }
+    }
+
+    pub fn with_velocity(self, vel: Vec2) -> Self {
+        Self { vel, ..self }
+    }
+
+    pub fn with_charge(self, charge: f32) -> Self {
+        Self { charge, ..self }
+    }
+
+    pub fn with_shape(self, shape: BodyShape) -> Self {
+        Self { shape, ..self }
+    }
+
+    pub fn with_oblateness(self, j2: f32, pole_angle: f32) -> Self {
+        Self { j2, pole_angle, ..self }
+    }
+
+    // Whether the body's gravity has a J2 term, which only points do
+    pub fn is_oblate(&self) -> bool {
+        self.j2 != 0.0 && self.shape.is_point()
+    }
+
+    // Unit vector along the pole, y pointing down like the rest of the world
+    pub fn pole(&self) -> Vec2 {
+        let radians = self.pole_angle.to_radians();
+        Vec2::new(radians.cos(), -radians.sin())
+    }
+
+    // J2 part of the force of an oblate body pulling with `strength` (the numerator of the
+    // inverse-square law) on a particle at `pos`. With the body's radius R as the reference
+    // radius, its potential is strength·J2·R²·(3z² - r²) / 2r⁵ for z the particle's offset along
+    // the pole, weakening the pull over the poles and strengthening it around the equator.
+    pub fn oblateness_force(&self, strength: f32, pos: Vec2) -> Vec2 {
+        let offset = pos - self.pos;
+        let r2 = offset.x * offset.x + offset.y * offset.y;
+        if r2 == 0.0 {
+            return Vec2::new(0.0, 0.0);
+        }
+        let pole = self.pole();
+        let z = offset.x * pole.x + offset.y * pole.y;
+        let scale = strength * self.j2 * self.radius * self.radius / (r2 * r2 * r2.sqrt());
+        let radial = 1.5 - 7.5 * z * z / r2;
+        (pole * (3.0 * z) + offset * radial) * -scale
+    }
+
+    // Potential energy of the same J2 term at `pos`
+    pub fn oblateness_potential(&self, strength: f32, pos: Vec2) -> f32 {
+        let offset = pos - self.pos;
+        let r2 = offset.x * offset.x + offset.y * offset.y;
+        if r2 == 0.0 {
+            return 0.0;
+        }
+        let pole = self.pole();
+        let z = offset.x * pole.x + offset.y * pole.y;
+        strength * self.j2 * self.radius * self.radius * (3.0 * z * z - r2) / (2.0 * r2 * r2 * r2.sqrt())
+    }
+
+    // Distance from `pos` to the body, the nearest point of its shape for bodies that aren't points
+    pub fn distance(&self, pos: Vec2) -> f32 {
+        if self.shape.is_point() {
+            return pos.distance(&self.pos);
+        }
+        self.shape.distance((pos - self.pos).into()) as f32
+    }
+
+    // Force of a body of this shape pulling with `strength` (the numerator of the inverse-square
+    // law for a point) on a particle at `pos`, None for points
+    fn shaped_force(&self, strength: f32, pos: Vec2) -> Option<Vec2> {
+        self.shape.field_at(self.pos.into(), strength as f64, pos.into()).map(|force| force.to_vec2())
+    }
+}
+
+// Velocity-dependent damping of the test particle, an acceleration of -linear·v - quadratic·|v|·v
+// on top of gravity, like motion through a uniform atmosphere. Both default to 0, no damping.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Drag {
+    #[serde(default)]
+    pub linear: f32,
+    #[serde(default)]
+    pub quadratic: f32,
+}
+
+impl Drag {
+    pub fn new(linear: f32, quadratic: f32) -> Self {
+        Self { linear, quadratic }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.linear != 0.0 || self.quadratic != 0.0
+    }
+
+    // Whether the drag only ever takes energy away, which the escape bounds in early_outcome rely on
+    pub fn is_dissipative(&self) -> bool {
+        self.linear >= 0.0 && self.quadratic >= 0.0
+    }
+
+    pub fn acceleration(&self, vel: Vec2) -> Vec2 {
+        vel * -(self.linear + self.quadratic * vel.length())
+    }
+
+    // Adds the drag on a particle moving at `vel` to an acceleration. Leaves it untouched without
+    // drag, so scenes without it simulate exactly as before.
+    pub fn apply(&self, acceleration: Vec2, vel: Vec2) -> Vec2 {
+        if self.is_active() {
+            acceleration + self.acceleration(vel)
+        } else {
+            acceleration
+        }
+    }
+}
+
+// Background field the wells sit in, like the pull of a distant massive body they orbit: a
+// constant acceleration plus a linear tidal term tidal·(pos - origin), with the symmetric tidal
+// tensor stored as [xx, xy, yy]. All zero by default, no field.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExternalField {
+    pub acceleration: Vec2,
+    pub tidal: [f32; 3],
+    pub origin: Vec2,
+}
+
+impl Default for ExternalField {
+    fn default() -> Self {
+        Self { acceleration: Vec2::new(0.0, 0.0), tidal: [0.0; 3], origin: Vec2::new(0.0, 0.0) }
+    }
+}
+
+impl ExternalField {
+    pub fn is_active(&self) -> bool {
+        self.acceleration != Vec2::new(0.0, 0.0) || self.tidal != [0.0; 3]
+    }
+
+    pub fn acceleration(&self, pos: Vec2) -> Vec2 {
+        let [xx, xy, yy] = self.tidal;
+        let d = pos - self.origin;
+        Vec2::new(self.acceleration.x + xx * d.x + xy * d.y, self.acceleration.y + xy * d.x + yy * d.y)
+    }
+
+    // Potential energy per unit mass the field's acceleration is the downhill gradient of
+    pub fn potential(&self, pos: Vec2) -> f32 {
+        let [xx, xy, yy] = self.tidal;
+        let d = pos - self.origin;
+        -(self.acceleration.x * d.x + self.acceleration.y * d.y) - 0.5 * (xx * d.x * d.x + 2.0 * xy * d.x * d.y + yy * d.y * d.y)
+    }
+
+    // Adds the field at `pos` to an acceleration, leaving it untouched without a field as Drag::apply does
+    pub fn apply(&self, acceleration: Vec2, pos: Vec2) -> Vec2 {
+        if self.is_active() {
+            acceleration + self.acceleration(pos)
+        } else {
+            acceleration
+        }
+    }
+}
+
+// What a particle reaching a wall does
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WallKind {
+    // Bounces back elastically
+    #[default]
+    Reflecting,
+    // Comes back in through the opposite wall, making the box a torus
+    Periodic,
+}
+
+// Box from `min` to `max` the particle is kept inside, so it can't drift off to infinity. With
+// periodic walls every body pulls on the particle from the image of it nearest the particle, and
+// collides with it there.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Walls {
+    pub min: Vec2,
+    pub max: Vec2,
+    #[serde(default)]
+    pub kind: WallKind,
+}
+
+impl Walls {
+    pub fn is_periodic(&self) -> bool {
+        self.kind == WallKind::Periodic
+    }
+
+    // Boxes that make no sense, or None if it's fine
+    pub fn problem(&self) -> Option<String> {
+        (self.max.x <= self.min.x || self.max.y <= self.min.y).then(|| "walls max must be above min on both axes".to_string())
+    }
+
+    // Position and velocity of a particle at `pos` moving at `vel` brought back inside the box,
+    // reflected off or wrapped through each wall it went past. Kept in f64 for the double
+    // precision simulation, which these are exact for inside the box.
+    pub fn fold(&self, pos: DVec2, vel: DVec2) -> (DVec2, DVec2) {
+        let (x, vx) = self.fold_axis(pos.x, vel.x, self.min.x as f64, self.max.x as f64);
+        let (y, vy) = self.fold_axis(pos.y, vel.y, self.min.y as f64, self.max.y as f64);
+        (DVec2::new(x, y), DVec2::new(vx, vy))
+    }
+
+    fn fold_axis(&self, pos: f64, vel: f64, min: f64, max: f64) -> (f64, f64) {
+        if (min..=max).contains(&pos) {
+            return (pos, vel);
+        }
+        match self.kind {
+            WallKind::Periodic => (min + (pos - min).rem_euclid(max - min), vel),
+            // Clamped too, in case it started further outside than the box is wide
+            WallKind::Reflecting => ((if pos < min { 2.0 * min - pos } else { 2.0 * max - pos }).clamp(min, max), -vel),
+        }
+    }
+
+    pub fn confine(&self, particle: &mut TestParticle) {
+        let (pos, vel) = self.fold(particle.pos.into(), particle.vel.into());
+        particle.pos = pos.to_vec2();
+        particle.vel = vel.to_vec2();
+    }
+
+    // Image of `pos` nearest to `from` under periodic walls, `pos` itself with reflecting ones
+    pub fn nearest_image(&self, pos: DVec2, from: DVec2) -> DVec2 {
+        if !self.is_periodic() {
+            return pos;
+        }
+        let (width, height) = ((self.max.x - self.min.x) as f64, (self.max.y - self.min.y) as f64);
+        let offset = pos - from;
+        DVec2::new(pos.x - width * (offset.x / width).round(), pos.y - height * (offset.y / height).round())
     }
 }
 
@@ -79,78 +405,234 @@ pub struct TestParticle {
     pub vel: Vec2,
     pub mass: f32,
     pub radius: f32,
+    pub charge: f32,
+    // Simulated time since launch, advanced by the integrators for time-dependent forces
+    pub time: f32,
+    // Times the particle has bounced off a body, in scenes with a restitution
+    pub bounces: u32,
 }
 
 impl TestParticle {
     pub fn new(pos: Vec2, vel: Vec2, mass: f32, radius: f32) -> Self {
-        Self { pos, vel, mass, radius }
+        Self { pos, vel, mass, radius, charge: 0.0, time: 0.0, bounces: 0 }
+    }
+
+    pub fn with_charge(self, charge: f32) -> Self {
+        Self { charge, ..self }
+    }
+}
+
+pub fn calculate_gravitational_force(particle: &TestParticle, stationary_body: &Body, g: f32) -> Vec2 {
+    if let Some(force) = stationary_body.shaped_force(g * particle.mass * stationary_body.mass, particle.pos) {
+        return force;
     }
+    let dir = stationary_body.pos - particle.pos;
+    let dist = dir.length();
+    if dist == 0.0 {
+        return Vec2::new(0.0, 0.0);
+    }
+    let force_mag = g * particle.mass * stationary_body.mass / (dist * dist);
+    let force = dir.normalize() * force_mag;
+    if stationary_body.j2 != 0.0 {
+        return force + stationary_body.oblateness_force(g * particle.mass * stationary_body.mass, particle.pos);
+    }
+    force
 }
 
-pub fn calculate_gravitational_force(particle: &TestParticle, stationary_body: &StationaryBody) -> Vec2 {
+// Electrostatic force on the particle from a body, repulsive for charges of the same sign
+pub fn calculate_coulomb_force(particle: &TestParticle, stationary_body: &Body, k: f32) -> Vec2 {
+    if let Some(force) = stationary_body.shaped_force(-k * particle.charge * stationary_body.charge, particle.pos) {
+        return force;
+    }
     let dir = stationary_body.pos - particle.pos;
     let dist = dir.length();
     if dist == 0.0 {
         return Vec2::new(0.0, 0.0);
     }
-    let force_mag = G * particle.mass * stationary_body.mass / (dist * dist);
+    let force_mag = -k * particle.charge * stationary_body.charge / (dist * dist);
     dir.normalize() * force_mag
 }
 
-pub fn calculate_acceleration(particle: &TestParticle, stationary_bodies: &[StationaryBody]) -> Vec2 {
+// Lorentz force q·v×B on the moving particle from a uniform magnetic field of strength `b`
+// perpendicular to the plane. It turns the particle without changing its speed, counterclockwise
+// on screen for positive q·b.
+pub fn calculate_magnetic_force(particle: &TestParticle, b: f32) -> Vec2 {
+    let qb = particle.charge * b;
+    Vec2::new(particle.vel.y * qb, -particle.vel.x * qb)
+}
+
+// First post-Newtonian correction to the particle's acceleration in the static field of the
+// bodies, from their Newtonian pull `gravity` (the gradient of U) and potential U = -GM/r summed
+// over them, as returned by jacobi::bodies_potential: ((v² - 4U)∇U - 4(v·∇U)v) / c² with U taken
+// positive. Around a single body this is the Schwarzschild geometry to first order in 1/c², which
+// turns an orbit's perihelion forward by 6πGM/(c²a) every revolution.
+pub fn calculate_post_newtonian_acceleration(particle: &TestParticle, gravity: Vec2, potential: f32, c: f32) -> Vec2 {
+    let u = -potential;
+    let v2 = particle.vel.x * particle.vel.x + particle.vel.y * particle.vel.y;
+    let v_gravity = particle.vel.x * gravity.x + particle.vel.y * gravity.y;
+    (gravity * (v2 - 4.0 * u) - particle.vel * (4.0 * v_gravity)) / (c * c)
+}
+
+pub fn calculate_acceleration(particle: &TestParticle, scene: &Scene) -> Vec2 {
+    let bodies = scene.bodies_around(particle);
     let mut total_force = Vec2::new(0.0, 0.0);
-    for body in stationary_bodies {
-        total_force = total_force + calculate_gravitational_force(particle, body);
+    for body in bodies.iter() {
+        total_force += calculate_gravitational_force(particle, body, scene.gravitational_constant);
+    }
+    // Taken from gravity alone, before the other forces join it
+    let post_newtonian = scene.speed_of_light.map(|c| {
+        calculate_post_newtonian_acceleration(particle, total_force / particle.mass, crate::jacobi::bodies_potential(particle.pos, &bodies, scene.gravitational_constant), c)
+    });
+    // Uncharged scenes skip the Coulomb term entirely, so they simulate exactly as before
+    if particle.charge != 0.0 && scene.has_charged_bodies() {
+        for body in bodies.iter() {
+            total_force += calculate_coulomb_force(particle, body, scene.coulomb_constant);
+        }
+    }
+    if particle.charge != 0.0 && scene.magnetic_field != 0.0 {
+        total_force += calculate_magnetic_force(particle, scene.magnetic_field);
     }
-    total_force / particle.mass
+    let acceleration = match post_newtonian {
+        Some(correction) => total_force / particle.mass + correction,
+        None => total_force / particle.mass,
+    };
+    apply_surroundings(acceleration, particle, scene)
+}
+
+// Adds what acts on the particle besides the bodies to their acceleration: drag, the external
+// field and any scripted force
+pub fn apply_surroundings(acceleration: Vec2, particle: &TestParticle, scene: &Scene) -> Vec2 {
+    let acceleration = scene.drag.apply(acceleration, particle.vel);
+    let acceleration = scene.external_field.apply(acceleration, particle.pos);
+    match scene.field_acceleration(particle) {
+        Some(field) => acceleration + field,
+        None => acceleration,
+    }
+}
+
+// Kinetic plus gravitational and electrostatic potential energy of the particle, conserved by the
+// exact dynamics unless the scene has drag, a scripted force or moving bodies. A magnetic field does
+// no work, so it conserves it too, and the external field and oblate bodies add their own potential.
+pub fn total_energy(particle: &TestParticle, scene: &Scene) -> f32 {
+    let kinetic = 0.5 * particle.mass * (particle.vel.x * particle.vel.x + particle.vel.y * particle.vel.y);
+    let bodies = scene.bodies_around(particle);
+    let potential: f32 = bodies.iter()
+        .map(|body| {
+            let strength = scene.gravitational_constant * particle.mass * body.mass - scene.coulomb_constant * particle.charge * body.charge;
+            if let Some(potential) = body.shape.potential_at(body.pos.into(), strength as f64, particle.pos.into()) {
+                return potential as f32;
+            }
+            let dist = particle.pos.distance(&body.pos);
+            let oblateness = if body.j2 != 0.0 {
+                body.oblateness_potential(scene.gravitational_constant * particle.mass * body.mass, particle.pos)
+            } else {
+                0.0
+            };
+            if dist == 0.0 {
+                0.0
+            } else {
+                (-scene.gravitational_constant * particle.mass * body.mass + scene.coulomb_constant * particle.charge * body.charge) / dist + oblateness
+            }
+        })
+        .sum();
+    let external = if scene.external_field.is_active() {
+        particle.mass * scene.external_field.potential(particle.pos)
+    } else {
+        0.0
+    };
+    // The energy the post-Newtonian dynamics conserve instead, to the same order:
+    // (3v⁴/8 + 3Uv²/2 + U²/2) / c² on top per unit mass
+    let post_newtonian = match scene.speed_of_light {
+        Some(c) => {
+            let u = -crate::jacobi::bodies_potential(particle.pos, &bodies, scene.gravitational_constant);
+            let v2 = particle.vel.x * particle.vel.x + particle.vel.y * particle.vel.y;
+            particle.mass * (0.375 * v2 * v2 + 1.5 * u * v2 + 0.5 * u * u) / (c * c)
+        }
+        None => 0.0,
+    };
+    kinetic + potential + external + post_newtonian
 }
 
 // Simple Euler integration
-pub fn update_particle_euler(particle: &mut TestParticle, stationary_bodies: &[StationaryBody], dt: f32) {
-    let acceleration = calculate_acceleration(particle, stationary_bodies);
-    particle.vel = particle.vel + acceleration * dt;
-    particle.pos = particle.pos + particle.vel * dt;
+pub fn update_particle_euler(particle: &mut TestParticle, scene: &Scene, dt: f32) {
+    euler_step(particle, dt, |p| calculate_acceleration(p, scene));
 }
 
 // Runge-Kutta 4th order integration for better accuracy
-pub fn update_particle_rk4(particle: &mut TestParticle, stationary_bodies: &[StationaryBody], dt: f32) {
+pub fn update_particle_rk4(particle: &mut TestParticle, scene: &Scene, dt: f32) {
+    rk4_step(particle, dt, |p| calculate_acceleration(p, scene));
+}
+
+// Euler step with any acceleration, e.g. an approximation for scenes with many bodies
+pub fn euler_step(particle: &mut TestParticle, dt: f32, acceleration: impl Fn(&TestParticle) -> Vec2) {
+    let acceleration = acceleration(particle);
+    particle.vel += acceleration * dt;
+    particle.pos += particle.vel * dt;
+    particle.time += dt;
+}
+
+// RK4 step with any acceleration
+pub fn rk4_step(particle: &mut TestParticle, dt: f32, acceleration: impl Fn(&TestParticle) -> Vec2) {
     let original_particle = *particle;
     
     // k1
-    let k1_vel = calculate_acceleration(&original_particle, stationary_bodies) * dt;
+    let k1_vel = acceleration(&original_particle) * dt;
     let k1_pos = original_particle.vel * dt;
     
     // k2
     let mut temp_particle = original_particle;
-    temp_particle.pos = temp_particle.pos + k1_pos * 0.5;
-    temp_particle.vel = temp_particle.vel + k1_vel * 0.5;
-    let k2_vel = calculate_acceleration(&temp_particle, stationary_bodies) * dt;
+    temp_particle.time = original_particle.time + dt * 0.5;
+    temp_particle.pos += k1_pos * 0.5;
+    temp_particle.vel += k1_vel * 0.5;
+    let k2_vel = acceleration(&temp_particle) * dt;
     let k2_pos = temp_particle.vel * dt;
     
     // k3
     temp_particle = original_particle;
-    temp_particle.pos = temp_particle.pos + k2_pos * 0.5;
-    temp_particle.vel = temp_particle.vel + k2_vel * 0.5;
-    let k3_vel = calculate_acceleration(&temp_particle, stationary_bodies) * dt;
+    temp_particle.time = original_particle.time + dt * 0.5;
+    temp_particle.pos += k2_pos * 0.5;
+    temp_particle.vel += k2_vel * 0.5;
+    let k3_vel = acceleration(&temp_particle) * dt;
     let k3_pos = temp_particle.vel * dt;
     
     // k4
     temp_particle = original_particle;
-    temp_particle.pos = temp_particle.pos + k3_pos;
-    temp_particle.vel = temp_particle.vel + k3_vel;
-    let k4_vel = calculate_acceleration(&temp_particle, stationary_bodies) * dt;
+    temp_particle.time = original_particle.time + dt;
+    temp_particle.pos += k3_pos;
+    temp_particle.vel += k3_vel;
+    let k4_vel = acceleration(&temp_particle) * dt;
     let k4_pos = temp_particle.vel * dt;
     
     // Final update
-    particle.vel = particle.vel + (k1_vel + k2_vel * 2.0 + k3_vel * 2.0 + k4_vel) / 6.0;
-    particle.pos = particle.pos + (k1_pos + k2_pos * 2.0 + k3_pos * 2.0 + k4_pos) / 6.0;
+    particle.vel += (k1_vel + k2_vel * 2.0 + k3_vel * 2.0 + k4_vel) / 6.0;
+    particle.pos += (k1_pos + k2_pos * 2.0 + k3_pos * 2.0 + k4_pos) / 6.0;
+    particle.time += dt;
 }
 
-pub fn check_collision(particle: &TestParticle, stationary_bodies: &[StationaryBody], collision_threshold: f32) -> Option<usize> {
+pub fn check_collision(particle: &TestParticle, stationary_bodies: &[Body], collision_threshold: f32) -> Option<usize> {
     for (i, body) in stationary_bodies.iter().enumerate() {
-        if particle.pos.distance(&body.pos) < collision_threshold {
+        if body.distance(particle.pos) < collision_threshold {
             return Some(i);
         }
     }
     None
 }
+
+// Bounces the particle off a body moving at `body_vel` whose collision radius it came within. The
+// part of its velocity relative to the body heading into the surface is reversed and scaled by
+// the coefficient of restitution, and the particle is put back out on the collision radius so it
+// doesn't hit again straight away. Returns whether it came to rest on the body instead, after
+// MAX_BOUNCES bounces or on any contact with a restitution of 0, which then captures particles
+// just as without bounces.
+pub fn bounce(particle: &mut TestParticle, body: &Body, body_vel: Vec2, restitution: f32, collision_threshold: f32) -> bool {
+    let (point, normal) = body.shape.surface((particle.pos - body.pos).into());
+    let normal = normal.to_vec2();
+    particle.pos = body.pos + point.to_vec2() + normal * collision_threshold;
+    let relative = particle.vel - body_vel;
+    let approach = relative.x * normal.x + relative.y * normal.y;
+    if approach < 0.0 {
+        particle.vel -= normal * ((1.0 + restitution) * approach);
+    }
+    particle.bounces += 1;
+    restitution == 0.0 || particle.bounces >= MAX_BOUNCES
+}
@@ -0,0 +1,135 @@
+use macroquad::prelude::*;
+
+// Space around the plot area inside the panel: the title above, tick labels left and below
+const TITLE_HEIGHT: f32 = 24.0;
+const LEFT_MARGIN: f32 = 48.0;
+const BOTTOM_MARGIN: f32 = 20.0;
+const RIGHT_MARGIN: f32 = 10.0;
+const LABEL_SIZE: f32 = 14.0;
+const GRID_COLOR: Color = Color::new(1.0, 1.0, 1.0, 0.15);
+
+// Small line plot drawn on a translucent panel, mapping data coordinates to the screen
+pub struct Plot {
+    // Panel bounds on screen
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    x_range: (f32, f32),
+    y_range: (f32, f32),
+}
+
+impl Plot {
+    // Draws the panel with its title; ranges with no extent are widened so nothing divides by zero
+    pub fn new(x: f32, y: f32, width: f32, height: f32, x_range: (f32, f32), y_range: (f32, f32), title: &str) -> Self {
+        let widen = |(min, max): (f32, f32)| if max - min > f32::EPSILON { (min, max) } else { (min - 1.0, max + 1.0) };
+        draw_rectangle(x, y, width, height, Color::new(0.0, 0.0, 0.0, 0.75));
+        draw_rectangle_lines(x, y, width, height, 1.0, GRAY);
+        draw_text(title, x + 6.0, y + 16.0, 16.0, WHITE);
+        Self { x, y, width, height, x_range: widen(x_range), y_range: widen(y_range) }
+    }
+
+    fn plot_left(&self) -> f32 {
+        self.x + LEFT_MARGIN
+    }
+
+    fn plot_width(&self) -> f32 {
+        self.width - LEFT_MARGIN - RIGHT_MARGIN
+    }
+
+    fn plot_top(&self) -> f32 {
+        self.y + TITLE_HEIGHT
+    }
+
+    fn plot_height(&self) -> f32 {
+        self.height - TITLE_HEIGHT - BOTTOM_MARGIN
+    }
+
+    pub fn to_screen(&self, x: f32, y: f32) -> Vec2 {
+        let fx = (x - self.x_range.0) / (self.x_range.1 - self.x_range.0);
+        let fy = (y - self.y_range.0) / (self.y_range.1 - self.y_range.0);
+        vec2(self.plot_left() + fx * self.plot_width(), self.plot_top() + (1.0 - fy) * self.plot_height())
+    }
+
+    // Horizontal gridlines with labels left of the plot
+    pub fn y_ticks(&self, values: &[f32], label: impl Fn(f32) -> String) {
+        for &value in values {
+            let line_y = self.to_screen(self.x_range.0, value).y;
+            draw_line(self.plot_left(), line_y, self.plot_left() + self.plot_width(), line_y, 1.0, GRID_COLOR);
+            draw_text(&label(value), self.x + 4.0, line_y + 4.0, LABEL_SIZE, GRAY);
+        }
+    }
+
+    // Vertical gridlines with labels below the plot
+    pub fn x_ticks(&self, values: &[f32], label: impl Fn(f32) -> String) {
+        let bottom = self.plot_top() + self.plot_height();
+        for &value in values {
+            let line_x = self.to_screen(value, self.y_range.0).x;
+            draw_line(line_x, self.plot_top(), line_x, bottom, 1.0, GRID_COLOR);
+            let text = label(value);
+            let text_width = measure_text(&text, None, LABEL_SIZE as u16, 1.0).width;
+            draw_text(&text, line_x - text_width / 2.0, bottom + 14.0, LABEL_SIZE, GRAY);
+        }
+    }
+
+    // Ticks at the ends of both ranges and at zero when it lies inside them
+    pub fn default_ticks(&self) {
+        let ticks = |(min, max): (f32, f32)| {
+            let mut values = vec![min, max];
+            if min < 0.0 && max > 0.0 {
+                values.push(0.0);
+            }
+            values
+        };
+        self.x_ticks(&ticks(self.x_range), format_tick);
+        self.y_ticks(&ticks(self.y_range), format_tick);
+    }
+
+    // Text in the bottom right corner of the plot area, e.g. the axis names
+    pub fn corner_label(&self, text: &str, color: Color) {
+        let text_width = measure_text(text, None, LABEL_SIZE as u16, 1.0).width;
+        draw_text(text, self.x + self.width - RIGHT_MARGIN - text_width, self.y + 16.0, LABEL_SIZE, color);
+    }
+
+    // Text on the `row`th line below the title, inside the plot area
+    pub fn legend(&self, row: usize, text: &str, color: Color) {
+        draw_text(text, self.plot_left() + 4.0, self.plot_top() + 14.0 * (row as f32 + 1.0), LABEL_SIZE, color);
+    }
+
+    pub fn line(&self, points: impl IntoIterator<Item = (f32, f32)>, thickness: f32, color: Color) {
+        let mut previous: Option<Vec2> = None;
+        for (x, y) in points {
+            let point = self.to_screen(x, y);
+            if let Some(previous) = previous {
+                draw_line(previous.x, previous.y, point.x, point.y, thickness, color);
+            }
+            previous = Some(point);
+        }
+    }
+
+    pub fn marker(&self, x: f32, y: f32, color: Color) {
+        let point = self.to_screen(x, y);
+        draw_circle(point.x, point.y, 3.0, color);
+    }
+}
+
+// Smallest and largest value, padded by a tenth of the span on both sides
+pub fn padded_range(values: impl IntoIterator<Item = f32>) -> (f32, f32) {
+    let (min, max) = values.into_iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), v| (min.min(v), max.max(v)));
+    if min > max {
+        return (0.0, 1.0);
+    }
+    let padding = (max - min) * 0.1;
+    (min - padding, max + padding)
+}
+
+fn format_tick(value: f32) -> String {
+    if value == 0.0 {
+        "0".to_string()
+    } else if value.abs() >= 1e4 || value.abs() < 1e-2 {
+        format!("{:.1e}", value)
+    } else {
+        format!("{:.1}", value)
+    }
+}
+
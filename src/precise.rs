@@ -0,0 +1,358 @@
+use std::ops::{Add, Div, Mul, Sub};
+
+use crate::force_field::{BodyShape, ForceField, ScriptedForce};
+use crate::motion::BodyPath;
+use crate::physics::{Vec2, Walls, MAX_BOUNCES};
+use crate::scene::Scene;
+use crate::simulation::{run_simulation_with_time, IntegrationMethod, SimulationParams, TEST_PARTICLE_MASS};
+
+// Double precision counterpart of the physics core, used for map pixels when
+// SimulationParams::double_precision is set. Deep into a zoom neighbouring pixels are closer
+// together than f32 can resolve, so they collapse onto the same start position and the basin
+// boundaries turn blocky.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DVec2 {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl DVec2 {
+    pub fn new(x: f64, y: f64) -> Self {
+        DVec2 { x, y }
+    }
+
+    pub fn length(&self) -> f64 {
+        (self.x * self.x + self.y * self.y).sqrt()
+    }
+
+    pub fn distance(&self, other: &Self) -> f64 {
+        (*self - *other).length()
+    }
+
+    pub fn to_vec2(self) -> Vec2 {
+        Vec2::new(self.x as f32, self.y as f32)
+    }
+}
+
+impl From<Vec2> for DVec2 {
+    fn from(v: Vec2) -> Self {
+        DVec2::new(v.x as f64, v.y as f64)
+    }
+}
+
+impl Add for DVec2 {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output {
+        Self { x: self.x + rhs.x, y: self.y + rhs.y }
+    }
+}
+
+impl Sub for DVec2 {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self { x: self.x - rhs.x, y: self.y - rhs.y }
+    }
+}
+
+impl Mul<f64> for DVec2 {
+    type Output = Self;
+    fn mul(self, rhs: f64) -> Self::Output {
+        Self { x: self.x * rhs, y: self.y * rhs }
+    }
+}
+
+impl Div<f64> for DVec2 {
+    type Output = Self;
+    fn div(self, rhs: f64) -> Self::Output {
+        Self { x: self.x / rhs, y: self.y / rhs }
+    }
+}
+
+// Bodies of a scene widened to f64 once, rather than on every force evaluation
+pub(crate) struct PreciseBody {
+    pub(crate) pos: DVec2,
+    pub(crate) mass: f64,
+    pub(crate) charge: f64,
+    pub(crate) shape: BodyShape,
+    // J2 times the body's radius squared and the pole, for oblate bodies
+    pub(crate) oblateness: Option<(f64, DVec2)>,
+    // Path the body follows, for moving bodies
+    pub(crate) path: Option<BodyPath>,
+}
+
+impl PreciseBody {
+    // Where the body is at simulated time t, as in Scene::bodies_at
+    pub(crate) fn pos_at(&self, t: f64) -> DVec2 {
+        match &self.path {
+            Some(path) => self.pos + path.offset(t),
+            None => self.pos,
+        }
+    }
+
+    // J2 part of the body's pull with `strength` on a particle `offset` from its center, and its
+    // potential energy, as in Body::oblateness_force
+    fn oblateness(&self, strength: f64, offset: DVec2) -> Option<(DVec2, f64)> {
+        let (j2_r2, pole) = self.oblateness?;
+        let r2 = offset.x * offset.x + offset.y * offset.y;
+        if r2 == 0.0 {
+            return None;
+        }
+        let z = offset.x * pole.x + offset.y * pole.y;
+        let r5 = r2 * r2 * r2.sqrt();
+        let scale = strength * j2_r2 / r5;
+        let force = (pole * (3.0 * z) + offset * (1.5 - 7.5 * z * z / r2)) * -scale;
+        Some((force, strength * j2_r2 * (3.0 * z * z - r2) / (2.0 * r5)))
+    }
+}
+
+pub(crate) struct PreciseScene {
+    pub(crate) bodies: Vec<PreciseBody>,
+    pub(crate) gravitational_constant: f64,
+    // Coulomb constant times the particle's charge, 0 for scenes without electrostatics
+    pub(crate) coulomb: f64,
+    // Speed of light of the post-Newtonian correction, if the scene has it
+    pub(crate) speed_of_light: Option<f64>,
+    // Coefficient of restitution, if particles bounce off bodies
+    pub(crate) restitution: Option<f64>,
+    pub(crate) walls: Option<Walls>,
+    // Particle charge times the magnetic field over the particle's mass, 0 without a magnetic field
+    pub(crate) magnetic: f64,
+    // Linear and quadratic drag coefficients
+    pub(crate) drag: (f64, f64),
+    // Constant acceleration, tidal tensor [xx, xy, yy] and its origin, if the scene has an external field
+    pub(crate) external_field: Option<(DVec2, [f64; 3], DVec2)>,
+    pub(crate) force_script: Option<ScriptedForce>,
+}
+
+impl PreciseScene {
+    pub(crate) fn new(scene: &Scene) -> Self {
+        Self {
+            bodies: scene.bodies.iter().enumerate()
+                .map(|(index, body)| PreciseBody {
+                    pos: body.pos.into(),
+                    mass: body.mass as f64,
+                    charge: body.charge as f64,
+                    shape: body.shape,
+                    oblateness: body.is_oblate().then(|| (body.j2 as f64 * body.radius as f64 * body.radius as f64, body.pole().into())),
+                    path: scene.motions.iter().find(|motion| motion.body == index).map(|motion| motion.path.clone()),
+                })
+                .collect(),
+            gravitational_constant: scene.gravitational_constant as f64,
+            coulomb: if scene.is_charged() { scene.coulomb_constant as f64 * scene.particle_charge as f64 } else { 0.0 },
+            speed_of_light: scene.speed_of_light.map(|c| c as f64),
+            restitution: scene.restitution.map(|restitution| restitution as f64),
+            walls: scene.walls,
+            magnetic: if scene.is_magnetized() {
+                scene.particle_charge as f64 * scene.magnetic_field as f64 / TEST_PARTICLE_MASS as f64
+            } else {
+                0.0
+            },
+            drag: (scene.drag.linear as f64, scene.drag.quadratic as f64),
+            external_field: scene.external_field.is_active().then(|| {
+                let field = &scene.external_field;
+                (field.acceleration.into(), field.tidal.map(|value| value as f64), field.origin.into())
+            }),
+            force_script: scene.force_script.clone(),
+        }
+    }
+
+    // Where a body is as the particle at `pos` sees it at simulated time t, as in Scene::bodies_around
+    fn center(&self, body: &PreciseBody, pos: DVec2, t: f64) -> DVec2 {
+        match &self.walls {
+            Some(walls) => walls.nearest_image(body.pos_at(t), pos),
+            None => body.pos_at(t),
+        }
+    }
+
+    pub(crate) fn acceleration(&self, pos: DVec2, vel: DVec2, t: f64) -> DVec2 {
+        let mass = TEST_PARTICLE_MASS as f64;
+        let mut total_force = DVec2::new(0.0, 0.0);
+        for body in &self.bodies {
+            let center = self.center(body, pos, t);
+            let strength = self.gravitational_constant * mass * body.mass - self.coulomb * body.charge;
+            if let Some(force) = body.shape.field_at(center, strength, pos) {
+                total_force = total_force + force;
+                continue;
+            }
+            let dir = center - pos;
+            let dist = dir.length();
+            if dist == 0.0 {
+                continue;
+            }
+            let force_mag = self.gravitational_constant * mass * body.mass / (dist * dist);
+            total_force = total_force + dir / dist * force_mag;
+            if let Some((force, _)) = body.oblateness(self.gravitational_constant * mass * body.mass, pos - center) {
+                total_force = total_force + force;
+            }
+            if self.coulomb != 0.0 {
+                total_force = total_force - dir / dist * (self.coulomb * body.charge / (dist * dist));
+            }
+        }
+        let mut acceleration = total_force / mass;
+        if let Some(c) = self.speed_of_light {
+            acceleration = acceleration + self.post_newtonian(pos, vel, t, c);
+        }
+        if self.magnetic != 0.0 {
+            acceleration = acceleration + magnetic_turn(vel, self.magnetic);
+        }
+        let (linear, quadratic) = self.drag;
+        if linear != 0.0 || quadratic != 0.0 {
+            acceleration = acceleration - vel * (linear + quadratic * vel.length());
+        }
+        if let Some((constant, [xx, xy, yy], origin)) = self.external_field {
+            let d = pos - origin;
+            acceleration = acceleration + constant + DVec2::new(xx * d.x + xy * d.y, xy * d.x + yy * d.y);
+        }
+        if let Some(script) = &self.force_script {
+            acceleration = acceleration + script.acceleration(pos, vel, t);
+        }
+        acceleration
+    }
+
+    // Same as calculate_post_newtonian_acceleration, from gravity alone summed again without the
+    // Coulomb force folded into it
+    fn post_newtonian(&self, pos: DVec2, vel: DVec2, t: f64, c: f64) -> DVec2 {
+        let mut gravity = DVec2::new(0.0, 0.0);
+        let mut u = 0.0;
+        for body in &self.bodies {
+            let center = self.center(body, pos, t);
+            let strength = self.gravitational_constant * body.mass;
+            if let (Some(field), Some(potential)) = (body.shape.field_at(center, strength, pos), body.shape.potential_at(center, strength, pos)) {
+                gravity = gravity + field;
+                u -= potential;
+                continue;
+            }
+            let dir = center - pos;
+            let dist = dir.length();
+            if dist == 0.0 {
+                continue;
+            }
+            gravity = gravity + dir * (strength / (dist * dist * dist));
+            u += strength / dist;
+            if let Some((field, potential)) = body.oblateness(strength, pos - center) {
+                gravity = gravity + field;
+                u -= potential;
+            }
+        }
+        let v2 = vel.x * vel.x + vel.y * vel.y;
+        let v_gravity = vel.x * gravity.x + vel.y * gravity.y;
+        (gravity * (v2 - 4.0 * u) - vel * (4.0 * v_gravity)) / (c * c)
+    }
+
+    // Steps from simulated time t to t + dt
+    fn step_euler(&self, pos: &mut DVec2, vel: &mut DVec2, t: f64, dt: f64) {
+        *vel = *vel + self.acceleration(*pos, *vel, t) * dt;
+        *pos = *pos + *vel * dt;
+    }
+
+    fn step_rk4(&self, pos: &mut DVec2, vel: &mut DVec2, t: f64, dt: f64) {
+        let (p, v) = (*pos, *vel);
+
+        let k1_vel = self.acceleration(p, v, t) * dt;
+        let k1_pos = v * dt;
+
+        let k2_vel = self.acceleration(p + k1_pos * 0.5, v + k1_vel * 0.5, t + dt * 0.5) * dt;
+        let k2_pos = (v + k1_vel * 0.5) * dt;
+
+        let k3_vel = self.acceleration(p + k2_pos * 0.5, v + k2_vel * 0.5, t + dt * 0.5) * dt;
+        let k3_pos = (v + k2_vel * 0.5) * dt;
+
+        let k4_vel = self.acceleration(p + k3_pos, v + k3_vel, t + dt) * dt;
+        let k4_pos = (v + k3_vel) * dt;
+
+        *vel = v + (k1_vel + k2_vel * 2.0 + k3_vel * 2.0 + k4_vel) / 6.0;
+        *pos = p + (k1_pos + k2_pos * 2.0 + k3_pos * 2.0 + k4_pos) / 6.0;
+    }
+
+    // Strength of a body's inverse-square pull per unit of particle mass: G times its mass, less
+    // the electrostatic push
+    pub(crate) fn attraction(&self, body: &PreciseBody) -> f64 {
+        self.gravitational_constant * body.mass - self.coulomb * body.charge / TEST_PARTICLE_MASS as f64
+    }
+
+    // Body the particle at `pos` at simulated time t is within the threshold of
+    pub(crate) fn collision(&self, pos: DVec2, t: f64, collision_threshold: f64) -> Option<usize> {
+        self.bodies.iter().position(|body| body.shape.distance(pos - self.center(body, pos, t)) < collision_threshold)
+    }
+
+    // simulation::settles for a particle at `pos` moving at `vel` that hit body `index` at
+    // simulated time t, having bounced `bounces` times before
+    fn settles(&self, index: usize, pos: &mut DVec2, vel: &mut DVec2, t: f64, bounces: &mut u32, collision_threshold: f64) -> bool {
+        let Some(restitution) = self.restitution else {
+            return true;
+        };
+        let body = &self.bodies[index];
+        if body.shape.is_black_hole() {
+            return true;
+        }
+        // Same as physics::bounce
+        let center = self.center(body, *pos, t);
+        let (point, normal) = body.shape.surface(*pos - center);
+        *pos = center + point + normal * collision_threshold;
+        let relative = *vel - body.path.as_ref().map_or(DVec2::new(0.0, 0.0), |path| path.velocity(t));
+        let approach = relative.x * normal.x + relative.y * normal.y;
+        if approach < 0.0 {
+            *vel = *vel - normal * ((1.0 + restitution) * approach);
+        }
+        *bounces += 1;
+        restitution == 0.0 || *bounces >= MAX_BOUNCES
+    }
+}
+
+// Acceleration of the magnetic field on a particle moving at `vel`, with `magnetic` its charge
+// times the field over its mass. Linear in the velocity, so deep zoom reuses it for velocity offsets.
+pub(crate) fn magnetic_turn(vel: DVec2, magnetic: f64) -> DVec2 {
+    DVec2::new(vel.y * magnetic, -vel.x * magnetic)
+}
+
+// run_simulation_with_time carried out in f64, from a start position given in f64. Scenes whose
+// bodies aren't stationary are only simulated in f32.
+pub fn run_simulation_with_time_f64(
+    start_pos: DVec2,
+    initial_velocity: Vec2,
+    scene: &Scene,
+    integration_method: IntegrationMethod,
+    params: &SimulationParams,
+) -> Option<(usize, usize)> {
+    if scene.is_dynamic() {
+        return run_simulation_with_time(start_pos.to_vec2(), initial_velocity, scene, integration_method, params);
+    }
+    integrate_from(&PreciseScene::new(scene), start_pos, initial_velocity.into(), 0, integration_method, params)
+}
+
+// Continues a particle from `first_substep`, counted across all timesteps, to the end of the
+// simulation and returns the body it hit and the timestep it hit at
+pub(crate) fn integrate_from(
+    scene: &PreciseScene,
+    mut pos: DVec2,
+    mut vel: DVec2,
+    first_substep: usize,
+    integration_method: IntegrationMethod,
+    params: &SimulationParams,
+) -> Option<(usize, usize)> {
+    let dt = params.dt as f64 / params.substeps as f64;
+    let threshold = params.collision_threshold as f64;
+    let mut bounces = 0;
+    if let Some(walls) = &scene.walls {
+        (pos, vel) = walls.fold(pos, vel);
+    }
+
+    for substep in first_substep..params.timesteps * params.substeps {
+        let t = substep as f64 * dt;
+        match integration_method {
+            IntegrationMethod::Euler => scene.step_euler(&mut pos, &mut vel, t, dt),
+            IntegrationMethod::RungeKutta4 => scene.step_rk4(&mut pos, &mut vel, t, dt),
+        }
+        if let Some(walls) = &scene.walls {
+            (pos, vel) = walls.fold(pos, vel);
+        }
+
+        if let Some(collision_index) = scene.collision(pos, t + dt, threshold) {
+            if scene.settles(collision_index, &mut pos, &mut vel, t + dt, &mut bounces, threshold) {
+                return Some((collision_index, substep / params.substeps));
+            }
+        }
+    }
+    None // No collision
+}
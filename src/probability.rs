@@ -0,0 +1,217 @@
+use std::f32::consts::TAU;
+use std::sync::atomic::AtomicUsize;
+
+use image::{ImageBuffer, Rgb, RgbImage};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+use crate::barnes_hut::Gravity;
+use crate::config::DEFAULT_NON_COLLISION_COLOR;
+use crate::image_gen::{capture_color, count_pixel, MapParams};
+use crate::physics::{check_collision, Body, Vec2};
+use crate::scene::Scene;
+use crate::simulation::{IntegrationMethod, SimulationParams};
+
+// Noise strength of probability maps that don't ask for one
+pub const DEFAULT_NOISE: f32 = 2.0;
+
+// Generator for one sample of a probability map. Every pixel and sample gets its own stream, so
+// maps come out the same whichever thread simulates which pixel.
+pub fn sample_rng(seed: u64, stream: u64) -> StdRng {
+    StdRng::seed_from_u64(seed ^ stream.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
+
+// Pair of independent standard normal numbers, by the Box-Muller transform
+pub fn gaussian_pair(rng: &mut impl Rng) -> (f32, f32) {
+    // 1 - [0, 1) keeps the logarithm finite
+    let radius = (-2.0 * (1.0 - rng.random::<f32>()).ln()).sqrt();
+    let angle = TAU * rng.random::<f32>();
+    (radius * angle.cos(), radius * angle.sin())
+}
+
+// run_simulation_with_time with random velocity kicks given to the particle after every substep,
+// like the buffeting of a thermal bath. Each is an independent Gaussian with a standard deviation
+// of `noise` times the square root of the substep length, so the spread they build up over a
+// stretch of simulated time doesn't depend on how finely it's divided. Nothing is decided early
+// since the kicks can undo any escape.
+pub fn run_noisy_simulation(
+    start_pos: Vec2,
+    initial_velocity: Vec2,
+    scene: &Scene,
+    integration_method: IntegrationMethod,
+    params: &SimulationParams,
+    noise: f32,
+    rng: &mut impl Rng,
+) -> Option<(usize, usize)> {
+    let mut particle = scene.launch_particle(start_pos, initial_velocity);
+    if let Some(index) = check_collision(&particle, &scene.bodies_around(&particle), params.collision_threshold) {
+        return Some((index, 0));
+    }
+    let dt = params.substep_dt();
+    let kick = noise * dt.sqrt();
+    let mut gravity = Gravity::new(scene, params);
+
+    for timestep in 0..params.timesteps {
+        for _ in 0..params.substeps {
+            gravity.step(integration_method, &mut particle, dt);
+            if noise > 0.0 {
+                let (x, y) = gaussian_pair(rng);
+                particle.vel += Vec2::new(x, y) * kick;
+            }
+
+            if let Some(collision_index) = gravity.collision(&mut particle, params.collision_threshold) {
+                return Some((collision_index, timestep));
+            }
+        }
+    }
+    None
+}
+
+// How the samples of every pixel of a probability map are drawn. Without noise or jitter every
+// sample would simply repeat the deterministic map.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProbabilitySettings {
+    // Samples simulated per pixel
+    pub samples: usize,
+    // Strength of the velocity kicks of run_noisy_simulation, 0 for none
+    pub noise: f32,
+    // Whether every sample starts at a uniformly random point of its pixel rather than its corner
+    pub jitter: bool,
+    pub seed: u64,
+}
+
+// How the pixels of a probability map are colored
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ProbabilityColoring {
+    // The colors of the bodies and of escapes, mixed in proportion to how many samples ended on each
+    #[default]
+    Mix,
+    // The color of the most common outcome, faded into the background by the share of samples
+    // that disagree with it, so uncertain pixels darken
+    Dominant,
+}
+
+impl ProbabilityColoring {
+    pub const ALL: [ProbabilityColoring; 2] = [ProbabilityColoring::Mix, ProbabilityColoring::Dominant];
+
+    // Identifier used on the command line
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            ProbabilityColoring::Mix => "mix",
+            ProbabilityColoring::Dominant => "dominant",
+        }
+    }
+}
+
+impl std::str::FromStr for ProbabilityColoring {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ProbabilityColoring::ALL.iter()
+            .find(|coloring| coloring.short_name() == s)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = ProbabilityColoring::ALL.iter().map(|coloring| coloring.short_name()).collect();
+                format!("unknown probability coloring '{}', expected one of: {}", s, names.join(", "))
+            })
+    }
+}
+
+// Outcomes of a map simulated several times per pixel: how many of the samples of each pixel
+// ended on each body, and how many never collided
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProbabilityGrid {
+    pub width: u32,
+    pub height: u32,
+    pub samples: usize,
+    pub bodies: usize,
+    // bodies + 1 counts per pixel in row order, the last of each for escapes
+    pub counts: Vec<u32>,
+}
+
+impl ProbabilityGrid {
+    // Counts of the pixel at (px, py), indexed by body with escapes last
+    pub fn counts(&self, px: u32, py: u32) -> &[u32] {
+        let start = (py * self.width + px) as usize * (self.bodies + 1);
+        &self.counts[start..start + self.bodies + 1]
+    }
+
+    // Share of the samples of the pixel at (px, py) that ended on `body`, or escaped for None
+    pub fn probability(&self, px: u32, py: u32, body: Option<usize>) -> f32 {
+        self.counts(px, py)[body.unwrap_or(self.bodies)] as f32 / self.samples.max(1) as f32
+    }
+
+    // Most common outcome of the pixel at (px, py), None for escapes, with the share of samples
+    // that ended there. Ties go to the lowest body index.
+    pub fn dominant(&self, px: u32, py: u32) -> (Option<usize>, f32) {
+        let counts = self.counts(px, py);
+        let (index, &count) = counts.iter().enumerate().rev().max_by_key(|&(_, count)| count).unwrap();
+        let outcome = (index < self.bodies).then_some(index);
+        (outcome, count as f32 / self.samples.max(1) as f32)
+    }
+}
+
+// Simulates every pixel of the map `settings.samples` times and counts the outcomes
+pub fn compute_probability_grid(
+    scene: &Scene,
+    params: &MapParams,
+    settings: &ProbabilitySettings,
+    progress: &(impl Fn(usize, usize) + Sync),
+) -> ProbabilityGrid {
+    let MapParams { width, height, initial_velocity, zoom_factor, integration_method, simulation, .. } = *params;
+    let camera = params.camera();
+    let bodies = scene.bodies.len();
+    let num_pixels = (width * height) as usize;
+    let mut counts = vec![0; num_pixels * (bodies + 1)];
+    let counter = AtomicUsize::new(0);
+    let samples = settings.samples.max(1);
+
+    counts.par_chunks_mut(bodies + 1).enumerate().for_each(|(i, pixel)| {
+        let px = (i % width as usize) as f32;
+        let py = (i / width as usize) as f32;
+        let world_pos = camera.screen_to_world(Vec2::new(px, py));
+        for sample in 0..samples {
+            let mut rng = sample_rng(settings.seed, (i * samples + sample) as u64);
+            let start = if settings.jitter {
+                world_pos + Vec2::new(rng.random::<f32>(), rng.random::<f32>()) * (1.0 / zoom_factor)
+            } else {
+                world_pos
+            };
+            let outcome = run_noisy_simulation(start, initial_velocity, scene, integration_method, &simulation, settings.noise, &mut rng);
+            pixel[outcome.map_or(bodies, |(index, _)| index)] += 1;
+        }
+        count_pixel(&counter, num_pixels, progress);
+    });
+    ProbabilityGrid { width, height, samples, bodies, counts }
+}
+
+// Colors every pixel by its capture probabilities
+pub fn render_probability_grid(
+    grid: &ProbabilityGrid,
+    stationary_bodies: &[Body],
+    coloring: ProbabilityColoring,
+) -> RgbImage {
+    let color_of = |index: usize| stationary_bodies.get(index).map_or(DEFAULT_NON_COLLISION_COLOR, capture_color);
+    ImageBuffer::from_fn(grid.width, grid.height, |px, py| {
+        let mut mixed = [0.0f32; 3];
+        match coloring {
+            ProbabilityColoring::Mix => {
+                for (index, &count) in grid.counts(px, py).iter().enumerate() {
+                    let weight = count as f32 / grid.samples as f32;
+                    for (channel, value) in mixed.iter_mut().zip(color_of(index)) {
+                        *channel += value as f32 * weight;
+                    }
+                }
+            }
+            ProbabilityColoring::Dominant => {
+                let (outcome, confidence) = grid.dominant(px, py);
+                let color = color_of(outcome.unwrap_or(grid.bodies));
+                for ((channel, value), background) in mixed.iter_mut().zip(color).zip(DEFAULT_NON_COLLISION_COLOR) {
+                    *channel = background as f32 + (value as f32 - background as f32) * confidence;
+                }
+            }
+        }
+        Rgb(mixed.map(|channel| channel.round().min(255.0) as u8))
+    })
+}
@@ -0,0 +1,68 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Progress of a generation running on another thread, updated through its progress callback
+// and read by the viewer to draw a progress bar
+pub struct GenerationProgress {
+    done: AtomicUsize,
+    total: AtomicUsize,
+    started: Instant,
+}
+
+impl GenerationProgress {
+    pub fn new() -> Self {
+        Self { done: AtomicUsize::new(0), total: AtomicUsize::new(0), started: Instant::now() }
+    }
+
+    // Matches the progress callback signature of the image_gen functions
+    pub fn update(&self, done: usize, total: usize) {
+        self.total.store(total, Ordering::Relaxed);
+        self.done.store(done, Ordering::Relaxed);
+    }
+
+    pub fn fraction(&self) -> f32 {
+        let total = self.total.load(Ordering::Relaxed);
+        if total == 0 {
+            return 0.0;
+        }
+        self.done.load(Ordering::Relaxed) as f32 / total as f32
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    // Remaining time extrapolated from the rate so far; None until there is something to go on
+    pub fn eta(&self) -> Option<Duration> {
+        let fraction = self.fraction();
+        if fraction <= 0.0 {
+            return None;
+        }
+        Some(self.elapsed().mul_f32((1.0 - fraction) / fraction))
+    }
+}
+
+impl Default for GenerationProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Shared flag for aborting a generation from another thread. Clones refer to the same flag.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,87 @@
+use std::path::Path;
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{imageops, Delay, Frame, RgbaImage};
+
+// Frames per second captured from the viewer; lower than the display rate to keep GIFs small
+pub const RECORDING_FPS: f64 = 15.0;
+// Recordings stop on their own after this many frames (30 seconds) to bound memory use
+pub const MAX_RECORDING_FRAMES: usize = 450;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecordingFormat {
+    Gif,
+    // Numbered PNGs in a directory, e.g. for encoding an MP4 with ffmpeg
+    Frames,
+}
+
+// An opaque image of a frame read back from the screen. OpenGL returns rows bottom to top, so
+// they are flipped here.
+pub fn framebuffer_image(width: u32, height: u32, bottom_up_rgba: Vec<u8>) -> Option<RgbaImage> {
+    let mut frame = RgbaImage::from_raw(width, height, bottom_up_rgba)?;
+    imageops::flip_vertical_in_place(&mut frame);
+    // The framebuffer's alpha is whatever blending left behind
+    for pixel in frame.pixels_mut() {
+        pixel[3] = 255;
+    }
+    Some(frame)
+}
+
+// Viewer frames captured while recording, held in memory until the recording is saved
+pub struct Recording {
+    pub format: RecordingFormat,
+    frames: Vec<RgbaImage>,
+    last_capture: Option<f64>,
+}
+
+impl Recording {
+    pub fn new(format: RecordingFormat) -> Self {
+        Self { format, frames: Vec::new(), last_capture: None }
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.frames.len() >= MAX_RECORDING_FRAMES
+    }
+
+    // Whether a frame is due at `time` (in seconds) to keep the capture rate at RECORDING_FPS
+    pub fn wants_frame(&self, time: f64) -> bool {
+        !self.is_full() && self.last_capture.is_none_or(|last| time - last >= 1.0 / RECORDING_FPS)
+    }
+
+    // Adds a frame read back from the screen
+    pub fn capture(&mut self, time: f64, width: u32, height: u32, bottom_up_rgba: Vec<u8>) {
+        let Some(frame) = framebuffer_image(width, height, bottom_up_rgba) else {
+            return;
+        };
+        self.frames.push(frame);
+        self.last_capture = Some(time);
+    }
+
+    // Writes `<name>.gif` or a `<name>` directory of frames and returns the path written
+    pub fn save(self, name: &str) -> Result<String, Box<dyn std::error::Error>> {
+        if self.frames.is_empty() {
+            return Err("no frames were recorded".into());
+        }
+        match self.format {
+            RecordingFormat::Gif => {
+                let filename = format!("{}.gif", name);
+                let mut encoder = GifEncoder::new(std::fs::File::create(&filename)?);
+                encoder.set_repeat(Repeat::Infinite)?;
+                let delay = Delay::from_numer_denom_ms(1000, RECORDING_FPS as u32);
+                encoder.encode_frames(self.frames.into_iter().map(|frame| Frame::from_parts(frame, 0, 0, delay)))?;
+                Ok(filename)
+            }
+            RecordingFormat::Frames => {
+                std::fs::create_dir_all(name)?;
+                for (i, frame) in self.frames.iter().enumerate() {
+                    frame.save(Path::new(name).join(format!("frame_{:05}.png", i)))?;
+                }
+                Ok(name.to_string())
+            }
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use std::sync::OnceLock;
+
+use fontdue::{Font, FontSettings};
+use image::{ImageBuffer, RgbImage, Rgba, RgbaImage};
+
+use crate::physics::Vec2;
+
+// Persistent floating point surface that trajectories are accumulated into ("long exposure").
+// Coordinates are in pixels of the surface.
+pub struct DensityBuffer {
+    pub width: u32,
+    pub height: u32,
+    values: Vec<f32>,
+    max_value: f32,
+}
+
+impl DensityBuffer {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            values: vec![0.0; (width * height) as usize],
+            max_value: 0.0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.values.fill(0.0);
+        self.max_value = 0.0;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.max_value == 0.0
+    }
+
+    pub fn add_point(&mut self, p: Vec2, weight: f32) {
+        if p.x < 0.0 || p.y < 0.0 || p.x >= self.width as f32 || p.y >= self.height as f32 {
+            return;
+        }
+        let index = (p.y as u32 * self.width + p.x as u32) as usize;
+        self.values[index] += weight;
+        self.max_value = self.max_value.max(self.values[index]);
+    }
+
+    // Deposits a line segment with one unit of weight per pixel of length, so fast-moving
+    // stretches of a trajectory don't show up as dotted lines
+    pub fn add_segment(&mut self, p1: Vec2, p2: Vec2) {
+        let length = p1.distance(&p2);
+        let samples = length.ceil().max(1.0) as usize;
+        for i in 0..samples {
+            let t = (i as f32 + 0.5) / samples as f32;
+            self.add_point(p1 + (p2 - p1) * t, length / samples as f32);
+        }
+    }
+
+    // Logarithmic tone mapping onto a black -> orange -> white ramp; alpha follows brightness
+    // so the result can be drawn on top of a basin map
+    pub fn tone_map(&self) -> RgbaImage {
+        let scale = (1.0 + self.max_value).ln().max(f32::EPSILON);
+        ImageBuffer::from_fn(self.width, self.height, |px, py| {
+            let value = self.values[(py * self.width + px) as usize];
+            let brightness = (1.0 + value).ln() / scale;
+            let r = (brightness * 2.0).min(1.0);
+            let g = (brightness * 1.5 - 0.25).clamp(0.0, 1.0);
+            let b = (brightness * 2.0 - 1.0).clamp(0.0, 1.0);
+            Rgba([(r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8, (brightness * 255.0) as u8])
+        })
+    }
+
+    pub fn save_png(&self, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+        // Flatten onto black so the exported image doesn't depend on the viewer's alpha blending
+        let img = image::DynamicImage::ImageRgba8(self.tone_map()).to_rgb8();
+        img.save(filename)?;
+        println!("Long exposure image saved to {}", filename);
+        Ok(())
+    }
+}
+
+// Maps t in [0, 1] onto a blue -> cyan -> green -> yellow -> red ramp, returned as RGB in [0, 1]
+pub fn gradient_color(t: f32) -> [f32; 3] {
+    const STOPS: [[f32; 3]; 5] = [
+        [0.1, 0.2, 1.0],
+        [0.0, 0.9, 1.0],
+        [0.2, 1.0, 0.2],
+        [1.0, 1.0, 0.0],
+        [1.0, 0.1, 0.1],
+    ];
+    let scaled = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let index = (scaled as usize).min(STOPS.len() - 2);
+    let frac = scaled - index as f32;
+    let (a, b) = (STOPS[index], STOPS[index + 1]);
+    [
+        a[0] + (b[0] - a[0]) * frac,
+        a[1] + (b[1] - a[1]) * frac,
+        a[2] + (b[2] - a[2]) * frac,
+    ]
+}
+
+// Fills a circle on an RGB image, clipped to the image bounds. Edge pixels are blended by how
+// much of them the circle covers, so the outline is antialiased.
+pub fn fill_circle(img: &mut RgbImage, center: Vec2, radius: f32, color: [u8; 3]) {
+    let min_x = (center.x - radius - 1.0).floor().max(0.0) as u32;
+    let min_y = (center.y - radius - 1.0).floor().max(0.0) as u32;
+    let max_x = (center.x + radius + 1.0).ceil().clamp(0.0, img.width() as f32) as u32;
+    let max_y = (center.y + radius + 1.0).ceil().clamp(0.0, img.height() as f32) as u32;
+    for py in min_y..max_y {
+        for px in min_x..max_x {
+            let d = Vec2::new(px as f32 + 0.5, py as f32 + 0.5).distance(&center);
+            let coverage = (radius - d + 0.5).clamp(0.0, 1.0);
+            if coverage > 0.0 {
+                let pixel = img.get_pixel_mut(px, py);
+                for (channel, target) in pixel.0.iter_mut().zip(color) {
+                    *channel = (*channel as f32 * (1.0 - coverage) + target as f32 * coverage).round() as u8;
+                }
+            }
+        }
+    }
+}
+
+// Fills the pixels within `half_width` of a shape given by `distance`, the distance from a pixel
+// center to it, antialiased like fill_circle. Only the box from `min` to `max` is visited.
+pub fn fill_near(img: &mut RgbImage, min: Vec2, max: Vec2, half_width: f32, color: [u8; 3], distance: impl Fn(Vec2) -> f32) {
+    let min_x = (min.x - half_width - 1.0).floor().max(0.0) as u32;
+    let min_y = (min.y - half_width - 1.0).floor().max(0.0) as u32;
+    let max_x = (max.x + half_width + 1.0).ceil().clamp(0.0, img.width() as f32) as u32;
+    let max_y = (max.y + half_width + 1.0).ceil().clamp(0.0, img.height() as f32) as u32;
+    for py in min_y..max_y {
+        for px in min_x..max_x {
+            let coverage = (half_width - distance(Vec2::new(px as f32 + 0.5, py as f32 + 0.5)) + 0.5).clamp(0.0, 1.0);
+            if coverage > 0.0 {
+                let pixel = img.get_pixel_mut(px, py);
+                for (channel, target) in pixel.0.iter_mut().zip(color) {
+                    *channel = (*channel as f32 * (1.0 - coverage) + target as f32 * coverage).round() as u8;
+                }
+            }
+        }
+    }
+}
+
+// Fills an axis-aligned rectangle on an RGB image, blending `color` over it with opacity `alpha`
+// and clipping to the image bounds
+pub fn fill_rect(img: &mut RgbImage, x: u32, y: u32, width: u32, height: u32, color: [u8; 3], alpha: f32) {
+    for py in y..(y + height).min(img.height()) {
+        for px in x..(x + width).min(img.width()) {
+            let pixel = img.get_pixel_mut(px, py);
+            for (channel, target) in pixel.0.iter_mut().zip(color) {
+                *channel = (*channel as f32 * (1.0 - alpha) + target as f32 * alpha).round() as u8;
+            }
+        }
+    }
+}
+
+// Hue in [0, 6), saturation and value in [0, 1]
+pub fn rgb_to_hsv(color: [u8; 3]) -> [f32; 3] {
+    let [r, g, b] = color.map(|c| c as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let chroma = max - r.min(g).min(b);
+    let hue = if chroma == 0.0 {
+        0.0
+    } else if max == r {
+        ((g - b) / chroma).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / chroma + 2.0
+    } else {
+        (r - g) / chroma + 4.0
+    };
+    let saturation = if max == 0.0 { 0.0 } else { chroma / max };
+    [hue, saturation, max]
+}
+
+pub fn hsv_to_rgb([hue, saturation, value]: [f32; 3]) -> [u8; 3] {
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (hue.rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    [r, g, b].map(|c| ((c + m) * 255.0).round().clamp(0.0, 255.0) as u8)
+}
+
+// Font used for text drawn into generated images
+fn label_font() -> &'static Font {
+    static FONT: OnceLock<Font> = OnceLock::new();
+    FONT.get_or_init(|| {
+        Font::from_bytes(epaint_default_fonts::HACK_REGULAR, FontSettings::default()).expect("built-in font is valid")
+    })
+}
+
+// Width in pixels of a line of text at the given font size
+pub fn text_width(text: &str, size: f32) -> f32 {
+    text.chars().map(|c| label_font().metrics(c, size).advance_width).sum()
+}
+
+// Draws one line of text with its top-left corner at (x, y), blending the glyphs over the image
+pub fn draw_text(img: &mut RgbImage, text: &str, x: f32, y: f32, size: f32, color: [u8; 3]) {
+    let font = label_font();
+    let ascent = font.horizontal_line_metrics(size).map_or(size, |metrics| metrics.ascent);
+    let baseline = y + ascent;
+    let mut pen_x = x;
+    for c in text.chars() {
+        let (metrics, coverage) = font.rasterize(c, size);
+        let left = (pen_x + metrics.xmin as f32).round() as i64;
+        let top = (baseline - metrics.height as f32 - metrics.ymin as f32).round() as i64;
+        for (i, alpha) in coverage.iter().enumerate() {
+            let px = left + (i % metrics.width) as i64;
+            let py = top + (i / metrics.width) as i64;
+            if *alpha == 0 || px < 0 || py < 0 || px >= img.width() as i64 || py >= img.height() as i64 {
+                continue;
+            }
+            let a = *alpha as f32 / 255.0;
+            let pixel = img.get_pixel_mut(px as u32, py as u32);
+            for (channel, target) in pixel.0.iter_mut().zip(color) {
+                *channel = (*channel as f32 * (1.0 - a) + target as f32 * a).round() as u8;
+            }
+        }
+        pen_x += metrics.advance_width;
+    }
+}
@@ -0,0 +1,113 @@
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use gravity_wells::image_gen::{ensure_map_image, MapParams};
+use gravity_wells::progress::{CancelToken, GenerationProgress};
+use gravity_wells::scene::Scene;
+
+// A map being generated on a background thread so the window stays responsive
+struct MapJob {
+    params: MapParams,
+    progress: Arc<GenerationProgress>,
+    cancel: CancelToken,
+    // Filename of the generated map, or the error as a string since it has to cross threads
+    handle: JoinHandle<Result<String, String>>,
+}
+
+impl MapJob {
+    fn start(scene: &Scene, params: MapParams) -> Self {
+        let progress = Arc::new(GenerationProgress::new());
+        let cancel = CancelToken::new();
+        let scene = scene.clone();
+        let (job_progress, job_cancel) = (Arc::clone(&progress), cancel.clone());
+        let handle = std::thread::spawn(move || {
+            ensure_map_image(&scene, &params, &|done, total| job_progress.update(done, total), &job_cancel)
+                .map_err(|e| e.to_string())
+        });
+        Self { params, progress, cancel, handle }
+    }
+}
+
+// Outcome of a job that has stopped running
+pub enum FinishedJob {
+    Generated { params: MapParams, filename: String },
+    Cancelled,
+    Failed(String),
+}
+
+// Runs one map generation at a time. Requests made while a map generates cancel it and wait in a
+// single pending slot, so a burst of requests only ever generates the latest one.
+pub struct RenderQueue {
+    active: Option<MapJob>,
+    pending: Option<(Scene, MapParams)>,
+    // Requests dropped because a newer one replaced them
+    pub superseded: usize,
+}
+
+impl RenderQueue {
+    pub fn new() -> Self {
+        Self { active: None, pending: None, superseded: 0 }
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.active.is_some() || self.pending.is_some()
+    }
+
+    pub fn request(&mut self, scene: &Scene, params: MapParams) {
+        if let Some(active) = &self.active {
+            if active.params == params && !active.cancel.is_cancelled() {
+                return;
+            }
+            if !active.cancel.is_cancelled() {
+                active.cancel.cancel();
+                self.superseded += 1;
+            }
+            if self.pending.replace((scene.clone(), params)).is_some() {
+                self.superseded += 1;
+            }
+        } else {
+            self.active = Some(MapJob::start(scene, params));
+        }
+    }
+
+    // Cancels the running job and drops any pending request
+    pub fn cancel_all(&mut self) {
+        if let Some(active) = &self.active {
+            active.cancel.cancel();
+        }
+        self.pending = None;
+    }
+
+    // Progress of the job that is generating a map that's still wanted
+    pub fn progress(&self) -> Option<&GenerationProgress> {
+        self.active.as_ref().filter(|job| !job.cancel.is_cancelled()).map(|job| job.progress.as_ref())
+    }
+
+    // Collects the running job once it stops and starts the pending request, if any
+    pub fn poll(&mut self) -> Option<FinishedJob> {
+        if !self.active.as_ref().is_some_and(|job| job.handle.is_finished()) {
+            return None;
+        }
+        let job = self.active.take().unwrap();
+        if let Some((scene, params)) = self.pending.take() {
+            self.active = Some(MapJob::start(&scene, params));
+        }
+        let cancelled = job.cancel.is_cancelled();
+        Some(match job.handle.join() {
+            Ok(Ok(filename)) => FinishedJob::Generated { params: job.params, filename },
+            Ok(Err(_)) if cancelled => FinishedJob::Cancelled,
+            Ok(Err(e)) => FinishedJob::Failed(e),
+            Err(_) => FinishedJob::Failed("map generation thread panicked".to_string()),
+        })
+    }
+
+    // One-line summary for the HUD while anything is running or queued
+    pub fn status(&self) -> Option<String> {
+        if !self.is_busy() {
+            return None;
+        }
+        let queued = if self.pending.is_some() { ", 1 queued" } else { "" };
+        let superseded = if self.superseded > 0 { format!(", {} superseded", self.superseded) } else { String::new() };
+        Some(format!("Generating map{}{} (Escape to cancel)", queued, superseded))
+    }
+}
@@ -0,0 +1,667 @@
+use std::borrow::Cow;
+use std::time::{Duration, Instant, SystemTime};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::force_field::{BodyShape, ForceField, ScriptedForce};
+use crate::motion::BodyMotion;
+use crate::physics::{Body, Drag, ExternalField, TestParticle, Vec2, Walls, COULOMB_CONSTANT, G};
+use crate::simulation::{TEST_PARTICLE_MASS, TEST_PARTICLE_RADIUS};
+use crate::config::{
+    body_radius, create_stationary_bodies, IMAGE_SIZE, RANDOM_EDGE_MARGIN, RANDOM_MASS_RANGE, RANDOM_MIN_SEPARATION,
+};
+
+// Everything about the simulated world that can be edited at runtime: the bodies and the paths
+// some of them move along, the strength of gravity and electrostatics, the speed of light, the particle's charge, the magnetic
+// field, the drag on it, the background field and any scripted force
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Scene {
+    pub bodies: Vec<Body>,
+    pub gravitational_constant: f32,
+    // Seed the scene was randomly generated from, if any
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default = "default_coulomb_constant")]
+    pub coulomb_constant: f32,
+    // Speed of light c of the first post-Newtonian correction to gravity, which is left out if
+    // None. Smaller values make orbits precess faster.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed_of_light: Option<f32>,
+    // Coefficient of restitution particles bounce off bodies with, from 0 to 1. Particles are
+    // captured by the first body they hit if None.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restitution: Option<f32>,
+    // Box keeping the particle in, none if None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub walls: Option<Walls>,
+    // Charge of every launched particle
+    #[serde(default)]
+    pub particle_charge: f32,
+    // Uniform magnetic field perpendicular to the plane, bending the path of a charged particle
+    #[serde(default)]
+    pub magnetic_field: f32,
+    #[serde(default)]
+    pub drag: Drag,
+    #[serde(default)]
+    pub external_field: ExternalField,
+    // Extra acceleration defined by a script, see ScriptedForce
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub force_script: Option<ScriptedForce>,
+    // Prescribed paths of moving bodies, at most one per body
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub motions: Vec<BodyMotion>,
+    // Whether the bodies stay put. Otherwise they set off at their velocities and pull on each
+    // other, and on the particle, which pulls back on them with `particle_mass`.
+    #[serde(default = "default_stationary")]
+    pub stationary: bool,
+    // Gravitating mass of the launched particle, only felt by bodies that aren't stationary
+    #[serde(default)]
+    pub particle_mass: f32,
+    // Whether bodies that aren't stationary merge when they touch, and take in the particle when
+    // it hits them, see nbody::merge_touching_bodies
+    #[serde(default)]
+    pub accretion: bool,
+    // Index each body had before accretion merged any away, empty while none have
+    #[serde(skip)]
+    pub merged_indices: Vec<usize>,
+}
+
+impl Scene {
+    pub fn new(bodies: Vec<Body>, gravitational_constant: f32) -> Self {
+        Self {
+            bodies,
+            gravitational_constant,
+            seed: None,
+            coulomb_constant: COULOMB_CONSTANT,
+            speed_of_light: None,
+            restitution: None,
+            walls: None,
+            particle_charge: 0.0,
+            magnetic_field: 0.0,
+            drag: Drag::default(),
+            external_field: ExternalField::default(),
+            force_script: None,
+            motions: Vec::new(),
+            stationary: true,
+            particle_mass: 0.0,
+            accretion: false,
+            merged_indices: Vec::new(),
+        }
+    }
+
+    // Test particle launched from `pos` at `vel`, carrying the scene's particle charge. Launches
+    // from outside the walls start from where the walls would have put them.
+    pub fn launch_particle(&self, pos: Vec2, vel: Vec2) -> TestParticle {
+        let mut particle = TestParticle::new(pos, vel, TEST_PARTICLE_MASS, TEST_PARTICLE_RADIUS).with_charge(self.particle_charge);
+        if let Some(walls) = &self.walls {
+            walls.confine(&mut particle);
+        }
+        particle
+    }
+
+    pub fn has_charged_bodies(&self) -> bool {
+        self.bodies.iter().any(|body| body.charge != 0.0)
+    }
+
+    // Whether launched particles feel an electrostatic force
+    pub fn is_charged(&self) -> bool {
+        self.particle_charge != 0.0 && self.has_charged_bodies()
+    }
+
+    // Whether launched particles feel the magnetic field
+    pub fn is_magnetized(&self) -> bool {
+        self.particle_charge != 0.0 && self.magnetic_field != 0.0
+    }
+
+    pub fn has_moving_bodies(&self) -> bool {
+        !self.motions.is_empty()
+    }
+
+    // Bodies where they are at simulated time `t` after launch: the scene's own bodies unless
+    // some of them move, otherwise a copy with the moving ones along their paths
+    pub fn bodies_at(&self, t: f32) -> Cow<'_, [Body]> {
+        if self.motions.is_empty() {
+            return Cow::Borrowed(&self.bodies);
+        }
+        let mut bodies = self.bodies.clone();
+        for motion in &self.motions {
+            if let Some(body) = bodies.get_mut(motion.body) {
+                body.pos += motion.path.offset(t as f64).to_vec2();
+            }
+        }
+        Cow::Owned(bodies)
+    }
+
+    // Bodies as the particle sees them: where they are at its time, and with periodic walls at
+    // their images nearest to it
+    pub fn bodies_around(&self, particle: &TestParticle) -> Cow<'_, [Body]> {
+        let mut bodies = self.bodies_at(particle.time);
+        if let Some(walls) = self.walls.filter(Walls::is_periodic) {
+            for body in bodies.to_mut() {
+                body.pos = walls.nearest_image(body.pos.into(), particle.pos.into()).to_vec2();
+            }
+        }
+        bodies
+    }
+
+    // Whether the bodies move under their mutual gravity, see nbody.rs
+    pub fn is_dynamic(&self) -> bool {
+        !self.stationary
+    }
+
+    // Index body `index` had in the scene as it was set up, before accretion merged any away, so
+    // outcomes always name the bodies the map is colored by
+    pub fn original_index(&self, index: usize) -> usize {
+        self.merged_indices.get(index).copied().unwrap_or(index)
+    }
+
+    // Velocity of body `index` at simulated time `t`, zero unless it moves
+    pub fn body_velocity(&self, index: usize, t: f32) -> Vec2 {
+        if self.is_dynamic() {
+            return self.bodies[index].vel;
+        }
+        self.motions.iter()
+            .find(|motion| motion.body == index)
+            .map_or(Vec2::new(0.0, 0.0), |motion| motion.path.velocity(t as f64).to_vec2())
+    }
+
+    pub fn has_oblate_bodies(&self) -> bool {
+        self.bodies.iter().any(Body::is_oblate)
+    }
+
+    // Whether gravity has the post-Newtonian correction
+    pub fn is_relativistic(&self) -> bool {
+        self.speed_of_light.is_some()
+    }
+
+    // Whether particles bounce off the bodies they hit instead of being captured
+    pub fn bounces(&self) -> bool {
+        self.restitution.is_some()
+    }
+
+    // Generates `count` bodies at random positions within the default view, at least
+    // RANDOM_MIN_SEPARATION apart, with masses drawn from RANDOM_MASS_RANGE. The same seed
+    // always gives the same scene.
+    pub fn random(seed: u64, count: usize) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (min_mass, max_mass) = RANDOM_MASS_RANGE;
+        let extent = IMAGE_SIZE as f32 - RANDOM_EDGE_MARGIN;
+        let mut bodies: Vec<Body> = Vec::with_capacity(count);
+        for _ in 0..count {
+            // Rejection sample a position far enough from the existing bodies, giving up on the
+            // separation constraint if the view is too crowded to satisfy it
+            let mut pos = Vec2::new(0.0, 0.0);
+            for _ in 0..1000 {
+                pos = Vec2::new(rng.random_range(RANDOM_EDGE_MARGIN..extent), rng.random_range(RANDOM_EDGE_MARGIN..extent));
+                if bodies.iter().all(|body| body.pos.distance(&pos) >= RANDOM_MIN_SEPARATION) {
+                    break;
+                }
+            }
+            let mass = rng.random_range(min_mass..max_mass);
+            // Bright colors so every basin stays distinguishable
+            let color = [rng.random_range(80..=255), rng.random_range(80..=255), rng.random_range(80..=255)];
+            bodies.push(Body::new(pos, mass, body_radius(mass), color));
+        }
+        Self { seed: Some(seed), ..Self::new(bodies, G) }
+    }
+
+    // Whether a scripted force or a body that isn't a point acts on the particle. Such scenes are
+    // simulated pixel by pixel, without the SIMD batches, the escape bounds or deep zoom's
+    // linearization.
+    pub fn has_force_fields(&self) -> bool {
+        self.force_script.is_some() || self.bodies.iter().any(|body| !body.shape.is_point())
+    }
+
+    // Acceleration on a particle from the scene's force fields, None if it has none
+    pub fn field_acceleration(&self, particle: &TestParticle) -> Option<Vec2> {
+        let script = self.force_script.as_ref()?;
+        Some(script.acceleration(particle.pos.into(), particle.vel.into(), particle.time as f64).to_vec2())
+    }
+
+    // Stable hash of every field that affects a simulation, used to keep cached maps of
+    // different scenes apart. FNV-1a over the raw bits so it doesn't change between runs.
+    pub fn cache_key(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let mut feed = |bits: u32| {
+            for byte in bits.to_le_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        };
+        feed(self.gravitational_constant.to_bits());
+        if let Some(seed) = self.seed {
+            feed(seed as u32);
+            feed((seed >> 32) as u32);
+        }
+        // Only scenes with drag feed it, so cached maps of scenes without it stay valid
+        if self.drag.is_active() {
+            feed(self.drag.linear.to_bits());
+            feed(self.drag.quadratic.to_bits());
+        }
+        // Only charged scenes feed the charges, as with drag
+        if self.is_charged() {
+            feed(self.coulomb_constant.to_bits());
+            feed(self.particle_charge.to_bits());
+            for body in &self.bodies {
+                feed(body.charge.to_bits());
+            }
+        }
+        if self.external_field.is_active() {
+            let field = &self.external_field;
+            for value in [field.acceleration.x, field.acceleration.y, field.tidal[0], field.tidal[1], field.tidal[2], field.origin.x, field.origin.y] {
+                feed(value.to_bits());
+            }
+        }
+        if self.is_magnetized() {
+            feed(self.particle_charge.to_bits());
+            feed(self.magnetic_field.to_bits());
+        }
+        if let Some(c) = self.speed_of_light {
+            feed(c.to_bits());
+        }
+        if let Some(restitution) = self.restitution {
+            feed(restitution.to_bits());
+        }
+        if let Some(walls) = self.walls {
+            for value in [walls.min.x, walls.min.y, walls.max.x, walls.max.y] {
+                feed(value.to_bits());
+            }
+            feed(walls.kind as u32);
+        }
+        // Only dynamic scenes feed the body velocities and the particle's mass, as with drag
+        if self.is_dynamic() {
+            feed(self.particle_mass.to_bits());
+            feed(self.accretion as u32);
+            for body in &self.bodies {
+                feed(body.vel.x.to_bits());
+                feed(body.vel.y.to_bits());
+            }
+        }
+        for motion in &self.motions {
+            feed(motion.body as u32);
+            for value in motion.path.parameters() {
+                feed(value.to_bits());
+            }
+        }
+        if let Some(script) = &self.force_script {
+            for chunk in script.source().as_bytes().chunks(4) {
+                let mut bytes = [0u8; 4];
+                bytes[..chunk.len()].copy_from_slice(chunk);
+                feed(u32::from_le_bytes(bytes));
+            }
+        }
+        for body in &self.bodies {
+            feed(body.pos.x.to_bits());
+            feed(body.pos.y.to_bits());
+            feed(body.mass.to_bits());
+            // Only bodies that aren't points feed their shape, as with drag
+            let shape = match body.shape {
+                BodyShape::Point => None,
+                BodyShape::Ring { radius } => Some([1.0, radius, 0.0]),
+                BodyShape::Disc { radius } => Some([2.0, radius, 0.0]),
+                BodyShape::Segment { length, angle } => Some([3.0, length, angle]),
+                BodyShape::Plummer { radius } => Some([4.0, radius, 0.0]),
+                BodyShape::King { core_radius, tidal_radius } => Some([5.0, core_radius, tidal_radius]),
+                BodyShape::BlackHole { schwarzschild_radius } => Some([6.0, schwarzschild_radius, 0.0]),
+            };
+            for value in shape.into_iter().flatten() {
+                feed(value.to_bits());
+            }
+            // Likewise only oblate bodies feed their oblateness, and their radius that it scales with
+            if body.j2 != 0.0 {
+                feed(body.j2.to_bits());
+                feed(body.pole_angle.to_bits());
+                feed(body.radius.to_bits());
+            }
+        }
+        hash
+    }
+}
+
+// Colors handed out to preset bodies in order
+const PRESET_COLORS: [[u8; 3]; 5] = [
+    [255, 100, 100],
+    [100, 255, 100],
+    [100, 100, 255],
+    [255, 220, 100],
+    [220, 100, 255],
+];
+
+// Builds a scene from (x, y, mass) triples, coloring the bodies from PRESET_COLORS
+fn preset_scene(bodies: &[(f32, f32, f32)]) -> Scene {
+    let bodies = bodies.iter().enumerate()
+        .map(|(i, (x, y, mass))| {
+            Body::new(Vec2::new(*x, *y), *mass, body_radius(*mass), PRESET_COLORS[i % PRESET_COLORS.len()])
+        })
+        .collect();
+    Scene::new(bodies, G)
+}
+
+impl Scene {
+    // Three equal masses on the corners of an equilateral triangle centered in the view
+    pub fn triangle() -> Self {
+        let center = IMAGE_SIZE as f32 / 2.0;
+        let radius = 180.0;
+        let corners: Vec<(f32, f32, f32)> = (0..3)
+            .map(|i| {
+                let angle = -std::f32::consts::FRAC_PI_2 + i as f32 * std::f32::consts::TAU / 3.0;
+                (center + radius * angle.cos(), center + radius * angle.sin(), 40000.0)
+            })
+            .collect();
+        preset_scene(&corners)
+    }
+
+    // Two equal masses side by side
+    pub fn binary() -> Self {
+        preset_scene(&[(220.0, 300.0, 40000.0), (380.0, 300.0, 40000.0)])
+    }
+
+    // Four equal masses evenly spaced along a horizontal line
+    pub fn line() -> Self {
+        preset_scene(&[(120.0, 300.0, 30000.0), (240.0, 300.0, 30000.0), (360.0, 300.0, 30000.0), (480.0, 300.0, 30000.0)])
+    }
+
+    // One heavy body in the middle with four light satellites around it
+    pub fn central_with_satellites() -> Self {
+        preset_scene(&[
+            (300.0, 300.0, 100000.0),
+            (300.0, 100.0, 8000.0),
+            (500.0, 300.0, 8000.0),
+            (300.0, 500.0, 8000.0),
+            (100.0, 300.0, 8000.0),
+        ])
+    }
+}
+
+// Built-in scenes selectable with the number keys or --preset
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScenePreset {
+    Default,
+    Triangle,
+    Binary,
+    Line,
+    Central,
+}
+
+impl ScenePreset {
+    // In number key order, starting at 1
+    pub const ALL: [ScenePreset; 5] = [
+        ScenePreset::Default,
+        ScenePreset::Triangle,
+        ScenePreset::Binary,
+        ScenePreset::Line,
+        ScenePreset::Central,
+    ];
+
+    // Identifier used on the command line
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            ScenePreset::Default => "default",
+            ScenePreset::Triangle => "triangle",
+            ScenePreset::Binary => "binary",
+            ScenePreset::Line => "line",
+            ScenePreset::Central => "central",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScenePreset::Default => "Default three wells",
+            ScenePreset::Triangle => "Equal-mass triangle",
+            ScenePreset::Binary => "Binary pair",
+            ScenePreset::Line => "Line of wells",
+            ScenePreset::Central => "Heavy central body with satellites",
+        }
+    }
+
+    pub fn scene(&self) -> Scene {
+        match self {
+            ScenePreset::Default => Scene::default(),
+            ScenePreset::Triangle => Scene::triangle(),
+            ScenePreset::Binary => Scene::binary(),
+            ScenePreset::Line => Scene::line(),
+            ScenePreset::Central => Scene::central_with_satellites(),
+        }
+    }
+}
+
+impl std::str::FromStr for ScenePreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ScenePreset::ALL.iter()
+            .find(|preset| preset.short_name() == s)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = ScenePreset::ALL.iter().map(|preset| preset.short_name()).collect();
+                format!("unknown preset '{}', expected one of: {}", s, names.join(", "))
+            })
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self::new(create_stationary_bodies(), G)
+    }
+}
+
+// On-disk scene description. TOML and JSON files share this layout, e.g. in TOML:
+//
+//     gravitational_constant = 100.0
+//     coulomb_constant = 100.0
+//     speed_of_light = 2000.0
+//     restitution = 0.8
+//     walls = { min = { x = 0.0, y = 0.0 }, max = { x = 600.0, y = 600.0 }, kind = "periodic" }
+//     particle_charge = 1.0
+//     magnetic_field = 0.5
+//     drag = { linear = 0.01, quadratic = 0.0 }
+//     external_field = { acceleration = { x = 0.0, y = 5.0 }, tidal = [0.01, 0.0, -0.005], origin = { x = 300.0, y = 300.0 } }
+//     force_script = "fn a(pos, vel, t) { vec2(0.0, 20.0) }"
+//     stationary = true
+//     particle_mass = 0.0
+//     accretion = false
+//     [[bodies]]
+//     x = 150.0
+//     y = 150.0
+//     mass = 50000.0
+//     color = [255, 100, 100]
+//     charge = 200.0
+//     shape = { type = "ring", radius = 80.0 }
+//     vx = 0.0
+//     vy = 0.0
+//     [[motions]]
+//     body = 0
+//     type = "circle"
+//     radius = 100.0
+//     period = 10.0
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct SceneFile {
+    #[serde(default = "default_gravitational_constant")]
+    gravitational_constant: f32,
+    #[serde(default = "default_coulomb_constant")]
+    coulomb_constant: f32,
+    // Newtonian gravity if left out
+    speed_of_light: Option<f32>,
+    // Particles are captured if left out
+    restitution: Option<f32>,
+    // Unbounded if left out, reflecting unless the kind says otherwise
+    walls: Option<Walls>,
+    #[serde(default)]
+    particle_charge: f32,
+    #[serde(default)]
+    magnetic_field: f32,
+    // Damping of the particle, none if left out
+    #[serde(default)]
+    drag: Drag,
+    // Background acceleration and tidal gradient, none if left out
+    #[serde(default)]
+    external_field: ExternalField,
+    // Rhai source defining a(pos, vel, t), see ScriptedForce
+    force_script: Option<String>,
+    bodies: Vec<BodyConfig>,
+    // Paths of moving bodies, see BodyPath
+    #[serde(default)]
+    motions: Vec<BodyMotion>,
+    // Bodies stay put unless set to false, see Scene::stationary
+    #[serde(default = "default_stationary")]
+    stationary: bool,
+    #[serde(default)]
+    particle_mass: f32,
+    #[serde(default)]
+    accretion: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct BodyConfig {
+    x: f32,
+    y: f32,
+    mass: f32,
+    color: [u8; 3],
+    // Defaults to the mass-based radius used for the built-in bodies
+    radius: Option<f32>,
+    #[serde(default)]
+    charge: f32,
+    // Point mass unless given, see BodyShape
+    #[serde(default)]
+    shape: BodyShape,
+    // Spherical unless given, see Body
+    #[serde(default)]
+    j2: f32,
+    #[serde(default)]
+    pole_angle: f32,
+    // Initial velocity, ignored if the scene's bodies are stationary
+    #[serde(default)]
+    vx: f32,
+    #[serde(default)]
+    vy: f32,
+}
+
+fn default_gravitational_constant() -> f32 {
+    G
+}
+
+fn default_coulomb_constant() -> f32 {
+    COULOMB_CONSTANT
+}
+
+fn default_stationary() -> bool {
+    true
+}
+
+impl Scene {
+    // Loads a scene file, picking the format from the extension (.toml, otherwise JSON)
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(path)?;
+        let file: SceneFile = if path.ends_with(".toml") {
+            toml::from_str(&contents)?
+        } else {
+            serde_json::from_str(&contents)?
+        };
+        if let Some((i, problem)) = file.bodies.iter().enumerate().find_map(|(i, body)| body.shape.problem().map(|problem| (i, problem))) {
+            return Err(format!("body {}: {}", i, problem).into());
+        }
+        if let Some(i) = file.bodies.iter().position(|body| body.j2 != 0.0 && !body.shape.is_point()) {
+            return Err(format!("body {}: only point bodies can have j2", i).into());
+        }
+        for (i, motion) in file.motions.iter().enumerate() {
+            if motion.body >= file.bodies.len() {
+                return Err(format!("motion {}: there is no body {}", i, motion.body).into());
+            }
+            if file.motions[..i].iter().any(|other| other.body == motion.body) {
+                return Err(format!("motion {}: body {} already has a path", i, motion.body).into());
+            }
+            if let Some(problem) = motion.path.problem() {
+                return Err(format!("motion {}: {} {}", i, motion.path.name(), problem).into());
+            }
+        }
+        if file.speed_of_light.is_some_and(|c| c <= 0.0) {
+            return Err("speed_of_light must be positive".into());
+        }
+        if file.restitution.is_some_and(|restitution| !(0.0..=1.0).contains(&restitution)) {
+            return Err("restitution must be between 0 and 1".into());
+        }
+        if let Some(problem) = file.walls.as_ref().and_then(Walls::problem) {
+            return Err(problem.into());
+        }
+        if !file.stationary && !file.motions.is_empty() {
+            return Err("bodies that aren't stationary can't also follow paths".into());
+        }
+        if !file.stationary && file.walls.is_some() {
+            return Err("bodies that aren't stationary can't be kept in walls".into());
+        }
+        if file.particle_mass < 0.0 {
+            return Err("particle_mass can't be negative".into());
+        }
+        if file.stationary && file.accretion {
+            return Err("accretion needs bodies that aren't stationary".into());
+        }
+        let bodies = file.bodies.iter()
+            .map(|body| Body::new(
+                Vec2::new(body.x, body.y),
+                body.mass,
+                body.radius.unwrap_or_else(|| body_radius(body.mass)),
+                body.color,
+            ).with_charge(body.charge).with_shape(body.shape).with_oblateness(body.j2, body.pole_angle).with_velocity(Vec2::new(body.vx, body.vy)))
+            .collect();
+        let force_script = file.force_script.as_deref()
+            .map(ScriptedForce::compile)
+            .transpose()
+            .map_err(|e| format!("force script: {}", e))?;
+        Ok(Self {
+            coulomb_constant: file.coulomb_constant,
+            speed_of_light: file.speed_of_light,
+            restitution: file.restitution,
+            walls: file.walls,
+            particle_charge: file.particle_charge,
+            magnetic_field: file.magnetic_field,
+            drag: file.drag,
+            external_field: file.external_field,
+            force_script,
+            motions: file.motions,
+            stationary: file.stationary,
+            particle_mass: file.particle_mass,
+            accretion: file.accretion,
+            ..Self::new(bodies, file.gravitational_constant)
+        })
+    }
+}
+
+// How often the scene file's modification time is checked
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+// Polls a scene file and reloads it whenever it changes on disk
+pub struct SceneWatcher {
+    pub path: String,
+    modified: Option<SystemTime>,
+    last_check: Instant,
+}
+
+impl SceneWatcher {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            modified: modification_time(path),
+            last_check: Instant::now(),
+        }
+    }
+
+    // Returns the reloaded scene (or the error reading it) if the file changed since the last call
+    pub fn poll(&mut self) -> Option<Result<Scene, Box<dyn std::error::Error>>> {
+        if self.last_check.elapsed() < WATCH_INTERVAL {
+            return None;
+        }
+        self.last_check = Instant::now();
+        let modified = modification_time(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(Scene::load(&self.path))
+    }
+}
+
+fn modification_time(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
@@ -0,0 +1,92 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use image::buffer::ConvertBuffer;
+use image::RgbImage;
+
+use crate::image_gen::MapParams;
+use crate::recording::framebuffer_image;
+use crate::render::{draw_text, fill_rect, text_width};
+use crate::scene::Scene;
+
+const CAPTION_FONT_SIZE: f32 = 14.0;
+const CAPTION_LINE_HEIGHT: f32 = 17.0;
+const CAPTION_MARGIN: f32 = 8.0;
+const CAPTION_PADDING: f32 = 6.0;
+
+// The parameter readout and the time burned into an annotated screenshot
+pub fn caption_lines(params: &MapParams, scene: &Scene, time: SystemTime) -> Vec<String> {
+    let simulation = &params.simulation;
+    vec![
+        params.label(),
+        format!("T={} substeps {} dt={:.4} collision {:.2} G={}",
+                simulation.timesteps, simulation.substeps, simulation.dt, simulation.collision_threshold, scene.gravitational_constant),
+        utc_timestamp(time),
+    ]
+}
+
+// `time` as "YYYY-MM-DD HH:MM:SS UTC"
+pub fn utc_timestamp(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+    let (year, month, day) = civil_date(seconds / 86400);
+    let time_of_day = seconds % 86400;
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+            year, month, day, time_of_day / 3600, time_of_day / 60 % 60, time_of_day % 60)
+}
+
+// Gregorian date of a day counted from 1970-01-01, by counting 400 year eras from 0000-03-01 so
+// leap days fall at the end of each year
+fn civil_date(days: u64) -> (u64, u64, u64) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 { month_from_march + 3 } else { month_from_march - 9 };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    (year, month, day)
+}
+
+// Draws `lines` in the bottom left corner of the image over a translucent dark box
+pub fn burn_in_caption(img: &mut RgbImage, lines: &[String]) {
+    let width = lines.iter().map(|line| text_width(line, CAPTION_FONT_SIZE)).fold(0.0, f32::max) + 2.0 * CAPTION_PADDING;
+    let height = lines.len() as f32 * CAPTION_LINE_HEIGHT + 2.0 * CAPTION_PADDING;
+    let left = CAPTION_MARGIN;
+    let top = (img.height() as f32 - CAPTION_MARGIN - height).max(0.0);
+    fill_rect(img, left as u32, top as u32, width.ceil() as u32, height.ceil() as u32, [0, 0, 0], 0.6);
+    for (i, line) in lines.iter().enumerate() {
+        let y = top + CAPTION_PADDING + i as f32 * CAPTION_LINE_HEIGHT;
+        draw_text(img, line, left + CAPTION_PADDING, y, CAPTION_FONT_SIZE, [255, 255, 255]);
+    }
+}
+
+// The first screenshot_<n>.png from n = `*next` on that doesn't exist yet, so screenshots from
+// earlier sessions are never overwritten. `*next` moves past it.
+pub fn next_screenshot_filename(next: &mut u32) -> String {
+    loop {
+        let filename = format!("screenshot_{}.png", *next);
+        *next += 1;
+        if !Path::new(&filename).exists() {
+            return filename;
+        }
+    }
+}
+
+// Saves a frame read back from the screen as `filename`, with `caption` burned in if given
+pub fn save_screenshot(
+    filename: &str,
+    width: u32,
+    height: u32,
+    bottom_up_rgba: Vec<u8>,
+    caption: Option<&[String]>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let frame = framebuffer_image(width, height, bottom_up_rgba).ok_or("screen data doesn't match the screen size")?;
+    let mut img: RgbImage = frame.convert();
+    if let Some(lines) = caption {
+        burn_in_caption(&mut img, lines);
+    }
+    img.save(filename)?;
+    Ok(())
+}
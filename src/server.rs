@@ -0,0 +1,211 @@
+use std::io::Cursor;
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+use serde::Deserialize;
+use tiny_http::{Header, Request, Response, Server};
+use tungstenite::Message;
+
+use crate::deep_zoom::DoubleDouble;
+use crate::events::SimulationObserver;
+use crate::image_gen::{ensure_map_image, terminal_progress, MapParams};
+use crate::progress::CancelToken;
+use crate::physics::{TestParticle, Vec2};
+use crate::scene::Scene;
+use crate::simulation::{IntegrationMethod, LiveSimulationState, SimulationParams};
+use crate::stream::LaunchRequest;
+
+// Largest map a single request may ask for, so one request can't tie the server up for minutes
+pub const MAX_RENDER_SIZE: u32 = 2048;
+// Timesteps sent per second on WebSocket streams, unless the client asks for another rate
+pub const DEFAULT_TICK_RATE: f32 = 60.0;
+const MAX_TICK_RATE: f32 = 1000.0;
+
+const USAGE: &str = "gravity-wells server\n\n\
+GET /render?vx=0&vy=0&cx=0&cy=0&zoom=1&width=600&height=600&method=rk4\n\
+    Returns the basin map for these parameters as a PNG. Every parameter is optional.\n\
+    cx/cy are the camera offset and method is rk4 or euler.\n";
+
+// Applies the query string of a /render request on top of the base view
+pub fn parse_render_query(query: &str, base: &MapParams) -> Result<MapParams, String> {
+    let mut params = *base;
+    // The camera is kept at full precision so deep zooms stay sharp
+    let mut camera = base.precise_camera();
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let number = || value.parse::<f32>().ok().filter(|v| v.is_finite())
+            .ok_or_else(|| format!("invalid value '{}' for {}", value, key));
+        let precise_number = || value.parse::<f64>().ok().filter(|v| v.is_finite())
+            .ok_or_else(|| format!("invalid value '{}' for {}", value, key));
+        let size = || value.parse::<u32>().ok().filter(|v| (1..=MAX_RENDER_SIZE).contains(v))
+            .ok_or_else(|| format!("{} must be between 1 and {}", key, MAX_RENDER_SIZE));
+        match key {
+            "vx" => params.initial_velocity.x = number()?,
+            "vy" => params.initial_velocity.y = number()?,
+            "cx" => camera.x = DoubleDouble::from_f64(precise_number()?),
+            "cy" => camera.y = DoubleDouble::from_f64(precise_number()?),
+            "zoom" => params.zoom_factor = number()?.max(1e-3),
+            "width" => params.width = size()?,
+            "height" => params.height = size()?,
+            "method" => {
+                params.integration_method = match value {
+                    "rk4" => IntegrationMethod::RungeKutta4,
+                    "euler" => IntegrationMethod::Euler,
+                    _ => return Err(format!("unknown method '{}', expected rk4 or euler", value)),
+                }
+            }
+            _ => return Err(format!("unknown parameter '{}'", key)),
+        }
+    }
+    Ok(params.with_camera(camera))
+}
+
+fn text_response(status: u16, text: &str) -> Response<Cursor<Vec<u8>>> {
+    Response::from_string(text).with_status_code(status)
+}
+
+fn handle_request(request: &Request, scene: &Scene, base: &MapParams, simulation: &SimulationParams) -> Response<Cursor<Vec<u8>>> {
+    let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+    match path {
+        "/" => text_response(200, USAGE),
+        "/render" => {
+            let mut params = match parse_render_query(query, base) {
+                Ok(params) => params,
+                Err(e) => return text_response(400, &e),
+            };
+            // The base's collision threshold was resolved at its zoom, which the query may change
+            params.simulation = simulation.resolved(scene, params.zoom_factor);
+            // Maps go through the same on-disk cache as the viewer, so repeated requests are instant
+            let png = ensure_map_image(scene, &params, &terminal_progress(), &CancelToken::new()).and_then(|filename| Ok(std::fs::read(filename)?));
+            match png {
+                Ok(bytes) => Response::from_data(bytes)
+                    .with_header(Header::from_bytes("Content-Type", "image/png").unwrap()),
+                Err(e) => text_response(500, &format!("error generating map: {}", e)),
+            }
+        }
+        _ => text_response(404, "not found"),
+    }
+}
+
+// First message of a WebSocket stream: the launch to simulate and optionally the tick rate
+#[derive(Debug, Deserialize)]
+struct WebSocketRequest {
+    #[serde(flatten)]
+    launch: LaunchRequest,
+    #[serde(default)]
+    tick_rate: Option<f32>,
+}
+
+// Close approaches of a streamed launch not sent yet
+struct PendingApproaches {
+    id: Option<serde_json::Value>,
+    messages: Vec<serde_json::Value>,
+}
+
+impl SimulationObserver for PendingApproaches {
+    fn on_close_approach(&mut self, timestep: usize, body: usize, distance: f32, _particle: &TestParticle) {
+        self.messages.push(serde_json::json!({ "id": self.id, "close_approach": body, "distance": distance, "timestep": timestep }));
+    }
+}
+
+// Runs one WebSocket client: waits for its launch, then sends the particle's state once per tick,
+// and a message for each close approach, until it collides or runs out of timesteps, and finally
+// a {"done": true, ...} message
+fn stream_trajectory(stream: TcpStream, scene: &Scene, base: &MapParams) -> Result<(), Box<dyn std::error::Error>> {
+    let mut socket = tungstenite::accept(stream).map_err(|e| e.to_string())?;
+    let request: WebSocketRequest = loop {
+        match socket.read()? {
+            Message::Text(text) => match serde_json::from_str(&text) {
+                Ok(request) => break request,
+                Err(e) => socket.send(Message::text(serde_json::json!({ "error": e.to_string() }).to_string()))?,
+            },
+            Message::Close(_) => return Ok(()),
+            _ => {}
+        }
+    };
+
+    let launch = &request.launch;
+    let tick = Duration::from_secs_f32(1.0 / request.tick_rate.unwrap_or(DEFAULT_TICK_RATE).clamp(1.0, MAX_TICK_RATE));
+    let mut sim = LiveSimulationState::new(
+        Vec2::new(launch.x, launch.y),
+        Vec2::new(launch.vx, launch.vy),
+        scene.clone(),
+        base.integration_method,
+        base.simulation,
+    );
+    let mut approaches = PendingApproaches { id: launch.id.clone(), messages: Vec::new() };
+    loop {
+        let state = serde_json::json!({
+            "id": launch.id,
+            "timestep": sim.current_timestep,
+            "x": sim.particle.pos.x,
+            "y": sim.particle.pos.y,
+            "vx": sim.particle.vel.x,
+            "vy": sim.particle.vel.y,
+        });
+        socket.send(Message::text(state.to_string()))?;
+        for message in approaches.messages.drain(..) {
+            socket.send(Message::text(message.to_string()))?;
+        }
+        if sim.is_finished() {
+            break;
+        }
+        std::thread::sleep(tick);
+        sim.step_observed(&mut approaches);
+    }
+    let done = serde_json::json!({ "id": launch.id, "done": true, "body": sim.collision_body_index, "timestep": sim.current_timestep });
+    socket.send(Message::text(done.to_string()))?;
+    socket.close(None)?;
+    // Let the close handshake finish
+    while socket.read().is_ok() {}
+    Ok(())
+}
+
+// Accepts WebSocket clients, each handled on its own thread
+fn serve_websocket(listener: TcpListener, scene: Scene, base: MapParams) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Error accepting WebSocket connection: {}", e);
+                continue;
+            }
+        };
+        let scene = scene.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = stream_trajectory(stream, &scene, &base) {
+                eprintln!("WebSocket stream ended with an error: {}", e);
+            }
+        });
+    }
+}
+
+// Serves basin maps of the scene over HTTP until the process is stopped, and live trajectories
+// over WebSocket if `websocket_address` is given. `base` supplies the defaults for parameters a
+// request leaves out, as well as the integrator and simulation settings for trajectories.
+// `simulation` are the simulation settings before resolving the collision policy, which maps
+// resolve again at the zoom they ask for.
+pub fn serve(
+    address: &str,
+    websocket_address: Option<&str>,
+    scene: &Scene,
+    base: &MapParams,
+    simulation: &SimulationParams,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(websocket_address) = websocket_address {
+        let listener = TcpListener::bind(websocket_address)?;
+        println!("Streaming trajectories on ws://{}", websocket_address);
+        let (scene, base) = (scene.clone(), *base);
+        std::thread::spawn(move || serve_websocket(listener, scene, base));
+    }
+
+    let server = Server::http(address).map_err(|e| e as Box<dyn std::error::Error>)?;
+    println!("Serving basin maps on http://{}/render", address);
+    for request in server.incoming_requests() {
+        let response = handle_request(&request, scene, base, simulation);
+        if let Err(e) = request.respond(response) {
+            eprintln!("Error sending response: {}", e);
+        }
+    }
+    Ok(())
+}
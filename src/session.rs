@@ -0,0 +1,57 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::deep_zoom::DeepPoint;
+use crate::physics::Vec2;
+use crate::scene::Scene;
+use crate::simulation::{IntegrationMethod, SimulationParams};
+use crate::thrust::Burn;
+
+pub const SESSION_FILE: &str = "session.json";
+
+// A live trajectory is stored as its launch conditions, the burns it fired and how far it had
+// run, since the path itself can be recomputed exactly
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedTrajectory {
+    pub start_pos: Vec2,
+    pub initial_velocity: Vec2,
+    pub timestep: usize,
+    #[serde(default)]
+    pub burns: Vec<Burn>,
+}
+
+// Viewer state written when the window closes and restored on the next launch
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub camera_offset: Vec2,
+    // camera_offset at full precision, for deep zooms
+    #[serde(default)]
+    pub deep_camera: Option<DeepPoint>,
+    pub zoom_factor: f32,
+    pub initial_velocity: Vec2,
+    pub integration_method: IntegrationMethod,
+    // Simulation settings the trajectories were run with; sessions saved before they were kept
+    // use the command line's
+    #[serde(default)]
+    pub sim_params: Option<SimulationParams>,
+    pub scene: Scene,
+    pub selected_pixel: Option<(u32, u32)>,
+    pub trajectories: Vec<SavedTrajectory>,
+}
+
+impl Session {
+    // Returns None when no session has been saved yet
+    pub fn load(path: &str) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        if !Path::new(path).exists() {
+            return Ok(None);
+        }
+        let contents = std::fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&contents)?))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
@@ -1,97 +1,639 @@
+use std::borrow::Cow;
+
+use rand::Rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::barnes_hut::{Gravity, BARNES_HUT_BODIES, OPENING_ANGLE};
+use crate::escape::TrajectoryStats;
+use crate::events::{ApproachDetector, SimulationObserver};
+use crate::nbody::{accrete_particle, step_system};
 use crate::physics::*;
+use crate::scene::Scene;
+use crate::thrust::{record_burn, thrust_at, Burn, Thrust};
 
+// Defaults for SimulationParams
 pub const SIMULATION_TIMESTEPS: usize = 2000;
 pub const SUBSTEPS: usize = 10;
+pub const COLLISION_THRESHOLD: f32 = 15.0;
+pub const TIMESTEP_DT: f32 = 0.016;
+
+// Longest stretch of real time the live simulation will catch up on in one frame, so a stalled
+// frame (e.g. while a map generates) doesn't make the particle jump ahead
+const MAX_FRAME_CATCH_UP: f32 = 0.25;
+
+// Most trajectory points a live simulation keeps by default before thinning them out
+pub const TRAJECTORY_POINT_LIMIT: usize = 20_000;
+
 pub const TEST_PARTICLE_MASS: f32 = 1.0;
 pub const TEST_PARTICLE_RADIUS: f32 = 1.0;
-pub const COLLISION_THRESHOLD: f32 = 15.0;
 
-#[derive(Clone, Copy, Debug)]
+// Runtime-adjustable settings controlling how long and how finely each particle is simulated
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SimulationParams {
+    pub timesteps: usize,
+    pub substeps: usize,
+    pub collision_threshold: f32,
+    // Simulated time covered by one timestep; each substep advances dt / substeps
+    pub dt: f32,
+    // Simulate map pixels in f64, for deep zooms where f32 start positions quantize
+    #[serde(default)]
+    pub double_precision: bool,
+    // Barnes-Hut opening angle (0 sums every body exactly) and the body count from which it applies
+    #[serde(default = "default_opening_angle")]
+    pub opening_angle: f32,
+    #[serde(default = "default_barnes_hut_bodies")]
+    pub barnes_hut_bodies: usize,
+    // What collision_threshold is measured in
+    #[serde(default)]
+    pub collision_policy: CollisionPolicy,
+}
+
+fn default_opening_angle() -> f32 {
+    OPENING_ANGLE
+}
+
+fn default_barnes_hut_bodies() -> usize {
+    BARNES_HUT_BODIES
+}
+
+impl SimulationParams {
+    pub fn substep_dt(&self) -> f32 {
+        self.dt / self.substeps as f32
+    }
+
+    // These parameters with the collision threshold in world units, for a view of `scene` at
+    // `zoom_factor` screen pixels per world unit. Every simulation takes its threshold from
+    // collision_threshold as it is, so this is applied wherever a view's parameters are made.
+    pub fn resolved(&self, scene: &Scene, zoom_factor: f32) -> SimulationParams {
+        let collision_threshold = match self.collision_policy {
+            CollisionPolicy::World => self.collision_threshold,
+            CollisionPolicy::Screen => self.collision_threshold / zoom_factor.max(f32::MIN_POSITIVE),
+            CollisionPolicy::BodyRadius => scene.bodies.iter()
+                .filter(|body| body.shape.is_point())
+                .map(|body| body.radius)
+                .reduce(f32::max)
+                .unwrap_or(self.collision_threshold),
+        };
+        SimulationParams { collision_threshold, collision_policy: CollisionPolicy::World, ..*self }
+    }
+}
+
+// How the collision threshold scales with the view
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum CollisionPolicy {
+    // collision_threshold world units from every body, the same at any zoom
+    #[default]
+    World,
+    // collision_threshold screen pixels at the view's zoom, so particles stop just as close to the
+    // drawn bodies whatever the zoom
+    Screen,
+    // The radius the largest point body is drawn with, so particles stop where the bodies visibly
+    // end (collision_threshold only applies to scenes without point bodies)
+    BodyRadius,
+}
+
+impl CollisionPolicy {
+    pub const ALL: [CollisionPolicy; 3] = [CollisionPolicy::World, CollisionPolicy::Screen, CollisionPolicy::BodyRadius];
+
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            CollisionPolicy::World => "world",
+            CollisionPolicy::Screen => "screen",
+            CollisionPolicy::BodyRadius => "body-radius",
+        }
+    }
+}
+
+impl std::str::FromStr for CollisionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CollisionPolicy::ALL.iter()
+            .find(|policy| policy.short_name() == s)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = CollisionPolicy::ALL.iter().map(|policy| policy.short_name()).collect();
+                format!("unknown collision policy '{}', expected one of: {}", s, names.join(", "))
+            })
+    }
+}
+
+impl Default for SimulationParams {
+    fn default() -> Self {
+        Self {
+            timesteps: SIMULATION_TIMESTEPS,
+            substeps: SUBSTEPS,
+            collision_threshold: COLLISION_THRESHOLD,
+            dt: TIMESTEP_DT,
+            double_precision: false,
+            opening_angle: OPENING_ANGLE,
+            barnes_hut_bodies: BARNES_HUT_BODIES,
+            collision_policy: CollisionPolicy::World,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum IntegrationMethod {
     Euler,
     RungeKutta4,
 }
 
+impl IntegrationMethod {
+    pub const ALL: [IntegrationMethod; 2] = [IntegrationMethod::Euler, IntegrationMethod::RungeKutta4];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            IntegrationMethod::Euler => "Euler",
+            IntegrationMethod::RungeKutta4 => "Runge-Kutta 4",
+        }
+    }
+
+    // Advances a particle by one substep of length dt
+    pub fn update(&self, particle: &mut TestParticle, scene: &Scene, dt: f32) {
+        match self {
+            IntegrationMethod::Euler => update_particle_euler(particle, scene, dt),
+            IntegrationMethod::RungeKutta4 => update_particle_rk4(particle, scene, dt),
+        }
+    }
+
+    // Advances a particle by one substep of length dt under any acceleration, e.g. gravity with
+    // thrust on top
+    pub fn step_with(&self, particle: &mut TestParticle, dt: f32, acceleration: impl Fn(&TestParticle) -> Vec2) {
+        match self {
+            IntegrationMethod::Euler => euler_step(particle, dt, acceleration),
+            IntegrationMethod::RungeKutta4 => rk4_step(particle, dt, acceleration),
+        }
+    }
+
+    // Short identifier used in cache filenames
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            IntegrationMethod::Euler => "euler",
+            IntegrationMethod::RungeKutta4 => "rk4",
+        }
+    }
+}
+
+// Recorded state of the particle at one point along its trajectory
+#[derive(Clone, Copy, Debug)]
+pub struct TrajectoryPoint {
+    pub timestep: usize,
+    pub pos: Vec2,
+    pub vel: Vec2,
+}
+
 pub struct LiveSimulationState {
     pub particle: TestParticle,
-    pub stationary_bodies: Vec<StationaryBody>,
-    pub trajectory_history: Vec<Vec2>,
+    pub scene: Scene,
+    pub trajectory_history: Vec<TrajectoryPoint>,
     pub current_timestep: usize,
     pub collision_body_index: Option<usize>,
     pub integration_method: IntegrationMethod,
+    pub params: SimulationParams,
+    // Thrust the controls are firing, applied to every timestep stepped until it is cleared
+    pub manual_thrust: Option<Thrust>,
+    // Scheduled burns and the manual thrust fired so far, so the trajectory can be reproduced
+    pub burns: Vec<Burn>,
+    // Least distance (world units) the particle moves between recorded trajectory points, so a
+    // viewer can keep one point per few screen pixels; 0 records every timestep
+    pub record_spacing: f32,
+    // Most points trajectory_history keeps. Past it every other point is dropped, keeping the
+    // first and the latest, and points are recorded half as often from then on.
+    pub point_limit: usize,
+    // Points ever recorded, including those thinned out since, so callers can tell how many of
+    // the latest points are new
+    pub recorded_points: usize,
+    approach: ApproachDetector,
+    // Real time not yet consumed by a whole timestep
+    time_accumulator: f32,
 }
 
 impl LiveSimulationState {
     pub fn new(
         start_pos: Vec2, 
         initial_velocity: Vec2,
-        stationary_bodies: Vec<StationaryBody>,
-        integration_method: IntegrationMethod
+        scene: Scene,
+        integration_method: IntegrationMethod,
+        params: SimulationParams
     ) -> Self {
-        let particle = TestParticle::new(start_pos, initial_velocity, TEST_PARTICLE_MASS, TEST_PARTICLE_RADIUS);
+        let particle = scene.launch_particle(start_pos, initial_velocity);
         Self {
             particle,
-            stationary_bodies,
-            trajectory_history: vec![start_pos],
+            scene,
+            trajectory_history: vec![TrajectoryPoint { timestep: 0, pos: start_pos, vel: initial_velocity }],
             current_timestep: 0,
             collision_body_index: None,
             integration_method,
+            params,
+            manual_thrust: None,
+            burns: Vec::new(),
+            record_spacing: 0.0,
+            point_limit: TRAJECTORY_POINT_LIMIT,
+            recorded_points: 1,
+            approach: ApproachDetector::default(),
+            time_accumulator: 0.0,
         }
     }
-    
+
+    // Advances the simulation by `elapsed` seconds of real time played back at `speed`x,
+    // running as many whole timesteps as fit
+    pub fn advance(&mut self, elapsed: f32, speed: f32) {
+        self.advance_observed(elapsed, speed, &mut ());
+    }
+
+    // advance, telling `observer` what happens along the way
+    pub fn advance_observed(&mut self, elapsed: f32, speed: f32, observer: &mut impl SimulationObserver) {
+        if self.is_finished() {
+            return;
+        }
+        self.time_accumulator += elapsed.min(MAX_FRAME_CATCH_UP) * speed;
+        while self.time_accumulator >= self.params.dt && !self.is_finished() {
+            self.step_observed(observer);
+            self.time_accumulator -= self.params.dt;
+        }
+    }
+
     pub fn step(&mut self) {
-        if self.current_timestep < SIMULATION_TIMESTEPS && self.collision_body_index.is_none() {
-            let dt = 0.016 / SUBSTEPS as f32;
-            for _ in 0..SUBSTEPS {
-                match self.integration_method {
-                    IntegrationMethod::Euler => {
-                        update_particle_euler(&mut self.particle, &self.stationary_bodies, dt);
-                    }
-                    IntegrationMethod::RungeKutta4 => {
-                        update_particle_rk4(&mut self.particle, &self.stationary_bodies, dt);
+        self.step_observed(&mut ());
+    }
+
+    // step, telling `observer` what happens along the way
+    pub fn step_observed(&mut self, observer: &mut impl SimulationObserver) {
+        if self.current_timestep < self.params.timesteps && self.collision_body_index.is_none() {
+            let dt = self.params.substep_dt();
+            if let Some(thrust) = self.manual_thrust {
+                record_burn(&mut self.burns, self.current_timestep, thrust);
+            }
+            let thrust = thrust_at(&self.burns, self.current_timestep);
+            for _ in 0..self.params.substeps {
+                // The simulation's own copy of the scene carries dynamic bodies along with it
+                if self.scene.is_dynamic() {
+                    let extra = |p: &TestParticle| thrust.map_or(Vec2::new(0.0, 0.0), |thrust| thrust.acceleration(p.vel));
+                    step_system(&mut self.scene, &mut self.particle, self.integration_method, dt, extra);
+                } else {
+                    match thrust {
+                        Some(thrust) => self.integration_method.step_with(&mut self.particle, dt, |p| {
+                            calculate_acceleration(p, &self.scene) + thrust.acceleration(p.vel)
+                        }),
+                        None => self.integration_method.update(&mut self.particle, &self.scene, dt),
                     }
                 }
                 
-                if let Some(collision_index) = check_collision(&self.particle, &self.stationary_bodies, COLLISION_THRESHOLD) {
-                    self.collision_body_index = Some(collision_index);
+                // Bodies are reported by the index they had in the scene the launch started from,
+                // in case accretion has merged some since
+                if let Some(collision_index) = collide(&mut self.particle, &self.scene, self.params.collision_threshold) {
+                    accrete_particle(&mut self.scene, collision_index, &self.particle);
+                    self.collision_body_index = Some(self.scene.original_index(collision_index));
                     break;
                 }
+                if let Some((body, distance)) = self.approach.check(&self.particle, &self.scene, self.params.collision_threshold) {
+                    observer.on_close_approach(self.current_timestep, self.scene.original_index(body), distance, &self.particle);
+                }
             }
             
-            // Record trajectory position every few steps for visualization
-            if self.current_timestep % 5 == 0 {
-                self.trajectory_history.push(self.particle.pos);
+            // Record the trajectory once the particle has moved record_spacing from the last point
+            // recorded, always keeping the final point
+            let last = self.trajectory_history[self.trajectory_history.len() - 1].pos;
+            if self.particle.pos.distance(&last) >= self.record_spacing
+                || self.collision_body_index.is_some()
+                || self.current_timestep + 1 >= self.params.timesteps {
+                self.record(TrajectoryPoint {
+                    timestep: self.current_timestep,
+                    pos: self.particle.pos,
+                    vel: self.particle.vel,
+                });
+            }
+            observer.on_step(self.current_timestep, &self.particle);
+            if let Some(collision_index) = self.collision_body_index {
+                observer.on_collision(self.current_timestep, collision_index);
+            } else if self.current_timestep + 1 >= self.params.timesteps {
+                observer.on_escape(self.current_timestep, &self.particle);
             }
             self.current_timestep += 1;
         }
     }
     
+    fn record(&mut self, point: TrajectoryPoint) {
+        self.trajectory_history.push(point);
+        self.recorded_points += 1;
+        if self.trajectory_history.len() <= self.point_limit.max(2) {
+            return;
+        }
+        let last = self.trajectory_history.len() - 1;
+        let mut index = 0;
+        self.trajectory_history.retain(|_| {
+            let keep = index % 2 == 0 || index == last;
+            index += 1;
+            keep
+        });
+        // The kept points are about twice as far apart as before, which later points match
+        let length: f32 = self.trajectory_history.windows(2).map(|pair| pair[0].pos.distance(&pair[1].pos)).sum();
+        let mean_spacing = length / (self.trajectory_history.len() - 1) as f32;
+        self.record_spacing = (self.record_spacing * 2.0).max(mean_spacing);
+    }
+
+    // Number of recorded trajectory points at or before the given timestep
+    pub fn history_len_at(&self, timestep: usize) -> usize {
+        self.trajectory_history.partition_point(|point| point.timestep <= timestep).max(1)
+    }
+
+    // Last recorded trajectory point at or before the given timestep
+    pub fn point_at(&self, timestep: usize) -> TrajectoryPoint {
+        self.trajectory_history[self.history_len_at(timestep) - 1]
+    }
+    
     pub fn is_finished(&self) -> bool {
-        self.collision_body_index.is_some() || self.current_timestep >= SIMULATION_TIMESTEPS
+        self.collision_body_index.is_some() || self.current_timestep >= self.params.timesteps
+    }
+
+    // Thrust fired during the last timestep stepped, if the engine was on
+    pub fn last_thrust(&self) -> Option<Thrust> {
+        self.current_timestep.checked_sub(1).and_then(|timestep| thrust_at(&self.burns, timestep))
+    }
+
+    // Total velocity change the burns so far could have given the particle
+    pub fn delta_v(&self) -> f32 {
+        self.burns.iter()
+            .map(|burn| Burn { end: burn.end.min(self.current_timestep), ..*burn })
+            .filter(|burn| burn.end > burn.start)
+            .map(|burn| burn.delta_v(&self.params))
+            .sum()
     }
 }
 
-pub fn run_simulation(
-    start_pos: Vec2, 
+// How the particles of an ensemble are scattered around the launch point
+#[derive(Clone, Copy, Debug)]
+pub struct EnsembleSpread {
+    // Radius of the disc start positions are sampled from
+    pub position_radius: f32,
+    // Maximum magnitude of the random velocity offset added to each particle
+    pub velocity_jitter: f32,
+}
+
+// Samples `count` particles uniformly from a disc around `center`, each with a uniformly jittered velocity
+pub fn sample_ensemble(
+    center: Vec2,
     initial_velocity: Vec2,
-    stationary_bodies: &[StationaryBody], 
-    integration_method: IntegrationMethod
-) -> Option<usize> {
-    let mut particle = TestParticle::new(start_pos, initial_velocity, TEST_PARTICLE_MASS, TEST_PARTICLE_RADIUS);
-    let dt = 0.016 / SUBSTEPS as f32;
-
-    for _ in 0..SIMULATION_TIMESTEPS {
-        for _ in 0..SUBSTEPS {
-            match integration_method {
-                IntegrationMethod::Euler => {
-                    update_particle_euler(&mut particle, stationary_bodies, dt);
+    count: usize,
+    spread: EnsembleSpread,
+    rng: &mut impl Rng
+) -> Vec<TestParticle> {
+    // Uniform point in a disc of the given radius
+    let mut sample_disc = |radius: f32| -> Vec2 {
+        let r = radius * rng.random::<f32>().sqrt();
+        let angle = rng.random::<f32>() * std::f32::consts::TAU;
+        Vec2::new(r * angle.cos(), r * angle.sin())
+    };
+
+    (0..count)
+        .map(|_| {
+            let pos = center + sample_disc(spread.position_radius);
+            let vel = initial_velocity + sample_disc(spread.velocity_jitter);
+            TestParticle::new(pos, vel, TEST_PARTICLE_MASS, TEST_PARTICLE_RADIUS)
+        })
+        .collect()
+}
+
+// A cloud of independent test particles stepped together, used for Monte Carlo launches
+pub struct EnsembleSimulation {
+    pub particles: Vec<TestParticle>,
+    // Body each particle collided with, if any; collided particles are no longer stepped
+    pub collisions: Vec<Option<usize>>,
+    pub scene: Scene,
+    // A copy of the scene per particle when its bodies aren't stationary, since every particle
+    // pulls them its own way; empty otherwise
+    pub particle_scenes: Vec<Scene>,
+    pub integration_method: IntegrationMethod,
+    pub params: SimulationParams,
+    pub current_timestep: usize,
+    time_accumulator: f32,
+}
+
+impl EnsembleSimulation {
+    pub fn new(
+        particles: Vec<TestParticle>,
+        scene: Scene,
+        integration_method: IntegrationMethod,
+        params: SimulationParams
+    ) -> Self {
+        let collisions = vec![None; particles.len()];
+        let particles: Vec<TestParticle> = particles.into_iter().map(|particle| particle.with_charge(scene.particle_charge)).collect();
+        let particle_scenes = if scene.is_dynamic() { vec![scene.clone(); particles.len()] } else { Vec::new() };
+        Self {
+            particles,
+            collisions,
+            scene,
+            particle_scenes,
+            integration_method,
+            params,
+            current_timestep: 0,
+            time_accumulator: 0.0,
+        }
+    }
+
+    // Advances every particle that hasn't collided yet by one timestep, in parallel
+    pub fn step(&mut self) {
+        if self.is_finished() {
+            return;
+        }
+
+        let dt = self.params.substep_dt();
+        let scene = &self.scene;
+        let method = self.integration_method;
+        let params = &self.params;
+        if !self.particle_scenes.is_empty() {
+            let particles = self.particles.par_iter_mut().zip(self.collisions.par_iter_mut());
+            particles.zip(self.particle_scenes.par_iter_mut()).for_each(|((particle, collision), scene)| {
+                if collision.is_some() {
+                    return;
                 }
-                IntegrationMethod::RungeKutta4 => {
-                    update_particle_rk4(&mut particle, stationary_bodies, dt);
+                for _ in 0..params.substeps {
+                    step_system(scene, particle, method, dt, |_| Vec2::new(0.0, 0.0));
+                    if let Some(collision_index) = collide(particle, scene, params.collision_threshold) {
+                        accrete_particle(scene, collision_index, particle);
+                        *collision = Some(scene.original_index(collision_index));
+                        break;
+                    }
                 }
+            });
+            self.current_timestep += 1;
+            return;
+        }
+        self.particles.par_iter_mut().zip(self.collisions.par_iter_mut()).for_each(|(particle, collision)| {
+            if collision.is_some() {
+                return;
             }
+            for _ in 0..params.substeps {
+                method.update(particle, scene, dt);
+                if let Some(collision_index) = collide(particle, scene, params.collision_threshold) {
+                    *collision = Some(collision_index);
+                    break;
+                }
+            }
+        });
+        self.current_timestep += 1;
+    }
+
+    // Same real-time accumulator scheme as LiveSimulationState::advance
+    pub fn advance(&mut self, elapsed: f32, speed: f32) {
+        self.time_accumulator += elapsed.min(MAX_FRAME_CATCH_UP) * speed;
+        while self.time_accumulator >= self.params.dt {
+            self.step();
+            self.time_accumulator -= self.params.dt;
+        }
+    }
+
+    // Fraction of the ensemble captured by each body so far
+    pub fn capture_fractions(&self) -> Vec<f32> {
+        let mut counts = vec![0usize; self.scene.bodies.len()];
+        for collision_index in self.collisions.iter().flatten() {
+            counts[*collision_index] += 1;
+        }
+        let total = self.particles.len().max(1) as f32;
+        counts.into_iter().map(|count| count as f32 / total).collect()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.current_timestep >= self.params.timesteps || self.collisions.iter().all(|c| c.is_some())
+    }
+}
+
+// Whether a particle that came within the collision radius of body `index` stops there. It's
+// captured unless the scene has a restitution, and black holes capture it regardless.
+pub fn settles(particle: &mut TestParticle, scene: &Scene, index: usize, collision_threshold: f32) -> bool {
+    let Some(restitution) = scene.restitution else {
+        return true;
+    };
+    let body = scene.bodies_around(particle)[index];
+    body.shape.is_black_hole()
+        || bounce(particle, &body, scene.body_velocity(index, particle.time), restitution, collision_threshold)
+}
+
+// Body the particle stopped at after coming within its collision radius, bouncing it off
+// instead if the scene says so, once any walls have brought it back inside
+pub fn collide(particle: &mut TestParticle, scene: &Scene, collision_threshold: f32) -> Option<usize> {
+    if let Some(walls) = &scene.walls {
+        walls.confine(particle);
+    }
+    check_collision(particle, &scene.bodies_around(particle), collision_threshold)
+        .filter(|&index| settles(particle, scene, index, collision_threshold))
+}
+
+// Cheap low-resolution trajectory used for previews: Euler integration with a single substep per
+// timestep for at most `max_timesteps`, stopping early on collision
+pub fn preview_trajectory(
+    start_pos: Vec2,
+    initial_velocity: Vec2,
+    scene: &Scene,
+    params: &SimulationParams,
+    max_timesteps: usize
+) -> Vec<Vec2> {
+    let mut particle = scene.launch_particle(start_pos, initial_velocity);
+    let mut points = vec![start_pos];
+    let mut scene = Cow::Borrowed(scene);
+    for _ in 0..max_timesteps.min(params.timesteps) {
+        if scene.is_dynamic() {
+            step_system(scene.to_mut(), &mut particle, IntegrationMethod::Euler, params.dt, |_| Vec2::new(0.0, 0.0));
+        } else {
+            update_particle_euler(&mut particle, &scene, params.dt);
+        }
+        points.push(particle.pos);
+        if collide(&mut particle, &scene, params.collision_threshold).is_some() {
+            break;
+        }
+    }
+    points
+}
+
+// Body a particle launched inside its collision radius hits before moving at all. With a
+// restitution it isn't captured there but bounces off on its first step, as in the live viewer
+// and the f64 maps.
+fn immediate_collision(particle: &TestParticle, scene: &Scene, collision_threshold: f32) -> Option<usize> {
+    check_collision(particle, &scene.bodies_around(particle), collision_threshold).filter(|_| !scene.bounces())
+}
+
+// Outcome of a launch that can be decided without integrating: a start inside a body's collision
+// radius hits it straight away (unless the scene bounces particles, which then bounce off on
+// their first step), and a particle that provably can't get within the collision radius of any
+// body escapes. Returns None when the particle has to be simulated.
+pub fn early_outcome(
+    start_pos: Vec2,
+    initial_velocity: Vec2,
+    scene: &Scene,
+    params: &SimulationParams
+) -> Option<Option<(usize, usize)>> {
+    let threshold = params.collision_threshold;
+    let particle = scene.launch_particle(start_pos, initial_velocity);
+    if let Some(index) = immediate_collision(&particle, scene, threshold) {
+        return Some(Some((index, 0)));
+    }
+    if scene.bodies.is_empty() {
+        return Some(None);
+    }
+    // The bounds below rely on every body attracting the particle from where it is, nothing adding
+    // energy, the Newtonian energy being the one conserved and no walls turning the particle back
+    let g = scene.gravitational_constant;
+    if g < 0.0 || scene.bodies.iter().any(|body| body.mass < 0.0) || threshold <= 0.0
+        || !scene.drag.is_dissipative() || scene.has_force_fields() || scene.is_charged() || scene.external_field.is_active()
+        || scene.is_relativistic() || scene.has_oblate_bodies() || scene.has_moving_bodies() || scene.walls.is_some()
+        || scene.is_dynamic() {
+        return None;
+    }
+    let total_gm: f32 = scene.bodies.iter().map(|body| g * body.mass).sum();
+
+    // Energy never grows, and outside every collision radius the potential is never below
+    // -total_gm / threshold, which caps the speed. Bodies further away than the particle can
+    // travel at that speed in the simulated time can't be reached.
+    let energy = total_energy(&particle, scene) / TEST_PARTICLE_MASS;
+    let max_speed = (2.0 * (energy + total_gm / threshold)).max(0.0).sqrt();
+    let reach = max_speed * params.dt * params.timesteps as f32;
+    let nearest = scene.bodies.iter().map(|body| start_pos.distance(&body.pos)).fold(f32::INFINITY, f32::min);
+    if nearest - threshold > reach {
+        return Some(None);
+    }
+
+    // Outside a circle around the bodies, a particle moving outward with more radial kinetic
+    // energy than all the mass at the circle's edge could take away never turns back, unless
+    // drag slows it down too or a magnetic field turns it around. The field does no work, so the
+    // bound above still holds.
+    if scene.drag.is_active() || scene.is_magnetized() {
+        return None;
+    }
+    let count = scene.bodies.len() as f32;
+    let centroid = scene.bodies.iter().fold(Vec2::new(0.0, 0.0), |sum, body| sum + body.pos) / count;
+    let radius = scene.bodies.iter().map(|body| body.pos.distance(&centroid)).fold(0.0, f32::max);
+    let offset = start_pos - centroid;
+    let distance = offset.length();
+    if distance > radius + threshold {
+        let radial_speed = offset.dot(&initial_velocity) / distance;
+        if radial_speed >= 0.0 && 0.5 * radial_speed * radial_speed >= total_gm / (distance - radius) {
+            return Some(None);
+        }
+    }
+    None
+}
+
+pub fn run_simulation(
+    start_pos: Vec2, 
+    initial_velocity: Vec2,
+    scene: &Scene,
+    integration_method: IntegrationMethod,
+    params: &SimulationParams
+) -> Option<usize> {
+    if let Some(outcome) = early_outcome(start_pos, initial_velocity, scene, params) {
+        return outcome.map(|(index, _)| index);
+    }
+    let mut particle = scene.launch_particle(start_pos, initial_velocity);
+    let dt = params.substep_dt();
+    let mut gravity = Gravity::new(scene, params);
+
+    for _ in 0..params.timesteps {
+        for _ in 0..params.substeps {
+            gravity.step(integration_method, &mut particle, dt);
             
-            if let Some(collision_index) = check_collision(&particle, stationary_bodies, COLLISION_THRESHOLD) {
+            if let Some(collision_index) = gravity.collision(&mut particle, params.collision_threshold) {
                 return Some(collision_index);
             }
         }
@@ -102,27 +644,95 @@ pub fn run_simulation(
 pub fn run_simulation_with_time(
     start_pos: Vec2, 
     initial_velocity: Vec2,
-    stationary_bodies: &[StationaryBody], 
-    integration_method: IntegrationMethod
+    scene: &Scene,
+    integration_method: IntegrationMethod,
+    params: &SimulationParams
 ) -> Option<(usize, usize)> {
-    let mut particle = TestParticle::new(start_pos, initial_velocity, TEST_PARTICLE_MASS, TEST_PARTICLE_RADIUS);
-    let dt = 0.016 / SUBSTEPS as f32;
-
-    for timestep in 0..SIMULATION_TIMESTEPS {
-        for _ in 0..SUBSTEPS {
-            match integration_method {
-                IntegrationMethod::Euler => {
-                    update_particle_euler(&mut particle, stationary_bodies, dt);
-                }
-                IntegrationMethod::RungeKutta4 => {
-                    update_particle_rk4(&mut particle, stationary_bodies, dt);
-                }
-            }
+    if let Some(outcome) = early_outcome(start_pos, initial_velocity, scene, params) {
+        return outcome;
+    }
+    let mut particle = scene.launch_particle(start_pos, initial_velocity);
+    let dt = params.substep_dt();
+    let mut gravity = Gravity::new(scene, params);
+
+    for timestep in 0..params.timesteps {
+        for _ in 0..params.substeps {
+            gravity.step(integration_method, &mut particle, dt);
             
-            if let Some(collision_index) = check_collision(&particle, stationary_bodies, COLLISION_THRESHOLD) {
+            if let Some(collision_index) = gravity.collision(&mut particle, params.collision_threshold) {
                 return Some((collision_index, timestep));
             }
         }
     }
     None // No collision
 }
+
+// run_simulation_with_time telling `observer` what happens along the way. Only starts inside a
+// collision radius are decided early, since the observer expects every step of the others.
+pub fn run_simulation_observed(
+    start_pos: Vec2,
+    initial_velocity: Vec2,
+    scene: &Scene,
+    integration_method: IntegrationMethod,
+    params: &SimulationParams,
+    observer: &mut impl SimulationObserver,
+) -> Option<(usize, usize)> {
+    let mut particle = scene.launch_particle(start_pos, initial_velocity);
+    if let Some(index) = immediate_collision(&particle, scene, params.collision_threshold) {
+        observer.on_collision(0, index);
+        return Some((index, 0));
+    }
+    let dt = params.substep_dt();
+    let mut gravity = Gravity::new(scene, params);
+    let mut approach = ApproachDetector::default();
+
+    for timestep in 0..params.timesteps {
+        for _ in 0..params.substeps {
+            gravity.step(integration_method, &mut particle, dt);
+
+            if let Some(collision_index) = gravity.collision(&mut particle, params.collision_threshold) {
+                observer.on_step(timestep, &particle);
+                observer.on_collision(timestep, collision_index);
+                return Some((collision_index, timestep));
+            }
+            if let Some((body, distance)) = approach.check(&particle, gravity.scene(), params.collision_threshold) {
+                observer.on_close_approach(timestep, body, distance, &particle);
+            }
+        }
+        observer.on_step(timestep, &particle);
+    }
+    observer.on_escape(params.timesteps.saturating_sub(1), &particle);
+    None // No collision
+}
+
+// run_simulation_with_time that also accumulates statistics along the trajectory, for coloring
+// pixels that never collide. Only starts inside a collision radius are decided early, since
+// skipping the simulation of an escaping particle would leave its statistics empty.
+pub fn run_simulation_with_stats(
+    start_pos: Vec2,
+    initial_velocity: Vec2,
+    scene: &Scene,
+    integration_method: IntegrationMethod,
+    params: &SimulationParams
+) -> (Option<(usize, usize)>, TrajectoryStats) {
+    let mut stats = TrajectoryStats::new();
+    let mut particle = scene.launch_particle(start_pos, initial_velocity);
+    stats.record(&particle, scene, params);
+    if let Some(index) = immediate_collision(&particle, scene, params.collision_threshold) {
+        return (Some((index, 0)), stats);
+    }
+    let dt = params.substep_dt();
+    let mut gravity = Gravity::new(scene, params);
+
+    for timestep in 0..params.timesteps {
+        for _ in 0..params.substeps {
+            gravity.step(integration_method, &mut particle, dt);
+            stats.record(&particle, gravity.scene(), params);
+
+            if let Some(collision_index) = gravity.collision(&mut particle, params.collision_threshold) {
+                return (Some((collision_index, timestep)), stats);
+            }
+        }
+    }
+    (None, stats)
+}
@@ -0,0 +1,145 @@
+#[cfg(feature = "audio")]
+use macroquad::audio::{load_sound_from_bytes, play_sound, PlaySoundParams, Sound};
+
+// Close approaches at this speed or faster whoosh at full volume, slower ones more quietly
+#[cfg(feature = "audio")]
+const WHOOSH_FULL_SPEED: f32 = 600.0;
+#[cfg(feature = "audio")]
+const WHOOSH_MIN_VOLUME: f32 = 0.15;
+
+// Something a live launch did that has a sound. Only the `audio` feature listens to them.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+pub enum SoundCue {
+    Collision { body: usize },
+    CloseApproach { speed: f32 },
+}
+
+// Sound effects for live launches: a tone when a particle hits a body, pitched by which body it
+// is, and a whoosh when it swings past one, louder the faster it goes. Without the `audio`
+// feature there is nothing to play and the effects stay off.
+pub struct SoundEffects {
+    pub enabled: bool,
+    #[cfg(feature = "audio")]
+    tones: Vec<Sound>,
+    #[cfg(feature = "audio")]
+    whoosh: Sound,
+}
+
+impl SoundEffects {
+    #[cfg(feature = "audio")]
+    pub async fn load() -> Self {
+        let mut tones = Vec::new();
+        for semitones in synth::SCALE_SEMITONES {
+            let tone = synth::tone(synth::note_frequency(semitones));
+            tones.push(load_sound_from_bytes(&synth::wav(&tone)).await.expect("synthesized WAV is valid"));
+        }
+        let whoosh = load_sound_from_bytes(&synth::wav(&synth::whoosh())).await.expect("synthesized WAV is valid");
+        Self { enabled: true, tones, whoosh }
+    }
+
+    #[cfg(not(feature = "audio"))]
+    pub async fn load() -> Self {
+        Self { enabled: false }
+    }
+
+    pub fn available(&self) -> bool {
+        cfg!(feature = "audio")
+    }
+
+    // Plays the cues of one frame. A cloud of launches can do the same thing many times in a
+    // frame, so each body's tone plays at most once and only the fastest approach whooshes.
+    #[cfg(feature = "audio")]
+    pub fn play(&self, cues: &[SoundCue]) {
+        if !self.enabled {
+            return;
+        }
+        let mut hit = vec![false; self.tones.len()];
+        let mut fastest: Option<f32> = None;
+        for cue in cues {
+            match *cue {
+                SoundCue::Collision { body } => hit[body % self.tones.len()] = true,
+                SoundCue::CloseApproach { speed } => fastest = Some(fastest.map_or(speed, |fastest| fastest.max(speed))),
+            }
+        }
+        for (tone, _) in self.tones.iter().zip(hit).filter(|(_, hit)| *hit) {
+            play_sound(tone, PlaySoundParams { looped: false, volume: 0.6 });
+        }
+        if let Some(speed) = fastest {
+            let volume = (speed / WHOOSH_FULL_SPEED).clamp(WHOOSH_MIN_VOLUME, 1.0);
+            play_sound(&self.whoosh, PlaySoundParams { looped: false, volume });
+        }
+    }
+
+    #[cfg(not(feature = "audio"))]
+    pub fn play(&self, _cues: &[SoundCue]) {}
+}
+
+// The effects are synthesized when the viewer starts, so there are no sound files to ship
+#[cfg(feature = "audio")]
+mod synth {
+    // Sample rate of the synthesized sounds
+    const SAMPLE_RATE: u32 = 22050;
+    // Collision tones walk up a major pentatonic scale from A3, one note per body, so any two
+    // bodies sound well together
+    const BASE_FREQUENCY: f32 = 220.0;
+    pub const SCALE_SEMITONES: [f32; 10] = [0.0, 2.0, 4.0, 7.0, 9.0, 12.0, 14.0, 16.0, 19.0, 21.0];
+    const TONE_SECONDS: f32 = 0.4;
+    const WHOOSH_SECONDS: f32 = 0.45;
+
+    pub fn note_frequency(semitones: f32) -> f32 {
+        BASE_FREQUENCY * 2f32.powf(semitones / 12.0)
+    }
+
+    // A struck tone: a sine with a little of its octave, starting quickly and dying away
+    pub fn tone(frequency: f32) -> Vec<f32> {
+        let len = (TONE_SECONDS * SAMPLE_RATE as f32) as usize;
+        (0..len)
+            .map(|i| {
+                let t = i as f32 / SAMPLE_RATE as f32;
+                let phase = std::f32::consts::TAU * frequency * t;
+                let envelope = (t / 0.005).min(1.0) * (-t * 8.0).exp();
+                envelope * (0.8 * phase.sin() + 0.2 * (2.0 * phase).sin())
+            })
+            .collect()
+    }
+
+    // Noise through a low-pass filter that opens and closes again, swelling and fading with it
+    pub fn whoosh() -> Vec<f32> {
+        use ::rand::{Rng, SeedableRng};
+
+        let mut rng = ::rand::rngs::StdRng::seed_from_u64(0);
+        let len = (WHOOSH_SECONDS * SAMPLE_RATE as f32) as usize;
+        let mut filtered = 0.0;
+        (0..len)
+            .map(|i| {
+                let swell = (std::f32::consts::PI * i as f32 / len as f32).sin();
+                let smoothing = 0.02 + 0.2 * swell;
+                filtered += smoothing * (rng.random_range(-1.0..1.0) - filtered);
+                swell * swell * filtered * 2.0
+            })
+            .collect()
+    }
+
+    // 16-bit mono PCM WAV file of samples between -1 and 1
+    pub fn wav(samples: &[f32]) -> Vec<u8> {
+        let data_len = 2 * samples.len() as u32;
+        let mut bytes = Vec::with_capacity(44 + data_len as usize);
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        bytes.extend_from_slice(&1u16.to_le_bytes()); // mono
+        bytes.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+        bytes.extend_from_slice(&(2 * SAMPLE_RATE).to_le_bytes());
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_len.to_le_bytes());
+        for sample in samples {
+            bytes.extend_from_slice(&((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes());
+        }
+        bytes
+    }
+}
@@ -0,0 +1,130 @@
+use std::sync::atomic::AtomicUsize;
+
+use image::{ImageBuffer, Rgb, RgbImage};
+use rayon::prelude::*;
+
+use crate::image_gen::count_pixel;
+use crate::nbody::step_bodies;
+use crate::physics::{Body, Vec2};
+
+// Velocity-space stability explorer: three equal bodies in a row, the outer two launched along y
+// with speeds picked by the pixel, and the map shaded by how long they last before any two collide.
+// The bodies all move and pull on each other, unlike the main app's test particle.
+
+// Size of the square stability map
+pub const STABILITY_MAP_SIZE: u32 = 400;
+pub const STABILITY_TIMESTEPS: usize = 1000;
+pub const STABILITY_SUBSTEPS: usize = 50;
+const STABILITY_DT: f32 = 0.016;
+// The explorer's bodies are heavy enough to orbit at these speeds with G = 1
+const STABILITY_G: f32 = 1.0;
+const STABILITY_BODY_MASS: f32 = 160000.0;
+const STABILITY_BODY_RADIUS: f32 = 1.0;
+const STABILITY_BODY_OFFSET: f32 = 50.0;
+// Initial y velocity of the right body across the map (left to right) and of the left body (top to bottom)
+pub const VY1_RANGE: (f32, f32) = (0.0, 80.0);
+pub const VY2_RANGE: (f32, f32) = (-80.0, 0.0);
+pub const STABILITY_BODY_COLORS: [[u8; 3]; 3] = [[253, 249, 0], [0, 121, 241], [255, 0, 255]];
+
+// Initial y velocities of the two outer bodies at pixel (px, py) of a map `size` pixels across
+pub fn pixel_velocities(px: u32, py: u32, size: u32) -> (f32, f32) {
+    let span = (size.max(2) - 1) as f32;
+    let vy1 = VY1_RANGE.0 + (VY1_RANGE.1 - VY1_RANGE.0) * (px as f32 / span);
+    let vy2 = VY2_RANGE.0 + (VY2_RANGE.1 - VY2_RANGE.0) * (py as f32 / span);
+    (vy1, vy2)
+}
+
+// The three bodies at launch: right moving at vy1, left at vy2 and the middle one at rest
+pub fn initial_bodies(vy1: f32, vy2: f32) -> Vec<Body> {
+    [(STABILITY_BODY_OFFSET, vy1), (-STABILITY_BODY_OFFSET, vy2), (0.0, 0.0)].iter()
+        .zip(STABILITY_BODY_COLORS)
+        .map(|((x, vy), color)| {
+            Body::new(Vec2::new(*x, 0.0), STABILITY_BODY_MASS, STABILITY_BODY_RADIUS, color).with_velocity(Vec2::new(0.0, *vy))
+        })
+        .collect()
+}
+
+// Whether any two bodies touch
+pub fn collided(bodies: &[Body]) -> bool {
+    bodies.iter().enumerate().any(|(i, body)| {
+        bodies[i + 1..].iter().any(|other| (body.pos - other.pos).length() < body.radius + other.radius)
+    })
+}
+
+// Advances the bodies by one timestep, stopping at the substep they collide in. Returns whether they did.
+pub fn step_timestep(bodies: &mut [Body]) -> bool {
+    let dt = STABILITY_DT / STABILITY_SUBSTEPS as f32;
+    for _ in 0..STABILITY_SUBSTEPS {
+        step_bodies(bodies, STABILITY_G, dt);
+        if collided(bodies) {
+            return true;
+        }
+    }
+    false
+}
+
+// Timestep of the first collision, or STABILITY_TIMESTEPS if the bodies never touch
+pub fn time_to_collision(mut bodies: Vec<Body>) -> usize {
+    (0..STABILITY_TIMESTEPS)
+        .find(|_| step_timestep(&mut bodies))
+        .unwrap_or(STABILITY_TIMESTEPS)
+}
+
+// Stability map `size` pixels across, brighter for systems that last longer
+pub fn render_stability_map(size: u32, progress: &(impl Fn(usize, usize) + Sync)) -> RgbImage {
+    let total = (size * size) as usize;
+    let counter = AtomicUsize::new(0);
+    let pixels: Vec<Rgb<u8>> = (0..total).into_par_iter().map(|i| {
+        let (vy1, vy2) = pixel_velocities(i as u32 % size, i as u32 / size, size);
+        let time = time_to_collision(initial_bodies(vy1, vy2));
+        count_pixel(&counter, total, progress);
+        let value = (time as f32 / STABILITY_TIMESTEPS as f32 * 255.0) as u8;
+        Rgb([value, value, value])
+    }).collect();
+    ImageBuffer::from_fn(size, size, |px, py| pixels[(py * size + px) as usize])
+}
+
+// The three bodies launched from a clicked pixel, with the path each has traced
+pub struct StabilitySimulation {
+    pub bodies: Vec<Body>,
+    pub trails: Vec<Vec<Vec2>>,
+    pub current_timestep: usize,
+    pub collided: bool,
+}
+
+impl StabilitySimulation {
+    pub fn new(vy1: f32, vy2: f32) -> Self {
+        let bodies = initial_bodies(vy1, vy2);
+        let trails = bodies.iter().map(|body| vec![body.pos]).collect();
+        Self { bodies, trails, current_timestep: 0, collided: false }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.collided || self.current_timestep >= STABILITY_TIMESTEPS
+    }
+
+    pub fn update(&mut self) {
+        if self.is_finished() {
+            return;
+        }
+        self.collided = step_timestep(&mut self.bodies);
+        if !self.collided {
+            self.current_timestep += 1;
+            for (trail, body) in self.trails.iter_mut().zip(&self.bodies) {
+                trail.push(body.pos);
+            }
+        }
+    }
+
+    pub fn center_of_mass(&self) -> Vec2 {
+        let total_mass: f32 = self.bodies.iter().map(|body| body.mass).sum();
+        self.bodies.iter().fold(Vec2::new(0.0, 0.0), |sum, body| sum + body.pos * body.mass) / total_mass
+    }
+
+    // Corners of the box around every trail
+    pub fn bounds(&self) -> (Vec2, Vec2) {
+        self.trails.iter().flatten().fold((self.bodies[0].pos, self.bodies[0].pos), |(min, max), p| {
+            (Vec2::new(min.x.min(p.x), min.y.min(p.y)), Vec2::new(max.x.max(p.x), max.y.max(p.y)))
+        })
+    }
+}
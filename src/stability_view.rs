@@ -0,0 +1,109 @@
+use macroquad::prelude::*;
+
+use gravity_wells::image_gen::terminal_progress;
+use gravity_wells::physics::Vec2;
+use gravity_wells::stability::{pixel_velocities, render_stability_map, StabilitySimulation, STABILITY_TIMESTEPS};
+
+use crate::hud::{draw_panel, HudCorner, HudLine};
+use crate::texture_from_image;
+
+// Margin left around the trails when the launch view zooms to fit them
+const FIT_PADDING: f32 = 1.2;
+// Largest on-screen radius of a body in the launch view, however far it zooms in
+const MAX_BODY_RADIUS: f32 = 10.0;
+
+pub fn window_conf(size: u32) -> Conf {
+    Conf {
+        window_title: "Stability Explorer".to_owned(),
+        window_width: 2 * size as i32,
+        window_height: size as i32,
+        ..Default::default()
+    }
+}
+
+// Map cached at `path` if it has the requested size, otherwise a freshly generated one saved there
+fn load_or_render_map(size: u32, path: &str) -> image::RgbImage {
+    if let Ok(img) = image::open(path) {
+        if img.width() == size && img.height() == size {
+            println!("Found existing {}, skipping generation.", path);
+            return img.to_rgb8();
+        }
+    }
+    println!("Generating {}x{} stability map...", size, size);
+    let img = render_stability_map(size, &terminal_progress());
+    match img.save(path) {
+        Ok(()) => println!("Stability map saved to {}", path),
+        Err(e) => eprintln!("Error saving stability map: {}", e),
+    }
+    img
+}
+
+// `stability` subcommand: the stability map on the left, and on the right the launch of the
+// clicked pixel, zoomed to fit the bodies' trails around their center of mass
+pub async fn run_stability_explorer(size: u32, path: String) {
+    let texture = texture_from_image(&load_or_render_map(size, &path));
+    let map_size = size as f32;
+    let mut simulation: Option<StabilitySimulation> = None;
+    let mut selected: Option<(u32, u32)> = None;
+
+    loop {
+        clear_background(BLACK);
+        draw_texture(&texture, 0.0, 0.0, WHITE);
+        draw_rectangle(map_size, 0.0, map_size, map_size, DARKGRAY);
+
+        if is_mouse_button_pressed(MouseButton::Left) {
+            let (mx, my) = mouse_position();
+            if mx >= 0.0 && mx < map_size && my >= 0.0 && my < map_size {
+                let (px, py) = (mx as u32, my as u32);
+                let (vy1, vy2) = pixel_velocities(px, py, size);
+                selected = Some((px, py));
+                simulation = Some(StabilitySimulation::new(vy1, vy2));
+            }
+        }
+
+        if let Some((px, py)) = selected {
+            draw_rectangle_lines(px as f32 - 2.0, py as f32 - 2.0, 4.0, 4.0, 2.0, RED);
+        }
+
+        if let Some(sim) = &mut simulation {
+            sim.update();
+
+            let (min, max) = sim.bounds();
+            let extent = max - min;
+            let zoom_x = if extent.x > 0.0 { map_size / extent.x / FIT_PADDING } else { 1.0 };
+            let zoom_y = if extent.y > 0.0 { map_size / extent.y / FIT_PADDING } else { 1.0 };
+            let zoom = zoom_x.min(zoom_y);
+            let center_of_mass = sim.center_of_mass();
+            let screen_center = Vec2::new(map_size * 1.5, map_size / 2.0);
+            let transform = |p: Vec2| (p - center_of_mass) * zoom + screen_center;
+
+            for (trail, body) in sim.trails.iter().zip(&sim.bodies) {
+                let color = Color::from_rgba(body.color[0], body.color[1], body.color[2], 255);
+                for segment in trail.windows(2) {
+                    let (from, to) = (transform(segment[0]), transform(segment[1]));
+                    draw_line(from.x, from.y, to.x, to.y, 1.0, color);
+                }
+            }
+            for body in &sim.bodies {
+                let p = transform(body.pos);
+                let color = Color::from_rgba(body.color[0], body.color[1], body.color[2], 255);
+                draw_circle(p.x, p.y, body.radius * zoom.min(MAX_BODY_RADIUS), color);
+            }
+
+            let (vy1, vy2) = selected.map_or((0.0, 0.0), |(px, py)| pixel_velocities(px, py, size));
+            let status = if sim.collided {
+                format!("Collided at timestep {}", sim.current_timestep)
+            } else if sim.is_finished() {
+                format!("No collision in {} timesteps", STABILITY_TIMESTEPS)
+            } else {
+                format!("Timestep {}", sim.current_timestep)
+            };
+            draw_panel(&[
+                HudLine::new(format!("vy1: {:.2}  vy2: {:.2}", vy1, vy2), SKYBLUE),
+                HudLine::new(status, SKYBLUE),
+            ], HudCorner::BottomRight);
+        }
+
+        next_frame().await;
+    }
+}
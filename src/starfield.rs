@@ -0,0 +1,81 @@
+use ::rand::rngs::StdRng;
+use ::rand::{Rng, SeedableRng};
+use macroquad::prelude::{draw_rectangle, Color};
+
+use gravity_wells::camera::Camera;
+use gravity_wells::physics::Vec2;
+
+// Side of a tile of stars on screen at the start of a zoom level; zooming in spreads a level's
+// tiles out to twice this before the next level takes over
+const TILE_SIZE: f32 = 160.0;
+
+// A layer of stars at one depth. Parallax is how much of the camera's motion the layer follows:
+// its stars pan by that fraction of the view's pan and zoom by that power of its zoom, so nearer
+// layers move more and the view seems to move through them.
+struct StarLayer {
+    parallax: f32,
+    stars_per_tile: usize,
+    // Size of its stars in pixels and their brightest shade
+    size: f32,
+    brightness: f32,
+}
+
+const LAYERS: [StarLayer; 3] = [
+    StarLayer { parallax: 0.1, stars_per_tile: 12, size: 1.0, brightness: 0.45 },
+    StarLayer { parallax: 0.3, stars_per_tile: 5, size: 1.5, brightness: 0.7 },
+    StarLayer { parallax: 0.6, stars_per_tile: 2, size: 2.0, brightness: 0.95 },
+];
+
+// Mixes a tile's coordinates into the seed of its stars (the splitmix64 finalizer), so every tile
+// of every layer and zoom level has its own stars and gets the same ones each time it's drawn
+fn tile_seed(seed: u64, layer: usize, level: i32, x: i64, y: i64) -> u64 {
+    let mut h = seed;
+    for part in [layer as u64, level as u64, x as u64, y as u64] {
+        h = (h ^ part).wrapping_add(0x9e37_79b9_7f4a_7c15);
+        h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        h ^= h >> 31;
+    }
+    h
+}
+
+// Draws purely cosmetic stars behind the map, which show the camera moving where the map doesn't
+// cover the view. They are generated from `seed`, so a scene always has the same sky. The layers
+// are worked out in f64, since deep zooms would scatter f32 tile coordinates.
+pub fn draw_starfield(view: &Camera, seed: u64) {
+    for (index, layer) in LAYERS.iter().enumerate() {
+        let parallax = layer.parallax as f64;
+        let zoom = (view.zoom as f64).powf(parallax);
+        let offset = (view.offset.x as f64 * parallax, view.offset.y as f64 * parallax);
+        // Zooming in spreads a level's stars apart while the next level's denser ones fade in,
+        // so the sky keeps about the same density at any zoom
+        let level = zoom.log2().floor();
+        let fade = (zoom.log2() - level) as f32;
+        for (level, alpha) in [(level as i32, 1.0 - fade), (level as i32 + 1, fade)] {
+            if alpha > 0.0 {
+                draw_level(seed, index, level, alpha, offset, zoom, view.viewport);
+            }
+        }
+    }
+}
+
+// One zoom level of a layer, whose point p lands on screen at (p + offset) * zoom
+fn draw_level(seed: u64, index: usize, level: i32, alpha: f32, offset: (f64, f64), zoom: f64, viewport: Vec2) {
+    let layer = &LAYERS[index];
+    let tile = TILE_SIZE as f64 / 2f64.powi(level);
+    let tiles = |offset: f64, extent: f32| (-offset / tile).floor() as i64..=((extent as f64 / zoom - offset) / tile).floor() as i64;
+    for y in tiles(offset.1, viewport.y) {
+        for x in tiles(offset.0, viewport.x) {
+            let mut rng = StdRng::seed_from_u64(tile_seed(seed, index, level, x, y));
+            let corner = ((x as f64 * tile + offset.0) * zoom, (y as f64 * tile + offset.1) * zoom);
+            for _ in 0..layer.stars_per_tile {
+                let (sx, sy) = (corner.0 + rng.random::<f64>() * tile * zoom, corner.1 + rng.random::<f64>() * tile * zoom);
+                let shade = layer.brightness * rng.random_range(0.4..1.0);
+                // A slight blue or yellow tint, like real stars
+                let tint = rng.random_range(-0.1..0.1);
+                let color = Color::new(shade * (1.0 + tint), shade, shade * (1.0 - tint), alpha);
+                draw_rectangle(sx as f32, sy as f32, layer.size, layer.size, color);
+            }
+        }
+    }
+}
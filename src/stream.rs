@@ -0,0 +1,100 @@
+use std::io::{BufRead, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::barnes_hut::Gravity;
+use crate::physics::Vec2;
+use crate::scene::Scene;
+use crate::simulation::{IntegrationMethod, SimulationParams};
+
+// One line of input: initial conditions of a launch
+#[derive(Clone, Debug, Deserialize)]
+pub struct LaunchRequest {
+    // Echoed back unchanged so callers can match results to requests
+    #[serde(default)]
+    pub id: Option<serde_json::Value>,
+    pub x: f32,
+    pub y: f32,
+    #[serde(default)]
+    pub vx: f32,
+    #[serde(default)]
+    pub vy: f32,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct FinalState {
+    pub x: f32,
+    pub y: f32,
+    pub vx: f32,
+    pub vy: f32,
+}
+
+// One line of output
+#[derive(Clone, Debug, Serialize)]
+pub struct LaunchResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<serde_json::Value>,
+    // Index of the body hit, or null if the particle survived every timestep
+    pub body: Option<usize>,
+    // Timestep of the collision and the simulated time it happened at
+    pub timestep: Option<usize>,
+    pub time: Option<f32>,
+    #[serde(rename = "final")]
+    pub final_state: FinalState,
+}
+
+pub fn simulate_launch(
+    request: &LaunchRequest,
+    scene: &Scene,
+    integration_method: IntegrationMethod,
+    params: &SimulationParams,
+) -> LaunchResult {
+    let start = Vec2::new(request.x, request.y);
+    let mut particle = scene.launch_particle(start, Vec2::new(request.vx, request.vy));
+    let dt = params.substep_dt();
+    let mut gravity = Gravity::new(scene, params);
+    let mut collision = None;
+
+    'timesteps: for timestep in 0..params.timesteps {
+        for substep in 0..params.substeps {
+            gravity.step(integration_method, &mut particle, dt);
+            if let Some(body) = gravity.collision(&mut particle, params.collision_threshold) {
+                collision = Some((body, timestep, timestep as f32 * params.dt + (substep + 1) as f32 * dt));
+                break 'timesteps;
+            }
+        }
+    }
+
+    LaunchResult {
+        id: request.id.clone(),
+        body: collision.map(|(body, _, _)| body),
+        timestep: collision.map(|(_, timestep, _)| timestep),
+        time: collision.map(|(_, _, time)| time),
+        final_state: FinalState { x: particle.pos.x, y: particle.pos.y, vx: particle.vel.x, vy: particle.vel.y },
+    }
+}
+
+// Reads one JSON launch per line and writes one JSON result per line, flushing after each so
+// the output can be consumed while input is still arriving. Lines that fail to parse produce
+// an {"error": ...} line instead of stopping the stream; blank lines are skipped.
+pub fn run_stream(
+    input: impl BufRead,
+    mut output: impl Write,
+    scene: &Scene,
+    integration_method: IntegrationMethod,
+    params: &SimulationParams,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<LaunchRequest>(&line) {
+            Ok(request) => serde_json::to_string(&simulate_launch(&request, scene, integration_method, params))?,
+            Err(e) => serde_json::json!({ "error": e.to_string() }).to_string(),
+        };
+        writeln!(output, "{}", response)?;
+        output.flush()?;
+    }
+    Ok(())
+}
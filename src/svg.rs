@@ -0,0 +1,96 @@
+use std::fmt::Write as _;
+
+use crate::config::{BODY_STROKE_WIDTH, DEFAULT_NON_COLLISION_COLOR};
+use crate::contours::extract_boundaries;
+use crate::force_field::BodyShape;
+use crate::image_gen::MapParams;
+use crate::outcome::OutcomeGrid;
+use crate::physics::Vec2;
+use crate::scene::Scene;
+
+const TRAJECTORY_WIDTH: f32 = 1.5;
+const BODY_OUTLINE_WIDTH: f32 = 2.0;
+const BOUNDARY_WIDTH: f32 = 0.75;
+
+// Trajectory drawn as a polyline, in world coordinates
+pub struct SvgTrajectory {
+    pub points: Vec<Vec2>,
+    pub color: [u8; 3],
+}
+
+fn rgb(color: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+fn polyline(points: impl IntoIterator<Item = Vec2>, color: &str, width: f32) -> String {
+    let mut coordinates = String::new();
+    for p in points {
+        let _ = write!(coordinates, "{:.2},{:.2} ", p.x, p.y);
+    }
+    format!(r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="{}" stroke-linejoin="round"/>"#,
+            coordinates.trim_end(), color, width)
+}
+
+// Writes the view in `params` as an SVG: the basin boundaries of `basins` if given, the
+// trajectories as polylines and the bodies as outlined circles, all in view pixel coordinates
+pub fn save_svg(
+    path: &str,
+    scene: &Scene,
+    params: &MapParams,
+    trajectories: &[SvgTrajectory],
+    basins: Option<&OutcomeGrid>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let to_view = |p: Vec2| (p + params.camera_offset) * params.zoom_factor;
+    let mut svg = String::new();
+    writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+             w = params.width, h = params.height)?;
+    writeln!(svg, r#"<rect width="100%" height="100%" fill="{}"/>"#, rgb(DEFAULT_NON_COLLISION_COLOR))?;
+
+    // Basin outlines in the color of their body; the region that never collides is left out, as
+    // its outline repeats those of the basins around it
+    if let Some(grid) = basins {
+        let scale = params.width as f32 / grid.width.max(1) as f32;
+        for boundary in extract_boundaries(grid) {
+            let Some(body) = boundary.body.and_then(|index| scene.bodies.get(index)) else {
+                continue;
+            };
+            writeln!(svg, "{}", polyline(boundary.points.iter().map(|&p| p * scale), &rgb(body.color), BOUNDARY_WIDTH))?;
+        }
+    }
+
+    for trajectory in trajectories.iter().filter(|trajectory| trajectory.points.len() >= 2) {
+        writeln!(svg, "{}", polyline(trajectory.points.iter().map(|&p| to_view(p)), &rgb(trajectory.color), TRAJECTORY_WIDTH))?;
+    }
+
+    for body in &scene.bodies {
+        let center = to_view(body.pos);
+        match body.shape {
+            // Lines with a dark outline underneath, as drawn in the viewer
+            BodyShape::Ring { radius } => {
+                for (color, width) in [("black".to_string(), BODY_STROKE_WIDTH + 2.0 * BODY_OUTLINE_WIDTH), (rgb(body.color), BODY_STROKE_WIDTH)] {
+                    writeln!(svg, r#"<circle cx="{:.2}" cy="{:.2}" r="{:.2}" fill="none" stroke="{}" stroke-width="{}"/>"#,
+                             center.x, center.y, radius * params.zoom_factor, color, width)?;
+                }
+            }
+            BodyShape::Segment { .. } => {
+                let Some((start, end)) = body.shape.ends() else { continue };
+                for (color, width) in [("black".to_string(), BODY_STROKE_WIDTH + 2.0 * BODY_OUTLINE_WIDTH), (rgb(body.color), BODY_STROKE_WIDTH)] {
+                    writeln!(svg, "{}", polyline([to_view(body.pos + start), to_view(body.pos + end)], &color, width))?;
+                }
+            }
+            shape => {
+                let radius = match shape {
+                    BodyShape::Disc { radius } | BodyShape::BlackHole { schwarzschild_radius: radius } => radius,
+                    _ => body.radius,
+                };
+                writeln!(svg, r#"<circle cx="{:.2}" cy="{:.2}" r="{:.2}" fill="{}" stroke="black" stroke-width="{}"/>"#,
+                         center.x, center.y, radius * params.zoom_factor + BODY_OUTLINE_WIDTH / 2.0, rgb(body.color), BODY_OUTLINE_WIDTH)?;
+            }
+        }
+    }
+    writeln!(svg, "</svg>")?;
+
+    std::fs::write(path, svg)?;
+    println!("SVG saved to {}", path);
+    Ok(())
+}
@@ -0,0 +1,217 @@
+use image::{imageops, Rgb, RgbImage};
+
+use crate::config::body_radius;
+use crate::image_gen::{compute_outcome_grid, render_outcome_grid, MapParams};
+use crate::physics::Vec2;
+use crate::render::{draw_text, text_width};
+use crate::scene::Scene;
+
+// Space around and between the cells of a montage
+const MONTAGE_MARGIN: u32 = 8;
+// Height of the column headers
+const LABEL_HEIGHT: u32 = 18;
+const LABEL_SIZE: f32 = 13.0;
+const MONTAGE_BACKGROUND: [u8; 3] = [24, 24, 24];
+
+// Parameter varied along one axis of a sweep
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SweepParameter {
+    // Magnitude of the initial velocity, keeping its direction (along +x if it starts at rest)
+    Speed,
+    // Direction of the initial velocity in degrees, keeping its magnitude
+    Angle,
+    VelocityX,
+    VelocityY,
+    Mass,
+    PositionX,
+    PositionY,
+    Gravity,
+}
+
+impl SweepParameter {
+    pub const ALL: [SweepParameter; 8] = [
+        SweepParameter::Speed,
+        SweepParameter::Angle,
+        SweepParameter::VelocityX,
+        SweepParameter::VelocityY,
+        SweepParameter::Mass,
+        SweepParameter::PositionX,
+        SweepParameter::PositionY,
+        SweepParameter::Gravity,
+    ];
+
+    // Identifier used on the command line and in labels
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            SweepParameter::Speed => "speed",
+            SweepParameter::Angle => "angle",
+            SweepParameter::VelocityX => "velocity-x",
+            SweepParameter::VelocityY => "velocity-y",
+            SweepParameter::Mass => "mass",
+            SweepParameter::PositionX => "position-x",
+            SweepParameter::PositionY => "position-y",
+            SweepParameter::Gravity => "g",
+        }
+    }
+
+    pub fn affects_body(&self) -> bool {
+        matches!(self, SweepParameter::Mass | SweepParameter::PositionX | SweepParameter::PositionY)
+    }
+}
+
+impl std::str::FromStr for SweepParameter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SweepParameter::ALL.iter()
+            .find(|parameter| parameter.short_name() == s)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = SweepParameter::ALL.iter().map(|parameter| parameter.short_name()).collect();
+                format!("unknown parameter '{}', expected one of: {}", s, names.join(", "))
+            })
+    }
+}
+
+// One axis of the sweep grid: `steps` evenly spaced values from `from` to `to`
+#[derive(Clone, Copy, Debug)]
+pub struct SweepAxis {
+    pub parameter: SweepParameter,
+    pub from: f32,
+    pub to: f32,
+    pub steps: usize,
+}
+
+impl SweepAxis {
+    pub fn value_at(&self, step: usize) -> f32 {
+        let t = if self.steps > 1 { step as f32 / (self.steps - 1) as f32 } else { 0.0 };
+        self.from + (self.to - self.from) * t
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SweepSettings {
+    pub columns: SweepAxis,
+    // Optional second parameter varied down the rows
+    pub rows: Option<SweepAxis>,
+    // Body whose mass or position is swept
+    pub body: usize,
+}
+
+impl SweepSettings {
+    fn axes(&self) -> impl Iterator<Item = &SweepAxis> {
+        std::iter::once(&self.columns).chain(self.rows.as_ref())
+    }
+
+    // Parameter values of the cell in the given column and row
+    pub fn values_at(&self, column: usize, row: usize) -> Vec<(SweepParameter, f32)> {
+        let mut values = vec![(self.columns.parameter, self.columns.value_at(column))];
+        if let Some(rows) = &self.rows {
+            values.push((rows.parameter, rows.value_at(row)));
+        }
+        values
+    }
+
+    // Map parameters and scene for one cell, starting from the base view
+    pub fn cell(&self, base: &MapParams, scene: &Scene, values: &[(SweepParameter, f32)]) -> (MapParams, Scene) {
+        let mut params = *base;
+        let mut scene = scene.clone();
+        // Speed and angle are applied in polar form so they combine regardless of order
+        let mut speed = base.initial_velocity.length();
+        let mut angle = base.initial_velocity.angle().to_degrees();
+        let mut polar = false;
+        for &(parameter, value) in values {
+            match parameter {
+                SweepParameter::Speed => {
+                    speed = value;
+                    polar = true;
+                }
+                SweepParameter::Angle => {
+                    angle = value;
+                    polar = true;
+                }
+                SweepParameter::VelocityX => params.initial_velocity.x = value,
+                SweepParameter::VelocityY => params.initial_velocity.y = value,
+                SweepParameter::Mass => {
+                    let body = &mut scene.bodies[self.body];
+                    body.mass = value;
+                    body.radius = body_radius(value);
+                }
+                SweepParameter::PositionX => scene.bodies[self.body].pos.x = value,
+                SweepParameter::PositionY => scene.bodies[self.body].pos.y = value,
+                SweepParameter::Gravity => scene.gravitational_constant = value,
+            }
+        }
+        if polar {
+            let radians = angle.to_radians();
+            params.initial_velocity = Vec2::new(radians.cos(), radians.sin()) * speed;
+        }
+        params.scene_key = scene.cache_key();
+        (params, scene)
+    }
+}
+
+// Header text for one row or column, e.g. "speed=20.0"
+fn axis_label(axis: &SweepAxis, step: usize) -> String {
+    format!("{}={:.1}", axis.parameter.short_name(), axis.value_at(step))
+}
+
+// Draws a label centered in a box of the given width, shrinking the text if it doesn't fit
+fn draw_label(img: &mut RgbImage, label: &str, x: f32, y: f32, width: f32) {
+    let size = LABEL_SIZE * (width / text_width(label, LABEL_SIZE)).min(1.0);
+    let left = x + (width - text_width(label, size)) / 2.0;
+    draw_text(img, label, left, y, size, [220, 220, 220]);
+}
+
+// Renders a small map for every cell of the sweep grid and composites them into one labeled
+// montage. `base` supplies the cell size and the settings that are not swept.
+pub fn render_sweep(base: &MapParams, scene: &Scene, settings: &SweepSettings) -> Result<RgbImage, Box<dyn std::error::Error>> {
+    if settings.axes().any(|axis| axis.parameter.affects_body()) && settings.body >= scene.bodies.len() {
+        return Err(format!("body {} does not exist, the scene has {} bodies", settings.body, scene.bodies.len()).into());
+    }
+    let columns = settings.columns.steps.max(1) as u32;
+    let rows = settings.rows.map_or(1, |rows| rows.steps.max(1)) as u32;
+
+    // Column headers go above the grid and row headers, if there is a second axis, to its left
+    let row_labels: Vec<String> = settings.rows.iter()
+        .flat_map(|axis| (0..axis.steps.max(1)).map(|step| axis_label(axis, step)))
+        .collect();
+    let header_width = row_labels.iter()
+        .map(|label| text_width(label, LABEL_SIZE).ceil() as u32 + MONTAGE_MARGIN)
+        .max()
+        .unwrap_or(0);
+    let grid_left = MONTAGE_MARGIN + header_width;
+    let grid_top = MONTAGE_MARGIN + LABEL_HEIGHT;
+
+    let cell_width = base.width + MONTAGE_MARGIN;
+    let cell_height = base.height + MONTAGE_MARGIN;
+    let mut montage = RgbImage::from_pixel(
+        grid_left + columns * cell_width,
+        grid_top + rows * cell_height,
+        Rgb(MONTAGE_BACKGROUND),
+    );
+
+    for column in 0..columns {
+        let label = axis_label(&settings.columns, column as usize);
+        draw_label(&mut montage, &label, (grid_left + column * cell_width) as f32, MONTAGE_MARGIN as f32, base.width as f32);
+    }
+    for (row, label) in row_labels.iter().enumerate() {
+        let y = grid_top + row as u32 * cell_height + (base.height - LABEL_HEIGHT.min(base.height)) / 2;
+        draw_label(&mut montage, label, MONTAGE_MARGIN as f32, y as f32, (header_width - MONTAGE_MARGIN) as f32);
+    }
+
+    for row in 0..rows {
+        for column in 0..columns {
+            let values = settings.values_at(column as usize, row as usize);
+            println!("Rendering cell {}/{}", row * columns + column + 1, rows * columns);
+            let (params, cell_scene) = settings.cell(base, scene, &values);
+            let grid = compute_outcome_grid(&cell_scene, &params);
+            let img = render_outcome_grid(&grid, &cell_scene.bodies);
+
+            let x = grid_left + column * cell_width;
+            let y = grid_top + row * cell_height;
+            imageops::replace(&mut montage, &img, x as i64, y as i64);
+        }
+    }
+    Ok(montage)
+}
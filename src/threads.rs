@@ -0,0 +1,64 @@
+// Cores left free for the window and the rest of the system in background mode
+pub const BACKGROUND_FREE_CORES: usize = 2;
+// Niceness given to the worker threads in background mode (Unix only)
+pub const BACKGROUND_NICENESS: i32 = 10;
+
+// Builder for the rayon pool that map generation runs on. Without any settings rayon uses one
+// thread per core at normal priority, which can make the viewer stutter while a map generates.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RenderPoolBuilder {
+    threads: Option<usize>,
+    background: bool,
+}
+
+impl RenderPoolBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Fixed number of worker threads; takes precedence over the background core count
+    pub fn threads(mut self, threads: Option<usize>) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    // Leaves BACKGROUND_FREE_CORES cores free and lowers the workers' priority
+    pub fn background(mut self, background: bool) -> Self {
+        self.background = background;
+        self
+    }
+
+    pub fn thread_count(&self) -> usize {
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        match self.threads {
+            Some(threads) => threads.max(1),
+            None if self.background => cores.saturating_sub(BACKGROUND_FREE_CORES).max(1),
+            None => cores,
+        }
+    }
+
+    // Installs the pool as rayon's global pool; must run before any map is generated
+    pub fn build_global(self) -> Result<(), rayon::ThreadPoolBuildError> {
+        let background = self.background;
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(self.thread_count())
+            .start_handler(move |_| {
+                if background {
+                    lower_thread_priority();
+                }
+            })
+            .build_global()
+    }
+}
+
+// On Linux nice() only affects the calling thread, so each worker lowers its own priority
+#[cfg(unix)]
+fn lower_thread_priority() {
+    // SAFETY: nice has no memory safety requirements; failure just leaves the priority unchanged
+    unsafe {
+        libc::nice(BACKGROUND_NICENESS);
+    }
+}
+
+#[cfg(not(unix))]
+fn lower_thread_priority() {}
@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+
+use crate::physics::Vec2;
+use crate::simulation::SimulationParams;
+
+// Default thrust acceleration, enough to visibly bend an orbit within a second of burning
+pub const THRUST_ACCELERATION: f32 = 100.0;
+// Factor the thrust changes by per key press
+pub const THRUST_STEP: f32 = 1.5;
+
+// Where the particle's engine points
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ThrustDirection {
+    // Along the velocity, speeding the particle up
+    Prograde,
+    // Against the velocity, slowing it down
+    Retrograde,
+    // A fixed direction in world coordinates, in degrees counterclockwise from +x as seen on
+    // screen (the y axis points down)
+    Angle(f32),
+}
+
+impl ThrustDirection {
+    pub fn name(&self) -> String {
+        match self {
+            ThrustDirection::Prograde => "prograde".to_string(),
+            ThrustDirection::Retrograde => "retrograde".to_string(),
+            ThrustDirection::Angle(degrees) => format!("{}°", degrees),
+        }
+    }
+}
+
+impl std::str::FromStr for ThrustDirection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "prograde" => Ok(ThrustDirection::Prograde),
+            "retrograde" => Ok(ThrustDirection::Retrograde),
+            _ => s.parse().map(ThrustDirection::Angle)
+                .map_err(|_| format!("unknown thrust direction '{}', expected prograde, retrograde or an angle in degrees", s)),
+        }
+    }
+}
+
+// Constant acceleration from the particle's engine
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Thrust {
+    pub magnitude: f32,
+    pub direction: ThrustDirection,
+}
+
+impl Thrust {
+    pub fn new(magnitude: f32, direction: ThrustDirection) -> Self {
+        Self { magnitude, direction }
+    }
+
+    // Acceleration on a particle moving at `vel`; prograde and retrograde thrust do nothing at rest
+    pub fn acceleration(&self, vel: Vec2) -> Vec2 {
+        let direction = match self.direction {
+            ThrustDirection::Prograde => vel.normalize(),
+            ThrustDirection::Retrograde => -vel.normalize(),
+            ThrustDirection::Angle(degrees) => {
+                let radians = degrees.to_radians();
+                Vec2::new(radians.cos(), -radians.sin())
+            }
+        };
+        direction * self.magnitude
+    }
+}
+
+// Thrust fired over a range of timesteps, from `start` up to but not including `end`
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Burn {
+    pub start: usize,
+    pub end: usize,
+    pub thrust: Thrust,
+}
+
+impl Burn {
+    // Burn starting `start` units of simulated time after launch and lasting `duration`
+    pub fn scheduled(start: f32, duration: f32, thrust: Thrust, params: &SimulationParams) -> Self {
+        let first = (start / params.dt).round().max(0.0) as usize;
+        let timesteps = (duration / params.dt).round().max(0.0) as usize;
+        Self { start: first, end: first + timesteps, thrust }
+    }
+
+    // Speed the burn can change the particle's velocity by in total
+    pub fn delta_v(&self, params: &SimulationParams) -> f32 {
+        self.thrust.magnitude * (self.end - self.start) as f32 * params.dt
+    }
+}
+
+// Thrust firing during a timestep, if any of the burns covers it
+pub fn thrust_at(burns: &[Burn], timestep: usize) -> Option<Thrust> {
+    burns.iter().find(|burn| (burn.start..burn.end).contains(&timestep)).map(|burn| burn.thrust)
+}
+
+// Adds one timestep of thrust to a log of burns, extending the last burn if it ended just before
+// with the same thrust
+pub fn record_burn(burns: &mut Vec<Burn>, timestep: usize, thrust: Thrust) {
+    match burns.last_mut() {
+        Some(last) if last.end == timestep && last.thrust == thrust => last.end += 1,
+        _ => burns.push(Burn { start: timestep, end: timestep + 1, thrust }),
+    }
+}
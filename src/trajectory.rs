@@ -0,0 +1,85 @@
+use std::io::Write;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::barnes_hut::Gravity;
+use crate::physics::{total_energy, TestParticle, Vec2};
+use crate::scene::Scene;
+use crate::simulation::{IntegrationMethod, SimulationParams};
+use crate::thrust::{thrust_at, Burn};
+
+// State of the particle at the end of one timestep
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct TrajectorySample {
+    pub t: f32,
+    pub x: f32,
+    pub y: f32,
+    pub vx: f32,
+    pub vy: f32,
+    pub energy: f32,
+}
+
+impl TrajectorySample {
+    fn new(t: f32, particle: &TestParticle, scene: &Scene) -> Self {
+        Self {
+            t,
+            x: particle.pos.x,
+            y: particle.pos.y,
+            vx: particle.vel.x,
+            vy: particle.vel.y,
+            energy: total_energy(particle, scene),
+        }
+    }
+}
+
+// Runs one launch, firing the given burns, and records every timestep, unlike the live simulation
+// which only keeps a point every few screen pixels. The run is deterministic, so this reproduces
+// a live trajectory exactly.
+pub fn record_trajectory(
+    start_pos: Vec2,
+    initial_velocity: Vec2,
+    scene: &Scene,
+    integration_method: IntegrationMethod,
+    params: &SimulationParams,
+    burns: &[Burn],
+) -> Vec<TrajectorySample> {
+    let mut particle = scene.launch_particle(start_pos, initial_velocity);
+    let dt = params.substep_dt();
+    let mut gravity = Gravity::new(scene, params);
+    let mut samples = vec![TrajectorySample::new(0.0, &particle, scene)];
+
+    'timesteps: for timestep in 0..params.timesteps {
+        let thrust = thrust_at(burns, timestep);
+        for substep in 0..params.substeps {
+            match thrust {
+                Some(thrust) => gravity.step_with(integration_method, &mut particle, dt, |p| thrust.acceleration(p.vel)),
+                None => gravity.step(integration_method, &mut particle, dt),
+            }
+            if gravity.collision(&mut particle, params.collision_threshold).is_some() {
+                let t = timestep as f32 * params.dt + (substep + 1) as f32 * dt;
+                samples.push(TrajectorySample::new(t, &particle, gravity.scene()));
+                break 'timesteps;
+            }
+        }
+        samples.push(TrajectorySample::new((timestep + 1) as f32 * params.dt, &particle, gravity.scene()));
+    }
+    samples
+}
+
+// Writes the samples as JSON if the path ends in .json, otherwise as CSV with a header row
+pub fn save_trajectory(path: &str, samples: &[TrajectorySample]) -> Result<(), Box<dyn std::error::Error>> {
+    let is_json = Path::new(path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    if is_json {
+        std::fs::write(path, serde_json::to_string_pretty(samples)?)?;
+    } else {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        writeln!(file, "t,x,y,vx,vy,energy")?;
+        for s in samples {
+            writeln!(file, "{},{},{},{},{},{}", s.t, s.x, s.y, s.vx, s.vy, s.energy)?;
+        }
+        file.flush()?;
+    }
+    println!("Trajectory with {} samples saved to {}", samples.len(), path);
+    Ok(())
+}
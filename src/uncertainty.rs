@@ -0,0 +1,135 @@
+use std::f32::consts::TAU;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::AtomicUsize;
+
+use rand::Rng;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::image_gen::{count_pixel, MapParams};
+use crate::physics::Vec2;
+use crate::probability::sample_rng;
+use crate::scene::Scene;
+use crate::simulation::run_simulation_with_time;
+
+// Separations sampled by an uncertainty exponent estimate, spaced evenly in log between the
+// largest and smallest, in world units
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UncertaintySettings {
+    // Pairs of launches compared at every separation
+    pub pairs: usize,
+    pub largest: f32,
+    pub smallest: f32,
+    pub steps: usize,
+    pub seed: u64,
+}
+
+impl UncertaintySettings {
+    pub fn separations(&self) -> Vec<f32> {
+        if self.steps <= 1 {
+            return vec![self.largest];
+        }
+        let ratio = (self.smallest / self.largest).ln();
+        (0..self.steps).map(|i| self.largest * (ratio * i as f32 / (self.steps - 1) as f32).exp()).collect()
+    }
+}
+
+// Share of the launch pairs a given distance apart that ended differently
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct UncertaintyPoint {
+    pub separation: f32,
+    pub pairs: usize,
+    pub uncertain: usize,
+    pub fraction: f64,
+}
+
+// The uncertain fraction f(ε) of a map scales like ε^α near its basin boundaries, and the
+// boundaries have a box-counting dimension of 2 - α. Smooth boundaries give α = 1, fractal ones
+// less, approaching 0 when almost every launch is uncertain at any resolution.
+#[derive(Clone, Debug, Serialize)]
+pub struct UncertaintyEstimate {
+    // Slope of the least-squares line through log10 f against log10 ε, None when fewer than two
+    // separations found any uncertain pairs to fit
+    pub exponent: Option<f64>,
+    pub intercept: Option<f64>,
+    pub boundary_dimension: Option<f64>,
+    pub points: Vec<UncertaintyPoint>,
+}
+
+// Launches pairs of particles from random points of the view with the map's initial velocity,
+// the second of each pair `separation` away in a random direction, and fits the share whose
+// outcomes (the body hit, or escape) disagree against the separation
+pub fn estimate_uncertainty_exponent(
+    scene: &Scene,
+    params: &MapParams,
+    settings: &UncertaintySettings,
+    progress: &(impl Fn(usize, usize) + Sync),
+) -> UncertaintyEstimate {
+    let MapParams { width, height, initial_velocity, camera_offset, zoom_factor, integration_method, simulation, .. } = *params;
+    let view_size = Vec2::new(width as f32 / zoom_factor, height as f32 / zoom_factor);
+    let separations = settings.separations();
+    let total = separations.len() * settings.pairs;
+    let counter = AtomicUsize::new(0);
+
+    let points = separations.iter().enumerate().map(|(step, &separation)| {
+        let uncertain = (0..settings.pairs).into_par_iter().filter(|&pair| {
+            let mut rng = sample_rng(settings.seed, (step * settings.pairs + pair) as u64);
+            let start = Vec2::new(
+                rng.random::<f32>() * view_size.x - camera_offset.x,
+                rng.random::<f32>() * view_size.y - camera_offset.y,
+            );
+            let angle = TAU * rng.random::<f32>();
+            let other = start + Vec2::new(angle.cos(), angle.sin()) * separation;
+            let outcome = |pos| run_simulation_with_time(pos, initial_velocity, scene, integration_method, &simulation)
+                .map(|(index, _)| index);
+            let disagree = outcome(start) != outcome(other);
+            count_pixel(&counter, total, progress);
+            disagree
+        }).count();
+        UncertaintyPoint { separation, pairs: settings.pairs, uncertain, fraction: uncertain as f64 / settings.pairs.max(1) as f64 }
+    }).collect::<Vec<_>>();
+
+    let fit = fit_line(points.iter()
+        .filter(|point| point.uncertain > 0)
+        .map(|point| ((point.separation as f64).log10(), point.fraction.log10())));
+    UncertaintyEstimate {
+        exponent: fit.map(|(slope, _)| slope),
+        intercept: fit.map(|(_, intercept)| intercept),
+        boundary_dimension: fit.map(|(slope, _)| 2.0 - slope),
+        points,
+    }
+}
+
+// Least-squares slope and intercept of y against x, None for fewer than two distinct x
+fn fit_line(samples: impl Iterator<Item = (f64, f64)>) -> Option<(f64, f64)> {
+    let samples: Vec<(f64, f64)> = samples.collect();
+    if samples.len() < 2 {
+        return None;
+    }
+    let n = samples.len() as f64;
+    let mean_x = samples.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = samples.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let spread: f64 = samples.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    if spread == 0.0 {
+        return None;
+    }
+    let slope = samples.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum::<f64>() / spread;
+    Some((slope, mean_y - slope * mean_x))
+}
+
+// Writes the estimate as JSON if the path ends in .json, otherwise its points as CSV
+pub fn save_uncertainty(path: &str, estimate: &UncertaintyEstimate) -> Result<(), Box<dyn std::error::Error>> {
+    let is_json = Path::new(path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    if is_json {
+        std::fs::write(path, serde_json::to_string_pretty(estimate)?)?;
+    } else {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        writeln!(file, "separation,pairs,uncertain,fraction")?;
+        for point in &estimate.points {
+            writeln!(file, "{},{},{},{}", point.separation, point.pairs, point.uncertain, point.fraction)?;
+        }
+        file.flush()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,280 @@
+use std::f64::consts::PI;
+
+use crate::barnes_hut::Gravity;
+use crate::physics::{total_energy, Body, TestParticle, Vec2};
+use crate::scene::Scene;
+use crate::simulation::{IntegrationMethod, SimulationParams, TEST_PARTICLE_MASS, TEST_PARTICLE_RADIUS};
+
+// Known-answer checks of the integrators, run by `cargo test` and by --self-test so the
+// physics can be verified on any machine the program runs on
+
+// Gravitational constant times mass of the wells in the test scenes; with a radius of 100 this
+// gives a circular speed of 100 and an orbital period of 2π
+const GM: f32 = 1.0e6;
+const ORBIT_RADIUS: f32 = 100.0;
+// Half the distance between the two wells of the oscillation test, and where the particle starts
+const WELL_SEPARATION: f32 = 100.0;
+const OSCILLATION_START: f32 = 150.0;
+
+pub struct CheckResult {
+    pub name: &'static str,
+    pub passed: bool,
+    pub detail: String,
+}
+
+impl CheckResult {
+    fn new(name: &'static str, passed: bool, detail: String) -> Self {
+        Self { name, passed, detail }
+    }
+}
+
+pub fn run_all() -> Vec<CheckResult> {
+    vec![
+        circular_orbit(),
+        two_well_oscillation(),
+        convergence_order(IntegrationMethod::RungeKutta4, 4.0),
+        convergence_order(IntegrationMethod::Euler, 1.0),
+        time_reversibility(),
+    ]
+}
+
+// Prints every check and returns whether all of them passed
+pub fn print_report(results: &[CheckResult]) -> bool {
+    for result in results {
+        println!("[{}] {}: {}", if result.passed { "PASS" } else { "FAIL" }, result.name, result.detail);
+    }
+    let failed = results.iter().filter(|result| !result.passed).count();
+    println!("{} of {} checks passed", results.len() - failed, results.len());
+    failed == 0
+}
+
+fn well(pos: Vec2) -> Body {
+    Body::new(pos, GM, 1.0, [255, 255, 255])
+}
+
+fn single_well() -> Scene {
+    Scene::new(vec![well(Vec2::new(0.0, 0.0))], 1.0)
+}
+
+fn particle(pos: Vec2, vel: Vec2) -> TestParticle {
+    TestParticle::new(pos, vel, TEST_PARTICLE_MASS, TEST_PARTICLE_RADIUS)
+}
+
+// Launch on the circular orbit of radius ORBIT_RADIUS, and the time one revolution takes
+fn circular_launch() -> (TestParticle, f32) {
+    let speed = (GM / ORBIT_RADIUS).sqrt();
+    let period = 2.0 * PI as f32 * ORBIT_RADIUS / speed;
+    (particle(Vec2::new(ORBIT_RADIUS, 0.0), Vec2::new(0.0, speed)), period)
+}
+
+// Integrates for `steps` equal steps covering `duration`, calling `inspect` after each one
+fn integrate(
+    scene: &Scene,
+    method: IntegrationMethod,
+    mut particle: TestParticle,
+    duration: f32,
+    steps: usize,
+    mut inspect: impl FnMut(&TestParticle),
+) -> TestParticle {
+    let dt = duration / steps as f32;
+    for _ in 0..steps {
+        method.update(&mut particle, scene, dt);
+        inspect(&particle);
+    }
+    particle
+}
+
+// Over one revolution RK4 should hold the radius and come back to where it started
+pub fn circular_orbit() -> CheckResult {
+    let scene = single_well();
+    let (start, period) = circular_launch();
+    let mut max_radius_error: f32 = 0.0;
+    let end = integrate(&scene, IntegrationMethod::RungeKutta4, start, period, 1000, |p| {
+        max_radius_error = max_radius_error.max((p.pos.length() - ORBIT_RADIUS).abs() / ORBIT_RADIUS);
+    });
+    let return_error = end.pos.distance(&start.pos) / ORBIT_RADIUS;
+    CheckResult::new(
+        "circular orbit",
+        max_radius_error < 1e-4 && return_error < 1e-3,
+        format!("radius error {:.2e}, return error {:.2e} after one period", max_radius_error, return_error),
+    )
+}
+
+// Potential of the two-well scene along the symmetry axis between the wells, in f64
+fn axis_potential(y: f64) -> f64 {
+    let separation = WELL_SEPARATION as f64;
+    -2.0 * GM as f64 / (separation * separation + y * y).sqrt()
+}
+
+// Period of the oscillation through the midpoint of two equal wells, by quadrature of
+// dt = dy / v(y). Substituting y = y0 sin θ removes the singularity at the turning point.
+fn oscillation_period() -> f64 {
+    let y0 = OSCILLATION_START as f64;
+    let intervals = 100_000;
+    let step = PI / 2.0 / intervals as f64;
+    let quarter: f64 = (0..intervals).map(|i| {
+        let theta = (i as f64 + 0.5) * step;
+        let y = y0 * theta.sin();
+        let speed = (2.0 * (axis_potential(y0) - axis_potential(y))).sqrt();
+        y0 * theta.cos() / speed * step
+    }).sum();
+    4.0 * quarter
+}
+
+// A particle released on the axis between two equal wells swings through the midpoint to the
+// mirror image of its start and back, never leaving the axis, in the period given by quadrature
+pub fn two_well_oscillation() -> CheckResult {
+    let scene = Scene::new(vec![well(Vec2::new(-WELL_SEPARATION, 0.0)), well(Vec2::new(WELL_SEPARATION, 0.0))], 1.0);
+    let start = particle(Vec2::new(0.0, OSCILLATION_START), Vec2::new(0.0, 0.0));
+    let period = oscillation_period() as f32;
+    let initial_energy = total_energy(&start, &scene);
+    let (mut max_off_axis, mut lowest, mut max_energy_error) = (0.0f32, f32::INFINITY, 0.0f32);
+    let end = integrate(&scene, IntegrationMethod::RungeKutta4, start, period, 2000, |p| {
+        max_off_axis = max_off_axis.max(p.pos.x.abs());
+        lowest = lowest.min(p.pos.y);
+        max_energy_error = max_energy_error.max(((total_energy(p, &scene) - initial_energy) / initial_energy).abs());
+    });
+    let turning_error = (lowest + OSCILLATION_START).abs() / OSCILLATION_START;
+    let return_error = end.pos.distance(&start.pos) / OSCILLATION_START;
+    CheckResult::new(
+        "two-well oscillation",
+        max_off_axis < 1e-3 && turning_error < 1e-4 && return_error < 1e-3 && max_energy_error < 1e-5,
+        format!("period {:.4}, off-axis {:.1e}, turning point error {:.2e}, return error {:.2e}, energy error {:.2e}",
+                period, max_off_axis, turning_error, return_error, max_energy_error),
+    )
+}
+
+// Halving the step size should divide the error after a quarter revolution by 2^order. Not a
+// whole one, where the errors of the (semi-implicit) Euler step partly cancel. The step counts
+// are coarse enough that f32 rounding stays far below the truncation error.
+pub fn convergence_order(method: IntegrationMethod, expected_order: f32) -> CheckResult {
+    let scene = single_well();
+    let (start, period) = circular_launch();
+    let steps: &[usize] = match method {
+        IntegrationMethod::Euler => &[500, 1000, 2000],
+        IntegrationMethod::RungeKutta4 => &[5, 10, 20],
+    };
+    let expected = Vec2::new(0.0, ORBIT_RADIUS);
+    let errors: Vec<f32> = steps.iter()
+        .map(|&n| integrate(&scene, method, start, period / 4.0, n, |_| {}).pos.distance(&expected))
+        .collect();
+    let orders: Vec<f32> = errors.windows(2).map(|pair| (pair[0] / pair[1]).log2()).collect();
+    let name = match method {
+        IntegrationMethod::Euler => "Euler convergence order",
+        IntegrationMethod::RungeKutta4 => "RK4 convergence order",
+    };
+    CheckResult::new(
+        name,
+        orders.iter().all(|order| (order - expected_order).abs() < 0.3),
+        format!("errors {}, observed orders {}, expected {}",
+                errors.iter().map(|e| format!("{:.2e}", e)).collect::<Vec<_>>().join(" / "),
+                orders.iter().map(|o| format!("{:.2}", o)).collect::<Vec<_>>().join(", "),
+                expected_order),
+    )
+}
+
+// Integrating one revolution forward and then one backward, with the step negated, should bring
+// RK4 back to the start up to f32 rounding
+pub fn time_reversibility() -> CheckResult {
+    let scene = single_well();
+    let (start, period) = circular_launch();
+    let end = integrate(&scene, IntegrationMethod::RungeKutta4, start, period, 1000, |_| {});
+    let back = integrate(&scene, IntegrationMethod::RungeKutta4, end, -period, 1000, |_| {});
+    let return_error = back.pos.distance(&start.pos) / ORBIT_RADIUS;
+    CheckResult::new(
+        "RK4 time reversibility",
+        return_error < 1e-4,
+        format!("return error {:.2e} after one period forward and back", return_error),
+    )
+}
+
+// One launch integrated forward for the whole simulation, or until it reaches a body, then
+// backward for as many substeps with the step negated. Exact dynamics would land on the start
+// again, so the distance the backward run ends from it is the error the integrator accumulated.
+// Burns are left out, and drag and bounces can't be undone by reversing time, so launches
+// relying on those miss by more than the integrator's error.
+pub struct Reversibility {
+    pub integration_method: IntegrationMethod,
+    // Position at the start and at the end of every timestep of each run
+    pub forward: Vec<Vec2>,
+    pub backward: Vec<Vec2>,
+    // Simulated time each way
+    pub duration: f32,
+    // Distance between the start and where the backward run ended
+    pub return_error: f32,
+}
+
+pub fn time_reversal(
+    start_pos: Vec2,
+    initial_velocity: Vec2,
+    scene: &Scene,
+    integration_method: IntegrationMethod,
+    params: &SimulationParams,
+) -> Reversibility {
+    let mut particle = scene.launch_particle(start_pos, initial_velocity);
+    let dt = params.substep_dt();
+    // Dynamic bodies are stepped back along with the particle, so both runs share the scene
+    let mut gravity = Gravity::new(scene, params);
+    let mut forward = vec![particle.pos];
+    let mut substeps = 0;
+    'timesteps: for _ in 0..params.timesteps {
+        for _ in 0..params.substeps {
+            gravity.step(integration_method, &mut particle, dt);
+            substeps += 1;
+            if gravity.collision(&mut particle, params.collision_threshold).is_some() {
+                forward.push(particle.pos);
+                break 'timesteps;
+            }
+        }
+        forward.push(particle.pos);
+    }
+
+    let mut backward = vec![particle.pos];
+    for substep in 1..=substeps {
+        gravity.step(integration_method, &mut particle, -dt);
+        if substep % params.substeps.max(1) == 0 || substep == substeps {
+            backward.push(particle.pos);
+        }
+    }
+    Reversibility {
+        integration_method,
+        forward,
+        backward,
+        duration: substeps as f32 * dt,
+        return_error: particle.pos.distance(&start_pos),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_passes(result: CheckResult) {
+        assert!(result.passed, "{}: {}", result.name, result.detail);
+    }
+
+    #[test]
+    fn circular_orbit_closes() {
+        assert_passes(circular_orbit());
+    }
+
+    #[test]
+    fn two_well_oscillation_matches_quadrature() {
+        assert_passes(two_well_oscillation());
+    }
+
+    #[test]
+    fn rk4_is_fourth_order() {
+        assert_passes(convergence_order(IntegrationMethod::RungeKutta4, 4.0));
+    }
+
+    #[test]
+    fn euler_is_first_order() {
+        assert_passes(convergence_order(IntegrationMethod::Euler, 1.0));
+    }
+
+    #[test]
+    fn rk4_retraces_its_steps() {
+        assert_passes(time_reversibility());
+    }
+}
@@ -0,0 +1,118 @@
+use std::path::Path;
+
+use image::{ImageBuffer, Rgb, RgbImage};
+
+use crate::image_gen::{compute_outcome_grid, draw_bodies, render_outcome_grid, MapParams};
+use crate::image_format::ImageOutput;
+use crate::deep_zoom::DeepPoint;
+use crate::physics::Vec2;
+use crate::precise::DVec2;
+use crate::scene::Scene;
+
+#[derive(Clone, Copy, Debug)]
+pub struct ZoomVideoSettings {
+    // World point the video zooms into; it stays at the center of every frame
+    pub target: DVec2,
+    pub start_zoom: f32,
+    pub end_zoom: f32,
+    pub frames: usize,
+    // Draw the bodies into every frame
+    pub draw_bodies: bool,
+    pub image_output: ImageOutput,
+}
+
+impl ZoomVideoSettings {
+    // Exponential schedule, so every frame zooms in by the same factor
+    pub fn zoom_at(&self, frame: usize) -> f32 {
+        let t = if self.frames > 1 { frame as f32 / (self.frames - 1) as f32 } else { 0.0 };
+        self.start_zoom * (self.end_zoom / self.start_zoom).powf(t)
+    }
+}
+
+// A map rendered at twice the frame resolution. It covers the view at `zoom`, so every frame
+// with a zoom between `zoom` and `2 * zoom` can be cut out of it with at least one map pixel
+// per frame pixel, and each octave of the zoom only needs one generated map.
+struct Keyframe {
+    zoom: f32,
+    image: RgbImage,
+}
+
+// Parameters for a view of the given size centered on `target`
+fn centered_view(base: &MapParams, target: DVec2, width: u32, height: u32, zoom: f32) -> MapParams {
+    let camera = DeepPoint::from_dvec2(DVec2::new(-target.x, -target.y))
+        .offset(width as f64 / 2.0 / zoom as f64, height as f64 / 2.0 / zoom as f64);
+    MapParams { width, height, zoom_factor: zoom, ..*base }.with_camera(camera)
+}
+
+fn render_keyframe(base: &MapParams, scene: &Scene, target: DVec2, zoom: f32) -> Keyframe {
+    let params = centered_view(base, target, base.width * 2, base.height * 2, zoom * 2.0);
+    let grid = compute_outcome_grid(scene, &params);
+    Keyframe { zoom, image: render_outcome_grid(&grid, &scene.bodies) }
+}
+
+// Bilinear sample of an image at fractional pixel coordinates, clamped to its edges
+fn sample_bilinear(img: &RgbImage, x: f32, y: f32) -> [u8; 3] {
+    let x = x.clamp(0.0, img.width() as f32 - 1.0);
+    let y = y.clamp(0.0, img.height() as f32 - 1.0);
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(img.width() - 1), (y0 + 1).min(img.height() - 1));
+    let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+    let mut out = [0u8; 3];
+    for (c, value) in out.iter_mut().enumerate() {
+        let top = img.get_pixel(x0, y0)[c] as f32 * (1.0 - fx) + img.get_pixel(x1, y0)[c] as f32 * fx;
+        let bottom = img.get_pixel(x0, y1)[c] as f32 * (1.0 - fx) + img.get_pixel(x1, y1)[c] as f32 * fx;
+        *value = (top * (1.0 - fy) + bottom * fy).round() as u8;
+    }
+    out
+}
+
+// Cuts the frame at `zoom` out of the keyframe, resampling it to the frame size
+fn frame_from_keyframe(keyframe: &Keyframe, width: u32, height: u32, zoom: f32) -> RgbImage {
+    // Keyframe pixels per frame pixel
+    let scale = 2.0 * keyframe.zoom / zoom;
+    let frame_center = Vec2::new(width as f32, height as f32) / 2.0;
+    let key_center = Vec2::new(keyframe.image.width() as f32, keyframe.image.height() as f32) / 2.0;
+    ImageBuffer::from_fn(width, height, |px, py| {
+        let p = key_center + (Vec2::new(px as f32, py as f32) - frame_center) * scale;
+        Rgb(sample_bilinear(&keyframe.image, p.x, p.y))
+    })
+}
+
+// Renders a zoom into `settings.target` as numbered PNG frames in `output_dir`, ready for ffmpeg.
+// `base` supplies the frame size, velocity, integrator and simulation settings.
+pub fn render_zoom_video(
+    base: &MapParams,
+    scene: &Scene,
+    settings: &ZoomVideoSettings,
+    output_dir: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if settings.start_zoom <= 0.0 || settings.end_zoom <= 0.0 {
+        return Err("zoom levels must be positive".into());
+    }
+    std::fs::create_dir_all(output_dir)?;
+
+    let mut keyframe: Option<Keyframe> = None;
+    for frame in 0..settings.frames {
+        let zoom = settings.zoom_at(frame);
+        // Octave of the zoom relative to the start; zooming out works the same way with negative octaves
+        let octave = (zoom / settings.start_zoom).log2().floor();
+        let key_zoom = settings.start_zoom * 2f32.powf(octave);
+        if keyframe.as_ref().is_none_or(|key| key.zoom != key_zoom) {
+            println!("Rendering keyframe at zoom {:.3} for frame {}/{}", key_zoom, frame + 1, settings.frames);
+            keyframe = Some(render_keyframe(base, scene, settings.target, key_zoom));
+        }
+        let mut img = frame_from_keyframe(keyframe.as_ref().unwrap(), base.width, base.height, zoom);
+
+        let params = centered_view(base, settings.target, base.width, base.height, zoom);
+        // Drawn after resampling so the bodies stay sharp between keyframes
+        if settings.draw_bodies {
+            draw_bodies(&mut img, &scene.bodies, &params, 1.0);
+        }
+        let filename = Path::new(output_dir).join(format!("frame_{:05}.{}", frame, settings.image_output.extension()));
+        settings.image_output.save(&img, &filename.to_string_lossy(), &params, scene)?;
+    }
+
+    println!("Frames saved to {}. Encode them with:", output_dir);
+    println!("  ffmpeg -framerate 30 -i {}/frame_%05d.{} -pix_fmt yuv420p zoom.mp4", output_dir, settings.image_output.extension());
+    Ok(())
+}